@@ -6,6 +6,63 @@ from typing import List, Dict, Any
 
 logger = logging.getLogger(__name__)
 
+# Must match ECONOMIC_MONTE_CARLO_RESULT_VERSION in the Rust library
+ECONOMIC_MONTE_CARLO_RESULT_VERSION = 1
+
+class EconomicMonteCarloResult(ctypes.Structure):
+    """Mirror of the Rust EconomicMonteCarloResult struct"""
+    _fields_ = [
+        (name, ctypes.c_double)
+        for name in (
+            "mean", "std_dev", "min", "max",
+            "irr_mean", "irr_std_dev", "irr_p10", "irr_p50", "irr_p90",
+            "irr_failure_rate",
+            "payback_mean", "payback_p10", "payback_p50", "payback_p90",
+            "never_paid_back",
+            "price_contribution", "cost_contribution", "production_contribution",
+            "skewness", "excess_kurtosis",
+            "standard_error", "ci_lower", "ci_upper",
+            "rejected_draws", "clamped_draws",
+        )
+    ]
+
+# PsdStatus codes returned by analyze_particle_distribution_v2
+PSD_STATUS_MESSAGES = {
+    1: "null pointer passed to Rust",
+    2: "empty input",
+    3: "non-finite particle size",
+    4: "non-finite weight",
+    5: "negative weight",
+    6: "weights sum to zero",
+    7: "negative particle size",
+    8: "unknown interpolation method",
+}
+
+# PercentileMethod ids accepted by the particle size functions
+PERCENTILE_METHODS = (0, 1, 2)
+
+# RecoveryStatus codes returned by calculate_protein_recovery_checked
+RECOVERY_STATUS_MESSAGES = {
+    1: "null pointer passed to Rust",
+    2: "protein yield outside the range of its unit",
+    3: "protein content outside the range of its unit",
+    4: "separation efficiency outside the range of its unit",
+    5: "unknown ratio unit",
+}
+
+# AllocationStatus codes returned by the checked allocation functions
+ALLOCATION_STATUS_MESSAGES = {
+    1: "null pointer passed to Rust",
+    2: "empty input",
+    3: "non-finite value",
+    4: "negative value",
+    5: "allocation keys sum to zero",
+    6: "protein content outside the range of its unit",
+    7: "allocation factors do not sum to 1",
+    8: "unknown protein content unit",
+    9: "unknown factor sum check",
+}
+
 class RustHandler:
     """Handles integration with Rust libraries for economic calculations"""
     
@@ -53,18 +110,35 @@ class RustHandler:
         """Configure Rust function signatures"""
         try:
             # Configure Monte Carlo simulation function
-            self.lib.run_economic_monte_carlo.argtypes = [
+            self.lib.economic_monte_carlo_result_size.argtypes = []
+            self.lib.economic_monte_carlo_result_size.restype = ctypes.c_size_t
+            self.lib.economic_monte_carlo_result_version.argtypes = []
+            self.lib.economic_monte_carlo_result_version.restype = ctypes.c_uint32
+
+            result_size = self.lib.economic_monte_carlo_result_size()
+            result_version = self.lib.economic_monte_carlo_result_version()
+            if (result_size != ctypes.sizeof(EconomicMonteCarloResult)
+                    or result_version != ECONOMIC_MONTE_CARLO_RESULT_VERSION):
+                raise RuntimeError(
+                    f"EconomicMonteCarloResult ABI mismatch: library has version "
+                    f"{result_version} ({result_size} bytes), bindings expect version "
+                    f"{ECONOMIC_MONTE_CARLO_RESULT_VERSION} "
+                    f"({ctypes.sizeof(EconomicMonteCarloResult)} bytes)"
+                )
+
+            self.lib.run_economic_monte_carlo_v2.argtypes = [
                 ctypes.POINTER(ctypes.c_double),  # base_values
                 ctypes.c_size_t,                  # len
                 ctypes.c_size_t,                  # iterations
                 ctypes.c_double,                  # price_uncertainty
                 ctypes.c_double,                  # cost_uncertainty
                 ctypes.c_double,                  # production_uncertainty
-                ctypes.c_size_t,                  # random_seed
+                ctypes.c_uint64,                  # random_seed
                 ctypes.c_double,                  # discount_rate
-                ctypes.POINTER(ctypes.c_double),  # results
+                ctypes.POINTER(EconomicMonteCarloResult),  # result
+                ctypes.c_void_p,                  # options (null = defaults)
             ]
-            self.lib.run_economic_monte_carlo.restype = ctypes.c_bool
+            self.lib.run_economic_monte_carlo_v2.restype = ctypes.c_int  # RunStatus
 
             # Configure sensitivity analysis function
             self.lib.run_sensitivity_analysis.argtypes = [
@@ -80,6 +154,8 @@ class RustHandler:
                 ctypes.POINTER(ctypes.c_double),  # results
             ]
             self.lib.run_sensitivity_analysis.restype = None
+            self.lib.sensitivity_last_status.argtypes = []
+            self.lib.sensitivity_last_status.restype = ctypes.c_int  # SensitivityStatus
 
             # Configure allocation functions
             self.lib.calculate_allocation.argtypes = [
@@ -99,6 +175,27 @@ class RustHandler:
             ]
             self.lib.calculate_hybrid_allocation.restype = ctypes.c_bool
 
+            self.lib.calculate_allocation_checked.argtypes = [
+                ctypes.POINTER(ctypes.c_double),  # impacts
+                ctypes.POINTER(ctypes.c_double),  # values
+                ctypes.c_size_t,                  # len
+                ctypes.POINTER(ctypes.c_double),  # allocation_factors
+                ctypes.POINTER(ctypes.c_size_t),  # invalid_index_out
+            ]
+            self.lib.calculate_allocation_checked.restype = ctypes.c_int  # AllocationStatus
+
+            self.lib.calculate_hybrid_allocation_checked.argtypes = [
+                ctypes.POINTER(ctypes.c_double),  # mass_factors
+                ctypes.POINTER(ctypes.c_double),  # economic_factors
+                ctypes.c_size_t,                  # len
+                ctypes.c_double,                  # weight
+                ctypes.c_uint32,                  # sum_check (FactorSumCheck id)
+                ctypes.POINTER(ctypes.c_double),  # results
+                ctypes.POINTER(ctypes.c_size_t),  # invalid_index_out
+                ctypes.POINTER(ctypes.c_bool),    # sum_mismatch_out
+            ]
+            self.lib.calculate_hybrid_allocation_checked.restype = ctypes.c_int  # AllocationStatus
+
             # Configure eco-efficiency functions
             self.lib.calculate_efficiency.argtypes = [
                 ctypes.c_double,  # economic_value
@@ -114,8 +211,17 @@ class RustHandler:
             ]
             self.lib.calculate_eco_efficiency_matrix.restype = ctypes.c_bool
 
+            self.lib.calculate_eco_efficiency_matrix_checked.argtypes = [
+                ctypes.POINTER(ctypes.c_double),  # economic_values
+                ctypes.POINTER(ctypes.c_double),  # environmental_impacts
+                ctypes.c_size_t,                  # len
+                ctypes.POINTER(ctypes.c_double),  # results
+                ctypes.POINTER(ctypes.c_size_t),  # invalid_index_out
+            ]
+            self.lib.calculate_eco_efficiency_matrix_checked.restype = ctypes.c_bool
+
             # Configure particle distribution analysis
-            self.lib.analyze_particle_distribution.argtypes = [
+            self.lib.analyze_particle_distribution_v2.argtypes = [
                 ctypes.POINTER(ctypes.c_double),  # sizes
                 ctypes.POINTER(ctypes.c_double),  # weights
                 ctypes.c_size_t,                  # len
@@ -124,8 +230,25 @@ class RustHandler:
                 ctypes.POINTER(ctypes.c_double),  # d90
                 ctypes.POINTER(ctypes.c_double),  # mean
                 ctypes.POINTER(ctypes.c_double),  # std_dev
+                ctypes.c_bool,                    # drop_invalid
+                ctypes.POINTER(ctypes.c_size_t),  # invalid_index_out
+                ctypes.POINTER(ctypes.c_size_t),  # dropped_out
+                ctypes.c_uint32,                  # interpolation_method (PercentileMethod id)
+            ]
+            self.lib.analyze_particle_distribution_v2.restype = ctypes.c_int  # PsdStatus
+
+            # Configure protein recovery
+            self.lib.calculate_protein_recovery_checked.argtypes = [
+                ctypes.c_double,                  # protein_yield
+                ctypes.c_uint32,                  # yield_unit (RatioUnit id)
+                ctypes.c_double,                  # protein_content
+                ctypes.c_uint32,                  # content_unit (RatioUnit id)
+                ctypes.c_double,                  # separation_efficiency
+                ctypes.c_uint32,                  # efficiency_unit (RatioUnit id)
+                ctypes.c_uint32,                  # output_unit (RatioUnit id)
+                ctypes.POINTER(ctypes.c_double),  # result
             ]
-            self.lib.analyze_particle_distribution.restype = ctypes.c_bool
+            self.lib.calculate_protein_recovery_checked.restype = ctypes.c_int  # RecoveryStatus
             
             logger.debug("Rust functions configured successfully")
             
@@ -163,10 +286,10 @@ class RustHandler:
             
             # Convert cash flows to C array
             arr = (ctypes.c_double * len(cash_flows))(*cash_flows)
-            results = (ctypes.c_double * 4)()  # [mean, std_dev, min_value, max_value]
+            result = EconomicMonteCarloResult()
             
             # Call Rust function with seed
-            success = self.lib.run_economic_monte_carlo(
+            status = self.lib.run_economic_monte_carlo_v2(
                 arr,
                 len(cash_flows),
                 iterations,
@@ -175,17 +298,18 @@ class RustHandler:
                 production_uncertainty,
                 random_seed,
                 discount_rate,
-                results
+                ctypes.byref(result),
+                None
             )
             
-            if not success:
-                raise RuntimeError("Monte Carlo simulation failed in Rust implementation")
+            if status != 0:
+                raise RuntimeError(f"Monte Carlo simulation failed in Rust implementation (status {status})")
             
             # Parse results
-            mean_npv = float(results[0])
-            std_dev = float(results[1])
-            min_val = float(results[2])
-            max_val = float(results[3])
+            mean_npv = float(result.mean)
+            std_dev = float(result.std_dev)
+            min_val = float(result.min)
+            max_val = float(result.max)
             
             logger.debug(f"Monte Carlo simulation completed:")
             logger.debug(f"  - Mean NPV: {mean_npv}")
@@ -207,7 +331,8 @@ class RustHandler:
     def analyze_particle_distribution(
         self,
         particle_sizes: List[float],
-        weights: List[float]
+        weights: List[float],
+        interpolation_method: int = 0
     ) -> Dict[str, float]:
         """
         Analyze particle size distribution using optimized Rust implementation
@@ -215,6 +340,8 @@ class RustHandler:
         Args:
             particle_sizes: List of particle sizes (must be non-empty)
             weights: List of weights for each size (must match particle_sizes length)
+            interpolation_method: Percentile convention, 0 for linear in cumulative
+                weight (default), 1 for nearest rank, 2 for midpoint cumulative
             
         Returns:
             Dictionary containing distribution metrics:
@@ -240,6 +367,8 @@ class RustHandler:
                 raise ValueError("Particle sizes must be positive")
             if any(x < 0 for x in weights):
                 raise ValueError("Weights cannot be negative")
+            if interpolation_method not in PERCENTILE_METHODS:
+                raise ValueError(f"Unknown interpolation method: {interpolation_method}")
                 
             # Convert lists to C arrays
             size_array = (ctypes.c_double * len(particle_sizes))(*particle_sizes)
@@ -251,9 +380,11 @@ class RustHandler:
             d90 = ctypes.c_double()
             mean = ctypes.c_double()
             std_dev = ctypes.c_double()
+            invalid_index = ctypes.c_size_t()
+            dropped = ctypes.c_size_t()
             
             # Call Rust function with error handling
-            success = self.lib.analyze_particle_distribution(
+            status = self.lib.analyze_particle_distribution_v2(
                 size_array,
                 weight_array,
                 len(particle_sizes),
@@ -261,11 +392,18 @@ class RustHandler:
                 ctypes.byref(d50),
                 ctypes.byref(d90),
                 ctypes.byref(mean),
-                ctypes.byref(std_dev)
+                ctypes.byref(std_dev),
+                False,
+                ctypes.byref(invalid_index),
+                ctypes.byref(dropped),
+                interpolation_method
             )
             
-            if not success:
-                raise RuntimeError("Particle size analysis failed in Rust implementation")
+            if status != 0:
+                reason = PSD_STATUS_MESSAGES.get(status, f"status {status}")
+                if invalid_index.value < len(particle_sizes):
+                    reason += f" at index {invalid_index.value}"
+                raise RuntimeError(f"Particle size analysis rejected the input: {reason}")
             
             return {
                 "D10": d10.value,
@@ -279,6 +417,49 @@ class RustHandler:
             logger.error(f"Error in particle size analysis: {str(e)}", exc_info=True)
             raise RuntimeError(f"Particle size analysis failed: {str(e)}")
 
+    def calculate_protein_recovery(
+        self,
+        protein_yield: float,
+        protein_content: float,
+        separation_efficiency: float,
+        yield_in_percent: bool = True,
+        content_in_percent: bool = True,
+        efficiency_in_percent: bool = True,
+        output_in_percent: bool = True
+    ) -> float:
+        """
+        Calculate protein recovery with explicit units for every input
+
+        Args:
+            protein_yield: Protein yield, 0-100 in percent or 0-1 as a fraction
+            protein_content: Protein content, 0-100 in percent or 0-1 as a fraction
+            separation_efficiency: Separation efficiency, 0-100 in percent or 0-1 as a fraction
+            yield_in_percent, content_in_percent, efficiency_in_percent:
+                Whether the matching input is given in percent
+            output_in_percent: Whether to return the recovery in percent
+
+        Returns:
+            Protein recovery in the requested unit
+
+        Raises:
+            ValueError: If an input lies outside the range of its unit
+        """
+        result = ctypes.c_double()
+        status = self.lib.calculate_protein_recovery_checked(
+            protein_yield,
+            int(yield_in_percent),
+            protein_content,
+            int(content_in_percent),
+            separation_efficiency,
+            int(efficiency_in_percent),
+            int(output_in_percent),
+            ctypes.byref(result)
+        )
+        if status != 0:
+            reason = RECOVERY_STATUS_MESSAGES.get(status, f"status {status}")
+            raise ValueError(f"Protein recovery rejected the input: {reason}")
+        return result.value
+
     def calculate_allocation_factors(
         self,
         impacts: List[float],
@@ -303,16 +484,22 @@ class RustHandler:
             values_array = (ctypes.c_double * len(values))(*values)
             results_array = (ctypes.c_double * len(impacts))()
 
+            invalid_index = ctypes.c_size_t()
+
             # Call Rust function
-            success = self.lib.calculate_allocation(
+            status = self.lib.calculate_allocation_checked(
                 impacts_array,
                 values_array,
                 len(impacts),
-                results_array
+                results_array,
+                ctypes.byref(invalid_index)
             )
 
-            if not success:
-                raise RuntimeError("Allocation calculation failed in Rust")
+            if status != 0:
+                reason = ALLOCATION_STATUS_MESSAGES.get(status, f"status {status}")
+                if invalid_index.value != ctypes.c_size_t(-1).value:
+                    reason += f" at index {invalid_index.value}"
+                raise ValueError(f"Allocation rejected the input: {reason}")
 
             return {
                 "allocation_factors": [results_array[i] / impacts[i] if impacts[i] != 0 else 0 
@@ -328,7 +515,8 @@ class RustHandler:
         self,
         mass_factors: List[float],
         economic_factors: List[float],
-        weight: float
+        weight: float,
+        reject_unnormalized: bool = False
     ) -> List[float]:
         """
         Calculate hybrid allocation factors using Rust implementation
@@ -337,9 +525,11 @@ class RustHandler:
             mass_factors: List of physical allocation factors
             economic_factors: List of economic allocation factors
             weight: Weight for physical allocation (1-weight for economic)
+            reject_unnormalized: Fail instead of warning when a factor set
+                does not sum to 1
             
         Returns:
-            List of hybrid allocation factors
+            List of hybrid allocation factors, renormalized to sum to 1
         """
         try:
             if len(mass_factors) != len(economic_factors):
@@ -350,17 +540,28 @@ class RustHandler:
             economic_array = (ctypes.c_double * len(economic_factors))(*economic_factors)
             results_array = (ctypes.c_double * len(mass_factors))()
 
+            invalid_index = ctypes.c_size_t()
+            sum_mismatch = ctypes.c_bool()
+
             # Call Rust function
-            success = self.lib.calculate_hybrid_allocation(
+            status = self.lib.calculate_hybrid_allocation_checked(
                 mass_array,
                 economic_array,
                 len(mass_factors),
                 weight,
-                results_array
+                int(reject_unnormalized),
+                results_array,
+                ctypes.byref(invalid_index),
+                ctypes.byref(sum_mismatch)
             )
 
-            if not success:
-                raise RuntimeError("Hybrid allocation calculation failed in Rust")
+            if status != 0:
+                reason = ALLOCATION_STATUS_MESSAGES.get(status, f"status {status}")
+                if invalid_index.value != ctypes.c_size_t(-1).value:
+                    reason += f" at index {invalid_index.value}"
+                raise ValueError(f"Hybrid allocation rejected the input: {reason}")
+            if sum_mismatch.value:
+                logger.warning("Hybrid allocation factors did not sum to 1; result was renormalized")
 
             return [results_array[i] for i in range(len(mass_factors))]
 
@@ -384,20 +585,29 @@ class RustHandler:
             List of eco-efficiency values
         """
         try:
+            if len(economic_values) != len(environmental_impacts):
+                raise ValueError("Economic values and environmental impacts must have the same length")
+            if not economic_values:
+                raise ValueError("At least one alternative is required")
+
             # Convert lists to C arrays
             values_array = (ctypes.c_double * len(economic_values))(*economic_values)
             impacts_array = (ctypes.c_double * len(environmental_impacts))(*environmental_impacts)
             results_array = (ctypes.c_double * len(economic_values))()
+            invalid_index = ctypes.c_size_t()
 
             # Call Rust function
-            success = self.lib.calculate_eco_efficiency_matrix(
+            success = self.lib.calculate_eco_efficiency_matrix_checked(
                 values_array,
                 impacts_array,
                 len(economic_values),
-                results_array
+                results_array,
+                ctypes.byref(invalid_index)
             )
 
             if not success:
+                if invalid_index.value != ctypes.c_size_t(-1).value:
+                    raise ValueError(f"Non-finite value or impact at index {invalid_index.value}")
                 raise RuntimeError("Eco-efficiency matrix calculation failed in Rust")
 
             return [results_array[i] for i in range(len(economic_values))]
@@ -486,6 +696,10 @@ class RustHandler:
                 variable_cost_ratio,
                 results_array
             )
+
+            status = self.lib.sensitivity_last_status()
+            if status != 0:
+                raise RuntimeError(f"Rust sensitivity analysis returned status {status}")
             
             # Get results and log them
             results = [results_array[i] for i in range(steps + 1)]
@@ -0,0 +1,203 @@
+use rand::rngs::StdRng;
+use rand_distr::{Distribution, Exp1, Gamma, StandardNormal};
+use statrs::distribution::{ContinuousCDF, Normal};
+use crate::matrix_ops::{cholesky, lower_mul_vec};
+
+/// Marginal distribution of a factor's multiplier, parameterized so that its
+/// mean is 1 and its spread is the factor's uncertainty. The Monte Carlo
+/// options carry it by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum MarginalDistribution {
+    /// `1 + sigma * z`
+    #[default]
+    Normal = 0,
+    /// `exp(sigma * z - sigma^2 / 2)`, never negative
+    LogNormal = 1,
+    /// Symmetric triangular on `1 +/- sigma * sqrt(6)` (standard deviation sigma)
+    Triangular = 2,
+}
+
+impl MarginalDistribution {
+    pub(crate) fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Normal),
+            1 => Some(Self::LogNormal),
+            2 => Some(Self::Triangular),
+            _ => None,
+        }
+    }
+
+    /// Multiplier at cumulative probability `u`.
+    pub(crate) fn quantile(self, u: f64, sigma: f64) -> f64 {
+        match self {
+            MarginalDistribution::Normal => 1.0 + sigma * standard_normal().inverse_cdf(u),
+            MarginalDistribution::LogNormal => {
+                (sigma * standard_normal().inverse_cdf(u) - sigma * sigma / 2.0).exp()
+            }
+            MarginalDistribution::Triangular => {
+                let half_width = sigma * 6.0_f64.sqrt();
+                let offset = if u < 0.5 {
+                    -half_width * (1.0 - (2.0 * u).sqrt())
+                } else {
+                    half_width * (1.0 - (2.0 * (1.0 - u)).sqrt())
+                };
+                1.0 + offset
+            }
+        }
+    }
+}
+
+/// Dependence structure between the price, cost and production shocks,
+/// carried by id in the Monte Carlo options.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum CopulaKind {
+    /// Independent factors
+    #[default]
+    Independent = 0,
+    /// Gaussian copula with a 3x3 correlation matrix
+    Gaussian = 1,
+    /// Clayton copula with parameter theta > 0 (lower-tail dependence)
+    Clayton = 2,
+}
+
+impl CopulaKind {
+    pub(crate) fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Independent),
+            1 => Some(Self::Gaussian),
+            2 => Some(Self::Clayton),
+            _ => None,
+        }
+    }
+}
+
+/// Validated copula ready for sampling.
+pub(crate) enum Copula {
+    Independent,
+    Gaussian { cholesky: Vec<f64> },
+    Clayton { theta: f64, frailty: Gamma<f64> },
+}
+
+impl Copula {
+    /// `correlation` is a row-major 3x3 matrix (Gaussian only) and `theta`
+    /// the Clayton parameter. Returns None for inadmissible parameters.
+    pub(crate) fn new(kind: CopulaKind, correlation: Option<&[f64]>, theta: f64) -> Option<Self> {
+        match kind {
+            CopulaKind::Independent => Some(Copula::Independent),
+            CopulaKind::Gaussian => {
+                let matrix = correlation?;
+                if !is_correlation_matrix(matrix, 3) {
+                    return None;
+                }
+                Some(Copula::Gaussian { cholesky: cholesky(matrix, 3)? })
+            }
+            CopulaKind::Clayton => {
+                if !(theta > 0.0 && theta.is_finite()) {
+                    return None;
+                }
+                let frailty = Gamma::new(1.0 / theta, 1.0).ok()?;
+                Some(Copula::Clayton { theta, frailty })
+            }
+        }
+    }
+
+    /// Draw one vector of dependent uniforms in (0, 1).
+    pub(crate) fn sample(&self, rng: &mut StdRng) -> [f64; 3] {
+        let uniforms = match self {
+            Copula::Independent => {
+                std::array::from_fn(|_| standard_normal().cdf(StandardNormal.sample(rng)))
+            }
+            Copula::Gaussian { cholesky } => {
+                let z: [f64; 3] = std::array::from_fn(|_| StandardNormal.sample(rng));
+                let correlated = lower_mul_vec(cholesky, &z, 3);
+                std::array::from_fn(|k| standard_normal().cdf(correlated[k]))
+            }
+            Copula::Clayton { theta, frailty } => {
+                // Marshall-Olkin: U_i = (1 + E_i / V)^(-1/theta), V ~ Gamma(1/theta)
+                let v = frailty.sample(rng);
+                std::array::from_fn(|_| {
+                    let e: f64 = Exp1.sample(rng);
+                    (1.0 + e / v).powf(-1.0 / theta)
+                })
+            }
+        };
+        // Keep the quantile functions away from their infinite endpoints
+        uniforms.map(|u: f64| u.clamp(f64::EPSILON, 1.0 - f64::EPSILON))
+    }
+}
+
+fn standard_normal() -> Normal {
+    Normal::new(0.0, 1.0).expect("standard normal parameters are valid")
+}
+
+fn is_correlation_matrix(matrix: &[f64], n: usize) -> bool {
+    if matrix.len() != n * n {
+        return false;
+    }
+    (0..n).all(|i| {
+        (matrix[i * n + i] - 1.0).abs() < 1e-12
+            && (0..n).all(|j| {
+                let value = matrix[i * n + j];
+                value.abs() <= 1.0 && (value - matrix[j * n + i]).abs() < 1e-12
+            })
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use rand::SeedableRng;
+
+    /// Share of draws with the first factor in its lowest 5% that also have
+    /// the second factor there.
+    fn lower_tail_co_movement(copula: &Copula) -> f64 {
+        let mut rng = StdRng::seed_from_u64(1);
+        let (mut first, mut both) = (0, 0);
+        for _ in 0..200_000 {
+            let u = copula.sample(&mut rng);
+            if u[0] < 0.05 {
+                first += 1;
+                if u[1] < 0.05 {
+                    both += 1;
+                }
+            }
+        }
+        both as f64 / first as f64
+    }
+
+    #[test]
+    fn clayton_has_more_lower_tail_dependence_than_gaussian_at_equal_tau() {
+        let tau: f64 = 0.5;
+        let rho = (std::f64::consts::PI * tau / 2.0).sin();
+        let correlation = [1.0, rho, rho, rho, 1.0, rho, rho, rho, 1.0];
+        let gaussian = Copula::new(CopulaKind::Gaussian, Some(&correlation), 0.0).unwrap();
+        let clayton = Copula::new(CopulaKind::Clayton, None, 2.0 * tau / (1.0 - tau)).unwrap();
+        let (gaussian_tail, clayton_tail) = (lower_tail_co_movement(&gaussian), lower_tail_co_movement(&clayton));
+        assert!(clayton_tail > gaussian_tail + 0.1, "{clayton_tail} vs {gaussian_tail}");
+        assert!(lower_tail_co_movement(&Copula::Independent) < 0.1);
+    }
+
+    #[test]
+    fn inadmissible_parameters_are_rejected() {
+        let indefinite = [1.0, 0.9, 0.9, 0.9, 1.0, -0.9, 0.9, -0.9, 1.0];
+        assert!(Copula::new(CopulaKind::Gaussian, Some(&indefinite), 0.0).is_none());
+        let asymmetric = [1.0, 0.5, 0.0, 0.4, 1.0, 0.0, 0.0, 0.0, 1.0];
+        assert!(Copula::new(CopulaKind::Gaussian, Some(&asymmetric), 0.0).is_none());
+        assert!(Copula::new(CopulaKind::Gaussian, None, 0.0).is_none());
+        for theta in [0.0, -1.0, f64::INFINITY, f64::NAN] {
+            assert!(Copula::new(CopulaKind::Clayton, None, theta).is_none(), "{theta}");
+        }
+    }
+
+    #[test]
+    fn marginals_are_centred_on_one() {
+        for marginal in [MarginalDistribution::Normal, MarginalDistribution::Triangular] {
+            assert!((marginal.quantile(0.5, 0.2) - 1.0).abs() < 1e-12);
+        }
+        assert!((MarginalDistribution::Triangular.quantile(f64::EPSILON, 0.2) - (1.0 - 0.2 * 6.0_f64.sqrt())).abs() < 1e-6);
+        assert!(MarginalDistribution::LogNormal.quantile(1e-12, 1.0) > 0.0);
+    }
+}
@@ -0,0 +1,123 @@
+use rayon::prelude::*;
+use std::sync::atomic::{AtomicU8, AtomicUsize, Ordering};
+
+/// Outcome of a long-running entry point.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RunStatus {
+    Ok = 0,
+    /// Null pointer, empty input or an invalid parameter
+    InvalidInput = 1,
+    /// Stopped early through the cancellation flag or progress callback;
+    /// the results buffer was left untouched
+    Cancelled = 2,
+}
+
+/// Caller-owned cancellation flag: any non-zero value requests a stop.
+///
+/// A null pointer means the run cannot be cancelled.
+#[derive(Clone, Copy)]
+pub(crate) struct CancelFlag<'a>(Option<&'a AtomicU8>);
+
+impl CancelFlag<'_> {
+    /// The caller keeps the flag alive for the duration of the call.
+    pub fn from_ptr(flag: *const AtomicU8) -> Self {
+        if flag.is_null() {
+            Self(None)
+        } else {
+            Self(Some(unsafe { &*flag }))
+        }
+    }
+
+    pub fn is_set(&self) -> bool {
+        self.0.is_some_and(|flag| flag.load(Ordering::Relaxed) != 0)
+    }
+}
+
+/// Map `f` over `0..n` in parallel, `chunk_size` items at a time, polling
+/// the cancellation flag once per chunk. Returns None when cancelled.
+pub(crate) fn par_map_chunked<T, F>(n: usize, chunk_size: usize, cancel: CancelFlag<'_>, f: F) -> Option<Vec<T>>
+where
+    T: Send,
+    F: Fn(usize) -> T + Sync,
+{
+    let chunk_size = chunk_size.max(1);
+    let chunks: Vec<Option<Vec<T>>> = (0..n.div_ceil(chunk_size)).into_par_iter().map(|chunk| {
+        if cancel.is_set() {
+            return None;
+        }
+        let start = chunk * chunk_size;
+        Some((start..(start + chunk_size).min(n)).map(&f).collect())
+    }).collect();
+
+    let mut values = Vec::with_capacity(n);
+    for chunk in chunks {
+        values.extend(chunk?);
+    }
+    Some(values)
+}
+
+// Thread cap applied to calls that pass max_threads = 0; 0 means no cap
+static GLOBAL_THREAD_LIMIT: AtomicUsize = AtomicUsize::new(0);
+
+/// Process-wide thread cap for the parallel entry points, used whenever a
+/// call passes `max_threads = 0`. 0 restores the default of one thread per
+/// core.
+#[no_mangle]
+pub extern "C" fn set_global_thread_limit(max_threads: usize) {
+    GLOBAL_THREAD_LIMIT.store(max_threads, Ordering::Relaxed);
+}
+
+/// Run `f` with its parallel iterators confined to `max_threads` threads.
+///
+/// 0 falls back to the global limit, and to rayon's global pool when that
+/// is unset too. Returns None when the thread pool cannot be created.
+pub(crate) fn with_thread_limit<R, F>(max_threads: usize, f: F) -> Option<R>
+where
+    R: Send,
+    F: FnOnce() -> R + Send,
+{
+    let threads = match max_threads {
+        0 => GLOBAL_THREAD_LIMIT.load(Ordering::Relaxed),
+        n => n,
+    };
+    if threads == 0 {
+        return Some(f());
+    }
+    let pool = rayon::ThreadPoolBuilder::new().num_threads(threads).build().ok()?;
+    Some(pool.install(f))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn chunked_map_keeps_the_order() {
+        let flag = AtomicU8::new(0);
+        let values = par_map_chunked(10, 3, CancelFlag::from_ptr(&flag), |i| i * i).unwrap();
+        assert_eq!(values, (0..10).map(|i| i * i).collect::<Vec<_>>());
+        assert_eq!(par_map_chunked(0, 3, CancelFlag::from_ptr(std::ptr::null()), |i| i), Some(Vec::new()));
+    }
+
+    #[test]
+    fn set_flag_cancels_the_map() {
+        let flag = AtomicU8::new(1);
+        assert!(par_map_chunked(10, 3, CancelFlag::from_ptr(&flag), |i| i).is_none());
+        assert!(!CancelFlag::from_ptr(std::ptr::null()).is_set());
+    }
+
+    #[test]
+    fn thread_limit_sizes_the_pool() {
+        assert_eq!(with_thread_limit(1, rayon::current_num_threads), Some(1));
+        assert_eq!(with_thread_limit(3, rayon::current_num_threads), Some(3));
+
+        // The only test touching the global limit; other runs are unaffected
+        // apart from their pool size
+        set_global_thread_limit(2);
+        let global = with_thread_limit(0, rayon::current_num_threads);
+        let explicit = with_thread_limit(3, rayon::current_num_threads);
+        set_global_thread_limit(0);
+        assert_eq!((global, explicit), (Some(2), Some(3)));
+    }
+}
@@ -1,33 +1,194 @@
 use std::ffi::c_double;
 use super::npv::calculate_npv_from_slice;
 
+const IRR_TOLERANCE: f64 = 1e-6;
+const IRR_MAX_ITERATIONS: usize = 100;
+// Rates at or below -100% make the discount factor meaningless
+const IRR_LOWER_BOUND: f64 = -0.99;
+const IRR_UPPER_LIMIT: f64 = 1e6;
+
 #[no_mangle]
 pub extern "C" fn calculate_irr(
     cash_flows: *const c_double,
     len: usize,
     result: *mut c_double
 ) -> bool {
+    if cash_flows.is_null() || result.is_null() || len == 0 {
+        return false;
+    }
+
     let flows = unsafe { std::slice::from_raw_parts(cash_flows, len) };
-    
-    // Newton-Raphson method for IRR calculation
-    let mut rate = 0.1;  // Initial guess
-    let max_iterations = 100;
-    let tolerance = 1e-6;
 
-    for _ in 0..max_iterations {
-        let npv = calculate_npv_from_slice(flows, rate);
-        if npv.abs() < tolerance {
+    match calculate_irr_from_slice(flows) {
+        Some(rate) => {
             unsafe { *result = rate; }
-            return true;
+            true
+        }
+        None => false,  // No sign change or failed to converge
+    }
+}
+
+/// Robust IRR solver used by both the FFI entry point and the simulations.
+///
+/// Returns `None` when the flows never change sign (no IRR exists) or when
+/// no root can be bracketed.
+pub(crate) fn calculate_irr_from_slice(flows: &[f64]) -> Option<f64> {
+    if !has_sign_change(flows) {
+        return None;
+    }
+    let guess = 0.1;
+    let npv_at_guess = calculate_npv_from_slice(flows, guess);
+    calculate_irr_with_hint(flows, guess, npv_at_guess)
+}
+
+/// Same as `calculate_irr_from_slice`, but reuses an NPV the caller already
+/// evaluated at `rate` to seed the bracket search.
+pub(crate) fn calculate_irr_with_hint(flows: &[f64], rate: f64, npv_at_rate: f64) -> Option<f64> {
+    if !has_sign_change(flows) || !npv_at_rate.is_finite() {
+        return None;
+    }
+    if npv_at_rate.abs() < IRR_TOLERANCE {
+        return Some(rate);
+    }
+
+    let (low, high) = bracket_root(flows, rate.max(IRR_LOWER_BOUND), npv_at_rate)?;
+    Some(solve_bracketed(flows, low, high))
+}
+
+fn has_sign_change(flows: &[f64]) -> bool {
+    flows.iter().any(|&cf| cf > 0.0) && flows.iter().any(|&cf| cf < 0.0)
+}
+
+/// Expand outwards from `rate` until NPV changes sign.
+///
+/// For a conventional project NPV falls as the rate rises, so a negative
+/// NPV at `rate` puts the IRR below it and a positive one above; that side
+/// is searched first and the other only if it has no sign change.
+fn bracket_root(flows: &[f64], rate: f64, npv_at_rate: f64) -> Option<(f64, f64)> {
+    if npv_at_rate < 0.0 {
+        bracket_below(flows, rate, npv_at_rate).or_else(|| bracket_above(flows, rate, npv_at_rate))
+    } else {
+        bracket_above(flows, rate, npv_at_rate).or_else(|| bracket_below(flows, rate, npv_at_rate))
+    }
+}
+
+/// Search upwards from `rate` with doubling steps up to `IRR_UPPER_LIMIT`.
+fn bracket_above(flows: &[f64], rate: f64, npv_at_rate: f64) -> Option<(f64, f64)> {
+    let mut low = rate;
+    let mut npv_low = npv_at_rate;
+    let mut step = 0.1;
+    while low < IRR_UPPER_LIMIT {
+        let high = low + step;
+        let npv_high = calculate_npv_from_slice(flows, high);
+        if npv_low.signum() != npv_high.signum() {
+            return Some((low, high));
+        }
+        low = high;
+        npv_low = npv_high;
+        step *= 2.0;
+    }
+    None
+}
+
+/// Search downwards from `rate` by halving the distance to -100%.
+fn bracket_below(flows: &[f64], rate: f64, npv_at_rate: f64) -> Option<(f64, f64)> {
+    let mut high = rate;
+    let mut npv_high = npv_at_rate;
+    while high > IRR_LOWER_BOUND {
+        let low = ((high + IRR_LOWER_BOUND) / 2.0).max(IRR_LOWER_BOUND);
+        let low = if high - low < 1e-4 { IRR_LOWER_BOUND } else { low };
+        let npv_low = calculate_npv_from_slice(flows, low);
+        if npv_low.signum() != npv_high.signum() {
+            return Some((low, high));
         }
-        
+        high = low;
+        npv_high = npv_low;
+    }
+    None
+}
+
+/// Newton-Raphson safeguarded by bisection inside [low, high].
+fn solve_bracketed(flows: &[f64], mut low: f64, mut high: f64) -> f64 {
+    let mut npv_low = calculate_npv_from_slice(flows, low);
+    let mut rate = (low + high) / 2.0;
+
+    for _ in 0..IRR_MAX_ITERATIONS {
+        let npv = calculate_npv_from_slice(flows, rate);
+        if npv.abs() < IRR_TOLERANCE || (high - low) < IRR_TOLERANCE * 1e-3 {
+            return rate;
+        }
+
+        if npv.signum() == npv_low.signum() {
+            low = rate;
+            npv_low = npv;
+        } else {
+            high = rate;
+        }
+
         // Calculate derivative
         let delta = 0.0001;
         let derivative = (calculate_npv_from_slice(flows, rate + delta) - npv) / delta;
-        
-        // Update rate
-        rate = rate - npv / derivative;
+        let newton = rate - npv / derivative;
+
+        // Fall back to bisection whenever Newton leaves the bracket
+        rate = if newton.is_finite() && newton > low && newton < high {
+            newton
+        } else {
+            (low + high) / 2.0
+        };
+    }
+
+    rate
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn irr_zeroes_the_npv() {
+        let flows = [-1000.0, 300.0, 300.0, 300.0, 300.0, 300.0];
+        let mut rate = 0.0;
+        assert!(calculate_irr(flows.as_ptr(), flows.len(), &mut rate));
+        assert!(calculate_npv_from_slice(&flows, rate).abs() < 1e-4);
+        assert!((rate - 0.152382).abs() < 1e-5);
+    }
+
+    #[test]
+    fn hint_gives_the_same_root() {
+        let flows = [-1000.0, 100.0, 100.0, 100.0];
+        let from_slice = calculate_irr_from_slice(&flows).unwrap();
+        let hinted = calculate_irr_with_hint(&flows, 0.08, calculate_npv_from_slice(&flows, 0.08)).unwrap();
+        assert!(from_slice < 0.0);
+        assert!((from_slice - hinted).abs() < 1e-6);
+    }
+
+    #[test]
+    fn no_sign_change_has_no_irr() {
+        let mut rate = f64::NAN;
+        assert!(!calculate_irr([100.0, 50.0].as_ptr(), 2, &mut rate));
+        assert!(!calculate_irr([-100.0, -50.0].as_ptr(), 2, &mut rate));
+        assert!(!calculate_irr(std::ptr::null(), 2, &mut rate));
+        assert!(rate.is_nan());
+    }
+
+    #[test]
+    fn bracket_search_starts_on_the_side_the_npv_points_to() {
+        // IRR about 5%, below the 10% hint, so the search goes straight down
+        let flows = [-1000.0, 230.0, 230.0, 230.0, 230.0, 230.0];
+        let npv = calculate_npv_from_slice(&flows, 0.1);
+        assert!(npv < 0.0);
+        let (low, high) = bracket_root(&flows, 0.1, npv).unwrap();
+        assert!(low < high && high <= 0.1, "{low} {high}");
+        // Roots at 0% and 50%: a negative NPV at 10% finds the lower one
+        let flows = [2.0, -5.0, 3.0];
+        let npv = calculate_npv_from_slice(&flows, 0.1);
+        assert!(npv < 0.0);
+        let (low, high) = bracket_root(&flows, 0.1, npv).unwrap();
+        assert!(low <= 0.0 && (0.0..=0.1).contains(&high), "{low} {high}");
+        // and a positive NPV still searches upwards first
+        let flows = [-1000.0, 400.0, 400.0, 400.0, 400.0];
+        let (low, high) = bracket_root(&flows, 0.1, calculate_npv_from_slice(&flows, 0.1)).unwrap();
+        assert!(0.1 <= low && low < high, "{low} {high}");
     }
-    
-    false  // Failed to converge
-} 
\ No newline at end of file
+}
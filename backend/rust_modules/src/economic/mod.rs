@@ -1,9 +1,66 @@
+pub mod copula;
+pub mod execution;
 pub mod monte_carlo;
 pub mod npv;
 pub mod irr;
+pub mod payback;
+pub mod running_stats;
+pub mod scenario;
 pub mod sensitivity;
+pub mod sobol;
 
+pub use copula::{CopulaKind, MarginalDistribution};
+pub use execution::{set_global_thread_limit, RunStatus};
+// Kept exported for existing bindings
+#[allow(deprecated)]
 pub use monte_carlo::run_economic_monte_carlo;
+pub use monte_carlo::{
+    run_economic_monte_carlo_v2,
+    run_economic_monte_carlo_extended,
+    run_economic_monte_carlo_batch,
+    economic_monte_carlo_extended_len,
+    economic_monte_carlo_default_options,
+    economic_monte_carlo_result_size,
+    economic_monte_carlo_result_version,
+    EconomicMonteCarloResult,
+    EconomicMonteCarloOptions,
+    ShockBounds,
+    ProgressCallback,
+    RiskEvent,
+    StratumSpec,
+};
 pub use npv::calculate_npv;
 pub use irr::calculate_irr;
-pub use sensitivity::run_sensitivity_analysis; 
\ No newline at end of file
+pub use payback::calculate_discounted_payback;
+pub use running_stats::{
+    running_stats_init,
+    running_stats_update,
+    merge_running_stats,
+    running_stats_finalize,
+    RunningStats,
+};
+pub use scenario::run_scenario_analysis;
+pub use sensitivity::{
+    compute_local_sensitivities,
+    factorial_sensitivity_result_len,
+    find_switching_values,
+    run_factorial_sensitivity,
+    run_morris_screening,
+    run_sensitivity_analysis,
+    run_sensitivity_analysis_v2,
+    run_sensitivity_elasticity,
+    run_spider_analysis,
+    run_tornado_analysis,
+    sensitivity_last_status,
+    MorrisDesign,
+    SensitivityMetric,
+    SensitivityNormalization,
+    SensitivityOutputMode,
+    SensitivityPointStatus,
+    SensitivityRequest,
+    SensitivityResponse,
+    SensitivityStatus,
+    SwitchingStatus,
+    SweepSpacing,
+}; 
+pub use sobol::{run_sobol_analysis, SOBOL_FACTORS, SOBOL_RESULT_WIDTH};
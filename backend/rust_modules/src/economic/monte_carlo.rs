@@ -1,7 +1,772 @@
 use rand_distr::{Distribution, Normal};
-use rand::{SeedableRng, rngs::StdRng};
+use rand::{Rng, SeedableRng, rngs::StdRng};
 use rayon::prelude::*;
+use std::ffi::c_void;
+use std::sync::Mutex;
+use std::sync::atomic::{AtomicBool, AtomicU8, Ordering};
+use statrs::distribution::{ContinuousCDF, Normal as StandardNormal};
+use super::copula::{Copula, CopulaKind, MarginalDistribution};
+use super::execution::{with_thread_limit, CancelFlag, RunStatus};
+use super::irr::calculate_irr_with_hint;
+use super::payback::npv_and_payback;
+use super::running_stats::RunningStats;
 
+/// Number of slots written by `run_economic_monte_carlo_extended`, one per
+/// field of `EconomicMonteCarloResult` in declaration order.
+///
+/// | index | value |
+/// |-------|-------|
+/// | 0-3   | NPV mean, std dev, min, max (same as `run_economic_monte_carlo`) |
+/// | 4-5   | IRR mean, std dev |
+/// | 6-8   | IRR P10, P50, P90 |
+/// | 9     | fraction of iterations where IRR could not be computed |
+/// | 10    | mean discounted payback period (years) |
+/// | 11-13 | discounted payback P10, P50, P90 |
+/// | 14    | probability of never paying back within the horizon |
+/// | 15-17 | price, cost, production contribution to NPV variance (%) |
+/// | 18    | NPV skewness |
+/// | 19    | NPV excess kurtosis |
+/// | 20    | standard error of the mean NPV |
+/// | 21-22 | lower, upper bound of the confidence interval for the mean NPV |
+/// | 23    | draws rejected for falling outside the truncation bounds |
+/// | 24    | draws clamped after exhausting the rejection attempts |
+///
+/// IRR and payback statistics only cover the iterations where the metric
+/// exists; they are NaN when no iteration produced one. Variance
+/// contributions are NaN unless requested through the options.
+pub const EXTENDED_RESULT_LEN: usize = 25;
+
+/// Bumped whenever a field of `EconomicMonteCarloResult` is added, removed
+/// or reordered.
+pub const ECONOMIC_MONTE_CARLO_RESULT_VERSION: u32 = 1;
+
+/// Statistics written by `run_economic_monte_carlo_v2`.
+///
+/// Every field is an f64, laid out in the order of the
+/// `EXTENDED_RESULT_LEN` table, so an array of results is interchangeable
+/// with the flat blocks of `run_economic_monte_carlo_extended`. Bindings
+/// should compare `economic_monte_carlo_result_size()` and
+/// `economic_monte_carlo_result_version()` with their own definition at
+/// load time.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct EconomicMonteCarloResult {
+    /// NPV mean, population standard deviation, min and max
+    pub mean: f64,
+    pub std_dev: f64,
+    pub min: f64,
+    pub max: f64,
+    pub irr_mean: f64,
+    pub irr_std_dev: f64,
+    pub irr_p10: f64,
+    pub irr_p50: f64,
+    pub irr_p90: f64,
+    /// Fraction of iterations where IRR could not be computed
+    pub irr_failure_rate: f64,
+    /// Discounted payback period statistics (years)
+    pub payback_mean: f64,
+    pub payback_p10: f64,
+    pub payback_p50: f64,
+    pub payback_p90: f64,
+    /// Probability of never paying back within the horizon
+    pub never_paid_back: f64,
+    /// Contributions to NPV variance (%)
+    pub price_contribution: f64,
+    pub cost_contribution: f64,
+    pub production_contribution: f64,
+    /// NPV distribution shape
+    pub skewness: f64,
+    pub excess_kurtosis: f64,
+    /// Standard error of the mean NPV and its confidence interval
+    pub standard_error: f64,
+    pub ci_lower: f64,
+    pub ci_upper: f64,
+    /// Draws rejected by, and clamped to, the truncation bounds
+    pub rejected_draws: f64,
+    pub clamped_draws: f64,
+}
+
+// Field reordering would silently break every binding
+const _: () = {
+    use std::mem::{offset_of, size_of};
+    assert!(size_of::<EconomicMonteCarloResult>() == EXTENDED_RESULT_LEN * size_of::<f64>());
+    assert!(offset_of!(EconomicMonteCarloResult, mean) == 0);
+    assert!(offset_of!(EconomicMonteCarloResult, max) == 3 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, irr_mean) == 4 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, irr_failure_rate) == 9 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, payback_mean) == 10 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, never_paid_back) == 14 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, price_contribution) == 15 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, skewness) == 18 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, standard_error) == 20 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, rejected_draws) == 23 * 8);
+    assert!(offset_of!(EconomicMonteCarloResult, clamped_draws) == 24 * 8);
+};
+
+impl EconomicMonteCarloResult {
+    fn to_array(self) -> [f64; EXTENDED_RESULT_LEN] {
+        [
+            self.mean, self.std_dev, self.min, self.max,
+            self.irr_mean, self.irr_std_dev, self.irr_p10, self.irr_p50, self.irr_p90,
+            self.irr_failure_rate,
+            self.payback_mean, self.payback_p10, self.payback_p50, self.payback_p90,
+            self.never_paid_back,
+            self.price_contribution, self.cost_contribution, self.production_contribution,
+            self.skewness, self.excess_kurtosis,
+            self.standard_error, self.ci_lower, self.ci_upper,
+            self.rejected_draws, self.clamped_draws,
+        ]
+    }
+}
+
+/// Optional settings for `run_economic_monte_carlo_extended`.
+///
+/// Passing a null pointer is the same as passing
+/// `economic_monte_carlo_default_options()`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct EconomicMonteCarloOptions {
+    /// Record each iteration's average price/cost/production multiplier and
+    /// attribute NPV variance to the three factors via squared Spearman rank
+    /// correlations, normalized to sum to 100%.
+    pub variance_contributions: bool,
+    /// Confidence level of the interval around the mean NPV, in (0, 1).
+    /// 0 selects the default of 0.95.
+    pub confidence_level: f64,
+    /// Truncate each factor's multiplier to [lower, upper] by rejection
+    /// sampling; draws still outside after 100 attempts are clamped.
+    pub price_bounds: ShockBounds,
+    pub cost_bounds: ShockBounds,
+    pub production_bounds: ShockBounds,
+    /// Optional progress callback, see `ProgressCallback`.
+    pub progress_callback: Option<ProgressCallback>,
+    /// Passed back unchanged to `progress_callback`.
+    pub progress_user_data: *mut c_void,
+    /// Iterations between progress reports; 0 selects 1024.
+    pub progress_interval: u64,
+    /// Optional cancellation flag polled once per chunk of iterations; any
+    /// non-zero value stops the run with `RunStatus::Cancelled`.
+    pub cancel_flag: *const AtomicU8,
+    /// Optional discrete risk events sampled in every iteration.
+    pub risk_events: *const RiskEvent,
+    pub n_risk_events: usize,
+    /// Optional output of n_risk_events values: the fraction of iterations
+    /// in which each risk event fired.
+    pub risk_event_fire_rates: *mut f64,
+    /// `MarginalDistribution` ids of the price, cost and production
+    /// multipliers; an unknown id is an invalid input.
+    pub marginals: [u32; 3],
+    /// `CopulaKind` id of the dependence between the three factors within a
+    /// year; an unknown id is an invalid input. With anything other than
+    /// independent normal marginals, each operating year draws a joint
+    /// (price, cost, production) vector from the copula and maps it through
+    /// the marginals; truncation bounds then clamp rather than resample so
+    /// the dependence structure is preserved.
+    pub copula: u32,
+    /// Row-major 3x3 correlation matrix for `CopulaKind::Gaussian`.
+    pub copula_correlation: *const f64,
+    /// Parameter for `CopulaKind::Clayton`, must be positive.
+    pub clayton_theta: f64,
+    /// Optional output of the NPV moments as a mergeable accumulator, so
+    /// runs over disjoint seed ranges can be combined later with
+    /// `merge_running_stats`.
+    pub npv_running_stats: *mut RunningStats,
+    /// Fold this run into the accumulator already in `npv_running_stats`
+    /// instead of overwriting it, to resume an interrupted study.
+    pub accumulate_running_stats: bool,
+    /// Worker threads for this call; 0 uses the limit from
+    /// `set_global_thread_limit`, or every core when none was set.
+    pub max_threads: usize,
+    /// Optional factor/year pairs to stratify, see `StratumSpec`. Requires
+    /// independent normal marginals, and the product of the strata counts
+    /// must divide the iteration count.
+    pub strata: *const StratumSpec,
+    pub n_strata: usize,
+    /// Only evaluate the NPV of each iteration, skipping the IRR solve and
+    /// the payback period; their statistics are then NaN.
+    pub npv_only: bool,
+}
+
+impl Default for EconomicMonteCarloOptions {
+    fn default() -> Self {
+        Self {
+            variance_contributions: false,
+            confidence_level: 0.0,
+            price_bounds: ShockBounds::default(),
+            cost_bounds: ShockBounds::default(),
+            production_bounds: ShockBounds::default(),
+            progress_callback: None,
+            progress_user_data: std::ptr::null_mut(),
+            progress_interval: 0,
+            cancel_flag: std::ptr::null(),
+            risk_events: std::ptr::null(),
+            n_risk_events: 0,
+            risk_event_fire_rates: std::ptr::null_mut(),
+            marginals: [MarginalDistribution::Normal as u32; 3],
+            copula: CopulaKind::Independent as u32,
+            copula_correlation: std::ptr::null(),
+            clayton_theta: 0.0,
+            npv_running_stats: std::ptr::null_mut(),
+            accumulate_running_stats: false,
+            max_threads: 0,
+            strata: std::ptr::null(),
+            n_strata: 0,
+            npv_only: false,
+        }
+    }
+}
+
+/// Scale one operating-year flow by the given multipliers.
+///
+/// Positive flows are treated as revenue and move with price, negative flows
+/// as costs and move with cost; both move with production. Shared by the
+/// Monte Carlo and the deterministic scenario table so the two agree.
+pub(crate) fn adjust_operating_flow(value: f64, price_factor: f64, cost_factor: f64, production_factor: f64) -> f64 {
+    if value > 0.0 {
+        value * price_factor * production_factor
+    } else {
+        value * cost_factor * production_factor
+    }
+}
+
+pub(crate) const PRICE: usize = 0;
+pub(crate) const COST: usize = 1;
+pub(crate) const PRODUCTION: usize = 2;
+
+// Draws attempted per shock before falling back to clamping
+const MAX_REJECTIONS: usize = 100;
+
+/// Truncation bounds for one factor's multiplier (`1 + shock`).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct ShockBounds {
+    pub enabled: bool,
+    pub lower: f64,
+    pub upper: f64,
+}
+
+impl ShockBounds {
+    fn validated(self) -> Option<Option<(f64, f64)>> {
+        if !self.enabled {
+            return Some(None);
+        }
+        if self.lower.is_finite() && self.upper.is_finite() && self.lower < self.upper {
+            Some(Some((self.lower, self.upper)))
+        } else {
+            None
+        }
+    }
+}
+
+/// Stratify one factor's multiplier in one year into `strata` equally
+/// likely quantile bands.
+///
+/// Iterations cycle through every combination of the requested bands, so
+/// each combination receives the same number of iterations; within a band
+/// the quantile is drawn uniformly. Truncation bounds are honored by
+/// stratifying the truncated distribution, so these draws are never
+/// rejected or clamped.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct StratumSpec {
+    /// 0 = price, 1 = cost, 2 = production
+    pub factor: usize,
+    /// Operating year (1-based: year 0 carries no shocks)
+    pub year: usize,
+    pub strata: usize,
+}
+
+/// A validated `StratumSpec` plus its place in the combination index.
+#[derive(Clone, Copy)]
+struct Stratum {
+    spec: StratumSpec,
+    /// Product of the strata counts of the specs before this one
+    radix: usize,
+}
+
+/// Rejection-sampling bookkeeping for one realization.
+#[derive(Default, Clone, Copy)]
+pub(crate) struct DrawTally {
+    rejected: u64,
+    clamped: u64,
+}
+
+/// Joint sampling of the three factors through a copula.
+struct Dependence {
+    copula: Copula,
+    marginals: [MarginalDistribution; 3],
+}
+
+pub(crate) struct ShockDistributions {
+    dists: [Normal<f64>; 3],
+    sigmas: [f64; 3],
+    bounds: [Option<(f64, f64)>; 3],
+    dependence: Option<Dependence>,
+    strata: Vec<Stratum>,
+    /// Number of band combinations, 1 without stratification
+    combinations: usize,
+}
+
+impl ShockDistributions {
+    pub(crate) fn new(price_uncertainty: f64, cost_uncertainty: f64, production_uncertainty: f64) -> Option<Self> {
+        Some(Self {
+            dists: [
+                Normal::new(0.0, price_uncertainty).ok()?,
+                Normal::new(0.0, cost_uncertainty).ok()?,
+                Normal::new(0.0, production_uncertainty).ok()?,
+            ],
+            sigmas: [price_uncertainty, cost_uncertainty, production_uncertainty],
+            bounds: [None; 3],
+            dependence: None,
+            strata: Vec::new(),
+            combinations: 1,
+        })
+    }
+
+    fn with_options(mut self, options: &EconomicMonteCarloOptions) -> Option<Self> {
+        self.bounds = [
+            options.price_bounds.validated()?,
+            options.cost_bounds.validated()?,
+            options.production_bounds.validated()?,
+        ];
+
+        let mut marginals = [MarginalDistribution::Normal; 3];
+        for (marginal, &id) in marginals.iter_mut().zip(&options.marginals) {
+            *marginal = MarginalDistribution::from_id(id)?;
+        }
+        let copula = CopulaKind::from_id(options.copula)?;
+        let all_normal = marginals.iter().all(|&m| m == MarginalDistribution::Normal);
+        if copula != CopulaKind::Independent || !all_normal {
+            let correlation = if options.copula_correlation.is_null() {
+                None
+            } else {
+                Some(unsafe { std::slice::from_raw_parts(options.copula_correlation, 9) })
+            };
+            self.dependence = Some(Dependence {
+                copula: Copula::new(copula, correlation, options.clayton_theta)?,
+                marginals,
+            });
+        }
+
+        let specs = match (options.strata.is_null(), options.n_strata) {
+            (_, 0) => &[][..],
+            (true, _) => return None,
+            (false, n) => unsafe { std::slice::from_raw_parts(options.strata, n) },
+        };
+        if !specs.is_empty() && self.dependence.is_some() {
+            return None;
+        }
+        for (k, spec) in specs.iter().enumerate() {
+            let duplicate = specs[..k].iter().any(|other| other.factor == spec.factor && other.year == spec.year);
+            if spec.factor > PRODUCTION || spec.year == 0 || spec.strata == 0 || duplicate {
+                return None;
+            }
+            self.strata.push(Stratum { spec: *spec, radix: self.combinations });
+            self.combinations = self.combinations.checked_mul(spec.strata)?;
+        }
+        Some(self)
+    }
+
+    /// Whether the stratification fits a run of `iterations` over `years`.
+    fn stratification_fits(&self, iterations: usize, years: usize) -> bool {
+        iterations.is_multiple_of(self.combinations)
+            && self.strata.iter().all(|stratum| stratum.spec.year < years)
+    }
+
+    /// Draw `factor`'s multiplier from band `band` of `strata` equally likely
+    /// quantile bands of its (possibly truncated) normal distribution.
+    fn draw_stratified(&self, factor: usize, band: usize, strata: usize, rng: &mut StdRng) -> f64 {
+        let sigma = self.sigmas[factor];
+        let standard_normal = StandardNormal::new(0.0, 1.0).expect("standard normal parameters are valid");
+        let (low, high) = match self.bounds[factor] {
+            Some((lower, upper)) if sigma > 0.0 => (
+                standard_normal.cdf((lower - 1.0) / sigma),
+                standard_normal.cdf((upper - 1.0) / sigma),
+            ),
+            _ => (0.0, 1.0),
+        };
+
+        let u = (band as f64 + rng.gen::<f64>()) / strata as f64;
+        let u = (low + u * (high - low)).clamp(f64::EPSILON, 1.0 - f64::EPSILON);
+        let multiplier = 1.0 + sigma * standard_normal.inverse_cdf(u);
+        match self.bounds[factor] {
+            Some((lower, upper)) => multiplier.clamp(lower, upper),
+            None => multiplier,
+        }
+    }
+
+    /// Draw this year's (price, cost, production) multipliers jointly through
+    /// the copula, clamping each to its bounds.
+    fn draw_joint(&self, dependence: &Dependence, rng: &mut StdRng, tally: &mut DrawTally) -> [f64; 3] {
+        let uniforms = dependence.copula.sample(rng);
+        std::array::from_fn(|k| {
+            let multiplier = dependence.marginals[k].quantile(uniforms[k], self.sigmas[k]);
+            match self.bounds[k] {
+                Some((lower, upper)) if multiplier < lower || multiplier > upper => {
+                    tally.clamped += 1;
+                    multiplier.clamp(lower, upper)
+                }
+                _ => multiplier,
+            }
+        })
+    }
+
+    /// Draw one multiplier for `factor`, resampling until it falls inside the
+    /// factor's bounds and clamping once `MAX_REJECTIONS` draws have failed.
+    pub(crate) fn draw(&self, factor: usize, rng: &mut StdRng, tally: &mut DrawTally) -> f64 {
+        let multiplier = 1.0 + self.dists[factor].sample(rng);
+        let (lower, upper) = match self.bounds[factor] {
+            Some(bounds) => bounds,
+            None => return multiplier,
+        };
+
+        let mut multiplier = multiplier;
+        for _ in 0..MAX_REJECTIONS {
+            if multiplier >= lower && multiplier <= upper {
+                return multiplier;
+            }
+            tally.rejected += 1;
+            multiplier = 1.0 + self.dists[factor].sample(rng);
+        }
+        if multiplier < lower || multiplier > upper {
+            tally.clamped += 1;
+        }
+        multiplier.clamp(lower, upper)
+    }
+
+    /// Perturb every operating year of `values` and write the undiscounted
+    /// flows into `flows`. `iteration` selects the stratification bands.
+    ///
+    /// Returns the average price, cost and production multiplier drawn for
+    /// this realization (1.0 for a factor that applied to no year).
+    fn apply(&self, values: &[f64], iteration: usize, rng: &mut StdRng, flows: &mut Vec<f64>, tally: &mut DrawTally) -> [f64; 3] {
+        let combination = iteration % self.combinations;
+
+        let mut sums = [0.0; 3];
+        let mut counts = [0usize; 3];
+
+        flows.clear();
+        flows.extend(values.iter().enumerate().map(|(i, &value)| {
+            if i == 0 {
+                // Initial investment - no uncertainty applied
+                value
+            } else {
+                let joint = self.dependence.as_ref().map(|dep| self.draw_joint(dep, rng, tally));
+                let multiplier = |factor: usize, rng: &mut StdRng, tally: &mut DrawTally| {
+                    if let Some(joint) = joint {
+                        return joint[factor];
+                    }
+                    let stratum = self.strata.iter().find(|s| s.spec.factor == factor && s.spec.year == i);
+                    match stratum {
+                        Some(&Stratum { spec, radix }) => {
+                            let band = (combination / radix) % spec.strata;
+                            self.draw_stratified(factor, band, spec.strata, rng)
+                        }
+                        None => self.draw(factor, rng, tally),
+                    }
+                };
+
+                let production = multiplier(PRODUCTION, rng, tally);
+                sums[PRODUCTION] += production;
+                counts[PRODUCTION] += 1;
+                if value > 0.0 {
+                    // Apply price uncertainty to positive cash flows (revenue)
+                    let price = multiplier(PRICE, rng, tally);
+                    sums[PRICE] += price;
+                    counts[PRICE] += 1;
+                    adjust_operating_flow(value, price, 1.0, production)
+                } else {
+                    // Apply cost uncertainty to negative cash flows (costs)
+                    let cost = multiplier(COST, rng, tally);
+                    sums[COST] += cost;
+                    counts[COST] += 1;
+                    adjust_operating_flow(value, 1.0, cost, production)
+                }
+            }
+        }));
+
+        std::array::from_fn(|k| if counts[k] == 0 { 1.0 } else { sums[k] / counts[k] as f64 })
+    }
+}
+
+/// A discrete event such as an unplanned equipment rebuild or a permanent
+/// loss of the protein premium.
+///
+/// From `earliest_year` on, the event fires in each year with probability
+/// `annual_probability` until it has fired once. A one-shot event adds
+/// `impact` to the year it fires; a persistent one adds it to that year and
+/// every later year. Impacts are added after the price/cost/production
+/// shocks and before discounting.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RiskEvent {
+    pub annual_probability: f64,
+    pub impact: f64,
+    pub persistent: bool,
+    pub earliest_year: usize,
+}
+
+impl RiskEvent {
+    fn is_valid(&self) -> bool {
+        (0.0..=1.0).contains(&self.annual_probability) && self.impact.is_finite()
+    }
+
+    /// Sample this event for one realization, returning whether it fired.
+    fn apply(&self, rng: &mut StdRng, flows: &mut [f64]) -> bool {
+        for year in self.earliest_year..flows.len() {
+            if rng.gen_bool(self.annual_probability) {
+                if self.persistent {
+                    flows[year..].iter_mut().for_each(|flow| *flow += self.impact);
+                } else {
+                    flows[year] += self.impact;
+                }
+                return true;
+            }
+        }
+        false
+    }
+}
+
+struct IterationOutcome {
+    npv: f64,
+    irr: Option<f64>,
+    payback: Option<f64>,
+    /// Average (price, cost, production) multiplier of this realization
+    factors: [f64; 3],
+    tally: DrawTally,
+    /// Which risk events fired, in the order they were supplied
+    fired: Vec<bool>,
+}
+
+fn discount_factors(len: usize, discount_rate: f64) -> Vec<f64> {
+    (0..len).map(|i| (1.0 + discount_rate).powi(i as i32)).collect()
+}
+
+/// Progress callback: `(completed, total, user_data) -> keep_going`.
+///
+/// Invoked from rayon worker threads, so it may run on a different thread
+/// each time, but calls are serialized and `completed` never decreases.
+/// Returning false stops any further chunks from being scheduled.
+pub type ProgressCallback = extern "C" fn(completed: u64, total: u64, user_data: *mut c_void) -> bool;
+
+// Iterations per chunk when no progress interval is configured
+const DEFAULT_CHUNK_SIZE: usize = 1024;
+
+/// Reports completed chunks to the caller and records stop requests.
+struct ProgressReporter<'a> {
+    callback: Option<ProgressCallback>,
+    user_data: *mut c_void,
+    completed: Mutex<u64>,
+    total: u64,
+    stop: AtomicBool,
+    cancel: CancelFlag<'a>,
+}
+
+// The user data pointer is only ever handed back to the caller's callback,
+// which is documented as being invoked from worker threads.
+unsafe impl Sync for ProgressReporter<'_> {}
+
+impl ProgressReporter<'_> {
+    fn new(options: &EconomicMonteCarloOptions, total: usize) -> Self {
+        Self {
+            callback: options.progress_callback,
+            user_data: options.progress_user_data,
+            completed: Mutex::new(0),
+            total: total as u64,
+            stop: AtomicBool::new(false),
+            cancel: CancelFlag::from_ptr(options.cancel_flag),
+        }
+    }
+
+    fn stopped(&self) -> bool {
+        self.stop.load(Ordering::Relaxed) || self.cancel.is_set()
+    }
+
+    fn chunk_done(&self, iterations: usize) {
+        let mut completed = self.completed.lock().unwrap_or_else(|e| e.into_inner());
+        *completed += iterations as u64;
+        if let Some(callback) = self.callback {
+            if !callback(*completed, self.total, self.user_data) {
+                self.stop.store(true, Ordering::Relaxed);
+            }
+        }
+    }
+}
+
+/// Everything a simulation run needs apart from progress reporting.
+struct Simulation<'a> {
+    values: &'a [f64],
+    iterations: usize,
+    dists: &'a ShockDistributions,
+    risk_events: &'a [RiskEvent],
+    seed: u64,
+    discount_rate: f64,
+    chunk_size: usize,
+    /// Skip the IRR and payback of each iteration
+    npv_only: bool,
+}
+
+impl Simulation<'_> {
+    /// Run every iteration in parallel, in chunks of `chunk_size`. Each
+    /// iteration is seeded with `seed + iteration` so results do not depend
+    /// on thread scheduling or chunking.
+    ///
+    /// Returns None when the run was cancelled or the progress callback
+    /// asked to stop before every chunk ran.
+    fn run(&self, progress: &ProgressReporter) -> Option<Vec<IterationOutcome>> {
+        let Simulation { values, iterations, dists, risk_events, seed, discount_rate, chunk_size, npv_only } = *self;
+        let factors = discount_factors(values.len(), discount_rate);
+        let n_chunks = iterations.div_ceil(chunk_size);
+
+        let chunks: Vec<Option<Vec<IterationOutcome>>> = (0..n_chunks).into_par_iter().map(|chunk| {
+            if progress.stopped() {
+                return None;
+            }
+
+            let start = chunk * chunk_size;
+            let end = (start + chunk_size).min(iterations);
+            let mut flows = Vec::with_capacity(values.len());
+
+            let outcomes: Vec<IterationOutcome> = (start..end).map(|i| {
+                // Create a unique seed for each iteration
+                let iteration_seed = seed.wrapping_add(i as u64);
+                let mut rng = StdRng::seed_from_u64(iteration_seed);
+
+                let mut tally = DrawTally::default();
+                let factors_drawn = dists.apply(values, i, &mut rng, &mut flows, &mut tally);
+                // Events draw after the shocks so runs without events keep their sequence
+                let fired = risk_events.iter().map(|event| event.apply(&mut rng, &mut flows)).collect();
+                let discounted = flows.iter().zip(&factors).map(|(cf, df)| cf / df);
+                let (npv, irr, payback) = if npv_only {
+                    (discounted.fold(0.0, |npv, flow| npv + flow), None, None)
+                } else {
+                    let (npv, payback) = npv_and_payback(discounted);
+                    // The NPV at the discount rate doubles as the IRR solver's starting bracket
+                    (npv, calculate_irr_with_hint(&flows, discount_rate, npv), payback)
+                };
+
+                IterationOutcome { npv, irr, payback, factors: factors_drawn, tally, fired }
+            }).collect();
+
+            progress.chunk_done(outcomes.len());
+            Some(outcomes)
+        }).collect();
+
+        let mut outcomes = Vec::with_capacity(iterations);
+        for chunk in chunks {
+            outcomes.extend(chunk?);
+        }
+        Some(outcomes)
+    }
+}
+
+/// Population mean and standard deviation.
+///
+/// Summed sequentially so the result does not depend on how rayon splits work.
+pub(crate) fn mean_and_std(samples: &[f64]) -> (f64, f64) {
+    let n = samples.len() as f64;
+    let mean = samples.iter().sum::<f64>() / n;
+    let variance = samples.iter()
+        .map(|&x| (x - mean).powi(2))
+        .sum::<f64>() / n;
+    (mean, variance.sqrt())
+}
+
+/// Mean, std dev, P10, P50 and P90 of the samples, NaN when there are none.
+fn summarize(mut samples: Vec<f64>) -> [f64; 5] {
+    if samples.is_empty() {
+        return [f64::NAN; 5];
+    }
+    samples.sort_unstable_by(f64::total_cmp);
+    let (mean, std_dev) = mean_and_std(&samples);
+    [
+        mean,
+        std_dev,
+        percentile_sorted(&samples, 0.1),
+        percentile_sorted(&samples, 0.5),
+        percentile_sorted(&samples, 0.9),
+    ]
+}
+
+/// Fractional ranks (ties share their average rank).
+fn ranks(samples: &[f64]) -> Vec<f64> {
+    let mut order: Vec<usize> = (0..samples.len()).collect();
+    order.sort_unstable_by(|&a, &b| samples[a].total_cmp(&samples[b]));
+
+    let mut ranks = vec![0.0; samples.len()];
+    let mut start = 0;
+    while start < order.len() {
+        let mut end = start + 1;
+        while end < order.len() && samples[order[end]] == samples[order[start]] {
+            end += 1;
+        }
+        let rank = (start + end - 1) as f64 / 2.0;
+        for &idx in &order[start..end] {
+            ranks[idx] = rank;
+        }
+        start = end;
+    }
+    ranks
+}
+
+/// Pearson correlation, 0.0 when either series is constant.
+fn correlation(x: &[f64], y: &[f64]) -> f64 {
+    let (mean_x, std_x) = mean_and_std(x);
+    let (mean_y, std_y) = mean_and_std(y);
+    if std_x == 0.0 || std_y == 0.0 {
+        return 0.0;
+    }
+    let covariance = x.iter().zip(y)
+        .map(|(a, b)| (a - mean_x) * (b - mean_y))
+        .sum::<f64>() / x.len() as f64;
+    covariance / (std_x * std_y)
+}
+
+/// Share of NPV variance (%) attributed to price, cost and production,
+/// from squared Spearman rank correlations. NaN when NPV does not vary.
+fn variance_contributions(outcomes: &[IterationOutcome]) -> [f64; 3] {
+    let npv_ranks = ranks(&outcomes.iter().map(|o| o.npv).collect::<Vec<_>>());
+    let squared: [f64; 3] = std::array::from_fn(|k| {
+        let factor_ranks = ranks(&outcomes.iter().map(|o| o.factors[k]).collect::<Vec<_>>());
+        correlation(&factor_ranks, &npv_ranks).powi(2)
+    });
+
+    let total: f64 = squared.iter().sum();
+    if total <= 0.0 {
+        return [f64::NAN; 3];
+    }
+    squared.map(|s| 100.0 * s / total)
+}
+
+/// Two-sided standard normal quantile for a confidence level, treating 0 as
+/// the default 95%.
+fn z_score(confidence_level: f64) -> Option<f64> {
+    let level = if confidence_level == 0.0 { 0.95 } else { confidence_level };
+    if !(level > 0.0 && level < 1.0) {
+        return None;
+    }
+    let standard_normal = StandardNormal::new(0.0, 1.0).ok()?;
+    Some(standard_normal.inverse_cdf(0.5 + level / 2.0))
+}
+
+/// Linearly interpolated percentile of an ascending slice, `p` in [0, 1].
+pub(crate) fn percentile_sorted(sorted: &[f64], p: f64) -> f64 {
+    if sorted.is_empty() {
+        return f64::NAN;
+    }
+    let rank = p * (sorted.len() - 1) as f64;
+    let lower = rank.floor() as usize;
+    let upper = rank.ceil() as usize;
+    sorted[lower] + (sorted[upper] - sorted[lower]) * (rank - lower as f64)
+}
+
+/// NPV mean, standard deviation, min and max written to `results[0..4]`,
+/// from a `run_economic_monte_carlo_v2` run with `npv_only` set.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to at least 4 f64 values
+#[deprecated(note = "use run_economic_monte_carlo_v2, which writes an EconomicMonteCarloResult")]
 #[no_mangle]
 pub extern "C" fn run_economic_monte_carlo(
     base_values: *const f64,
@@ -14,73 +779,911 @@ pub extern "C" fn run_economic_monte_carlo(
     discount_rate: f64,
     results: *mut f64
 ) -> bool {
-    // Safety checks
-    if base_values.is_null() || results.is_null() || len == 0 || iterations == 0 {
+    if results.is_null() {
         return false;
     }
 
+    // Only the NPV statistics are returned, so skip the IRR and payback
+    let options = EconomicMonteCarloOptions { npv_only: true, ..Default::default() };
+    let mut result = EconomicMonteCarloResult::default();
+    let status = run_economic_monte_carlo_v2(
+        base_values,
+        len,
+        iterations,
+        price_uncertainty,
+        cost_uncertainty,
+        production_uncertainty,
+        seed,
+        discount_rate,
+        &mut result,
+        &options,
+    );
+    if status != RunStatus::Ok {
+        return false;
+    }
+
+    let results = unsafe { std::slice::from_raw_parts_mut(results, 4) };
+    results.copy_from_slice(&[result.mean, result.std_dev, result.min, result.max]);
+    true
+}
+
+/// Size of the results buffer expected by `run_economic_monte_carlo_extended`.
+#[no_mangle]
+pub extern "C" fn economic_monte_carlo_extended_len() -> usize {
+    EXTENDED_RESULT_LEN
+}
+
+/// Default options for `run_economic_monte_carlo_extended`.
+#[no_mangle]
+pub extern "C" fn economic_monte_carlo_default_options() -> EconomicMonteCarloOptions {
+    EconomicMonteCarloOptions::default()
+}
+
+/// Size in bytes of `EconomicMonteCarloResult`.
+#[no_mangle]
+pub extern "C" fn economic_monte_carlo_result_size() -> usize {
+    std::mem::size_of::<EconomicMonteCarloResult>()
+}
+
+/// Layout version of `EconomicMonteCarloResult`.
+#[no_mangle]
+pub extern "C" fn economic_monte_carlo_result_version() -> u32 {
+    ECONOMIC_MONTE_CARLO_RESULT_VERSION
+}
+
+/// Validated settings shared by the single-project and batch entry points.
+struct ExtendedRun {
+    options: EconomicMonteCarloOptions,
+    dists: ShockDistributions,
+    risk_events: Vec<RiskEvent>,
+    z: f64,
+    chunk_size: usize,
+}
+
+// The raw pointers in the options are the caller's cancellation flag and
+// callback user data, both documented as shared with worker threads.
+unsafe impl Sync for ExtendedRun {}
+
+impl ExtendedRun {
+    fn new(uncertainties: [f64; 3], options: EconomicMonteCarloOptions) -> Option<Self> {
+        let [price, cost, production] = uncertainties;
+        let dists = ShockDistributions::new(price, cost, production)?.with_options(&options)?;
+        let z = z_score(options.confidence_level)?;
+        let chunk_size = match options.progress_interval {
+            0 => DEFAULT_CHUNK_SIZE,
+            interval => interval as usize,
+        };
+
+        let risk_events = match (options.risk_events.is_null(), options.n_risk_events) {
+            (_, 0) => Vec::new(),
+            (true, _) => return None,
+            (false, n) => unsafe { std::slice::from_raw_parts(options.risk_events, n) }.to_vec(),
+        };
+        if !risk_events.iter().all(RiskEvent::is_valid) {
+            return None;
+        }
+
+        Some(Self { options, dists, risk_events, z, chunk_size })
+    }
+
+    /// Simulate one project, filling `result` only when the run completes.
+    fn execute(&self, values: &[f64], iterations: usize, seed: u64, discount_rate: f64, result: &mut EconomicMonteCarloResult) -> RunStatus {
+        let options = &self.options;
+        if !self.dists.stratification_fits(iterations, values.len()) {
+            return RunStatus::InvalidInput;
+        }
+        let simulation = Simulation {
+            values,
+            iterations,
+            dists: &self.dists,
+            risk_events: &self.risk_events,
+            seed,
+            discount_rate,
+            chunk_size: self.chunk_size,
+            npv_only: options.npv_only,
+        };
+        let progress = ProgressReporter::new(options, iterations);
+        let outcomes = match simulation.run(&progress) {
+            Some(outcomes) => outcomes,
+            None => return RunStatus::Cancelled,
+        };
+
+        let simulated_npvs: Vec<f64> = outcomes.iter().map(|o| o.npv).collect();
+        let (npv_mean, npv_std) = mean_and_std(&simulated_npvs);
+        result.mean = npv_mean;
+        result.std_dev = npv_std;
+        result.min = simulated_npvs.iter().copied().fold(f64::INFINITY, f64::min);
+        result.max = simulated_npvs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+
+        let irrs: Vec<f64> = outcomes.iter().filter_map(|o| o.irr).collect();
+        let irr_failures = iterations - irrs.len();
+        [result.irr_mean, result.irr_std_dev, result.irr_p10, result.irr_p50, result.irr_p90] = summarize(irrs);
+        result.irr_failure_rate = irr_failures as f64 / iterations as f64;
+
+        let paybacks: Vec<f64> = outcomes.iter().filter_map(|o| o.payback).collect();
+        let never_paid_back = iterations - paybacks.len();
+        [result.payback_mean, _, result.payback_p10, result.payback_p50, result.payback_p90] = summarize(paybacks);
+        result.never_paid_back = never_paid_back as f64 / iterations as f64;
+        if options.npv_only {
+            result.irr_failure_rate = f64::NAN;
+            result.never_paid_back = f64::NAN;
+        }
+
+        let contributions = if options.variance_contributions {
+            variance_contributions(&outcomes)
+        } else {
+            [f64::NAN; 3]
+        };
+        [result.price_contribution, result.cost_contribution, result.production_contribution] = contributions;
+
+        let moments = RunningStats::from_samples(&simulated_npvs);
+        result.skewness = moments.skewness();
+        result.excess_kurtosis = moments.excess_kurtosis();
+
+        if !options.npv_running_stats.is_null() {
+            let output = unsafe { &mut *options.npv_running_stats };
+            *output = if options.accumulate_running_stats { output.merge(&moments) } else { moments };
+        }
+
+        // z-based interval: the mean of many iterations is close to normal
+        let standard_error = npv_std / (iterations as f64).sqrt();
+        let half_width = self.z * standard_error;
+        result.standard_error = standard_error;
+        result.ci_lower = npv_mean - half_width;
+        result.ci_upper = npv_mean + half_width;
+
+        result.rejected_draws = outcomes.iter().map(|o| o.tally.rejected).sum::<u64>() as f64;
+        result.clamped_draws = outcomes.iter().map(|o| o.tally.clamped).sum::<u64>() as f64;
+
+        if !options.risk_event_fire_rates.is_null() {
+            let rates = unsafe { std::slice::from_raw_parts_mut(options.risk_event_fire_rates, self.risk_events.len()) };
+            for (k, rate) in rates.iter_mut().enumerate() {
+                let fired = outcomes.iter().filter(|o| o.fired[k]).count();
+                *rate = fired as f64 / iterations as f64;
+            }
+        }
+
+        RunStatus::Ok
+    }
+}
+
+fn options_or_default(options: *const EconomicMonteCarloOptions) -> EconomicMonteCarloOptions {
+    if options.is_null() {
+        EconomicMonteCarloOptions::default()
+    } else {
+        unsafe { *options }
+    }
+}
+
+/// Economic Monte Carlo reporting the NPV, IRR and discounted payback
+/// distributions into an `EconomicMonteCarloResult`.
+///
+/// Returns `RunStatus::InvalidInput` for bad pointers or parameters and
+/// `RunStatus::Cancelled` when stopped early, in which case `result` is left
+/// untouched.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - result points to a valid `EconomicMonteCarloResult`
+/// - options is null or points to a valid `EconomicMonteCarloOptions`
+#[no_mangle]
+pub extern "C" fn run_economic_monte_carlo_v2(
+    base_values: *const f64,
+    len: usize,
+    iterations: usize,
+    price_uncertainty: f64,
+    cost_uncertainty: f64,
+    production_uncertainty: f64,
+    seed: u64,
+    discount_rate: f64,
+    result: *mut EconomicMonteCarloResult,
+    options: *const EconomicMonteCarloOptions
+) -> RunStatus {
+    if base_values.is_null() || result.is_null() || len == 0 || iterations == 0 {
+        return RunStatus::InvalidInput;
+    }
+
     let values = unsafe { std::slice::from_raw_parts(base_values, len) };
-    let mut simulated_npvs = Vec::with_capacity(iterations);
-    
-    // Create distributions for each uncertainty type
-    let price_dist = match Normal::new(0.0, price_uncertainty) {
-        Ok(dist) => dist,
-        Err(_) => return false,
+    let result = unsafe { &mut *result };
+
+    let uncertainties = [price_uncertainty, cost_uncertainty, production_uncertainty];
+    let run = match ExtendedRun::new(uncertainties, options_or_default(options)) {
+        Some(run) => run,
+        None => return RunStatus::InvalidInput,
     };
-    let cost_dist = match Normal::new(0.0, cost_uncertainty) {
-        Ok(dist) => dist,
-        Err(_) => return false,
+    with_thread_limit(run.options.max_threads, || run.execute(values, iterations, seed, discount_rate, result))
+        .unwrap_or(RunStatus::InvalidInput)
+}
+
+/// Same as `run_economic_monte_carlo_v2`, writing the result as a flat
+/// array; see `EXTENDED_RESULT_LEN` for the layout.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length results_len, which
+///   must be at least `economic_monte_carlo_extended_len()`
+/// - options is null or points to a valid `EconomicMonteCarloOptions`
+#[no_mangle]
+pub extern "C" fn run_economic_monte_carlo_extended(
+    base_values: *const f64,
+    len: usize,
+    iterations: usize,
+    price_uncertainty: f64,
+    cost_uncertainty: f64,
+    production_uncertainty: f64,
+    seed: u64,
+    discount_rate: f64,
+    results: *mut f64,
+    results_len: usize,
+    options: *const EconomicMonteCarloOptions
+) -> RunStatus {
+    if base_values.is_null() || results.is_null() || len == 0 || iterations == 0
+        || results_len < EXTENDED_RESULT_LEN
+    {
+        return RunStatus::InvalidInput;
+    }
+
+    let mut result = EconomicMonteCarloResult::default();
+    let status = run_economic_monte_carlo_v2(
+        base_values,
+        len,
+        iterations,
+        price_uncertainty,
+        cost_uncertainty,
+        production_uncertainty,
+        seed,
+        discount_rate,
+        &mut result,
+        options,
+    );
+    if status == RunStatus::Ok {
+        let results = unsafe { std::slice::from_raw_parts_mut(results, EXTENDED_RESULT_LEN) };
+        results.copy_from_slice(&result.to_array());
+    }
+    status
+}
+
+/// Seed offset between consecutive projects of a batch, so adding a project
+/// never changes the draws of the others.
+pub const BATCH_SEED_STRIDE: u64 = 1_000_000_000;
+
+/// Run the extended Monte Carlo for several projects in one call.
+///
+/// `cash_flows` is a row-major n_projects x years matrix. Project `p` is
+/// simulated exactly like `run_economic_monte_carlo_extended` with seed
+/// `seed + p * BATCH_SEED_STRIDE` and writes its `EXTENDED_RESULT_LEN` block
+/// at `results[p * EXTENDED_RESULT_LEN..]`.
+///
+/// `uncertainties` is either null, to use the shared price/cost/production
+/// uncertainties for every project, or a row-major n_projects x 3 matrix of
+/// per-project overrides. Projects run in parallel; the progress callback,
+/// risk event fire-rate output and running-stats output in `options` are
+/// ignored, but the cancellation flag stops the whole batch.
+///
+/// # Safety
+/// - cash_flows points to a valid array of f64 with length n_projects * years
+/// - uncertainties is null or points to n_projects * 3 f64 values
+/// - results points to a valid array of f64 with length results_len, which
+///   must be at least n_projects * `economic_monte_carlo_extended_len()`
+/// - options is null or points to a valid `EconomicMonteCarloOptions`
+#[no_mangle]
+pub extern "C" fn run_economic_monte_carlo_batch(
+    cash_flows: *const f64,
+    n_projects: usize,
+    years: usize,
+    iterations: usize,
+    price_uncertainty: f64,
+    cost_uncertainty: f64,
+    production_uncertainty: f64,
+    uncertainties: *const f64,
+    seed: u64,
+    discount_rate: f64,
+    results: *mut f64,
+    results_len: usize,
+    options: *const EconomicMonteCarloOptions
+) -> RunStatus {
+    if cash_flows.is_null() || results.is_null() || n_projects == 0 || years == 0 || iterations == 0
+        || results_len < n_projects * EXTENDED_RESULT_LEN
+    {
+        return RunStatus::InvalidInput;
+    }
+
+    let matrix = unsafe { std::slice::from_raw_parts(cash_flows, n_projects * years) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, n_projects * EXTENDED_RESULT_LEN) };
+    let overrides = if uncertainties.is_null() {
+        None
+    } else {
+        Some(unsafe { std::slice::from_raw_parts(uncertainties, n_projects * 3) })
     };
-    let production_dist = match Normal::new(0.0, production_uncertainty) {
-        Ok(dist) => dist,
-        Err(_) => return false,
+
+    let mut options = options_or_default(options);
+    options.progress_callback = None;
+    options.risk_event_fire_rates = std::ptr::null_mut();
+    options.npv_running_stats = std::ptr::null_mut();
+
+    // Validate every project before simulating any of them
+    let runs: Option<Vec<ExtendedRun>> = (0..n_projects).map(|p| {
+        let project_uncertainties = match overrides {
+            Some(rows) => [rows[p * 3], rows[p * 3 + 1], rows[p * 3 + 2]],
+            None => [price_uncertainty, cost_uncertainty, production_uncertainty],
+        };
+        ExtendedRun::new(project_uncertainties, options)
+    }).collect();
+    let runs = match runs {
+        Some(runs) => runs,
+        None => return RunStatus::InvalidInput,
     };
 
-    simulated_npvs.par_extend(
-        (0..iterations).into_par_iter().map(|i| {
-            // Create a unique seed for each iteration
-            let iteration_seed = seed.wrapping_add(i as u64);
-            let mut rng = StdRng::seed_from_u64(iteration_seed);
-            
-            values.iter().enumerate().map(|(i, &value)| {
-                if i == 0 {
-                    // Initial investment - no uncertainty applied
-                    value
-                } else {
-                    let production_var = production_dist.sample(&mut rng);
-                    let adjusted_value = if value > 0.0 {
-                        // Apply price uncertainty to positive cash flows (revenue)
-                        let price_var = price_dist.sample(&mut rng);
-                        value * (1.0 + price_var) * (1.0 + production_var)
-                    } else {
-                        // Apply cost uncertainty to negative cash flows (costs)
-                        let cost_var = cost_dist.sample(&mut rng);
-                        value * (1.0 + cost_var) * (1.0 + production_var)
-                    };
-                    adjusted_value / ((1.0 + discount_rate).powi(i as i32))
+    let statuses = with_thread_limit(options.max_threads, || {
+        matrix.par_chunks(years)
+            .zip(results.par_chunks_mut(EXTENDED_RESULT_LEN))
+            .zip(runs.par_iter())
+            .enumerate()
+            .map(|(p, ((values, block), run))| {
+                let project_seed = seed.wrapping_add((p as u64).wrapping_mul(BATCH_SEED_STRIDE));
+                let mut result = EconomicMonteCarloResult::default();
+                let status = run.execute(values, iterations, project_seed, discount_rate, &mut result);
+                if status == RunStatus::Ok {
+                    block.copy_from_slice(&result.to_array());
                 }
-            }).sum::<f64>()
-        })
-    );
-    
-    // Calculate statistics
-    let mean = simulated_npvs.par_iter().sum::<f64>() / iterations as f64;
-    let variance = simulated_npvs.par_iter()
-        .map(|&x| (x - mean).powi(2))
-        .sum::<f64>() / iterations as f64;
-    let std_dev = variance.sqrt();
-    
-    // Find min and max values
-    let min_val = simulated_npvs.iter().copied().fold(f64::INFINITY, f64::min);
-    let max_val = simulated_npvs.iter().copied().fold(f64::NEG_INFINITY, f64::max);
-    
-    // Store results safely
-    unsafe {
-        *results.offset(0) = mean;
-        *results.offset(1) = std_dev;
-        *results.offset(2) = min_val;
-        *results.offset(3) = max_val;
-    }
-    
-    true
-} 
\ No newline at end of file
+                status
+            })
+            .collect::<Vec<RunStatus>>()
+    });
+    let statuses = match statuses {
+        Some(statuses) => statuses,
+        None => return RunStatus::InvalidInput,
+    };
+
+    statuses.into_iter()
+        .find(|&status| status != RunStatus::Ok)
+        .unwrap_or(RunStatus::Ok)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::economic::calculate_irr;
+
+    const FLOWS: [f64; 6] = [-1000.0, 250.0, 300.0, 320.0, 340.0, 360.0];
+
+    fn run_v2(options: &EconomicMonteCarloOptions) -> EconomicMonteCarloResult {
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(FLOWS.as_ptr(), FLOWS.len(), 2000, 0.1, 0.1, 0.05, 42, 0.08, &mut result, options);
+        assert_eq!(status, RunStatus::Ok);
+        result
+    }
+
+    #[test]
+    #[allow(deprecated)]
+    fn deprecated_shim_reports_the_v2_npv_statistics() {
+        let full = run_v2(&EconomicMonteCarloOptions::default());
+        let mut results = [0.0; 4];
+        assert!(run_economic_monte_carlo(FLOWS.as_ptr(), FLOWS.len(), 2000, 0.1, 0.1, 0.05, 42, 0.08, results.as_mut_ptr()));
+        assert_eq!(results, [full.mean, full.std_dev, full.min, full.max]);
+        assert!(!run_economic_monte_carlo(FLOWS.as_ptr(), FLOWS.len(), 2000, 0.1, 0.1, 0.05, 42, 0.08, std::ptr::null_mut()));
+    }
+
+    #[test]
+    fn npv_only_skips_irr_and_payback() {
+        let full = run_v2(&EconomicMonteCarloOptions::default());
+        let npv_only = run_v2(&EconomicMonteCarloOptions { npv_only: true, ..Default::default() });
+        assert_eq!(full.to_array()[..4], npv_only.to_array()[..4]);
+        assert!(full.irr_mean.is_finite() && full.payback_mean.is_finite());
+        assert!(npv_only.irr_mean.is_nan() && npv_only.irr_failure_rate.is_nan());
+        assert!(npv_only.payback_p50.is_nan() && npv_only.never_paid_back.is_nan());
+    }
+
+    const ANNUITY: [f64; 6] = [-1000.0, 300.0, 300.0, 300.0, 300.0, 300.0];
+
+    fn simulate(flows: &[f64], iterations: usize, sigma: f64, seed: u64, options: &EconomicMonteCarloOptions) -> EconomicMonteCarloResult {
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), iterations, sigma, sigma, sigma, seed, 0.08, &mut result, options);
+        assert_eq!(status, RunStatus::Ok);
+        result
+    }
+
+    #[test]
+    fn median_irr_is_near_the_deterministic_irr_for_small_shocks() {
+        let mut deterministic = 0.0;
+        assert!(calculate_irr(ANNUITY.as_ptr(), ANNUITY.len(), &mut deterministic));
+        let result = simulate(&ANNUITY, 2000, 0.01, 42, &Default::default());
+        assert!((result.irr_p50 - deterministic).abs() < 0.005, "{} vs {deterministic}", result.irr_p50);
+        assert!(result.irr_p10 < result.irr_p50 && result.irr_p50 < result.irr_p90);
+        assert_eq!(result.irr_failure_rate, 0.0);
+    }
+
+    #[test]
+    fn irr_failures_are_reported_as_a_fraction() {
+        // Never negative, so no iteration has an IRR
+        let result = simulate(&[100.0, 50.0, 50.0], 200, 0.1, 1, &Default::default());
+        assert_eq!(result.irr_failure_rate, 1.0);
+        assert!(result.irr_mean.is_nan() && result.irr_p50.is_nan());
+        assert!(result.mean.is_finite());
+    }
+
+    #[test]
+    fn zero_uncertainty_reproduces_the_discounted_payback() {
+        let mut payback = 0.0;
+        assert!(crate::economic::calculate_discounted_payback(ANNUITY.as_ptr(), ANNUITY.len(), 0.08, &mut payback));
+        let result = simulate(&ANNUITY, 100, 0.0, 42, &Default::default());
+        assert_eq!([result.payback_p10, result.payback_p50, result.payback_p90], [payback; 3]);
+        assert!((result.payback_mean - payback).abs() < 1e-12);
+        assert_eq!(result.never_paid_back, 0.0);
+    }
+
+    #[test]
+    fn payback_distribution_counts_projects_that_never_pay_back() {
+        // Barely pays back at 8%, so wide shocks push many iterations past the horizon
+        let result = simulate(&[-1000.0, 260.0, 260.0, 260.0, 260.0, 260.0], 2000, 0.3, 42, &Default::default());
+        assert!(result.never_paid_back > 0.0 && result.never_paid_back < 1.0);
+        assert!(result.payback_p10 <= result.payback_p50 && result.payback_p50 <= result.payback_p90);
+        assert!(result.payback_p90 <= 5.0);
+        assert_eq!(economic_monte_carlo_extended_len(), EXTENDED_RESULT_LEN);
+    }
+
+    #[test]
+    fn price_only_uncertainty_is_attributed_to_price() {
+        let options = EconomicMonteCarloOptions { variance_contributions: true, ..Default::default() };
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(ANNUITY.as_ptr(), ANNUITY.len(), 2000, 0.2, 0.0, 0.0, 42, 0.08, &mut result, &options);
+        assert_eq!(status, RunStatus::Ok);
+        assert!(result.price_contribution > 99.0, "{}", result.price_contribution);
+        let total = result.price_contribution + result.cost_contribution + result.production_contribution;
+        assert!((total - 100.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn variance_contributions_are_nan_unless_requested() {
+        let result = simulate(&FLOWS, 500, 0.2, 42, &Default::default());
+        assert!(result.price_contribution.is_nan() && result.cost_contribution.is_nan() && result.production_contribution.is_nan());
+        let requested = simulate(&FLOWS, 500, 0.2, 42, &EconomicMonteCarloOptions { variance_contributions: true, ..Default::default() });
+        assert!(requested.production_contribution > 0.0);
+        // The attribution does not change the draws
+        assert_eq!(requested.mean, result.mean);
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_statistics() {
+        let options = EconomicMonteCarloOptions::default();
+        let first = simulate(&FLOWS, 1000, 0.1, 7, &options);
+        let second = simulate(&FLOWS, 1000, 0.1, 7, &options);
+        assert_eq!([first.mean, first.std_dev, first.min, first.max], [second.mean, second.std_dev, second.min, second.max]);
+        // Iteration i draws from seed + i, so nearby seeds share most draws
+        let other = simulate(&FLOWS, 1000, 0.1, 1_000_007, &options);
+        assert_ne!(first.mean, other.mean);
+        assert_ne!(first.min, other.min);
+    }
+
+    fn price_only(flows: &[f64], sigma: f64, options: &EconomicMonteCarloOptions) -> EconomicMonteCarloResult {
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), 20000, sigma, 0.0, 0.0, 42, 0.08, &mut result, options);
+        assert_eq!(status, RunStatus::Ok);
+        result
+    }
+
+    #[test]
+    fn symmetric_shocks_give_no_skewness() {
+        let result = price_only(&[0.0, 1000.0], 0.2, &EconomicMonteCarloOptions::default());
+        assert!(result.skewness.abs() < 0.05, "{}", result.skewness);
+        assert!(result.excess_kurtosis.abs() < 0.1, "{}", result.excess_kurtosis);
+    }
+
+    #[test]
+    fn lognormal_shocks_skew_the_npv_right() {
+        let options = EconomicMonteCarloOptions {
+            marginals: [MarginalDistribution::LogNormal as u32, MarginalDistribution::Normal as u32, MarginalDistribution::Normal as u32],
+            ..Default::default()
+        };
+        let result = price_only(&[0.0, 1000.0], 0.5, &options);
+        assert!(result.skewness > 1.0, "{}", result.skewness);
+        assert!(result.excess_kurtosis > 1.0, "{}", result.excess_kurtosis);
+        assert!(result.min > 0.0);
+    }
+
+    #[test]
+    fn confidence_interval_shrinks_with_the_square_root_of_iterations() {
+        let flows = [-1000.0, 500.0, 500.0, 500.0];
+        let half_widths: Vec<f64> = [1000, 4000, 16000].iter().map(|&iterations| {
+            let result = simulate(&flows, iterations, 0.3, 42, &EconomicMonteCarloOptions::default());
+            assert_eq!(result.standard_error, result.std_dev / (iterations as f64).sqrt());
+            assert!((result.ci_upper + result.ci_lower - 2.0 * result.mean).abs() < 1e-9);
+            (result.ci_upper - result.ci_lower) / 2.0
+        }).collect();
+        for pair in half_widths.windows(2) {
+            let ratio = pair[0] / pair[1];
+            assert!((ratio - 2.0).abs() < 0.2, "{ratio}");
+        }
+    }
+
+    #[test]
+    fn confidence_level_sets_the_interval_width() {
+        let default = simulate(&FLOWS, 1000, 0.1, 42, &EconomicMonteCarloOptions::default());
+        let half_width = (default.ci_upper - default.ci_lower) / 2.0;
+        assert!((half_width / default.standard_error - 1.959964).abs() < 1e-5);
+        let wider = simulate(&FLOWS, 1000, 0.1, 42, &EconomicMonteCarloOptions { confidence_level: 0.99, ..Default::default() });
+        assert!((wider.ci_upper - wider.mean) / wider.standard_error > 2.57);
+
+        let mut result = EconomicMonteCarloResult::default();
+        for level in [1.0, 1.5, -0.5, f64::NAN] {
+            let options = EconomicMonteCarloOptions { confidence_level: level, ..Default::default() };
+            let status = run_economic_monte_carlo_v2(FLOWS.as_ptr(), FLOWS.len(), 10, 0.1, 0.1, 0.1, 42, 0.08, &mut result, &options);
+            assert_eq!(status, RunStatus::InvalidInput, "{level}");
+        }
+    }
+
+    fn bounded_production(lower: f64, upper: f64) -> EconomicMonteCarloOptions {
+        EconomicMonteCarloOptions {
+            production_bounds: ShockBounds { enabled: true, lower, upper },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn truncated_multipliers_stay_within_their_bounds() {
+        let options = bounded_production(0.0, 1.2);
+        let dists = ShockDistributions::new(0.0, 0.0, 0.5).unwrap().with_options(&options).unwrap();
+        let mut rng = StdRng::seed_from_u64(42);
+        let mut tally = DrawTally::default();
+        for _ in 0..10_000 {
+            let multiplier = dists.draw(PRODUCTION, &mut rng, &mut tally);
+            assert!((0.0..=1.2).contains(&multiplier), "{multiplier}");
+        }
+        assert!(tally.rejected > 0);
+        assert_eq!(tally.clamped, 0);
+
+        let flows = [-1000.0, 500.0, 500.0, 500.0];
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), 1000, 0.0, 0.0, 0.5, 42, 0.08, &mut result, &options);
+        assert_eq!(status, RunStatus::Ok);
+        let revenue: f64 = (1..4).map(|year| 500.0 / 1.08_f64.powi(year)).sum();
+        assert!(result.min >= -1000.0 && result.max <= -1000.0 + 1.2 * revenue + 1e-9);
+        assert!(result.rejected_draws > 0.0);
+    }
+
+    #[test]
+    fn inconsistent_sigma_is_reported_as_clamped_draws() {
+        let flows = [-1000.0, 500.0, 500.0, 500.0];
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), 1000, 0.0, 0.0, 0.5, 42, 0.08, &mut result, &bounded_production(5.0, 6.0));
+        assert_eq!(status, RunStatus::Ok);
+        assert_eq!(result.clamped_draws, 3000.0);
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), 1000, 0.0, 0.0, 0.5, 42, 0.08, &mut result, &bounded_production(6.0, 1.0));
+        assert_eq!(status, RunStatus::InvalidInput);
+    }
+
+    extern "C" fn record_progress(completed: u64, total: u64, user_data: *mut c_void) -> bool {
+        // Calls are serialized by the reporter
+        let calls = unsafe { &mut *(user_data as *mut Vec<(u64, u64)>) };
+        calls.push((completed, total));
+        true
+    }
+
+    extern "C" fn stop_after_300(completed: u64, _total: u64, _user_data: *mut c_void) -> bool {
+        completed < 300
+    }
+
+    #[test]
+    fn progress_increases_up_to_the_iteration_count() {
+        let mut calls: Vec<(u64, u64)> = Vec::new();
+        let options = EconomicMonteCarloOptions {
+            progress_callback: Some(record_progress),
+            progress_user_data: &mut calls as *mut Vec<(u64, u64)> as *mut c_void,
+            progress_interval: 100,
+            ..Default::default()
+        };
+        let reported = simulate(&FLOWS, 1050, 0.1, 42, &options);
+        assert_eq!(calls.len(), 11);
+        assert!(calls.windows(2).all(|pair| pair[0].0 < pair[1].0));
+        assert!(calls.iter().all(|&(_, total)| total == 1050));
+        assert_eq!(calls.last(), Some(&(1050, 1050)));
+
+        // Reporting does not change the results
+        let silent = simulate(&FLOWS, 1050, 0.1, 42, &EconomicMonteCarloOptions::default());
+        assert_eq!(reported.to_array()[..4], silent.to_array()[..4]);
+    }
+
+    #[test]
+    fn false_from_the_callback_cancels_the_run() {
+        let options = EconomicMonteCarloOptions {
+            progress_callback: Some(stop_after_300),
+            progress_interval: 100,
+            ..Default::default()
+        };
+        let mut result = EconomicMonteCarloResult { mean: 123.0, ..Default::default() };
+        let status = run_economic_monte_carlo_v2(FLOWS.as_ptr(), FLOWS.len(), 100_000, 0.1, 0.1, 0.1, 42, 0.08, &mut result, &options);
+        assert_eq!(status, RunStatus::Cancelled);
+        assert_eq!(result.mean, 123.0);
+    }
+
+    #[test]
+    fn cancel_flag_stops_a_long_run() {
+        let flag = AtomicU8::new(0);
+        let options = EconomicMonteCarloOptions { cancel_flag: &flag, ..Default::default() };
+        let mut result = EconomicMonteCarloResult { mean: 123.0, ..Default::default() };
+        // Far more iterations than could finish before the flag is raised
+        let status = std::thread::scope(|scope| {
+            scope.spawn(|| {
+                std::thread::sleep(std::time::Duration::from_millis(20));
+                flag.store(1, Ordering::Relaxed);
+            });
+            run_economic_monte_carlo_v2(FLOWS.as_ptr(), FLOWS.len(), 5_000_000, 0.1, 0.1, 0.1, 42, 0.08, &mut result, &options)
+        });
+        assert_eq!(status, RunStatus::Cancelled);
+        assert_eq!(result.mean, 123.0);
+    }
+
+    const PROJECTS: [f64; 12] = [
+        -1000.0, 500.0, 500.0, 500.0,
+        -800.0, 100.0, 400.0, 600.0,
+        -1.0, 1.0, 1.0, 1.0,
+    ];
+
+    fn batch(n_projects: usize, uncertainties: *const f64) -> Vec<f64> {
+        let mut results = vec![0.0; n_projects * EXTENDED_RESULT_LEN];
+        let status = run_economic_monte_carlo_batch(PROJECTS.as_ptr(), n_projects, 4, 3000, 0.1, 0.1, 0.1, uncertainties, 42, 0.08,
+            results.as_mut_ptr(), results.len(), std::ptr::null());
+        assert_eq!(status, RunStatus::Ok);
+        results
+    }
+
+    fn bits(values: &[f64]) -> Vec<u64> {
+        values.iter().map(|v| v.to_bits()).collect()
+    }
+
+    #[test]
+    fn batch_matches_single_project_runs_bit_for_bit() {
+        let uncertainties = [0.1, 0.1, 0.1, 0.2, 0.3, 0.1, 0.05, 0.0, 0.2];
+        let results = batch(3, uncertainties.as_ptr());
+        for (p, block) in results.chunks(EXTENDED_RESULT_LEN).enumerate() {
+            let mut single = [0.0; EXTENDED_RESULT_LEN];
+            let [price, cost, production] = [uncertainties[3 * p], uncertainties[3 * p + 1], uncertainties[3 * p + 2]];
+            let status = run_economic_monte_carlo_extended(PROJECTS[4 * p..].as_ptr(), 4, 3000, price, cost, production,
+                42 + p as u64 * BATCH_SEED_STRIDE, 0.08, single.as_mut_ptr(), EXTENDED_RESULT_LEN, std::ptr::null());
+            assert_eq!(status, RunStatus::Ok);
+            assert_eq!(bits(block), bits(&single), "project {p}");
+        }
+    }
+
+    #[test]
+    fn adding_a_project_keeps_the_others_results() {
+        let two = batch(2, std::ptr::null());
+        let three = batch(3, std::ptr::null());
+        assert_eq!(bits(&two), bits(&three[..two.len()]));
+
+        let mut short = vec![0.0; 2 * EXTENDED_RESULT_LEN - 1];
+        let status = run_economic_monte_carlo_batch(PROJECTS.as_ptr(), 2, 4, 10, 0.1, 0.1, 0.1, std::ptr::null(), 42, 0.08,
+            short.as_mut_ptr(), short.len(), std::ptr::null());
+        assert_eq!(status, RunStatus::InvalidInput);
+    }
+
+    fn with_events(events: &[RiskEvent], rates: &mut [f64]) -> EconomicMonteCarloResult {
+        let options = EconomicMonteCarloOptions {
+            risk_events: events.as_ptr(),
+            n_risk_events: events.len(),
+            risk_event_fire_rates: rates.as_mut_ptr(),
+            ..Default::default()
+        };
+        simulate(&ANNUITY, 3000, 0.1, 42, &options)
+    }
+
+    #[test]
+    fn certain_events_shift_the_mean_by_their_discounted_impact() {
+        let base = simulate(&ANNUITY, 3000, 0.1, 42, &EconomicMonteCarloOptions::default());
+        let events = [
+            RiskEvent { annual_probability: 1.0, impact: -200.0, persistent: false, earliest_year: 2 },
+            RiskEvent { annual_probability: 1.0, impact: -50.0, persistent: true, earliest_year: 4 },
+        ];
+        let mut rates = [f64::NAN; 2];
+        let result = with_events(&events, &mut rates);
+        assert_eq!(rates, [1.0, 1.0]);
+        let expected = -200.0 / 1.08_f64.powi(2) - 50.0 / 1.08_f64.powi(4) - 50.0 / 1.08_f64.powi(5);
+        assert!((result.mean - base.mean - expected).abs() < 1e-9);
+        assert!((result.std_dev - base.std_dev).abs() < 1e-9);
+    }
+
+    #[test]
+    fn impossible_events_change_nothing() {
+        let base = simulate(&ANNUITY, 3000, 0.1, 42, &EconomicMonteCarloOptions::default());
+        let events = [RiskEvent { annual_probability: 0.0, impact: -5000.0, persistent: true, earliest_year: 1 }];
+        let mut rates = [f64::NAN];
+        let result = with_events(&events, &mut rates);
+        assert_eq!(rates, [0.0]);
+        assert_eq!(bits(&result.to_array()), bits(&base.to_array()));
+    }
+
+    #[test]
+    fn fire_rate_tracks_the_annual_probability() {
+        // Five chances at 10% each: 1 - 0.9^5 of the iterations see the event
+        let events = [RiskEvent { annual_probability: 0.1, impact: -200.0, persistent: false, earliest_year: 1 }];
+        let mut rates = [f64::NAN];
+        with_events(&events, &mut rates);
+        assert!((rates[0] - (1.0 - 0.9_f64.powi(5))).abs() < 0.03, "{}", rates[0]);
+
+        let invalid = [RiskEvent { annual_probability: 1.5, impact: -1.0, persistent: false, earliest_year: 1 }];
+        let options = EconomicMonteCarloOptions { risk_events: invalid.as_ptr(), n_risk_events: 1, ..Default::default() };
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(ANNUITY.as_ptr(), ANNUITY.len(), 10, 0.1, 0.1, 0.1, 42, 0.08, &mut result, &options);
+        assert_eq!(status, RunStatus::InvalidInput);
+    }
+
+    fn accumulate(iterations: usize, seed: u64, stats: &mut RunningStats, accumulate_running_stats: bool) -> EconomicMonteCarloResult {
+        let options = EconomicMonteCarloOptions { npv_running_stats: stats, accumulate_running_stats, ..Default::default() };
+        simulate(&FLOWS, iterations, 0.1, seed, &options)
+    }
+
+    #[test]
+    fn merged_half_runs_match_one_full_run() {
+        let (mut first, mut second, mut full) = (RunningStats::default(), RunningStats::default(), RunningStats::default());
+        accumulate(5000, 11, &mut first, false);
+        // Iteration i uses seed + i, so the second half continues the first
+        accumulate(5000, 5011, &mut second, false);
+        let result = accumulate(10_000, 11, &mut full, false);
+        let merged = first.merge(&second);
+        assert_eq!(merged.count, 10_000);
+        assert!((merged.mean - full.mean).abs() < 1e-9 && (merged.std_dev() - full.std_dev()).abs() < 1e-9);
+        assert!((merged.mean - result.mean).abs() < 1e-9 && (merged.std_dev() - result.std_dev).abs() < 1e-9);
+
+        let mut resumed = first;
+        accumulate(5000, 5011, &mut resumed, true);
+        assert_eq!(resumed.count, 10_000);
+        assert!((resumed.mean - merged.mean).abs() < 1e-9);
+    }
+
+    extern "C" fn record_thread(_completed: u64, _total: u64, user_data: *mut c_void) -> bool {
+        let threads = unsafe { &*(user_data as *const Mutex<std::collections::HashSet<std::thread::ThreadId>>) };
+        threads.lock().unwrap().insert(std::thread::current().id());
+        true
+    }
+
+    /// Results of a run limited to `max_threads`, and how many distinct
+    /// threads reported progress.
+    fn run_on_threads(max_threads: usize) -> (EconomicMonteCarloResult, usize) {
+        let threads = Mutex::new(std::collections::HashSet::<std::thread::ThreadId>::new());
+        let options = EconomicMonteCarloOptions {
+            max_threads,
+            progress_callback: Some(record_thread),
+            progress_user_data: &threads as *const _ as *mut c_void,
+            progress_interval: 100,
+            ..Default::default()
+        };
+        let result = simulate(&FLOWS, 20_000, 0.1, 3, &options);
+        (result, threads.into_inner().unwrap().len())
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        let (serial, serial_threads) = run_on_threads(1);
+        assert_eq!(serial_threads, 1);
+        for max_threads in [4, 0] {
+            let (parallel, _) = run_on_threads(max_threads);
+            assert_eq!(bits(&parallel.to_array()), bits(&serial.to_array()), "{max_threads} threads");
+        }
+    }
+
+    const EARLY_YEAR_STRATA: [StratumSpec; 4] = [
+        StratumSpec { factor: PRICE, year: 1, strata: 10 },
+        StratumSpec { factor: PRODUCTION, year: 1, strata: 10 },
+        StratumSpec { factor: PRICE, year: 2, strata: 5 },
+        StratumSpec { factor: PRODUCTION, year: 2, strata: 5 },
+    ];
+
+    fn stratified(flows: &[f64], iterations: usize, seed: u64, strata: &[StratumSpec], extra: EconomicMonteCarloOptions) -> (RunStatus, EconomicMonteCarloResult) {
+        let options = EconomicMonteCarloOptions { strata: strata.as_ptr(), n_strata: strata.len(), ..extra };
+        let mut result = EconomicMonteCarloResult::default();
+        let status = run_economic_monte_carlo_v2(flows.as_ptr(), flows.len(), iterations, 0.2, 0.1, 0.2, seed, 0.15, &mut result, &options);
+        (status, result)
+    }
+
+    #[test]
+    fn stratifying_early_years_reduces_the_estimator_variance() {
+        let flows = [-1000.0, 400.0, 350.0, 300.0, 300.0, 300.0];
+        let variance = |strata: &[StratumSpec]| {
+            let means: Vec<f64> = (0..20).map(|k| {
+                let (status, result) = stratified(&flows, 2500, k * 100_000, strata, Default::default());
+                assert_eq!(status, RunStatus::Ok);
+                result.mean
+            }).collect();
+            let stats = RunningStats::from_samples(&means);
+            stats.m2 / 19.0
+        };
+        let (plain, stratified) = (variance(&[]), variance(&EARLY_YEAR_STRATA));
+        assert!(stratified < plain, "{stratified} vs {plain}");
+    }
+
+    #[test]
+    fn stratified_bounds_are_honored_without_rejection() {
+        let strata = [StratumSpec { factor: PRICE, year: 1, strata: 4 }];
+        let extra = EconomicMonteCarloOptions { price_bounds: ShockBounds { enabled: true, lower: 0.9, upper: 1.1 }, ..Default::default() };
+        let dists = ShockDistributions::new(0.2, 0.0, 0.0).unwrap().with_options(&extra).unwrap();
+        let mut rng = StdRng::seed_from_u64(1);
+        for band in 0..4 {
+            let multiplier = dists.draw_stratified(PRICE, band, 4, &mut rng);
+            assert!((0.9..=1.1).contains(&multiplier), "{multiplier}");
+        }
+        let (status, _) = stratified(&ANNUITY, 2500, 1, &strata, extra);
+        assert_eq!(status, RunStatus::Ok);
+    }
+
+    #[test]
+    fn invalid_stratification_is_rejected() {
+        let flows = [-1000.0, 400.0, 350.0, 300.0, 300.0, 300.0];
+        // 10 * 10 * 5 * 5 combinations do not divide 2501 iterations
+        assert_eq!(stratified(&flows, 2501, 1, &EARLY_YEAR_STRATA, Default::default()).0, RunStatus::InvalidInput);
+        // Year 2 does not exist in a two-year project
+        assert_eq!(stratified(&flows[..2], 2500, 1, &EARLY_YEAR_STRATA, Default::default()).0, RunStatus::InvalidInput);
+        let duplicate = [EARLY_YEAR_STRATA[0], EARLY_YEAR_STRATA[0]];
+        assert_eq!(stratified(&flows, 100, 1, &duplicate, Default::default()).0, RunStatus::InvalidInput);
+        let year_zero = [StratumSpec { factor: PRICE, year: 0, strata: 2 }];
+        assert_eq!(stratified(&flows, 100, 1, &year_zero, Default::default()).0, RunStatus::InvalidInput);
+        let with_copula = EconomicMonteCarloOptions { copula: CopulaKind::Clayton as u32, clayton_theta: 2.0, ..Default::default() };
+        assert_eq!(stratified(&flows, 100, 1, &year_zero[..0], with_copula).0, RunStatus::Ok);
+        let valid = [StratumSpec { factor: PRICE, year: 1, strata: 2 }];
+        assert_eq!(stratified(&flows, 100, 1, &valid, with_copula).0, RunStatus::InvalidInput);
+    }
+
+    #[test]
+    fn unknown_copula_and_marginal_ids_are_rejected() {
+        let shocks = || ShockDistributions::new(0.2, 0.1, 0.1).unwrap();
+        let triangular = EconomicMonteCarloOptions { marginals: [0, 2, 0], ..Default::default() };
+        assert!(shocks().with_options(&triangular).is_some());
+        let unknown_marginal = EconomicMonteCarloOptions { marginals: [0, 3, 0], ..Default::default() };
+        assert!(shocks().with_options(&unknown_marginal).is_none());
+        let unknown_copula = EconomicMonteCarloOptions { copula: 3, ..Default::default() };
+        assert!(shocks().with_options(&unknown_copula).is_none());
+    }
+
+    #[test]
+    fn result_fields_sit_at_their_table_offsets() {
+        use std::mem::offset_of;
+        let offsets = [
+            offset_of!(EconomicMonteCarloResult, mean), offset_of!(EconomicMonteCarloResult, std_dev),
+            offset_of!(EconomicMonteCarloResult, min), offset_of!(EconomicMonteCarloResult, max),
+            offset_of!(EconomicMonteCarloResult, irr_mean), offset_of!(EconomicMonteCarloResult, irr_std_dev),
+            offset_of!(EconomicMonteCarloResult, irr_p10), offset_of!(EconomicMonteCarloResult, irr_p50),
+            offset_of!(EconomicMonteCarloResult, irr_p90), offset_of!(EconomicMonteCarloResult, irr_failure_rate),
+            offset_of!(EconomicMonteCarloResult, payback_mean), offset_of!(EconomicMonteCarloResult, payback_p10),
+            offset_of!(EconomicMonteCarloResult, payback_p50), offset_of!(EconomicMonteCarloResult, payback_p90),
+            offset_of!(EconomicMonteCarloResult, never_paid_back), offset_of!(EconomicMonteCarloResult, price_contribution),
+            offset_of!(EconomicMonteCarloResult, cost_contribution), offset_of!(EconomicMonteCarloResult, production_contribution),
+            offset_of!(EconomicMonteCarloResult, skewness), offset_of!(EconomicMonteCarloResult, excess_kurtosis),
+            offset_of!(EconomicMonteCarloResult, standard_error), offset_of!(EconomicMonteCarloResult, ci_lower),
+            offset_of!(EconomicMonteCarloResult, ci_upper), offset_of!(EconomicMonteCarloResult, rejected_draws),
+            offset_of!(EconomicMonteCarloResult, clamped_draws),
+        ];
+        let expected: Vec<usize> = (0..EXTENDED_RESULT_LEN).map(|index| index * 8).collect();
+        assert_eq!(offsets.to_vec(), expected);
+        assert_eq!(economic_monte_carlo_result_size(), EXTENDED_RESULT_LEN * 8);
+        assert_eq!(economic_monte_carlo_result_version(), ECONOMIC_MONTE_CARLO_RESULT_VERSION);
+    }
+
+    #[test]
+    fn flat_results_are_the_struct_in_memory_order() {
+        let result = run_v2(&EconomicMonteCarloOptions { variance_contributions: true, ..Default::default() });
+        let in_memory = unsafe { *(&result as *const EconomicMonteCarloResult as *const [f64; EXTENDED_RESULT_LEN]) };
+        assert_eq!(bits(&in_memory), bits(&result.to_array()));
+
+        let mut flat = [0.0; EXTENDED_RESULT_LEN];
+        let options = EconomicMonteCarloOptions { variance_contributions: true, ..Default::default() };
+        let status = run_economic_monte_carlo_extended(FLOWS.as_ptr(), FLOWS.len(), 2000, 0.1, 0.1, 0.05, 42, 0.08,
+            flat.as_mut_ptr(), flat.len(), &options);
+        assert_eq!(status, RunStatus::Ok);
+        assert_eq!(bits(&flat), bits(&in_memory));
+    }
+
+    #[test]
+    fn v2_rejects_null_pointers_and_empty_runs() {
+        let mut result = EconomicMonteCarloResult::default();
+        let options = EconomicMonteCarloOptions::default();
+        let run = |flows: *const f64, len: usize, iterations: usize, result: *mut EconomicMonteCarloResult| {
+            run_economic_monte_carlo_v2(flows, len, iterations, 0.1, 0.1, 0.1, 42, 0.08, result, &options)
+        };
+        assert_eq!(run(std::ptr::null(), 6, 10, &mut result), RunStatus::InvalidInput);
+        assert_eq!(run(FLOWS.as_ptr(), 6, 10, std::ptr::null_mut()), RunStatus::InvalidInput);
+        assert_eq!(run(FLOWS.as_ptr(), 0, 10, &mut result), RunStatus::InvalidInput);
+        assert_eq!(run(FLOWS.as_ptr(), 6, 0, &mut result), RunStatus::InvalidInput);
+        let mut short = [0.0; EXTENDED_RESULT_LEN - 1];
+        let status = run_economic_monte_carlo_extended(FLOWS.as_ptr(), 6, 10, 0.1, 0.1, 0.1, 42, 0.08, short.as_mut_ptr(), short.len(), std::ptr::null());
+        assert_eq!(status, RunStatus::InvalidInput);
+    }
+}
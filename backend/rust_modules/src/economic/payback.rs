@@ -0,0 +1,84 @@
+use std::ffi::c_double;
+
+/// Discounted payback period in (fractional) years.
+///
+/// Returns false when the cumulative discounted cash flow never turns
+/// non-negative within the horizon.
+#[no_mangle]
+pub extern "C" fn calculate_discounted_payback(
+    cash_flows: *const c_double,
+    len: usize,
+    discount_rate: c_double,
+    result: *mut c_double
+) -> bool {
+    if cash_flows.is_null() || result.is_null() || len == 0 {
+        return false;
+    }
+
+    let flows = unsafe { std::slice::from_raw_parts(cash_flows, len) };
+
+    match discounted_payback_from_slice(flows, discount_rate) {
+        Some(years) => {
+            unsafe { *result = years; }
+            true
+        }
+        None => false,
+    }
+}
+
+pub(crate) fn discounted_payback_from_slice(flows: &[f64], rate: f64) -> Option<f64> {
+    let discounted = flows.iter()
+        .enumerate()
+        .map(|(year, &flow)| flow / (1.0 + rate).powi(year as i32));
+    npv_and_payback(discounted).1
+}
+
+/// Walk discounted flows once, returning their sum (the NPV) and the
+/// fractional year at which the cumulative sum first becomes non-negative.
+///
+/// The crossing year is interpolated linearly within the year it happens.
+pub(crate) fn npv_and_payback(discounted_flows: impl Iterator<Item = f64>) -> (f64, Option<f64>) {
+    let mut cumulative = 0.0;
+    let mut payback = None;
+
+    for (year, flow) in discounted_flows.enumerate() {
+        let previous = cumulative;
+        cumulative += flow;
+
+        if payback.is_none() && cumulative >= 0.0 {
+            payback = if year == 0 {
+                // Nothing to recover
+                Some(0.0)
+            } else if previous < 0.0 {
+                Some((year - 1) as f64 + (-previous / flow))
+            } else {
+                None
+            };
+        }
+    }
+
+    (cumulative, payback)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn payback_is_interpolated_within_the_crossing_year() {
+        // Undiscounted: 1000 recovered after 3 years and 1/3 of the fourth
+        let mut years = 0.0;
+        assert!(calculate_discounted_payback([-1000.0, 300.0, 300.0, 300.0, 300.0].as_ptr(), 5, 0.0, &mut years));
+        assert!((years - (3.0 + 1.0 / 3.0)).abs() < 1e-12);
+        let (npv, payback) = npv_and_payback([-100.0, 50.0, 50.0].into_iter());
+        assert_eq!((npv, payback), (0.0, Some(2.0)));
+    }
+
+    #[test]
+    fn never_paying_back_returns_false() {
+        let mut years = -1.0;
+        assert!(!calculate_discounted_payback([-1000.0, 300.0, 300.0].as_ptr(), 3, 0.08, &mut years));
+        assert_eq!(years, -1.0);
+        assert_eq!(discounted_payback_from_slice(&[100.0, 10.0], 0.08), Some(0.0));
+    }
+}
@@ -0,0 +1,273 @@
+/// Number of values written by `running_stats_finalize`.
+///
+/// | Index | Value |
+/// |-------|-------|
+/// | 0     | mean |
+/// | 1     | population standard deviation |
+/// | 2-3   | min, max |
+/// | 4     | skewness |
+/// | 5     | excess kurtosis |
+pub const RUNNING_STATS_RESULT_LEN: usize = 6;
+
+/// Streaming central moments up to the fourth order.
+///
+/// Updated one sample at a time with the Welford/Terriberry recurrences, so
+/// higher moments stay accurate for NPVs in the millions without forming
+/// huge raw powers. Two accumulators combine with the parallel formulas of
+/// Chan et al., so a study split across processes can be merged without
+/// shipping raw samples.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct RunningStats {
+    pub count: u64,
+    pub mean: f64,
+    pub m2: f64,
+    pub m3: f64,
+    pub m4: f64,
+    pub min: f64,
+    pub max: f64,
+}
+
+impl Default for RunningStats {
+    fn default() -> Self {
+        Self {
+            count: 0,
+            mean: 0.0,
+            m2: 0.0,
+            m3: 0.0,
+            m4: 0.0,
+            min: f64::INFINITY,
+            max: f64::NEG_INFINITY,
+        }
+    }
+}
+
+impl RunningStats {
+    pub fn from_samples(samples: &[f64]) -> Self {
+        let mut stats = Self::default();
+        for &x in samples {
+            stats.push(x);
+        }
+        stats
+    }
+
+    pub fn push(&mut self, x: f64) {
+        let n1 = self.count as f64;
+        self.count += 1;
+        let n = self.count as f64;
+
+        let delta = x - self.mean;
+        let delta_n = delta / n;
+        let delta_n2 = delta_n * delta_n;
+        let term1 = delta * delta_n * n1;
+
+        self.mean += delta_n;
+        self.m4 += term1 * delta_n2 * (n * n - 3.0 * n + 3.0)
+            + 6.0 * delta_n2 * self.m2
+            - 4.0 * delta_n * self.m3;
+        self.m3 += term1 * delta_n * (n - 2.0) - 3.0 * delta_n * self.m2;
+        self.m2 += term1;
+
+        self.min = self.min.min(x);
+        self.max = self.max.max(x);
+    }
+
+    /// Combine two accumulators as if all their samples had been pushed into
+    /// one.
+    pub fn merge(&self, other: &Self) -> Self {
+        if other.count == 0 {
+            return *self;
+        }
+        if self.count == 0 {
+            return *other;
+        }
+
+        let na = self.count as f64;
+        let nb = other.count as f64;
+        let n = na + nb;
+        let delta = other.mean - self.mean;
+        let delta2 = delta * delta;
+
+        let m2 = self.m2 + other.m2 + delta2 * na * nb / n;
+        let m3 = self.m3 + other.m3
+            + delta * delta2 * na * nb * (na - nb) / (n * n)
+            + 3.0 * delta * (na * other.m2 - nb * self.m2) / n;
+        let m4 = self.m4 + other.m4
+            + delta2 * delta2 * na * nb * (na * na - na * nb + nb * nb) / (n * n * n)
+            + 6.0 * delta2 * (na * na * other.m2 + nb * nb * self.m2) / (n * n)
+            + 4.0 * delta * (na * other.m3 - nb * self.m3) / n;
+
+        Self {
+            count: self.count + other.count,
+            mean: self.mean + delta * nb / n,
+            m2,
+            m3,
+            m4,
+            min: self.min.min(other.min),
+            max: self.max.max(other.max),
+        }
+    }
+
+    /// Population standard deviation, NaN when empty.
+    pub fn std_dev(&self) -> f64 {
+        if self.count == 0 {
+            return f64::NAN;
+        }
+        (self.m2 / self.count as f64).sqrt()
+    }
+
+    /// Sample skewness g1, NaN when the samples do not vary.
+    pub fn skewness(&self) -> f64 {
+        if self.count == 0 || self.m2 <= 0.0 {
+            return f64::NAN;
+        }
+        (self.count as f64).sqrt() * self.m3 / self.m2.powf(1.5)
+    }
+
+    /// Excess kurtosis g2 (0 for a normal distribution), NaN when the
+    /// samples do not vary.
+    pub fn excess_kurtosis(&self) -> f64 {
+        if self.count == 0 || self.m2 <= 0.0 {
+            return f64::NAN;
+        }
+        self.count as f64 * self.m4 / (self.m2 * self.m2) - 3.0
+    }
+}
+
+/// Reset `stats` to an empty accumulator.
+#[no_mangle]
+pub extern "C" fn running_stats_init(stats: *mut RunningStats) -> bool {
+    if stats.is_null() {
+        return false;
+    }
+    unsafe { *stats = RunningStats::default(); }
+    true
+}
+
+/// Push `len` samples into `stats`.
+///
+/// # Safety
+/// - stats points to a valid `RunningStats`
+/// - values points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn running_stats_update(stats: *mut RunningStats, values: *const f64, len: usize) -> bool {
+    if stats.is_null() || (values.is_null() && len > 0) {
+        return false;
+    }
+    let stats = unsafe { &mut *stats };
+    if len > 0 {
+        for &x in unsafe { std::slice::from_raw_parts(values, len) } {
+            stats.push(x);
+        }
+    }
+    true
+}
+
+/// Combine `a` and `b` into `out`, which may alias either input.
+#[no_mangle]
+pub extern "C" fn merge_running_stats(a: *const RunningStats, b: *const RunningStats, out: *mut RunningStats) -> bool {
+    if a.is_null() || b.is_null() || out.is_null() {
+        return false;
+    }
+    let merged = unsafe { (*a).merge(&*b) };
+    unsafe { *out = merged; }
+    true
+}
+
+/// Write the finalized statistics of `stats`, see `RUNNING_STATS_RESULT_LEN`.
+/// Returns false for an empty accumulator.
+///
+/// # Safety
+/// - stats points to a valid `RunningStats`
+/// - results points to a valid array of f64 with length results_len, which
+///   must be at least `RUNNING_STATS_RESULT_LEN`
+#[no_mangle]
+pub extern "C" fn running_stats_finalize(stats: *const RunningStats, results: *mut f64, results_len: usize) -> bool {
+    if stats.is_null() || results.is_null() || results_len < RUNNING_STATS_RESULT_LEN {
+        return false;
+    }
+    let stats = unsafe { &*stats };
+    if stats.count == 0 {
+        return false;
+    }
+
+    let results = unsafe { std::slice::from_raw_parts_mut(results, RUNNING_STATS_RESULT_LEN) };
+    results[0] = stats.mean;
+    results[1] = stats.std_dev();
+    results[2] = stats.min;
+    results[3] = stats.max;
+    results[4] = stats.skewness();
+    results[5] = stats.excess_kurtosis();
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SAMPLES: [f64; 7] = [1.0, 2.0, 3.0, 4.0, 10.0, -2.5, 7.25];
+
+    /// Central moment sums computed the textbook two-pass way.
+    fn two_pass(samples: &[f64]) -> (f64, [f64; 3]) {
+        let mean = samples.iter().sum::<f64>() / samples.len() as f64;
+        let moment = |power: i32| samples.iter().map(|x| (x - mean).powi(power)).sum::<f64>();
+        (mean, [moment(2), moment(3), moment(4)])
+    }
+
+    #[test]
+    fn streaming_moments_match_two_pass_sums() {
+        let stats = RunningStats::from_samples(&SAMPLES);
+        let (mean, [m2, m3, m4]) = two_pass(&SAMPLES);
+        assert_eq!(stats.count, 7);
+        assert!((stats.mean - mean).abs() < 1e-12);
+        assert!((stats.m2 - m2).abs() < 1e-9 && (stats.m3 - m3).abs() < 1e-9 && (stats.m4 - m4).abs() < 1e-9);
+        assert_eq!((stats.min, stats.max), (-2.5, 10.0));
+    }
+
+    #[test]
+    fn large_offsets_do_not_swamp_the_moments() {
+        let shifted: Vec<f64> = SAMPLES.iter().map(|x| x + 1e7).collect();
+        let (plain, shifted) = (RunningStats::from_samples(&SAMPLES), RunningStats::from_samples(&shifted));
+        assert!((plain.skewness() - shifted.skewness()).abs() < 1e-6);
+        assert!((plain.excess_kurtosis() - shifted.excess_kurtosis()).abs() < 1e-6);
+    }
+
+    #[test]
+    fn merge_equals_pushing_every_sample() {
+        let full = RunningStats::from_samples(&SAMPLES);
+        for split in 0..=SAMPLES.len() {
+            let merged = RunningStats::from_samples(&SAMPLES[..split]).merge(&RunningStats::from_samples(&SAMPLES[split..]));
+            assert_eq!(merged.count, full.count);
+            for (a, b) in [(merged.mean, full.mean), (merged.m2, full.m2), (merged.m3, full.m3), (merged.m4, full.m4)] {
+                assert!((a - b).abs() < 1e-9, "split {split}: {a} vs {b}");
+            }
+            assert_eq!((merged.min, merged.max), (full.min, full.max));
+        }
+    }
+
+    #[test]
+    fn ffi_functions_accumulate_merge_and_finalize() {
+        let mut stats = RunningStats { count: 3, ..Default::default() };
+        assert!(running_stats_init(&mut stats));
+        assert_eq!(stats.count, 0);
+        assert!(running_stats_update(&mut stats, SAMPLES.as_ptr(), SAMPLES.len()));
+        assert!(running_stats_update(&mut stats, std::ptr::null(), 0));
+        assert!(!running_stats_update(&mut stats, std::ptr::null(), 1));
+
+        // The output may alias an input
+        let mut head = RunningStats::from_samples(&SAMPLES[..3]);
+        let tail = RunningStats::from_samples(&SAMPLES[3..]);
+        let head_ptr: *mut RunningStats = &mut head;
+        assert!(merge_running_stats(head_ptr, &tail, head_ptr));
+        assert_eq!(head.count, 7);
+        assert!((head.m4 - stats.m4).abs() < 1e-9);
+
+        let mut results = [0.0; RUNNING_STATS_RESULT_LEN];
+        assert!(running_stats_finalize(&stats, results.as_mut_ptr(), results.len()));
+        assert_eq!(results[..4], [stats.mean, stats.std_dev(), -2.5, 10.0]);
+        assert_eq!(results[4..], [stats.skewness(), stats.excess_kurtosis()]);
+        assert!(!running_stats_finalize(&stats, results.as_mut_ptr(), RUNNING_STATS_RESULT_LEN - 1));
+        assert!(!running_stats_finalize(&RunningStats::default(), results.as_mut_ptr(), results.len()));
+        assert!(RunningStats::from_samples(&[2.0, 2.0]).skewness().is_nan());
+    }
+}
@@ -0,0 +1,109 @@
+use std::ffi::c_double;
+use super::irr::calculate_irr_from_slice;
+use super::monte_carlo::adjust_operating_flow;
+use super::npv::calculate_npv_from_slice;
+use super::payback::discounted_payback_from_slice;
+
+/// Number of values written per scenario by `run_scenario_analysis`.
+pub const SCENARIO_RESULT_WIDTH: usize = 3;
+
+/// Deterministic scenario table (best/base/worst or any custom set).
+///
+/// `multipliers` is a row-major n_scenarios x 3 matrix of
+/// (price, cost, production) factors, applied to the operating years exactly
+/// as the Monte Carlo applies `1 + shock`. Year 0 is never adjusted.
+///
+/// Each results row holds (NPV, IRR, discounted payback); IRR and payback are
+/// NaN when they do not exist for that scenario.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - multipliers points to a valid array of f64 with length n_scenarios * 3
+/// - results points to a valid array of f64 with length n_scenarios * 3
+#[no_mangle]
+pub extern "C" fn run_scenario_analysis(
+    base_values: *const c_double,
+    len: usize,
+    discount_rate: c_double,
+    multipliers: *const c_double,
+    n_scenarios: usize,
+    results: *mut c_double
+) -> bool {
+    if base_values.is_null() || multipliers.is_null() || results.is_null()
+        || len == 0 || n_scenarios == 0
+    {
+        return false;
+    }
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let multipliers = unsafe { std::slice::from_raw_parts(multipliers, n_scenarios * 3) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, n_scenarios * SCENARIO_RESULT_WIDTH) };
+
+    for (factors, row) in multipliers.chunks_exact(3).zip(results.chunks_exact_mut(SCENARIO_RESULT_WIDTH)) {
+        let flows = scenario_flows(values, factors[0], factors[1], factors[2]);
+        let npv = calculate_npv_from_slice(&flows, discount_rate);
+
+        row[0] = npv;
+        row[1] = calculate_irr_from_slice(&flows).unwrap_or(f64::NAN);
+        row[2] = discounted_payback_from_slice(&flows, discount_rate).unwrap_or(f64::NAN);
+    }
+
+    true
+}
+
+fn scenario_flows(values: &[f64], price_factor: f64, cost_factor: f64, production_factor: f64) -> Vec<f64> {
+    values.iter()
+        .enumerate()
+        .map(|(i, &value)| {
+            if i == 0 {
+                value
+            } else {
+                adjust_operating_flow(value, price_factor, cost_factor, production_factor)
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::economic::{calculate_irr, calculate_npv};
+
+    const FLOWS: [f64; 6] = [-1000.0, 400.0, -50.0, 400.0, 400.0, 400.0];
+
+    fn scenarios(multipliers: &[f64]) -> Vec<f64> {
+        let n = multipliers.len() / 3;
+        let mut results = vec![0.0; n * SCENARIO_RESULT_WIDTH];
+        assert!(run_scenario_analysis(FLOWS.as_ptr(), FLOWS.len(), 0.08, multipliers.as_ptr(), n, results.as_mut_ptr()));
+        results
+    }
+
+    #[test]
+    fn neutral_scenario_equals_the_base_case_exactly() {
+        let results = scenarios(&[1.0, 1.0, 1.0]);
+        let mut irr = 0.0;
+        assert!(calculate_irr(FLOWS.as_ptr(), FLOWS.len(), &mut irr));
+        assert_eq!(results[0], calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.08));
+        assert_eq!(results[1], irr);
+        assert_eq!(results[2], discounted_payback_from_slice(&FLOWS, 0.08).unwrap());
+    }
+
+    #[test]
+    fn factors_move_revenue_and_cost_years_like_the_monte_carlo() {
+        let results = scenarios(&[1.0, 1.0, 1.0, 1.2, 1.0, 1.0, 1.0, 2.0, 1.0]);
+        // +20% price lifts every positive operating year
+        let revenue_gain: f64 = [1, 3, 4, 5].iter().map(|&year| 80.0 / 1.08_f64.powi(year)).sum();
+        assert!((results[3] - results[0] - revenue_gain).abs() < 1e-9);
+        // Doubling cost only touches the negative operating year
+        assert!((results[0] - results[6] - 50.0 / 1.08_f64.powi(2)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_metrics_are_nan() {
+        let results = scenarios(&[0.1, 1.0, 1.0]);
+        assert!(results[0] < 0.0 && results[2].is_nan());
+        let mut out = [0.0; 3];
+        assert!(!run_scenario_analysis(FLOWS.as_ptr(), FLOWS.len(), 0.08, [1.0; 3].as_ptr(), 0, out.as_mut_ptr()));
+    }
+}
@@ -1,17 +1,340 @@
-use rayon::prelude::*;
 use crate::npv::calculate_npv;
+use rand::{seq::SliceRandom, Rng, SeedableRng, rngs::StdRng};
+use std::borrow::Cow;
+use std::cell::Cell;
 use std::ffi::c_double;
+use std::sync::atomic::AtomicU8;
+use super::execution::{par_map_chunked, with_thread_limit, CancelFlag};
+use super::irr::calculate_irr_from_slice;
+use super::payback::discounted_payback_from_slice;
 
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy)]
 pub enum SensitivityVariable {
     DiscountRate = 0,
     ProductionVolume = 1,
     OperatingCosts = 2,
     Revenue = 3,
+    /// Multiplier on the initial investment in year 0
+    CapitalInvestment = 4,
+    /// Absolute tax rate; needs the pre-tax operating flows
+    TaxRate = 5,
 }
 
+impl SensitivityVariable {
+    pub const ALL: [SensitivityVariable; 6] = [
+        SensitivityVariable::DiscountRate,
+        SensitivityVariable::ProductionVolume,
+        SensitivityVariable::OperatingCosts,
+        SensitivityVariable::Revenue,
+        SensitivityVariable::CapitalInvestment,
+        SensitivityVariable::TaxRate,
+    ];
+
+    pub fn from_index(index: usize) -> Option<Self> {
+        Self::ALL.get(index).copied()
+    }
+
+    /// The value a relative change is applied to: the discount rate or base
+    /// tax rate itself, or a multiplier of 1 for the other variables.
+    fn base_value(self, discount_rate: f64, inputs: &SweepInputs) -> f64 {
+        match self {
+            SensitivityVariable::DiscountRate => discount_rate,
+            SensitivityVariable::TaxRate => inputs.tax.base_tax_rate,
+            _ => 1.0,
+        }
+    }
+}
+
+/// Quantity evaluated at each point of a sensitivity sweep, passed to
+/// `run_tornado_analysis` and in `SensitivityRequest` by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitivityMetric {
+    Npv = 0,
+    Irr = 1,
+    /// Discounted payback period in years
+    DiscountedPayback = 2,
+}
+
+impl SensitivityMetric {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Npv),
+            1 => Some(Self::Irr),
+            2 => Some(Self::DiscountedPayback),
+            _ => None,
+        }
+    }
+}
+
+/// Whether the metric exists at one point of a sweep; points without one
+/// hold NaN.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitivityPointStatus {
+    Ok = 0,
+    /// The modified flows never change sign or no root could be bracketed
+    NoIrr = 1,
+    /// The cumulative discounted flows never turn non-negative
+    NeverPaysBack = 2,
+}
+
+/// Rows written by `run_tornado_analysis`, one per `SensitivityVariable`.
+pub const TORNADO_VARIABLES: usize = SensitivityVariable::ALL.len();
+
+// Steps evaluated between polls of the cancellation flag
+const SENSITIVITY_CHUNK_SIZE: usize = 16;
+
+/// Outcome of `run_sensitivity_analysis_v2`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitivityStatus {
+    Ok = 0,
+    /// The request, response, base_values or results pointer is null
+    NullPointer = 1,
+    /// `variable` is not a `SensitivityVariable` index
+    InvalidVariable = 2,
+    /// A per-year cost ratio lies outside [0, 1]
+    InvalidCostRatio = 3,
+    /// A `TaxRate` sweep without `pre_tax_flows`
+    MissingPreTaxFlows = 4,
+    /// `len` is 0 or `results_len` is smaller than steps + 1
+    LengthMismatch = 5,
+    /// Stopped through the cancellation flag; the results were left untouched
+    Cancelled = 6,
+    /// The thread pool for `max_threads` could not be created
+    ThreadPoolError = 7,
+    /// An IRR sweep over the discount rate, which IRR does not depend on, or
+    /// a `metric` that is not a `SensitivityMetric` id
+    MetricNotApplicable = 8,
+    /// `steps` is 0
+    ZeroSteps = 9,
+    /// `range_min` is greater than `range_max`, or either is NaN
+    InvertedRange = 10,
+    /// Logarithmic spacing over a range that is not strictly positive
+    NonPositiveLogRange = 11,
+    /// `base_volume` is negative or infinite
+    InvalidBaseVolume = 12,
+    /// Only one of `revenues` and `costs` was given
+    IncompleteComponents = 13,
+    /// `spacing` is not a `SweepSpacing` id
+    UnknownSpacing = 14,
+    /// `output_mode` is not a `SensitivityOutputMode` id
+    UnknownOutputMode = 15,
+}
+
+/// How the steps + 1 factors of a sweep are distributed over its range.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SweepSpacing {
+    /// Equal differences between consecutive factors
+    Linear = 0,
+    /// Equal ratios between consecutive factors; for ranges spanning an
+    /// order of magnitude or more
+    Logarithmic = 1,
+}
+
+impl SweepSpacing {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Linear),
+            1 => Some(Self::Logarithmic),
+            _ => None,
+        }
+    }
+}
+
+/// How sensitivity results are expressed relative to the base case (the
+/// metric of the unmodified flows); the tornado and spider functions take
+/// it by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SensitivityOutputMode {
+    Absolute = 0,
+    /// Value minus the base value
+    DeltaFromBase = 1,
+    /// 100 * (value - base) / |base|; falls back to `DeltaFromBase` when the
+    /// base is ~0
+    PercentOfBase = 2,
+}
+
+impl SensitivityOutputMode {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Absolute),
+            1 => Some(Self::DeltaFromBase),
+            2 => Some(Self::PercentOfBase),
+            _ => None,
+        }
+    }
+
+    /// The mode applied around `base`: `PercentOfBase` becomes
+    /// `DeltaFromBase` when |base| is at most `ZERO_NPV_TOLERANCE * scale`
+    /// or not finite.
+    fn resolve(self, base: f64, scale: f64) -> Self {
+        if self == SensitivityOutputMode::PercentOfBase && !(base.abs() > ZERO_NPV_TOLERANCE * scale && base.is_finite()) {
+            SensitivityOutputMode::DeltaFromBase
+        } else {
+            self
+        }
+    }
+
+    fn apply(self, value: f64, base: f64) -> f64 {
+        match self {
+            SensitivityOutputMode::Absolute => value,
+            SensitivityOutputMode::DeltaFromBase => value - base,
+            SensitivityOutputMode::PercentOfBase => 100.0 * (value - base) / base.abs(),
+        }
+    }
+}
+
+/// Base value used to normalize sensitivity results and the output mode
+/// actually applied, so callers can reconstruct absolute values.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SensitivityNormalization {
+    pub base_value: f64,
+    /// Differs from the requested mode when `PercentOfBase` fell back to
+    /// `DeltaFromBase`
+    pub mode_used: SensitivityOutputMode,
+}
+
+impl SensitivityNormalization {
+    /// Normalization of `metric` around the unmodified `values`.
+    fn new(values: &[f64], discount_rate: f64, metric: SensitivityMetric, mode: SensitivityOutputMode) -> Self {
+        let (base_value, _) = evaluate_flows(values, discount_rate, metric);
+        // NPVs are compared with the gross discounted flows, rates and years directly
+        let scale = match metric {
+            SensitivityMetric::Npv => gross_discounted_flows(values, discount_rate),
+            _ => 1.0,
+        };
+        Self { base_value, mode_used: mode.resolve(base_value, scale) }
+    }
+
+    fn apply(&self, value: f64) -> f64 {
+        self.mode_used.apply(value, self.base_value)
+    }
+
+    /// Copy into the caller's out-pointer, if any.
+    fn write_to(self, out: *mut SensitivityNormalization) {
+        if !out.is_null() {
+            unsafe { *out = self };
+        }
+    }
+}
+
+thread_local! {
+    // Outcome of the last run_sensitivity_analysis call on this thread
+    static LAST_SENSITIVITY_STATUS: Cell<SensitivityStatus> = const { Cell::new(SensitivityStatus::Ok) };
+}
+
+/// Outcome of the last `run_sensitivity_analysis` call made on the calling
+/// thread, for callers of the legacy symbol, which returns nothing.
+#[no_mangle]
+pub extern "C" fn sensitivity_last_status() -> SensitivityStatus {
+    LAST_SENSITIVITY_STATUS.with(Cell::get)
+}
+
+/// Inputs of one sensitivity sweep, see `run_sensitivity_analysis_v2` for
+/// the meaning of each field.
+///
+/// Every pointer is borrowed for the duration of the call; the optional ones
+/// (`fixed_cost_ratios`, `variable_cost_ratios`, `pre_tax_flows`,
+/// `cancel_flag`) may be null and the arrays have length `len`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SensitivityRequest {
+    pub base_values: *const f64,
+    pub len: usize,
+    /// `SensitivityVariable` index
+    pub variable: usize,
+    pub range_min: f64,
+    pub range_max: f64,
+    pub steps: usize,
+    pub discount_rate: f64,
+    pub fixed_cost_ratio: f64,
+    pub variable_cost_ratio: f64,
+    pub fixed_cost_ratios: *const f64,
+    pub variable_cost_ratios: *const f64,
+    pub pre_tax_flows: *const f64,
+    pub base_tax_rate: f64,
+    pub cancel_flag: *const AtomicU8,
+    pub max_threads: usize,
+    /// `SensitivityMetric` id of the quantity written to the results
+    pub metric: u32,
+    /// `SweepSpacing` id
+    pub spacing: u32,
+    /// `SensitivityOutputMode` id
+    pub output_mode: u32,
+    /// Production volume of the unmodified flows for `ProductionVolume`
+    /// sweeps; 0 or NaN means the midpoint of the range
+    pub base_volume: f64,
+    /// Null, or the revenue of each year; given together with `costs`, the
+    /// two replace `base_values` (which may then be null), see
+    /// `FlowComponents`
+    pub revenues: *const f64,
+    /// Null, or the costs of each year as positive amounts
+    pub costs: *const f64,
+}
+
+/// Caller-owned output buffers of one sensitivity sweep.
+#[repr(C)]
+#[derive(Debug, Clone, Copy)]
+pub struct SensitivityResponse {
+    /// Receives the NPV at each step; holds `results_len` values
+    pub results: *mut f64,
+    pub results_len: usize,
+    /// Null, or receives the factor at each step; holds `results_len` values
+    pub factors: *mut f64,
+    /// Set to the number of steps written (steps + 1 on success, else 0)
+    pub points_written: usize,
+    /// Null, or receives whether the metric exists at each step; holds
+    /// `results_len` values
+    pub statuses: *mut SensitivityPointStatus,
+    /// Set to the base value and effective output mode on success
+    pub normalization: SensitivityNormalization,
+}
+
+// Field reordering would silently break every binding
+#[cfg(target_pointer_width = "64")]
+const _: () = {
+    use std::mem::{offset_of, size_of};
+    assert!(size_of::<SensitivityRequest>() == 20 * 8);
+    assert!(offset_of!(SensitivityRequest, base_values) == 0);
+    assert!(offset_of!(SensitivityRequest, variable) == 2 * 8);
+    assert!(offset_of!(SensitivityRequest, steps) == 5 * 8);
+    assert!(offset_of!(SensitivityRequest, fixed_cost_ratio) == 7 * 8);
+    assert!(offset_of!(SensitivityRequest, variable_cost_ratio) == 8 * 8);
+    assert!(offset_of!(SensitivityRequest, pre_tax_flows) == 11 * 8);
+    assert!(offset_of!(SensitivityRequest, cancel_flag) == 13 * 8);
+    assert!(offset_of!(SensitivityRequest, max_threads) == 14 * 8);
+    assert!(offset_of!(SensitivityRequest, metric) == 15 * 8);
+    assert!(offset_of!(SensitivityRequest, spacing) == 15 * 8 + 4);
+    assert!(offset_of!(SensitivityRequest, output_mode) == 16 * 8);
+    assert!(offset_of!(SensitivityRequest, base_volume) == 17 * 8);
+    assert!(offset_of!(SensitivityRequest, revenues) == 18 * 8);
+    assert!(offset_of!(SensitivityRequest, costs) == 19 * 8);
+    assert!(size_of::<SensitivityResponse>() == 7 * 8);
+    assert!(offset_of!(SensitivityResponse, factors) == 2 * 8);
+    assert!(offset_of!(SensitivityResponse, points_written) == 3 * 8);
+    assert!(offset_of!(SensitivityResponse, statuses) == 4 * 8);
+    assert!(offset_of!(SensitivityResponse, normalization) == 5 * 8);
+    assert!(size_of::<SensitivityNormalization>() == 2 * 8);
+};
+
 /// Run sensitivity analysis on cash flows
 /// 
+/// Evaluates steps + 1 evenly spaced factors from `range_min` to
+/// `range_max`, both included. `ProductionVolume` sweeps take absolute
+/// volumes around the midpoint of the range.
+///
+/// A request rejected by `run_sensitivity_analysis_v2` (an unknown
+/// `variable_index`, zero `steps`, `range_min > range_max`, ...) sets all
+/// steps + 1 results to NaN; `sensitivity_last_status` tells which check
+/// failed. Spacing, per-year cost ratios, tax rate sweeps, other metrics,
+/// relative output, cancellation and thread limits are only available
+/// through `run_sensitivity_analysis_v2`.
+///
 /// # Safety
 /// This function is unsafe because it works with raw pointers.
 /// The caller must ensure that:
@@ -30,189 +353,2334 @@ pub extern "C" fn run_sensitivity_analysis(
     variable_cost_ratio: f64,
     results: *mut f64
 ) {
+    let request = SensitivityRequest {
+        base_values,
+        len,
+        variable: variable_index,
+        range_min,
+        range_max,
+        steps,
+        discount_rate,
+        fixed_cost_ratio,
+        variable_cost_ratio,
+        fixed_cost_ratios: std::ptr::null(),
+        variable_cost_ratios: std::ptr::null(),
+        pre_tax_flows: std::ptr::null(),
+        base_tax_rate: 0.0,
+        cancel_flag: std::ptr::null(),
+        max_threads: 0,
+        metric: SensitivityMetric::Npv as u32,
+        spacing: SweepSpacing::Linear as u32,
+        output_mode: SensitivityOutputMode::Absolute as u32,
+        base_volume: 0.0,
+        revenues: std::ptr::null(),
+        costs: std::ptr::null(),
+    };
+    let mut response = SensitivityResponse {
+        results,
+        results_len: steps.saturating_add(1),
+        factors: std::ptr::null_mut(),
+        points_written: 0,
+        statuses: std::ptr::null_mut(),
+        normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+    };
+    let status = run_sensitivity_analysis_v2(&request, &mut response);
+    LAST_SENSITIVITY_STATUS.with(|last| last.set(status));
+    if status != SensitivityStatus::Ok && !results.is_null() {
+        unsafe { std::slice::from_raw_parts_mut(results, steps + 1) }.fill(f64::NAN);
+    }
+}
+
+/// Struct-based form of `run_sensitivity_analysis`.
+///
+/// Validates every field of `request` before evaluating anything and
+/// reports the first problem found; on any status but `Ok` the response
+/// buffers are left untouched and `points_written` is 0.
+///
+/// Evaluates steps + 1 factors from `range_min` to `range_max`, both
+/// included, spaced linearly or geometrically as `spacing` selects;
+/// logarithmic spacing needs both endpoints strictly positive. When
+/// `response.factors` is non-null it receives the exact factor used at
+/// each step. Returns `Cancelled`, leaving the results untouched, when
+/// `cancel_flag` is set before all steps were evaluated.
+///
+/// `ProductionVolume` sweeps take absolute volumes and scale the flows by
+/// their ratio to `base_volume`, the volume behind `base_values`; 0 or NaN
+/// falls back to the midpoint of the range.
+///
+/// `fixed_cost_ratios` and `variable_cost_ratios` override the scalar ratios
+/// year by year (null keeps the scalar for every year) and must lie in
+/// [0, 1]. `TaxRate` sweeps need `pre_tax_flows` and the `base_tax_rate` that
+/// turned them into `base_values`, see `TaxInputs`.
+///
+/// `max_threads` caps the worker threads for this call; 0 uses the limit
+/// from `set_global_thread_limit`, or every core when none was set.
+///
+/// `request.metric` selects what each step evaluates on the modified flows:
+/// NPV, IRR (through the same solver as `calculate_irr`) or discounted
+/// payback. Steps where the metric does not exist hold NaN and, when
+/// `response.statuses` is non-null, the reason. IRR does not depend on the
+/// discount rate, so an IRR sweep of `DiscountRate` returns
+/// `MetricNotApplicable`, as does a `metric` that is not a
+/// `SensitivityMetric` id.
+///
+/// `request.output_mode` expresses the results relative to the metric of
+/// the unmodified flows, reported in `response.normalization`. Unknown
+/// `spacing` and `output_mode` ids return `UnknownSpacing` and
+/// `UnknownOutputMode`.
+///
+/// # Safety
+/// - request points to a valid `SensitivityRequest` whose `base_values`,
+///   `revenues` and `costs` are null or hold `len` values, as are its
+///   optional per-year arrays
+/// - cancel_flag is null or points to a flag that outlives the call
+/// - response points to a valid `SensitivityResponse` whose buffers hold
+///   `results_len` values each
+#[no_mangle]
+pub extern "C" fn run_sensitivity_analysis_v2(
+    request: *const SensitivityRequest,
+    response: *mut SensitivityResponse
+) -> SensitivityStatus {
+    if request.is_null() || response.is_null() {
+        return SensitivityStatus::NullPointer;
+    }
+    let request = unsafe { &*request };
+    let response = unsafe { &mut *response };
+    response.points_written = 0;
+
+    let components = match (request.revenues.is_null(), request.costs.is_null()) {
+        (true, true) => false,
+        (false, false) => true,
+        _ => return SensitivityStatus::IncompleteComponents,
+    };
+    if (request.base_values.is_null() && !components) || response.results.is_null() {
+        return SensitivityStatus::NullPointer;
+    }
+    let variable = match SensitivityVariable::from_index(request.variable) {
+        Some(variable) => variable,
+        None => return SensitivityStatus::InvalidVariable,
+    };
+    let metric = match SensitivityMetric::from_id(request.metric) {
+        Some(SensitivityMetric::Irr) if matches!(variable, SensitivityVariable::DiscountRate) => {
+            return SensitivityStatus::MetricNotApplicable;
+        }
+        Some(metric) => metric,
+        None => return SensitivityStatus::MetricNotApplicable,
+    };
+    let spacing = match SweepSpacing::from_id(request.spacing) {
+        Some(spacing) => spacing,
+        None => return SensitivityStatus::UnknownSpacing,
+    };
+    let output_mode = match SensitivityOutputMode::from_id(request.output_mode) {
+        Some(mode) => mode,
+        None => return SensitivityStatus::UnknownOutputMode,
+    };
+    if request.steps == 0 {
+        return SensitivityStatus::ZeroSteps;
+    }
+    if request.range_min.is_nan() || request.range_max.is_nan() || request.range_min > request.range_max {
+        return SensitivityStatus::InvertedRange;
+    }
+    if spacing == SweepSpacing::Logarithmic && request.range_min <= 0.0 {
+        return SensitivityStatus::NonPositiveLogRange;
+    }
+    if request.base_volume < 0.0 || request.base_volume.is_infinite() {
+        return SensitivityStatus::InvalidBaseVolume;
+    }
+    let points = request.steps + 1;
+    if request.len == 0 || response.results_len < points {
+        return SensitivityStatus::LengthMismatch;
+    }
+
     // Convert input slice safely
-    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
-    let step_size = (range_max - range_min) / (steps as f64);
+    let len = request.len;
+    let components = components.then(|| FlowComponents {
+        revenues: unsafe { std::slice::from_raw_parts(request.revenues, len) },
+        costs: unsafe { std::slice::from_raw_parts(request.costs, len) },
+    });
+    let net_flows;
+    let values = match components {
+        Some(components) => {
+            net_flows = components.net();
+            &net_flows[..]
+        }
+        None => unsafe { std::slice::from_raw_parts(request.base_values, len) },
+    };
+    let tax = TaxInputs::from_ptr(request.pre_tax_flows, len, request.base_tax_rate);
+    if matches!(variable, SensitivityVariable::TaxRate) && tax.pre_tax_flows.is_none() {
+        return SensitivityStatus::MissingPreTaxFlows;
+    }
+    let ratios = CostRatios::from_ptrs(
+        request.fixed_cost_ratio,
+        request.variable_cost_ratio,
+        request.fixed_cost_ratios,
+        request.variable_cost_ratios,
+        len,
+    );
+    let ratios = match ratios {
+        Some(ratios) => ratios,
+        None => return SensitivityStatus::InvalidCostRatio,
+    };
+    let (steps, range_min, range_max, discount_rate) = (request.steps, request.range_min, request.range_max, request.discount_rate);
+    let inputs = SweepInputs { ratios, range_min, range_max, base_volume: request.base_volume, tax, components };
     
     // Parallel sensitivity analysis using rayon
-    let sensitivity_results: Vec<f64> = (0..=steps)
-        .into_par_iter()
-        .map(|i| {
-            let factor = range_min + (i as f64) * step_size;
-            
-            // Match on SensitivityVariable
-            match variable_index {
-                0 => calculate_npv_with_rate(values, factor), // Discount rate
-                1 => calculate_with_volume_factor(values, factor, discount_rate, fixed_cost_ratio, range_min, range_max), // Production volume
-                2 => calculate_with_opex_factor(values, factor, discount_rate, fixed_cost_ratio, variable_cost_ratio), // Operating costs
-                3 => calculate_with_revenue_factor(values, factor, discount_rate), // Revenue
-                _ => calculate_npv_with_rate(values, discount_rate) // Default to discount rate if unknown
-            }
-        })
-        .collect();
+    let cancel = CancelFlag::from_ptr(request.cancel_flag);
+    let sensitivity_results = with_thread_limit(request.max_threads, || par_map_chunked(points, SENSITIVITY_CHUNK_SIZE, cancel, |i| {
+        let factor = sweep_factor(i, steps, range_min, range_max, spacing);
+        evaluate_metric(values, variable, factor, discount_rate, &inputs, metric)
+    }));
+    let sensitivity_results = match sensitivity_results {
+        Some(Some(results)) => results,
+        Some(None) => return SensitivityStatus::Cancelled,
+        None => return SensitivityStatus::ThreadPoolError,
+    };
     
     // Store results safely
-    unsafe {
-        for (i, &value) in sensitivity_results.iter().enumerate() {
-            *results.add(i) = value;
+    let normalization = SensitivityNormalization::new(values, discount_rate, metric, output_mode);
+    let results = unsafe { std::slice::from_raw_parts_mut(response.results, points) };
+    for (slot, &(value, _)) in results.iter_mut().zip(&sensitivity_results) {
+        *slot = normalization.apply(value);
+    }
+    if !response.statuses.is_null() {
+        let statuses = unsafe { std::slice::from_raw_parts_mut(response.statuses, points) };
+        for (slot, &(_, status)) in statuses.iter_mut().zip(&sensitivity_results) {
+            *slot = status;
+        }
+    }
+    if !response.factors.is_null() {
+        let factors = unsafe { std::slice::from_raw_parts_mut(response.factors, points) };
+        for (i, factor) in factors.iter_mut().enumerate() {
+            *factor = sweep_factor(i, steps, range_min, range_max, spacing);
         }
     }
+    response.points_written = points;
+    response.normalization = normalization;
+
+    SensitivityStatus::Ok
 }
 
-fn calculate_npv_with_rate(cash_flows: &[f64], discount_rate: f64) -> f64 {
-    calculate_npv(
-        cash_flows.as_ptr(),
-        cash_flows.len(),
-        discount_rate
-    )
+/// Factor at step `i` of a sweep over [range_min, range_max]; the last step
+/// is exactly `range_max` rather than the accumulated sum.
+fn sweep_factor(i: usize, steps: usize, range_min: f64, range_max: f64, spacing: SweepSpacing) -> f64 {
+    debug_assert!(steps > 0);
+    if i == 0 {
+        range_min
+    } else if i == steps {
+        range_max
+    } else {
+        match spacing {
+            SweepSpacing::Linear => range_min + (i as f64) * (range_max - range_min) / (steps as f64),
+            SweepSpacing::Logarithmic => range_min * (range_max / range_min).powf((i as f64) / (steps as f64)),
+        }
+    }
 }
 
-fn calculate_npv_with_modified_flows(cash_flows: &[f64], modifier: f64, discount_rate: f64) -> f64 {
-    let modified_flows: Vec<f64> = cash_flows.iter()
-        .enumerate()
-        .map(|(i, &v)| if i == 0 { v } else { v * modifier })
-        .collect();
-    
-    calculate_npv(
-        modified_flows.as_ptr(),
-        modified_flows.len(),
-        discount_rate
-    )
+/// Pre-tax operating flows for re-taxing at a different rate.
+///
+/// `base_values` are assumed to be the pre-tax flows taxed at
+/// `base_tax_rate` on positive years only, so a sweep at the base rate
+/// reproduces them exactly.
+#[derive(Clone, Copy)]
+struct TaxInputs<'a> {
+    pre_tax_flows: Option<&'a [f64]>,
+    base_tax_rate: f64,
 }
 
-fn calculate_with_volume_factor(
-    cash_flows: &[f64],
-    factor: f64,
-    discount_rate: f64,
+impl TaxInputs<'_> {
+    fn from_ptr(pre_tax_flows: *const f64, len: usize, base_tax_rate: f64) -> Self {
+        let pre_tax_flows = if pre_tax_flows.is_null() {
+            None
+        } else {
+            Some(unsafe { std::slice::from_raw_parts(pre_tax_flows, len) })
+        };
+        Self { pre_tax_flows, base_tax_rate }
+    }
+}
+
+/// Fixed and variable cost shares of each year's flow.
+#[derive(Clone, Copy)]
+struct CostRatios<'a> {
     fixed_cost_ratio: f64,
-    range_min: f64,
-    range_max: f64
-) -> f64 {
-    let initial_investment = cash_flows[0];
-    
-    // For production volume, the input range is typically [min, max]
-    // We want to convert this to a relative scale where:
-    // - The middle point (base case) maps to 1.0
-    // - Values below base case give factors < 1.0
-    // - Values above base case give factors > 1.0
-    
-    // Example: If range is [750000, 2250000], base case is 1500000
-    // Then 750000 should map to 0.5, 1500000 to 1.0, and 2250000 to 1.5
-    let base_volume = (range_max + range_min) / 2.0;
-    let relative_factor = if factor > 0.0 { factor / base_volume } else { 0.0 };
-    
-    let modified_flows: Vec<f64> = std::iter::once(initial_investment)
-        .chain(cash_flows[1..].iter().map(|&cf| {
-            // For each cash flow:
-            // 1. Fixed portion remains unchanged
-            // 2. Variable portion scales with production volume
-            // 3. Revenue (positive cash flows) also scales with production volume
-            
-            let is_revenue = cf > 0.0;
-            if is_revenue {
-                // Revenue scales directly with production volume
-                cf * relative_factor
-            } else {
-                // Costs are split between fixed and variable
-                let fixed_portion = cf * fixed_cost_ratio;
-                let variable_portion = cf * (1.0 - fixed_cost_ratio);
-                fixed_portion + (variable_portion * relative_factor)
+    variable_cost_ratio: f64,
+    /// Per-year overrides of the scalar ratios
+    fixed_by_year: Option<&'a [f64]>,
+    variable_by_year: Option<&'a [f64]>,
+}
+
+impl<'a> CostRatios<'a> {
+    fn uniform(fixed_cost_ratio: f64, variable_cost_ratio: f64) -> Self {
+        Self { fixed_cost_ratio, variable_cost_ratio, fixed_by_year: None, variable_by_year: None }
+    }
+
+    /// None when a per-year ratio lies outside [0, 1].
+    fn from_ptrs(
+        fixed_cost_ratio: f64,
+        variable_cost_ratio: f64,
+        fixed_cost_ratios: *const f64,
+        variable_cost_ratios: *const f64,
+        len: usize
+    ) -> Option<Self> {
+        let by_year = |ratios: *const f64| -> Option<Option<&'a [f64]>> {
+            if ratios.is_null() {
+                return Some(None);
             }
-        }))
-        .collect();
-    
-    calculate_npv(
-        modified_flows.as_ptr(),
-        modified_flows.len(),
-        discount_rate
-    )
+            let ratios = unsafe { std::slice::from_raw_parts(ratios, len) };
+            ratios.iter().all(|ratio| (0.0..=1.0).contains(ratio)).then_some(Some(ratios))
+        };
+        Some(Self {
+            fixed_cost_ratio,
+            variable_cost_ratio,
+            fixed_by_year: by_year(fixed_cost_ratios)?,
+            variable_by_year: by_year(variable_cost_ratios)?,
+        })
+    }
+
+    fn fixed(&self, year: usize) -> f64 {
+        self.fixed_by_year.map_or(self.fixed_cost_ratio, |ratios| ratios[year])
+    }
+
+    fn variable(&self, year: usize) -> f64 {
+        self.variable_by_year.map_or(self.variable_cost_ratio, |ratios| ratios[year])
+    }
 }
 
-fn calculate_with_opex_factor(cash_flows: &[f64], factor: f64, discount_rate: f64, fixed_cost_ratio: f64, variable_cost_ratio: f64) -> f64 {
-    let initial_investment = cash_flows[0];
-    let modified_flows: Vec<f64> = std::iter::once(initial_investment)
-        .chain(cash_flows[1..].iter().map(|&cf| {
-            // Use actual cost ratios from analysis
-            let variable_cost_portion = cf.abs() * variable_cost_ratio;
-            let fixed_portion = cf.abs() * fixed_cost_ratio;
-            let revenue_portion = cf + variable_cost_portion;
-            revenue_portion - (variable_cost_portion * factor) - fixed_portion
-        }))
-        .collect();
-    
-    calculate_npv(
-        modified_flows.as_ptr(),
-        modified_flows.len(),
-        discount_rate
-    )
+/// Revenue and costs (as positive amounts) of each year, kept apart so each
+/// modifier scales only the component it affects.
+///
+/// The net-flow helpers have to guess the components from the sign of a
+/// year's net flow, which is only exact when a year is all revenue or all
+/// cost; with the components, a volume factor at the base volume and an
+/// operating cost factor of 1 reproduce the net flows exactly. The cost
+/// ratios are then shares of each year's costs.
+#[derive(Clone, Copy)]
+struct FlowComponents<'a> {
+    revenues: &'a [f64],
+    costs: &'a [f64],
 }
 
-fn calculate_with_revenue_factor(cash_flows: &[f64], factor: f64, discount_rate: f64) -> f64 {
-    let initial_investment = cash_flows[0];
-    let modified_flows: Vec<f64> = std::iter::once(initial_investment)
-        .chain(cash_flows[1..].iter().map(|&cf| {
-            if cf > 0.0 {
-                // Scale only positive cash flows (revenue)
-                cf * factor
-            } else {
-                // Leave negative cash flows (costs) unchanged
-                cf
+impl FlowComponents<'_> {
+    fn net(&self) -> Vec<f64> {
+        self.revenues.iter().zip(self.costs).map(|(revenue, cost)| revenue - cost).collect()
+    }
+
+    /// Net flows with `variable` set to `factor`, for the variables that
+    /// act on a component; None for the discount and tax rates, which act
+    /// on the net flows.
+    fn flows(&self, variable: SensitivityVariable, factor: f64, inputs: &SweepInputs) -> Option<Vec<f64>> {
+        if matches!(variable, SensitivityVariable::DiscountRate | SensitivityVariable::TaxRate) {
+            return None;
+        }
+        let ratios = &inputs.ratios;
+        let relative_volume = if factor > 0.0 { factor / inputs.base_volume() } else { 0.0 };
+
+        // Each change is written as a correction to revenue - cost, so a
+        // neutral factor leaves the net flow bit for bit unchanged
+        Some(self.revenues.iter().zip(self.costs).enumerate().map(|(year, (&revenue, &cost))| {
+            match (year, variable) {
+                (0, SensitivityVariable::CapitalInvestment) => revenue - cost * factor,
+                (0, _) => revenue - cost,
+                // Revenue and the variable share of costs scale with volume
+                (_, SensitivityVariable::ProductionVolume) => {
+                    revenue * relative_volume - cost - cost * (1.0 - ratios.fixed(year)) * (relative_volume - 1.0)
+                }
+                (_, SensitivityVariable::OperatingCosts) => revenue - cost - cost * ratios.variable(year) * (factor - 1.0),
+                (_, SensitivityVariable::Revenue) => revenue * factor - cost,
+                _ => revenue - cost,
             }
-        }))
-        .collect();
-    
-    calculate_npv(
-        modified_flows.as_ptr(),
-        modified_flows.len(),
-        discount_rate
-    )
+        }).collect())
+    }
+}
+
+/// Cost split, volume range and tax inputs shared by the per-variable NPV
+/// helpers.
+struct SweepInputs<'a> {
+    ratios: CostRatios<'a>,
+    /// Production volume sweep range
+    range_min: f64,
+    range_max: f64,
+    /// Production volume of the unmodified flows; 0 or NaN means the
+    /// midpoint of the range
+    base_volume: f64,
+    tax: TaxInputs<'a>,
+    /// Separate revenue and cost arrays behind the net flows, when known
+    components: Option<FlowComponents<'a>>,
+}
+
+impl<'a> SweepInputs<'a> {
+    /// A unit volume range makes the volume factor relative to the base volume.
+    fn relative(fixed_cost_ratio: f64, variable_cost_ratio: f64, tax: TaxInputs<'a>) -> Self {
+        let ratios = CostRatios::uniform(fixed_cost_ratio, variable_cost_ratio);
+        Self { ratios, range_min: 1.0, range_max: 1.0, base_volume: 1.0, tax, components: None }
+    }
+
+    /// The volume a swept volume is divided by to get its relative factor.
+    fn base_volume(&self) -> f64 {
+        if self.base_volume == 0.0 || self.base_volume.is_nan() {
+            (self.range_max + self.range_min) / 2.0
+        } else {
+            self.base_volume
+        }
+    }
+}
+
+/// NPV with `variable` set to `factor`, as evaluated at one step of a
+/// sensitivity sweep. NaN for `TaxRate` without pre-tax flows.
+fn evaluate_variable(values: &[f64], variable: SensitivityVariable, factor: f64, discount_rate: f64, inputs: &SweepInputs) -> f64 {
+    evaluate_metric(values, variable, factor, discount_rate, inputs, SensitivityMetric::Npv).0
+}
+
+/// `metric` with `variable` set to `factor`: the flows are modified exactly
+/// as for the NPV, then evaluated with the chosen metric. NaN, with the
+/// reason in the status, when the metric does not exist at this point.
+fn evaluate_metric(
+    values: &[f64],
+    variable: SensitivityVariable,
+    factor: f64,
+    discount_rate: f64,
+    inputs: &SweepInputs,
+    metric: SensitivityMetric
+) -> (f64, SensitivityPointStatus) {
+    if let Some(flows) = inputs.components.and_then(|components| components.flows(variable, factor, inputs)) {
+        return evaluate_flows(&flows, discount_rate, metric);
+    }
+    let (flows, discount_rate) = match variable {
+        SensitivityVariable::DiscountRate => (Cow::Borrowed(values), factor),
+        SensitivityVariable::ProductionVolume => (Cow::Owned(flows_with_volume_factor(values, factor, &inputs.ratios, inputs.base_volume())), discount_rate),
+        SensitivityVariable::OperatingCosts => (Cow::Owned(flows_with_opex_factor(values, factor, &inputs.ratios)), discount_rate),
+        SensitivityVariable::Revenue => (Cow::Owned(flows_with_revenue_factor(values, factor)), discount_rate),
+        SensitivityVariable::CapitalInvestment => (Cow::Owned(flows_with_capex_factor(values, factor)), discount_rate),
+        SensitivityVariable::TaxRate => match inputs.tax.pre_tax_flows {
+            Some(pre_tax_flows) => (Cow::Owned(flows_with_tax_rate(values, pre_tax_flows, factor, inputs.tax.base_tax_rate)), discount_rate),
+            None => return (f64::NAN, SensitivityPointStatus::Ok),
+        },
+    };
+
+    evaluate_flows(&flows, discount_rate, metric)
+}
+
+/// `metric` of already modified flows at `discount_rate`.
+fn evaluate_flows(flows: &[f64], discount_rate: f64, metric: SensitivityMetric) -> (f64, SensitivityPointStatus) {
+    match metric {
+        SensitivityMetric::Npv => (calculate_npv_with_rate(flows, discount_rate), SensitivityPointStatus::Ok),
+        SensitivityMetric::Irr => match calculate_irr_from_slice(flows) {
+            Some(irr) => (irr, SensitivityPointStatus::Ok),
+            None => (f64::NAN, SensitivityPointStatus::NoIrr),
+        },
+        SensitivityMetric::DiscountedPayback => match discounted_payback_from_slice(flows, discount_rate) {
+            Some(payback) => (payback, SensitivityPointStatus::Ok),
+            None => (f64::NAN, SensitivityPointStatus::NeverPaysBack),
+        },
+    }
 }
 
+/// Tornado diagram data for every `SensitivityVariable` in one call.
+///
+/// Each variable is moved to `low_factor` and `high_factor` times its base
+/// value (the discount rate itself for `DiscountRate`, a relative multiplier
+/// for the others) with the same helpers as `run_sensitivity_analysis`.
+/// `results` receives a `TORNADO_VARIABLES` x 3 row-major matrix of
+/// (low NPV, base NPV, high NPV), rows sorted by descending
+/// |high NPV - low NPV|, and `order[row]` is the `SensitivityVariable` index
+/// of each row. The base column is the NPV of the unmodified flows.
+///
+/// `metric`, a `SensitivityMetric` id, selects the quantity in each column
+/// as in `run_sensitivity_analysis_v2`; values are NaN where it does not
+/// exist, including the whole `DiscountRate` row for IRR. `output_mode`, a
+/// `SensitivityOutputMode` id, expresses every column relative to the base
+/// column, whose value and the mode applied go to `normalization` when it
+/// is non-null; the row order is the same in every mode. Returns false on
+/// null pointers, `len` of 0 or an unknown metric or mode id.
+///
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2`; its values are NaN when `pre_tax_flows` is
+/// null.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length TORNADO_VARIABLES * 3
+/// - order points to a valid array of usize with length TORNADO_VARIABLES
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
 #[no_mangle]
-pub extern "C" fn analyze_sensitivity(
-    cash_flows: *const c_double,
+pub extern "C" fn run_tornado_analysis(
+    base_values: *const f64,
     len: usize,
-    discount_rate: c_double,
-    _production_volume: c_double,  // Prefix with _ to indicate intentionally unused
-    _operating_costs: c_double,    // Prefix with _ to indicate intentionally unused
-    _revenue: c_double            // Prefix with _ to indicate intentionally unused
-) -> *mut c_double {
-    let cash_flows_slice = unsafe {
-        std::slice::from_raw_parts(cash_flows, len)
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    low_factor: f64,
+    high_factor: f64,
+    metric: u32,
+    output_mode: u32,
+    results: *mut f64,
+    order: *mut usize,
+    normalization: *mut SensitivityNormalization
+) -> bool {
+    if base_values.is_null() || results.is_null() || order.is_null() || len == 0 {
+        return false;
+    }
+    let (metric, output_mode) = match (SensitivityMetric::from_id(metric), SensitivityOutputMode::from_id(output_mode)) {
+        (Some(metric), Some(output_mode)) => (metric, output_mode),
+        _ => return false,
     };
-    
-    // Calculate base NPV
-    let base_npv = calculate_npv_with_rate(cash_flows_slice, discount_rate);
-    
-    // Calculate NPV with modified discount rate
-    let dr_low = discount_rate * 0.8;
-    let dr_high = discount_rate * 1.2;
-    let npv_dr_low = calculate_npv_with_rate(cash_flows_slice, dr_low);
-    let npv_dr_high = calculate_npv_with_rate(cash_flows_slice, dr_high);
-    
-    // Calculate NPV with modified production volume
-    let pv_low = calculate_npv_with_modified_flows(cash_flows_slice, 0.8, discount_rate);
-    let pv_high = calculate_npv_with_modified_flows(cash_flows_slice, 1.2, discount_rate);
-    
-    // Calculate NPV with modified operating costs
-    let opex_low = calculate_npv_with_modified_flows(cash_flows_slice, 0.8, discount_rate);
-    let opex_high = calculate_npv_with_modified_flows(cash_flows_slice, 1.2, discount_rate);
-    
-    // Calculate NPV with modified revenue
-    let rev_low = calculate_npv_with_modified_flows(cash_flows_slice, 0.8, discount_rate);
-    let rev_high = calculate_npv_with_modified_flows(cash_flows_slice, 1.2, discount_rate);
-    
-    // Create results array
-    let results = vec![
-        base_npv,
-        npv_dr_low, npv_dr_high,
-        pv_low, pv_high,
-        opex_low, opex_high,
-        rev_low, rev_high
-    ];
-    
-    // Convert to raw pointer
-    let ptr = results.as_ptr() as *mut c_double;
-    std::mem::forget(results);  // Prevent deallocation
-    ptr
-} 
\ No newline at end of file
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, TORNADO_VARIABLES * 3) };
+    let order = unsafe { std::slice::from_raw_parts_mut(order, TORNADO_VARIABLES) };
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let normalized = SensitivityNormalization::new(values, discount_rate, metric, output_mode);
+    let base_value = normalized.base_value;
+
+    let mut rows: Vec<(usize, [f64; 3])> = SensitivityVariable::ALL.iter().map(|variable| {
+        if matches!(variable, SensitivityVariable::DiscountRate) && metric == SensitivityMetric::Irr {
+            return (*variable as usize, [f64::NAN; 3]);
+        }
+        let base = variable.base_value(discount_rate, &inputs);
+        let (low, high) = (base * low_factor, base * high_factor);
+        let (low_value, _) = evaluate_metric(values, *variable, low, discount_rate, &inputs, metric);
+        let (high_value, _) = evaluate_metric(values, *variable, high, discount_rate, &inputs, metric);
+        (*variable as usize, [low_value, base_value, high_value])
+    }).collect();
+
+    // Stable, so equal swings keep the enum order; rows without a value go last
+    let swing = |npvs: &[f64; 3]| {
+        let swing = (npvs[2] - npvs[0]).abs();
+        if swing.is_nan() { f64::NEG_INFINITY } else { swing }
+    };
+    rows.sort_by(|(_, a), (_, b)| swing(b).total_cmp(&swing(a)));
+
+    for (row, (index, npvs)) in rows.into_iter().enumerate() {
+        order[row] = index;
+        results[row * 3..row * 3 + 3].copy_from_slice(&npvs.map(|npv| normalized.apply(npv)));
+    }
+    normalized.write_to(normalization);
+
+    true
+}
+
+/// Values per variable written by `run_sensitivity_elasticity`.
+///
+/// | Index | Value |
+/// |-------|-------|
+/// | 0     | central-difference elasticity at the base point |
+/// | 1     | arc elasticity over [low_factor, high_factor] |
+/// | 2     | 1.0 when the base NPV is ~0 and both values are absolute sensitivities (NPV change per 100% change of the variable), else 0.0 |
+pub const ELASTICITY_RESULT_WIDTH: usize = 3;
+
+// Base NPVs smaller than this fraction of the gross discounted flows count as zero
+const ZERO_NPV_TOLERANCE: f64 = 1e-9;
+
+/// Elasticities (%ΔNPV per %Δvariable) of every `SensitivityVariable`.
+///
+/// The base point of `DiscountRate` is `discount_rate`; the other variables
+/// are relative multipliers with base 1, evaluated with the same helpers as
+/// `run_sensitivity_analysis`. The central difference moves the variable by
+/// ±`relative_step` of its base value; the arc elasticity spans
+/// `low_factor`..`high_factor` times the base value.
+///
+/// `results` receives `TORNADO_VARIABLES` rows of `ELASTICITY_RESULT_WIDTH`
+/// values in `SensitivityVariable` order.
+///
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2`; its values are NaN when `pre_tax_flows` is
+/// null.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length
+///   TORNADO_VARIABLES * ELASTICITY_RESULT_WIDTH
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn run_sensitivity_elasticity(
+    base_values: *const f64,
+    len: usize,
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    relative_step: f64,
+    low_factor: f64,
+    high_factor: f64,
+    results: *mut f64
+) -> bool {
+    if base_values.is_null() || results.is_null() || len == 0
+        || !(relative_step > 0.0 && high_factor > low_factor)
+    {
+        return false;
+    }
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, TORNADO_VARIABLES * ELASTICITY_RESULT_WIDTH) };
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let gross = gross_discounted_flows(values, discount_rate);
+
+    for variable in SensitivityVariable::ALL {
+        let index = variable as usize;
+        let base = variable.base_value(discount_rate, &inputs);
+        let npv_at = |multiplier: f64| evaluate_variable(values, variable, base * multiplier, discount_rate, &inputs);
+
+        let base_npv = npv_at(1.0);
+        let central = (npv_at(1.0 + relative_step) - npv_at(1.0 - relative_step)) / (2.0 * relative_step);
+        let arc = (npv_at(high_factor) - npv_at(low_factor)) / (high_factor - low_factor);
+
+        let row = &mut results[index * ELASTICITY_RESULT_WIDTH..(index + 1) * ELASTICITY_RESULT_WIDTH];
+        if base_npv.abs() <= ZERO_NPV_TOLERANCE * gross {
+            row.copy_from_slice(&[central, arc, 1.0]);
+        } else {
+            row.copy_from_slice(&[central / base_npv, arc / base_npv, 0.0]);
+        }
+    }
+
+    true
+}
+
+/// Values per variable written by `compute_local_sensitivities`.
+///
+/// | Index | Value |
+/// |-------|-------|
+/// | 0     | dNPV/dx at the base point, per unit of the variable |
+/// | 1     | elasticity (dNPV/dx * x / NPV), NaN when the base NPV is ~0 |
+/// | 2     | 1.0 when the NPV change over the difference is within rounding noise, else 0.0 |
+pub const LOCAL_SENSITIVITY_RESULT_WIDTH: usize = 3;
+
+// Step used by compute_local_sensitivities when the caller passes 0
+const DEFAULT_LOCAL_STEP: f64 = 0.01;
+// NPV changes within this many ulps of the gross discounted flows are noise
+const ROUNDOFF_ULPS: f64 = 1e3;
+
+/// Derivative of NPV with respect to every `SensitivityVariable` at the base
+/// point, by central finite differences.
+///
+/// Each variable moves by ±`relative_step` (0 means 1%) of its base value,
+/// or by ±`relative_step` in absolute terms when the base value is 0 (a zero
+/// discount or tax rate), with the same helpers as
+/// `run_sensitivity_analysis`. When the NPV difference is too small to rise
+/// above floating-point rounding of the flows, the derivative is still
+/// written but flagged; use a larger step.
+///
+/// `results` receives `TORNADO_VARIABLES` rows of
+/// `LOCAL_SENSITIVITY_RESULT_WIDTH` values in `SensitivityVariable` order.
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2`; its row is NaN when `pre_tax_flows` is null.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length
+///   TORNADO_VARIABLES * LOCAL_SENSITIVITY_RESULT_WIDTH
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn compute_local_sensitivities(
+    base_values: *const f64,
+    len: usize,
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    relative_step: f64,
+    results: *mut f64
+) -> bool {
+    if base_values.is_null() || results.is_null() || len == 0
+        || relative_step.is_nan() || relative_step < 0.0
+    {
+        return false;
+    }
+    let relative_step = if relative_step == 0.0 { DEFAULT_LOCAL_STEP } else { relative_step };
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, TORNADO_VARIABLES * LOCAL_SENSITIVITY_RESULT_WIDTH) };
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let base_npv = calculate_npv_with_rate(values, discount_rate);
+    let gross = gross_discounted_flows(values, discount_rate);
+
+    for variable in SensitivityVariable::ALL {
+        let index = variable as usize;
+        let row = &mut results[index * LOCAL_SENSITIVITY_RESULT_WIDTH..(index + 1) * LOCAL_SENSITIVITY_RESULT_WIDTH];
+        if matches!(variable, SensitivityVariable::TaxRate) && inputs.tax.pre_tax_flows.is_none() {
+            row.fill(f64::NAN);
+            continue;
+        }
+
+        let base = variable.base_value(discount_rate, &inputs);
+        let step = if base == 0.0 { relative_step } else { relative_step * base.abs() };
+        let change = evaluate_variable(values, variable, base + step, discount_rate, &inputs)
+            - evaluate_variable(values, variable, base - step, discount_rate, &inputs);
+        let derivative = change / (2.0 * step);
+
+        let elasticity = if base_npv.abs() <= ZERO_NPV_TOLERANCE * gross {
+            f64::NAN
+        } else {
+            derivative * base / base_npv
+        };
+        let noisy = change.abs() <= ROUNDOFF_ULPS * f64::EPSILON * gross;
+        row.copy_from_slice(&[derivative, elasticity, if noisy { 1.0 } else { 0.0 }]);
+    }
+
+    true
+}
+
+/// Spider plot data: every `SensitivityVariable` swept over the same
+/// relative range.
+///
+/// Step `i` changes each variable by `min_change + i * (max_change -
+/// min_change) / steps` of its base value (-0.3 for -30%), using the same
+/// helpers as `run_sensitivity_analysis`. `results` receives a
+/// `TORNADO_VARIABLES` x (steps + 1) row-major matrix in
+/// `SensitivityVariable` order, expressed relative to the NPV of the
+/// unmodified flows as the `SensitivityOutputMode` with id `output_mode`
+/// selects; that NPV and the mode applied go to `normalization` when it is
+/// non-null. Returns false on null pointers, `len` or `steps` of 0, or an
+/// unknown mode id.
+///
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2`; its values are NaN when `pre_tax_flows` is
+/// null.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length
+///   TORNADO_VARIABLES * (steps + 1)
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn run_spider_analysis(
+    base_values: *const f64,
+    len: usize,
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    min_change: f64,
+    max_change: f64,
+    steps: usize,
+    output_mode: u32,
+    results: *mut f64,
+    normalization: *mut SensitivityNormalization
+) -> bool {
+    if base_values.is_null() || results.is_null() || len == 0 || steps == 0 {
+        return false;
+    }
+    let output_mode = match SensitivityOutputMode::from_id(output_mode) {
+        Some(mode) => mode,
+        None => return false,
+    };
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let columns = steps + 1;
+    let results = unsafe { std::slice::from_raw_parts_mut(results, TORNADO_VARIABLES * columns) };
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let normalized = SensitivityNormalization::new(values, discount_rate, SensitivityMetric::Npv, output_mode);
+    let step_size = (max_change - min_change) / steps as f64;
+
+    for variable in SensitivityVariable::ALL {
+        let index = variable as usize;
+        let base = variable.base_value(discount_rate, &inputs);
+        let row = &mut results[index * columns..(index + 1) * columns];
+        for (i, slot) in row.iter_mut().enumerate() {
+            let change = min_change + i as f64 * step_size;
+            let npv = evaluate_variable(values, variable, base * (1.0 + change), discount_rate, &inputs);
+            *slot = normalized.apply(npv);
+        }
+    }
+    normalized.write_to(normalization);
+
+    true
+}
+
+/// Outcome of the switching value search for one variable.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SwitchingStatus {
+    Found = 0,
+    /// NPV stays positive over the whole range
+    NoCrossingInRange = 1,
+    /// NPV is negative or zero over the whole range
+    NpvNeverPositive = 2,
+}
+
+// Bisection steps before settling for the best bracket midpoint
+const MAX_BISECTIONS: usize = 200;
+
+/// Switching value of every `SensitivityVariable`: the value at which NPV
+/// crosses zero.
+///
+/// `ranges` holds a (min, max) pair per variable in `SensitivityVariable`
+/// order, in the same units as `run_sensitivity_analysis` (including the
+/// midpoint convention for production volume). Each range is scanned in
+/// `steps` equal steps, the first sign change is bracketed and then bisected
+/// until |NPV| <= `tolerance`. `values` receives the switching value (NaN
+/// unless found) and `statuses` the outcome per variable.
+///
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2`; its values are NaN when `pre_tax_flows` is
+/// null.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - ranges points to a valid array of f64 with length TORNADO_VARIABLES * 2
+/// - values and statuses point to TORNADO_VARIABLES elements each
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn find_switching_values(
+    base_values: *const f64,
+    len: usize,
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    ranges: *const f64,
+    steps: usize,
+    tolerance: f64,
+    values: *mut f64,
+    statuses: *mut SwitchingStatus
+) -> bool {
+    if base_values.is_null() || ranges.is_null() || values.is_null() || statuses.is_null()
+        || len == 0 || steps == 0 || tolerance.is_nan() || tolerance <= 0.0
+    {
+        return false;
+    }
+
+    let flows = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let ranges = unsafe { std::slice::from_raw_parts(ranges, TORNADO_VARIABLES * 2) };
+    let values = unsafe { std::slice::from_raw_parts_mut(values, TORNADO_VARIABLES) };
+    let statuses = unsafe { std::slice::from_raw_parts_mut(statuses, TORNADO_VARIABLES) };
+    let tax = TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate);
+
+    for variable in SensitivityVariable::ALL {
+        let index = variable as usize;
+        let (range_min, range_max) = (ranges[index * 2], ranges[index * 2 + 1]);
+        let ratios = CostRatios::uniform(fixed_cost_ratio, variable_cost_ratio);
+        let inputs = SweepInputs { ratios, range_min, range_max, base_volume: 0.0, tax, components: None };
+        let npv_at = |factor: f64| evaluate_variable(flows, variable, factor, discount_rate, &inputs);
+
+        let (value, status) = switching_value(npv_at, range_min, range_max, steps, tolerance);
+        values[index] = value;
+        statuses[index] = status;
+    }
+
+    true
+}
+
+/// Scan `npv_at` over [range_min, range_max] for the first sign change and
+/// bisect it down to |NPV| <= tolerance.
+fn switching_value(npv_at: impl Fn(f64) -> f64, range_min: f64, range_max: f64, steps: usize, tolerance: f64) -> (f64, SwitchingStatus) {
+    let step_size = (range_max - range_min) / steps as f64;
+    let mut lower = range_min;
+    let mut lower_npv = npv_at(lower);
+    let mut ever_positive = lower_npv > 0.0;
+
+    for i in 1..=steps {
+        if lower_npv.abs() <= tolerance {
+            return (lower, SwitchingStatus::Found);
+        }
+        let upper = range_min + i as f64 * step_size;
+        let upper_npv = npv_at(upper);
+        ever_positive |= upper_npv > 0.0;
+
+        if lower_npv.signum() != upper_npv.signum() {
+            return (bisect(&npv_at, lower, lower_npv, upper, tolerance), SwitchingStatus::Found);
+        }
+        lower = upper;
+        lower_npv = upper_npv;
+    }
+
+    if lower_npv.abs() <= tolerance {
+        (lower, SwitchingStatus::Found)
+    } else if ever_positive {
+        (f64::NAN, SwitchingStatus::NoCrossingInRange)
+    } else {
+        (f64::NAN, SwitchingStatus::NpvNeverPositive)
+    }
+}
+
+fn bisect(npv_at: impl Fn(f64) -> f64, mut lower: f64, mut lower_npv: f64, mut upper: f64, tolerance: f64) -> f64 {
+    let mut mid = (lower + upper) / 2.0;
+    for _ in 0..MAX_BISECTIONS {
+        mid = (lower + upper) / 2.0;
+        let mid_npv = npv_at(mid);
+        if mid_npv.abs() <= tolerance {
+            break;
+        }
+        if mid_npv.signum() == lower_npv.signum() {
+            lower = mid;
+            lower_npv = mid_npv;
+        } else {
+            upper = mid;
+        }
+    }
+    mid
+}
+
+/// How `run_morris_screening` lays out its evaluation points, passed by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MorrisDesign {
+    /// Morris trajectories: each step moves one more input by ±delta
+    Trajectory = 0,
+    /// Radial (Campolongo) design: each point moves one input of a base
+    /// point to its value in an auxiliary point
+    Radial = 1,
+}
+
+impl MorrisDesign {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Trajectory),
+            1 => Some(Self::Radial),
+            _ => None,
+        }
+    }
+}
+
+/// Values per variable written by `run_morris_screening`: mean elementary
+/// effect (mu), mean absolute elementary effect (mu*) and the standard
+/// deviation of the elementary effects (sigma).
+pub const MORRIS_RESULT_WIDTH: usize = 3;
+
+/// Morris elementary effects screening of every `SensitivityVariable`.
+///
+/// `ranges` holds a (min, max) pair per variable in `SensitivityVariable`
+/// order: the discount rate and tax rate as absolute values, the other
+/// variables as relative multipliers (1 is the base case). Each range is
+/// split into a grid of `levels` values and every trajectory yields one
+/// elementary effect per variable, the NPV change per unit of normalized
+/// range, so the effects are comparable across variables. A zero-width range
+/// is a dummy input whose effects are all 0.
+///
+/// All variables move together, chaining the adjustments of the
+/// per-variable helpers. `TaxRate` uses `pre_tax_flows` and `base_tax_rate`
+/// as in `run_sensitivity_analysis_v2`; without pre-tax flows it is left at
+/// the base rate and its row is NaN.
+///
+/// Each trajectory is seeded with `seed + trajectory`, so the same inputs
+/// always produce the same screening. `results` receives `TORNADO_VARIABLES`
+/// rows of `MORRIS_RESULT_WIDTH` values; sigma is NaN for a single trajectory.
+/// Returns false on null pointers, `len` of 0, a non-finite or inverted
+/// range, fewer than 2 levels, no trajectories or a `design` that is not a
+/// `MorrisDesign` id.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - ranges points to a valid array of f64 with length TORNADO_VARIABLES * 2
+/// - results points to a valid array of f64 with length
+///   TORNADO_VARIABLES * MORRIS_RESULT_WIDTH
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn run_morris_screening(
+    base_values: *const f64,
+    len: usize,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    ranges: *const f64,
+    trajectories: usize,
+    levels: usize,
+    design: u32,
+    seed: u64,
+    results: *mut f64
+) -> bool {
+    if base_values.is_null() || ranges.is_null() || results.is_null()
+        || len == 0 || trajectories == 0 || levels < 2
+    {
+        return false;
+    }
+    let design = match MorrisDesign::from_id(design) {
+        Some(design) => design,
+        None => return false,
+    };
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let ranges = unsafe { std::slice::from_raw_parts(ranges, TORNADO_VARIABLES * 2) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, TORNADO_VARIABLES * MORRIS_RESULT_WIDTH) };
+    if ranges.chunks_exact(2).any(|range| !(range[0].is_finite() && range[1].is_finite() && range[0] <= range[1])) {
+        return false;
+    }
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let npv_at = |unit: &[f64; TORNADO_VARIABLES]| {
+        let point = std::array::from_fn(|i| ranges[2 * i] + unit[i] * (ranges[2 * i + 1] - ranges[2 * i]));
+        evaluate_point(values, &point, &inputs)
+    };
+
+    let mut effects = vec![[0.0; TORNADO_VARIABLES]; trajectories];
+    for (trajectory, effects) in effects.iter_mut().enumerate() {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(trajectory as u64));
+        let grid = |rng: &mut StdRng| rng.gen_range(0..levels) as f64 / (levels - 1) as f64;
+        let mut order: [usize; TORNADO_VARIABLES] = std::array::from_fn(|i| i);
+        order.shuffle(&mut rng);
+
+        let mut point: [f64; TORNADO_VARIABLES] = std::array::from_fn(|_| grid(&mut rng));
+        let mut npv = npv_at(&point);
+        match design {
+            MorrisDesign::Trajectory => {
+                let delta = (levels / 2) as f64 / (levels - 1) as f64;
+                for &i in &order {
+                    let step = if point[i] + delta <= 1.0 { delta } else { -delta };
+                    point[i] += step;
+                    let moved = npv_at(&point);
+                    effects[i] = (moved - npv) / step;
+                    npv = moved;
+                }
+            }
+            MorrisDesign::Radial => {
+                for &i in &order {
+                    // Redraw until the auxiliary value differs from the base
+                    let mut auxiliary = grid(&mut rng);
+                    while auxiliary == point[i] {
+                        auxiliary = grid(&mut rng);
+                    }
+                    let mut moved_point = point;
+                    moved_point[i] = auxiliary;
+                    effects[i] = (npv_at(&moved_point) - npv) / (auxiliary - point[i]);
+                }
+            }
+        }
+    }
+
+    let n = trajectories as f64;
+    for variable in SensitivityVariable::ALL {
+        let index = variable as usize;
+        let row = &mut results[index * MORRIS_RESULT_WIDTH..(index + 1) * MORRIS_RESULT_WIDTH];
+        if matches!(variable, SensitivityVariable::TaxRate) && inputs.tax.pre_tax_flows.is_none() {
+            row.fill(f64::NAN);
+            continue;
+        }
+        let mu = effects.iter().map(|e| e[index]).sum::<f64>() / n;
+        let mu_star = effects.iter().map(|e| e[index].abs()).sum::<f64>() / n;
+        let sigma = if trajectories > 1 {
+            (effects.iter().map(|e| (e[index] - mu).powi(2)).sum::<f64>() / (n - 1.0)).sqrt()
+        } else {
+            f64::NAN
+        };
+        row.copy_from_slice(&[mu, mu_star, sigma]);
+    }
+
+    true
+}
+
+/// Most variables `run_factorial_sensitivity` combines: 3^6 = 729 points.
+pub const MAX_FACTORIAL_VARIABLES: usize = TORNADO_VARIABLES;
+
+/// Number of values written by `run_factorial_sensitivity` for
+/// `n_variables` variables, or 0 when that count is not supported.
+///
+/// | Index | Value |
+/// |-------|-------|
+/// | 0 .. 3^k | NPV of every low/base/high combination, see `run_factorial_sensitivity` |
+/// | next k | main effect of each variable |
+/// | next k(k-1)/2 | two-way interaction of each pair (0,1), (0,2), .., (k-2,k-1) |
+#[no_mangle]
+pub extern "C" fn factorial_sensitivity_result_len(n_variables: usize) -> usize {
+    if n_variables == 0 || n_variables > MAX_FACTORIAL_VARIABLES {
+        return 0;
+    }
+    3usize.pow(n_variables as u32) + n_variables + n_variables * (n_variables - 1) / 2
+}
+
+/// Full-factorial sensitivity: NPV at every combination of a low, base and
+/// high value of `n_variables` variables.
+///
+/// `variables` lists distinct `SensitivityVariable` indices and `levels`
+/// holds a (low, base, high) triple per listed variable, in the units of
+/// `run_tornado_analysis` (the discount and tax rates as absolute values,
+/// the others as multipliers). Unlisted variables stay at their base value.
+/// All listed variables move together as in `run_morris_screening`.
+///
+/// The table lists the 3^k combinations with the first variable changing
+/// slowest; the level digit of variable j (0 = low, 1 = base, 2 = high) in
+/// row r is `(r / 3^(k-1-j)) % 3`. The main effect of a variable is the
+/// mean NPV at its high level minus the mean at its low level; the
+/// interaction of a pair is half the difference between the mean NPV where
+/// both are at the same extreme and where they are at opposite extremes, so
+/// it is 0 when the two act additively.
+///
+/// `TaxRate` uses `pre_tax_flows` and `base_tax_rate` as in
+/// `run_sensitivity_analysis_v2` and can only be listed with pre-tax flows.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - variables points to a valid array of usize with length n_variables
+/// - levels points to a valid array of f64 with length n_variables * 3
+/// - results points to a valid array of f64 with length
+///   factorial_sensitivity_result_len(n_variables)
+/// - pre_tax_flows is null or points to a valid array of f64 with length len
+#[no_mangle]
+pub extern "C" fn run_factorial_sensitivity(
+    base_values: *const f64,
+    len: usize,
+    discount_rate: f64,
+    fixed_cost_ratio: f64,
+    variable_cost_ratio: f64,
+    pre_tax_flows: *const f64,
+    base_tax_rate: f64,
+    variables: *const usize,
+    n_variables: usize,
+    levels: *const f64,
+    results: *mut f64
+) -> bool {
+    let result_len = factorial_sensitivity_result_len(n_variables);
+    if base_values.is_null() || variables.is_null() || levels.is_null() || results.is_null()
+        || len == 0 || result_len == 0
+    {
+        return false;
+    }
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let indices = unsafe { std::slice::from_raw_parts(variables, n_variables) };
+    let levels = unsafe { std::slice::from_raw_parts(levels, n_variables * 3) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, result_len) };
+
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, TaxInputs::from_ptr(pre_tax_flows, len, base_tax_rate));
+    let mut listed = Vec::with_capacity(n_variables);
+    for (j, &index) in indices.iter().enumerate() {
+        let variable = match SensitivityVariable::from_index(index) {
+            Some(variable) => variable,
+            None => return false,
+        };
+        let missing_tax = matches!(variable, SensitivityVariable::TaxRate) && inputs.tax.pre_tax_flows.is_none();
+        if indices[..j].contains(&index) || missing_tax {
+            return false;
+        }
+        listed.push(variable);
+    }
+
+    let combinations = 3usize.pow(n_variables as u32);
+    let digit = |row: usize, j: usize| (row / 3usize.pow((n_variables - 1 - j) as u32)) % 3;
+    let base_point: [f64; TORNADO_VARIABLES] = std::array::from_fn(|i| SensitivityVariable::ALL[i].base_value(discount_rate, &inputs));
+
+    let (table, effects) = results.split_at_mut(combinations);
+    for (row, slot) in table.iter_mut().enumerate() {
+        let mut point = base_point;
+        for (j, variable) in listed.iter().enumerate() {
+            point[*variable as usize] = levels[j * 3 + digit(row, j)];
+        }
+        *slot = evaluate_point(values, &point, &inputs);
+    }
+
+    // Mean NPV over the rows selected by `keep`
+    let mean_where = |keep: &dyn Fn(usize) -> bool| {
+        let (sum, count) = table.iter().enumerate()
+            .filter(|&(row, _)| keep(row))
+            .fold((0.0, 0usize), |(sum, count), (_, &npv)| (sum + npv, count + 1));
+        sum / count as f64
+    };
+    let (main_effects, interactions) = effects.split_at_mut(n_variables);
+    for (j, effect) in main_effects.iter_mut().enumerate() {
+        *effect = mean_where(&|row| digit(row, j) == 2) - mean_where(&|row| digit(row, j) == 0);
+    }
+    let pairs = (0..n_variables).flat_map(|a| (a + 1..n_variables).map(move |b| (a, b)));
+    for ((a, b), interaction) in pairs.zip(interactions.iter_mut()) {
+        let at = |level_a: usize, level_b: usize| mean_where(&|row| digit(row, a) == level_a && digit(row, b) == level_b);
+        *interaction = (at(2, 2) + at(0, 0) - at(2, 0) - at(0, 2)) / 2.0;
+    }
+
+    true
+}
+
+/// NPV with every variable moved at once: `point` holds one value per
+/// `SensitivityVariable`, in the units of `run_tornado_analysis`. The
+/// per-variable adjustments are chained on each flow as in the single
+/// variable sweeps, so a point at the base values reproduces the base NPV.
+/// Tax is left at the base rate without pre-tax flows.
+fn evaluate_point(values: &[f64], point: &[f64; TORNADO_VARIABLES], inputs: &SweepInputs) -> f64 {
+    let volume = point[SensitivityVariable::ProductionVolume as usize];
+    let relative_volume = if volume > 0.0 { volume / inputs.base_volume() } else { 0.0 };
+    let ratios = &inputs.ratios;
+
+    let modified_flows: Vec<f64> = std::iter::once(values[0] * point[SensitivityVariable::CapitalInvestment as usize])
+        .chain(values.iter().enumerate().skip(1).map(|(year, &cf)| {
+            let cf = volume_adjusted_flow(cf, relative_volume, ratios.fixed(year));
+            let cf = opex_adjusted_flow(cf, point[SensitivityVariable::OperatingCosts as usize], ratios.fixed(year), ratios.variable(year));
+            let cf = revenue_adjusted_flow(cf, point[SensitivityVariable::Revenue as usize]);
+            match inputs.tax.pre_tax_flows {
+                Some(pre_tax_flows) => tax_adjusted_flow(cf, pre_tax_flows[year], point[SensitivityVariable::TaxRate as usize], inputs.tax.base_tax_rate),
+                None => cf,
+            }
+        }))
+        .collect();
+
+    calculate_npv_with_rate(&modified_flows, point[SensitivityVariable::DiscountRate as usize])
+}
+
+/// Sum of the absolute discounted flows, the scale against which an NPV
+/// counts as zero.
+fn gross_discounted_flows(cash_flows: &[f64], discount_rate: f64) -> f64 {
+    cash_flows.iter()
+        .enumerate()
+        .map(|(year, &flow)| flow.abs() / (1.0 + discount_rate).powi(year as i32))
+        .sum()
+}
+
+fn calculate_npv_with_rate(cash_flows: &[f64], discount_rate: f64) -> f64 {
+    calculate_npv(
+        cash_flows.as_ptr(),
+        cash_flows.len(),
+        discount_rate
+    )
+}
+
+/// Net-flow approximation: positive years count as revenue and negative
+/// years as cost, see `FlowComponents` for years that mix both.
+fn flows_with_volume_factor(
+    cash_flows: &[f64],
+    factor: f64,
+    ratios: &CostRatios,
+    base_volume: f64
+) -> Vec<f64> {
+    let initial_investment = cash_flows[0];
+    
+    // For production volume, the input is an absolute volume
+    // We want to convert this to a relative scale where:
+    // - The base case volume maps to 1.0
+    // - Values below base case give factors < 1.0
+    // - Values above base case give factors > 1.0
+    
+    // Example: If the base case is 1500000 and the range [1200000, 3000000],
+    // then 1200000 maps to 0.8, 1500000 to 1.0, and 3000000 to 2.0
+    let relative_factor = if factor > 0.0 { factor / base_volume } else { 0.0 };
+    
+    std::iter::once(initial_investment)
+        .chain(cash_flows.iter().enumerate().skip(1).map(|(year, &cf)| {
+            volume_adjusted_flow(cf, relative_factor, ratios.fixed(year))
+        }))
+        .collect()
+}
+
+/// Net-flow approximation: a year's revenue is reconstructed as the net flow
+/// plus both cost portions of |net flow|, which is only right for years that
+/// are all revenue or all cost. Pass `FlowComponents` for mixed years.
+fn flows_with_opex_factor(cash_flows: &[f64], factor: f64, ratios: &CostRatios) -> Vec<f64> {
+    let initial_investment = cash_flows[0];
+    std::iter::once(initial_investment)
+        .chain(cash_flows.iter().enumerate().skip(1).map(|(year, &cf)| {
+            opex_adjusted_flow(cf, factor, ratios.fixed(year), ratios.variable(year))
+        }))
+        .collect()
+}
+
+fn flows_with_revenue_factor(cash_flows: &[f64], factor: f64) -> Vec<f64> {
+    let initial_investment = cash_flows[0];
+    std::iter::once(initial_investment)
+        .chain(cash_flows[1..].iter().map(|&cf| revenue_adjusted_flow(cf, factor)))
+        .collect()
+}
+
+fn flows_with_capex_factor(cash_flows: &[f64], factor: f64) -> Vec<f64> {
+    // Scale only the initial investment
+    std::iter::once(cash_flows[0] * factor)
+        .chain(cash_flows[1..].iter().copied())
+        .collect()
+}
+
+fn flows_with_tax_rate(cash_flows: &[f64], pre_tax_flows: &[f64], tax_rate: f64, base_tax_rate: f64) -> Vec<f64> {
+    let initial_investment = cash_flows[0];
+    std::iter::once(initial_investment)
+        .chain(cash_flows[1..].iter().zip(&pre_tax_flows[1..]).map(|(&cf, &pre_tax)| {
+            tax_adjusted_flow(cf, pre_tax, tax_rate, base_tax_rate)
+        }))
+        .collect()
+}
+
+fn volume_adjusted_flow(cf: f64, relative_factor: f64, fixed_ratio: f64) -> f64 {
+    // For each cash flow:
+    // 1. Fixed portion remains unchanged
+    // 2. Variable portion scales with production volume
+    // 3. Revenue (positive cash flows) also scales with production volume
+    let is_revenue = cf > 0.0;
+    if is_revenue {
+        // Revenue scales directly with production volume
+        cf * relative_factor
+    } else {
+        // Costs are split between fixed and variable
+        let fixed_portion = cf * fixed_ratio;
+        let variable_portion = cf * (1.0 - fixed_ratio);
+        fixed_portion + (variable_portion * relative_factor)
+    }
+}
+
+fn opex_adjusted_flow(cf: f64, factor: f64, fixed_ratio: f64, variable_ratio: f64) -> f64 {
+    // Operating costs are the fixed and variable shares of the flow; the
+    // factor scales both, so a factor of 1 reproduces the flow
+    let operating_costs = cf.abs() * (fixed_ratio + variable_ratio);
+    cf - operating_costs * (factor - 1.0)
+}
+
+fn revenue_adjusted_flow(cf: f64, factor: f64) -> f64 {
+    if cf > 0.0 {
+        // Scale only positive cash flows (revenue)
+        cf * factor
+    } else {
+        // Leave negative cash flows (costs) unchanged
+        cf
+    }
+}
+
+fn tax_adjusted_flow(cf: f64, pre_tax: f64, tax_rate: f64, base_tax_rate: f64) -> f64 {
+    // Only profitable years are taxed; adjust by the change in tax
+    cf - (tax_rate - base_tax_rate) * pre_tax.max(0.0)
+}
+
+/// Number of values written by `analyze_sensitivity`: the base NPV followed
+/// by (low, high) pairs for the discount rate, production volume, operating
+/// costs and revenue.
+pub const ANALYZE_SENSITIVITY_RESULT_LEN: usize = 9;
+
+// Relative change applied to each variable by analyze_sensitivity
+const ANALYZE_SENSITIVITY_CHANGE: f64 = 0.2;
+
+/// NPV at ±20% of each `SensitivityVariable`, written to a caller-allocated
+/// buffer of `ANALYZE_SENSITIVITY_RESULT_LEN` values.
+///
+/// # Safety
+/// - cash_flows points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length
+///   ANALYZE_SENSITIVITY_RESULT_LEN
+#[no_mangle]
+pub extern "C" fn analyze_sensitivity(
+    cash_flows: *const c_double,
+    len: usize,
+    discount_rate: c_double,
+    fixed_cost_ratio: c_double,
+    variable_cost_ratio: c_double,
+    results: *mut c_double
+) -> bool {
+    if cash_flows.is_null() || results.is_null() || len == 0 {
+        return false;
+    }
+
+    let cash_flows_slice = unsafe {
+        std::slice::from_raw_parts(cash_flows, len)
+    };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, ANALYZE_SENSITIVITY_RESULT_LEN) };
+    let no_tax = TaxInputs { pre_tax_flows: None, base_tax_rate: 0.0 };
+    let inputs = SweepInputs::relative(fixed_cost_ratio, variable_cost_ratio, no_tax);
+
+    // Calculate base NPV
+    results[0] = calculate_npv_with_rate(cash_flows_slice, discount_rate);
+
+    let variables = [
+        SensitivityVariable::DiscountRate,
+        SensitivityVariable::ProductionVolume,
+        SensitivityVariable::OperatingCosts,
+        SensitivityVariable::Revenue,
+    ];
+    for (index, variable) in variables.into_iter().enumerate() {
+        let base = variable.base_value(discount_rate, &inputs);
+        let low = base * (1.0 - ANALYZE_SENSITIVITY_CHANGE);
+        let high = base * (1.0 + ANALYZE_SENSITIVITY_CHANGE);
+        results[1 + 2 * index] = evaluate_variable(cash_flows_slice, variable, low, discount_rate, &inputs);
+        results[2 + 2 * index] = evaluate_variable(cash_flows_slice, variable, high, discount_rate, &inputs);
+    }
+
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FLOWS: [f64; 5] = [-1000.0, 300.0, 350.0, 400.0, 450.0];
+
+    fn spider(fixed_cost_ratio: f64, output_mode: SensitivityOutputMode) -> (Vec<f64>, SensitivityNormalization) {
+        let steps = 6;
+        let mut results = vec![0.0; TORNADO_VARIABLES * (steps + 1)];
+        let mut normalization = SensitivityNormalization { base_value: f64::NAN, mode_used: output_mode };
+        let pre_tax: Vec<f64> = FLOWS.iter().map(|flow| flow / 0.75).collect();
+        assert!(run_spider_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, fixed_cost_ratio, 0.4, pre_tax.as_ptr(), 0.25,
+            -0.3, 0.3, steps, output_mode as u32, results.as_mut_ptr(), &mut normalization
+        ));
+        (results, normalization)
+    }
+
+    fn request(variable: SensitivityVariable, range_min: f64, range_max: f64, steps: usize) -> SensitivityRequest {
+        SensitivityRequest {
+            base_values: FLOWS.as_ptr(),
+            len: FLOWS.len(),
+            variable: variable as usize,
+            range_min,
+            range_max,
+            steps,
+            discount_rate: 0.1,
+            fixed_cost_ratio: 0.3,
+            variable_cost_ratio: 0.4,
+            fixed_cost_ratios: std::ptr::null(),
+            variable_cost_ratios: std::ptr::null(),
+            pre_tax_flows: std::ptr::null(),
+            base_tax_rate: 0.0,
+            cancel_flag: std::ptr::null(),
+            max_threads: 0,
+            metric: SensitivityMetric::Npv as u32,
+            spacing: SweepSpacing::Linear as u32,
+            output_mode: SensitivityOutputMode::Absolute as u32,
+            base_volume: 0.0,
+            revenues: std::ptr::null(),
+            costs: std::ptr::null(),
+        }
+    }
+
+    fn run_v2(request: &SensitivityRequest) -> (SensitivityStatus, Vec<f64>) {
+        let mut results = vec![-1.0; request.steps + 1];
+        let mut response = SensitivityResponse {
+            results: results.as_mut_ptr(),
+            results_len: results.len(),
+            factors: std::ptr::null_mut(),
+            points_written: 0,
+            statuses: std::ptr::null_mut(),
+            normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+        };
+        (run_sensitivity_analysis_v2(request, &mut response), results)
+    }
+
+    #[test]
+    fn legacy_sweep_matches_v2_defaults() {
+        for variable in [SensitivityVariable::DiscountRate, SensitivityVariable::ProductionVolume, SensitivityVariable::OperatingCosts, SensitivityVariable::Revenue] {
+            let mut legacy = [0.0; 5];
+            run_sensitivity_analysis(FLOWS.as_ptr(), FLOWS.len(), variable as usize, 0.05, 0.2, 4, 0.1, 0.3, 0.4, legacy.as_mut_ptr());
+            assert_eq!(sensitivity_last_status(), SensitivityStatus::Ok);
+            let (status, results) = run_v2(&request(variable, 0.05, 0.2, 4));
+            assert_eq!(status, SensitivityStatus::Ok);
+            assert_eq!(legacy.to_vec(), results);
+        }
+    }
+
+    #[test]
+    fn legacy_sweep_fills_rejected_requests_with_nan() {
+        let mut results = [0.0; 3];
+        run_sensitivity_analysis(FLOWS.as_ptr(), FLOWS.len(), 99, 0.0, 1.0, 2, 0.1, 0.3, 0.4, results.as_mut_ptr());
+        assert_eq!(sensitivity_last_status(), SensitivityStatus::InvalidVariable);
+        assert!(results.iter().all(|r| r.is_nan()));
+        run_sensitivity_analysis(FLOWS.as_ptr(), FLOWS.len(), 3, 1.0, 0.0, 2, 0.1, 0.3, 0.4, results.as_mut_ptr());
+        assert_eq!(sensitivity_last_status(), SensitivityStatus::InvertedRange);
+    }
+
+    #[test]
+    fn opex_factor_scales_fixed_and_variable_costs() {
+        // 30 fixed and 40 variable operating costs behind a flow of 100
+        assert_eq!(opex_adjusted_flow(100.0, 1.0, 0.3, 0.4), 100.0);
+        assert_eq!(opex_adjusted_flow(100.0, 1.5, 0.3, 0.4), 65.0);
+        assert_eq!(opex_adjusted_flow(100.0, 1.5, 0.0, 0.4), 80.0);
+    }
+
+    #[test]
+    fn fixed_cost_ratio_changes_operating_cost_results() {
+        let analyze = |fixed_cost_ratio: f64| {
+            let mut results = [0.0; ANALYZE_SENSITIVITY_RESULT_LEN];
+            assert!(analyze_sensitivity(FLOWS.as_ptr(), FLOWS.len(), 0.1, fixed_cost_ratio, 0.4, results.as_mut_ptr()));
+            results
+        };
+        let (without, with) = (analyze(0.0), analyze(0.3));
+        assert_eq!(without[0], with[0]);
+        // A larger cost share widens the operating cost pair around the base
+        assert!(with[5] - with[6] > without[5] - without[6]);
+    }
+
+    #[test]
+    fn spider_zero_change_column_is_base_npv() {
+        for fixed_cost_ratio in [0.0, 0.3] {
+            let (results, normalization) = spider(fixed_cost_ratio, SensitivityOutputMode::Absolute);
+            let base = calculate_npv_with_rate(&FLOWS, 0.1);
+            assert_eq!(normalization.base_value, base);
+            for variable in 0..TORNADO_VARIABLES {
+                let zero = results[variable * 7 + 3];
+                assert!((zero - base).abs() < 1e-9 * base.abs(), "variable {variable}: {zero} vs {base}");
+            }
+        }
+    }
+
+    #[test]
+    fn tornado_and_spider_agree_at_the_same_change() {
+        let (spider_rows, _) = spider(0.3, SensitivityOutputMode::Absolute);
+        let pre_tax: Vec<f64> = FLOWS.iter().map(|flow| flow / 0.75).collect();
+        let (mut tornado, mut order) = ([f64::NAN; TORNADO_VARIABLES * 3], [0; TORNADO_VARIABLES]);
+        assert!(run_tornado_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, pre_tax.as_ptr(), 0.25, 0.7, 1.3,
+            SensitivityMetric::Npv as u32, SensitivityOutputMode::Absolute as u32, tornado.as_mut_ptr(), order.as_mut_ptr(), std::ptr::null_mut(),
+        ));
+        for (row, &variable) in order.iter().enumerate() {
+            // Spider columns run from -30% to +30% in 10% steps
+            let spider_row = &spider_rows[variable * 7..(variable + 1) * 7];
+            assert!((tornado[row * 3] - spider_row[0]).abs() < 1e-9, "variable {variable}");
+            assert!((tornado[row * 3 + 1] - spider_row[3]).abs() < 1e-9, "variable {variable}");
+            assert!((tornado[row * 3 + 2] - spider_row[6]).abs() < 1e-9, "variable {variable}");
+        }
+    }
+
+    #[test]
+    fn spider_rows_share_the_relative_axis() {
+        let (absolute, _) = spider(0.3, SensitivityOutputMode::Absolute);
+        let (delta, normalization) = spider(0.3, SensitivityOutputMode::DeltaFromBase);
+        for (a, d) in absolute.iter().zip(&delta) {
+            assert!((a - normalization.base_value - d).abs() < 1e-9);
+        }
+        // Revenue rises with its factor, operating costs fall
+        let row = |variable: SensitivityVariable| &absolute[variable as usize * 7..(variable as usize + 1) * 7];
+        assert!(row(SensitivityVariable::Revenue).windows(2).all(|w| w[1] > w[0]));
+        assert!(row(SensitivityVariable::OperatingCosts).windows(2).all(|w| w[1] < w[0]));
+    }
+
+    #[test]
+    fn spider_rejects_zero_steps_and_null_pointers() {
+        let mut results = [0.0; TORNADO_VARIABLES];
+        let null = std::ptr::null_mut();
+        assert!(!run_spider_analysis(FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0,
+            -0.3, 0.3, 0, SensitivityOutputMode::Absolute as u32, results.as_mut_ptr(), null));
+        assert!(!run_spider_analysis(std::ptr::null(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0,
+            -0.3, 0.3, 1, SensitivityOutputMode::Absolute as u32, results.as_mut_ptr(), null));
+    }
+
+    #[test]
+    fn unknown_output_mode_ids_are_rejected() {
+        let mut spider_rows = vec![f64::NAN; TORNADO_VARIABLES * 2];
+        let mut normalization = SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute };
+        assert!(!run_spider_analysis(FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0,
+            -0.3, 0.3, 1, 3, spider_rows.as_mut_ptr(), &mut normalization));
+        let (mut tornado, mut order) = ([f64::NAN; TORNADO_VARIABLES * 3], [usize::MAX; TORNADO_VARIABLES]);
+        assert!(!run_tornado_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.25, 0.8, 1.2,
+            SensitivityMetric::Npv as u32, u32::MAX, tornado.as_mut_ptr(), order.as_mut_ptr(), &mut normalization
+        ));
+        assert!(spider_rows.iter().chain(&tornado).all(|value| value.is_nan()));
+        assert!(normalization.base_value.is_nan());
+    }
+
+    #[test]
+    fn set_cancel_flag_returns_cancelled() {
+        let flag = AtomicU8::new(1);
+        let request = SensitivityRequest { cancel_flag: &flag, ..request(SensitivityVariable::Revenue, 0.5, 1.5, 10) };
+        assert_eq!(run_v2(&request).0, SensitivityStatus::Cancelled);
+        flag.store(0, std::sync::atomic::Ordering::Relaxed);
+        assert_eq!(run_v2(&request).0, SensitivityStatus::Ok);
+    }
+
+    #[test]
+    fn results_do_not_depend_on_the_thread_count() {
+        let serial = run_v2(&SensitivityRequest { max_threads: 1, ..request(SensitivityVariable::DiscountRate, 0.0, 0.2, 100) });
+        let parallel = run_v2(&SensitivityRequest { max_threads: 4, ..request(SensitivityVariable::DiscountRate, 0.0, 0.2, 100) });
+        assert_eq!(serial.0, SensitivityStatus::Ok);
+        assert_eq!(serial, parallel);
+    }
+
+    fn tornado(metric: SensitivityMetric, pre_tax_flows: *const f64) -> ([f64; TORNADO_VARIABLES * 3], [usize; TORNADO_VARIABLES]) {
+        let mut results = [0.0; TORNADO_VARIABLES * 3];
+        let mut order = [usize::MAX; TORNADO_VARIABLES];
+        assert!(run_tornado_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, pre_tax_flows, 0.25, 0.8, 1.2,
+            metric as u32, SensitivityOutputMode::Absolute as u32, results.as_mut_ptr(), order.as_mut_ptr(), std::ptr::null_mut()
+        ));
+        (results, order)
+    }
+
+    #[test]
+    fn tornado_rows_share_the_base_npv_and_sort_by_swing() {
+        let pre_tax: Vec<f64> = FLOWS.iter().map(|flow| flow / 0.75).collect();
+        let (results, order) = tornado(SensitivityMetric::Npv, pre_tax.as_ptr());
+        let mut sorted_order = order;
+        sorted_order.sort_unstable();
+        assert_eq!(sorted_order.to_vec(), (0..TORNADO_VARIABLES).collect::<Vec<_>>());
+
+        let base = calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.1);
+        let rows: Vec<&[f64]> = results.chunks(3).collect();
+        assert!(rows.iter().all(|row| row[1] == base));
+        let swings: Vec<f64> = rows.iter().map(|row| (row[2] - row[0]).abs()).collect();
+        assert!(swings.windows(2).all(|pair| pair[0] >= pair[1]), "{swings:?}");
+    }
+
+    #[test]
+    fn tornado_bounds_match_the_single_variable_sweep() {
+        let (results, order) = tornado(SensitivityMetric::Npv, std::ptr::null());
+        for variable in [SensitivityVariable::ProductionVolume, SensitivityVariable::OperatingCosts, SensitivityVariable::Revenue] {
+            let (status, sweep) = run_v2(&request(variable, 0.8, 1.2, 1));
+            assert_eq!(status, SensitivityStatus::Ok);
+            let row = order.iter().position(|&index| index == variable as usize).unwrap();
+            assert_eq!([results[row * 3], results[row * 3 + 2]], [sweep[0], sweep[1]], "{variable:?}");
+        }
+
+        // Without pre-tax flows the tax row has no values and sorts last
+        assert_eq!(order[TORNADO_VARIABLES - 1], SensitivityVariable::TaxRate as usize);
+        assert!(results[(TORNADO_VARIABLES - 1) * 3].is_nan());
+    }
+
+    #[test]
+    fn tornado_irr_has_no_discount_rate_row() {
+        let (results, order) = tornado(SensitivityMetric::Irr, std::ptr::null());
+        let row = order.iter().position(|&index| index == SensitivityVariable::DiscountRate as usize).unwrap();
+        assert!(results[row * 3..row * 3 + 3].iter().all(|value| value.is_nan()));
+        assert!(!run_tornado_analysis(
+            std::ptr::null(), 6, 0.1, 0.3, 0.4, std::ptr::null(), 0.0, 0.8, 1.2,
+            SensitivityMetric::Npv as u32, SensitivityOutputMode::Absolute as u32, [0.0; 18].as_mut_ptr(), [0; 6].as_mut_ptr(), std::ptr::null_mut()
+        ));
+    }
+
+    #[test]
+    fn tornado_rejects_unknown_metric_ids() {
+        let mut results = [f64::NAN; TORNADO_VARIABLES * 3];
+        let mut order = [usize::MAX; TORNADO_VARIABLES];
+        assert!(!run_tornado_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.25, 0.8, 1.2,
+            3, SensitivityOutputMode::Absolute as u32, results.as_mut_ptr(), order.as_mut_ptr(), std::ptr::null_mut()
+        ));
+        assert!(results.iter().all(|value| value.is_nan()));
+        assert!(order.iter().all(|&index| index == usize::MAX));
+    }
+
+    fn elasticities(flows: &[f64]) -> Vec<f64> {
+        let mut results = vec![0.0; TORNADO_VARIABLES * ELASTICITY_RESULT_WIDTH];
+        assert!(run_sensitivity_elasticity(
+            flows.as_ptr(), flows.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0, 0.01, 0.8, 1.2, results.as_mut_ptr()
+        ));
+        results
+    }
+
+    #[test]
+    fn revenue_elasticity_exceeds_one_when_revenue_outweighs_the_investment() {
+        let results = elasticities(&FLOWS);
+        let row = &results[SensitivityVariable::Revenue as usize * ELASTICITY_RESULT_WIDTH..][..ELASTICITY_RESULT_WIDTH];
+        let npv = calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.1);
+        let discounted_revenue = npv + 1000.0;
+        // NPV is linear in the revenue multiplier, so both estimates agree
+        assert!((row[0] - discounted_revenue / npv).abs() < 1e-6, "{row:?}");
+        assert!((row[1] - row[0]).abs() < 1e-6);
+        assert!(row[0] > 1.0);
+        assert_eq!(row[2], 0.0);
+        assert!(results[SensitivityVariable::TaxRate as usize * ELASTICITY_RESULT_WIDTH].is_nan());
+    }
+
+    #[test]
+    fn zero_base_npv_reports_absolute_sensitivity() {
+        let results = elasticities(&[-1000.0, 1100.0]);
+        let row = &results[SensitivityVariable::Revenue as usize * ELASTICITY_RESULT_WIDTH..][..ELASTICITY_RESULT_WIDTH];
+        assert_eq!(row[2], 1.0);
+        assert!((row[0] - 1000.0).abs() < 1e-6, "{row:?}");
+
+        let mut out = [0.0; TORNADO_VARIABLES * ELASTICITY_RESULT_WIDTH];
+        for (step, low, high) in [(0.0, 0.8, 1.2), (0.01, 1.2, 0.8), (f64::NAN, 0.8, 1.2)] {
+            assert!(!run_sensitivity_elasticity(
+                FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0, step, low, high, out.as_mut_ptr()
+            ));
+        }
+    }
+
+    #[test]
+    fn analyze_sensitivity_gives_distinct_variable_effects() {
+        // Volume and revenue only differ on cost years
+        let flows = [-1000.0, 300.0, -50.0, 400.0, 350.0, 300.0];
+        let mut results = [f64::NAN; ANALYZE_SENSITIVITY_RESULT_LEN];
+        assert!(analyze_sensitivity(flows.as_ptr(), flows.len(), 0.1, 0.3, 0.4, results.as_mut_ptr()));
+        assert!((results[0] - calculate_npv(flows.as_ptr(), flows.len(), 0.1)).abs() < 1e-9);
+        for i in 0..ANALYZE_SENSITIVITY_RESULT_LEN {
+            assert!(results[..i].iter().all(|&other| other != results[i]), "{results:?}");
+        }
+
+        let variables = [
+            (SensitivityVariable::DiscountRate, 0.08, 0.12),
+            (SensitivityVariable::ProductionVolume, 0.8, 1.2),
+            (SensitivityVariable::OperatingCosts, 0.8, 1.2),
+            (SensitivityVariable::Revenue, 0.8, 1.2),
+        ];
+        for (index, (variable, low, high)) in variables.into_iter().enumerate() {
+            let (status, sweep) = run_v2(&SensitivityRequest { base_values: flows.as_ptr(), len: flows.len(), ..request(variable, low, high, 1) });
+            assert_eq!(status, SensitivityStatus::Ok);
+            assert!((results[1 + 2 * index] - sweep[0]).abs() < 1e-9, "{variable:?}");
+            assert!((results[2 + 2 * index] - sweep[1]).abs() < 1e-9, "{variable:?}");
+        }
+    }
+
+    #[test]
+    fn analyze_sensitivity_rejects_null_pointers() {
+        let mut results = [0.0; ANALYZE_SENSITIVITY_RESULT_LEN];
+        assert!(!analyze_sensitivity(std::ptr::null(), 5, 0.1, 0.3, 0.4, results.as_mut_ptr()));
+        assert!(!analyze_sensitivity(FLOWS.as_ptr(), 5, 0.1, 0.3, 0.4, std::ptr::null_mut()));
+        assert!(!analyze_sensitivity(FLOWS.as_ptr(), 0, 0.1, 0.3, 0.4, results.as_mut_ptr()));
+    }
+
+    fn switching(flows: &[f64], ranges: &[f64; TORNADO_VARIABLES * 2], pre_tax_flows: *const f64) -> ([f64; TORNADO_VARIABLES], [SwitchingStatus; TORNADO_VARIABLES]) {
+        let mut values = [0.0; TORNADO_VARIABLES];
+        let mut statuses = [SwitchingStatus::Found; TORNADO_VARIABLES];
+        assert!(find_switching_values(
+            flows.as_ptr(), flows.len(), 0.1, 0.3, 0.4, pre_tax_flows, 0.25,
+            ranges.as_ptr(), 20, 1e-6, values.as_mut_ptr(), statuses.as_mut_ptr()
+        ));
+        (values, statuses)
+    }
+
+    #[test]
+    fn switching_values_zero_the_npv() {
+        let flows = [-1000.0, 300.0, -50.0, 400.0, 350.0, 300.0];
+        let pre_tax: Vec<f64> = flows.iter().map(|flow| flow / 0.75).collect();
+        let ranges = [0.0, 0.5, 0.0, 1.5, -3.0, 3.0, 0.5, 1.5, 0.5, 3.0, 0.0, 1.0];
+        let (values, statuses) = switching(&flows, &ranges, pre_tax.as_ptr());
+        for variable in SensitivityVariable::ALL {
+            let index = variable as usize;
+            assert_eq!(statuses[index], SwitchingStatus::Found, "{variable:?}");
+            let request = SensitivityRequest {
+                base_values: flows.as_ptr(),
+                len: flows.len(),
+                pre_tax_flows: pre_tax.as_ptr(),
+                base_tax_rate: 0.25,
+                // Volumes are relative to the midpoint of the searched range
+                base_volume: (ranges[index * 2] + ranges[index * 2 + 1]) / 2.0,
+                ..request(variable, values[index], values[index], 1)
+            };
+            let (status, npv) = run_v2(&request);
+            assert_eq!(status, SensitivityStatus::Ok);
+            assert!(npv[0].abs() <= 1e-6, "{variable:?} at {}: {}", values[index], npv[0]);
+        }
+    }
+
+    #[test]
+    fn ranges_without_a_crossing_report_why() {
+        let flows = [-1000.0, 300.0, -50.0, 400.0, 350.0, 300.0];
+        // Profitable at low discount rates, loss-making at a fraction of the revenue
+        let ranges = [0.0, 0.05, 0.9, 1.1, 0.9, 1.1, 0.1, 0.2, 0.9, 1.1, 0.0, 1.0];
+        let (values, statuses) = switching(&flows, &ranges, std::ptr::null());
+        assert_eq!(statuses[SensitivityVariable::DiscountRate as usize], SwitchingStatus::NoCrossingInRange);
+        assert_eq!(statuses[SensitivityVariable::Revenue as usize], SwitchingStatus::NpvNeverPositive);
+        for (value, status) in values.iter().zip(statuses) {
+            assert_eq!(value.is_nan(), status != SwitchingStatus::Found);
+        }
+
+        let mut out = [0.0; TORNADO_VARIABLES];
+        let mut statuses = [SwitchingStatus::Found; TORNADO_VARIABLES];
+        assert!(!find_switching_values(
+            flows.as_ptr(), flows.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.0,
+            ranges.as_ptr(), 20, 0.0, out.as_mut_ptr(), statuses.as_mut_ptr()
+        ));
+    }
+
+    #[test]
+    fn new_variables_reproduce_the_base_npv_at_their_base_value() {
+        let base = calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.1);
+        let pre_tax: Vec<f64> = FLOWS.iter().map(|flow| flow / 0.75).collect();
+
+        let (status, capex) = run_v2(&request(SensitivityVariable::CapitalInvestment, 1.0, 1.3, 1));
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert_eq!(capex[0], base);
+        assert!((capex[1] - (base - 300.0)).abs() < 1e-9);
+
+        let tax_request = SensitivityRequest {
+            pre_tax_flows: pre_tax.as_ptr(),
+            base_tax_rate: 0.25,
+            ..request(SensitivityVariable::TaxRate, 0.0, 0.25, 1)
+        };
+        let (status, tax) = run_v2(&tax_request);
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert!((tax[1] - base).abs() < 1e-9);
+        // Untaxed, every profitable year keeps its whole pre-tax flow
+        let untaxed: Vec<f64> = FLOWS.iter().zip(&pre_tax).map(|(&flow, &pre)| if flow > 0.0 { pre } else { flow }).collect();
+        assert!((tax[0] - calculate_npv(untaxed.as_ptr(), untaxed.len(), 0.1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn tax_rate_needs_pre_tax_flows_and_unknown_variables_are_rejected() {
+        let (status, _) = run_v2(&request(SensitivityVariable::TaxRate, 0.0, 0.5, 2));
+        assert_eq!(status, SensitivityStatus::MissingPreTaxFlows);
+        let unknown = SensitivityRequest { variable: TORNADO_VARIABLES, ..request(SensitivityVariable::Revenue, 0.5, 1.5, 2) };
+        assert_eq!(run_v2(&unknown).0, SensitivityStatus::InvalidVariable);
+    }
+
+    // Commissioning years 1-2 are costs, then steady-state revenue
+    const COMMISSIONING: [f64; 6] = [-1000.0, -200.0, -100.0, 400.0, 450.0, 500.0];
+
+    fn ratio_curves(fixed_cost_ratio: f64, fixed_cost_ratios: *const f64) -> Vec<f64> {
+        [SensitivityVariable::ProductionVolume, SensitivityVariable::OperatingCosts].into_iter().flat_map(|variable| {
+            let request = SensitivityRequest {
+                base_values: COMMISSIONING.as_ptr(),
+                len: COMMISSIONING.len(),
+                fixed_cost_ratio,
+                fixed_cost_ratios,
+                ..request(variable, 0.5, 1.5, 4)
+            };
+            let (status, results) = run_v2(&request);
+            assert_eq!(status, SensitivityStatus::Ok);
+            results
+        }).collect()
+    }
+
+    #[test]
+    fn per_year_ratios_give_curves_no_constant_ratio_can() {
+        let per_year = [0.0, 0.9, 0.9, 0.4, 0.4, 0.4];
+        let curves = ratio_curves(0.3, per_year.as_ptr());
+        // The volume curve needs the commissioning ratio, the opex curve a
+        // blend of every year's, so no single ratio fits both
+        let best_error = (0..=1000).map(|k| {
+            let constant = ratio_curves(k as f64 / 1000.0, std::ptr::null());
+            constant.iter().zip(&curves).map(|(a, b)| (a - b).powi(2)).sum::<f64>()
+        }).fold(f64::INFINITY, f64::min);
+        assert!(best_error > 100.0, "{best_error}");
+    }
+
+    #[test]
+    fn uniform_per_year_ratios_match_the_scalars() {
+        let fixed = [0.3; 6];
+        let variable = [0.4; 6];
+        let request = |fixed_cost_ratios: *const f64, variable_cost_ratios: *const f64| SensitivityRequest {
+            base_values: COMMISSIONING.as_ptr(),
+            len: COMMISSIONING.len(),
+            fixed_cost_ratios,
+            variable_cost_ratios,
+            ..request(SensitivityVariable::OperatingCosts, 0.5, 1.5, 4)
+        };
+        assert_eq!(run_v2(&request(fixed.as_ptr(), variable.as_ptr())), run_v2(&request(std::ptr::null(), std::ptr::null())));
+
+        let out_of_range = [0.3, 0.3, 1.5, 0.3, 0.3, 0.3];
+        assert_eq!(run_v2(&request(out_of_range.as_ptr(), std::ptr::null())).0, SensitivityStatus::InvalidCostRatio);
+        assert_eq!(run_v2(&request(std::ptr::null(), out_of_range.as_ptr())).0, SensitivityStatus::InvalidCostRatio);
+    }
+
+    fn run_with_factors(request: &SensitivityRequest) -> (SensitivityStatus, Vec<f64>, Vec<f64>, usize) {
+        let mut results = vec![f64::NAN; request.steps + 1];
+        let mut factors = vec![f64::NAN; request.steps + 1];
+        let mut response = SensitivityResponse {
+            results: results.as_mut_ptr(),
+            results_len: results.len(),
+            factors: factors.as_mut_ptr(),
+            points_written: 0,
+            statuses: std::ptr::null_mut(),
+            normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+        };
+        let status = run_sensitivity_analysis_v2(request, &mut response);
+        (status, results, factors, response.points_written)
+    }
+
+    #[test]
+    fn sweep_includes_both_endpoints_and_reports_its_factors() {
+        let (status, results, factors, written) = run_with_factors(&request(SensitivityVariable::Revenue, 0.1, 0.8, 7));
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert_eq!(written, 8);
+        assert_eq!((factors[0], factors[7]), (0.1, 0.8));
+        for (i, &factor) in factors.iter().enumerate() {
+            assert!((factor - (0.1 + i as f64 * 0.1)).abs() < 1e-12, "{factors:?}");
+            let (_, single) = run_v2(&request(SensitivityVariable::Revenue, factor, factor, 1));
+            assert_eq!(results[i], single[0]);
+        }
+        assert_eq!(run_with_factors(&request(SensitivityVariable::Revenue, 0.1, 0.8, 0)).0, SensitivityStatus::ZeroSteps);
+    }
+
+    // Discount rate held fixed as the dummy input
+    const MORRIS_RANGES: [f64; TORNADO_VARIABLES * 2] = [0.08, 0.08, 0.9, 1.1, 0.95, 1.05, 0.5, 1.5, 0.9, 1.1, 0.2, 0.3];
+
+    fn morris(levels: usize, design: MorrisDesign, trajectories: usize) -> [f64; TORNADO_VARIABLES * MORRIS_RESULT_WIDTH] {
+        let flows = [-1000.0, 400.0, 400.0, 400.0, 400.0];
+        let mut results = [f64::NAN; TORNADO_VARIABLES * MORRIS_RESULT_WIDTH];
+        assert!(run_morris_screening(
+            flows.as_ptr(), flows.len(), 0.3, 0.4, std::ptr::null(), 0.25,
+            MORRIS_RANGES.as_ptr(), trajectories, levels, design as u32, 3, results.as_mut_ptr()
+        ));
+        results
+    }
+
+    #[test]
+    fn morris_ranks_revenue_first_and_the_dummy_last() {
+        for design in [MorrisDesign::Trajectory, MorrisDesign::Radial] {
+            for levels in [2, 3, 4] {
+                let results = morris(levels, design, 20);
+                let rows: Vec<&[f64]> = results.chunks(MORRIS_RESULT_WIDTH).collect();
+                assert_eq!(rows[SensitivityVariable::DiscountRate as usize][..2], [0.0, 0.0], "{design:?} {levels}");
+                let top = (0..SensitivityVariable::TaxRate as usize).max_by(|&a, &b| rows[a][1].total_cmp(&rows[b][1])).unwrap();
+                assert_eq!(top, SensitivityVariable::Revenue as usize, "{design:?} {levels}");
+                // Without pre-tax flows the tax rate has no effect to measure
+                assert!(rows[SensitivityVariable::TaxRate as usize].iter().all(|value| value.is_nan()));
+            }
+        }
+    }
+
+    #[test]
+    fn morris_is_deterministic_for_a_seed() {
+        let first = morris(4, MorrisDesign::Radial, 20);
+        assert_eq!(first.map(f64::to_bits), morris(4, MorrisDesign::Radial, 20).map(f64::to_bits));
+        assert!(morris(4, MorrisDesign::Trajectory, 1)[SensitivityVariable::Revenue as usize * MORRIS_RESULT_WIDTH + 2].is_nan());
+        let mut results = [0.0; TORNADO_VARIABLES * MORRIS_RESULT_WIDTH];
+        assert!(!run_morris_screening(
+            FLOWS.as_ptr(), FLOWS.len(), 0.3, 0.4, std::ptr::null(), 0.25,
+            MORRIS_RANGES.as_ptr(), 10, 1, MorrisDesign::Radial as u32, 3, results.as_mut_ptr()
+        ));
+    }
+
+    #[test]
+    fn morris_rejects_unknown_design_ids() {
+        let mut results = [f64::NAN; TORNADO_VARIABLES * MORRIS_RESULT_WIDTH];
+        assert!(!run_morris_screening(
+            FLOWS.as_ptr(), FLOWS.len(), 0.3, 0.4, std::ptr::null(), 0.25,
+            MORRIS_RANGES.as_ptr(), 10, 4, 2, 3, results.as_mut_ptr()
+        ));
+        assert!(results.iter().all(|value| value.is_nan()));
+    }
+
+    fn local_sensitivities(flows: &[f64], pre_tax_flows: *const f64) -> [f64; TORNADO_VARIABLES * LOCAL_SENSITIVITY_RESULT_WIDTH] {
+        let mut results = [0.0; TORNADO_VARIABLES * LOCAL_SENSITIVITY_RESULT_WIDTH];
+        assert!(compute_local_sensitivities(flows.as_ptr(), flows.len(), 0.08, 0.3, 0.4, pre_tax_flows, 0.2, 0.0, results.as_mut_ptr()));
+        results
+    }
+
+    fn local_row(results: &[f64], variable: SensitivityVariable) -> &[f64] {
+        &results[variable as usize * LOCAL_SENSITIVITY_RESULT_WIDTH..][..LOCAL_SENSITIVITY_RESULT_WIDTH]
+    }
+
+    #[test]
+    fn discount_rate_derivative_matches_the_analytic_one() {
+        let flows = [-1000.0, 400.0, 300.0, 500.0, 200.0];
+        let pre_tax = [-1000.0, 500.0, 375.0, 625.0, 250.0];
+        let results = local_sensitivities(&flows, pre_tax.as_ptr());
+        // dNPV/dr = Σ -t * CF_t / (1 + r)^(t + 1)
+        let analytic: f64 = flows.iter().enumerate().map(|(t, &cf)| -(t as f64) * cf / 1.08_f64.powi(t as i32 + 1)).sum();
+        let row = local_row(&results, SensitivityVariable::DiscountRate);
+        assert!((row[0] - analytic).abs() < 1e-3 * analytic.abs(), "{} vs {analytic}", row[0]);
+        let npv = calculate_npv(flows.as_ptr(), flows.len(), 0.08);
+        assert!((row[1] - row[0] * 0.08 / npv).abs() < 1e-12);
+        assert_eq!(row[2], 0.0);
+
+        // NPV is linear in the revenue multiplier: the derivative is the discounted revenue
+        let revenue = local_row(&results, SensitivityVariable::Revenue);
+        assert!((revenue[0] - (npv + 1000.0)).abs() < 1e-6);
+        assert!(local_row(&results, SensitivityVariable::TaxRate)[0] < 0.0);
+    }
+
+    #[test]
+    fn noise_dominated_differences_are_flagged() {
+        let results = local_sensitivities(&[0.0, 400.0, 300.0], std::ptr::null());
+        // Scaling a zero investment changes nothing
+        assert_eq!(local_row(&results, SensitivityVariable::CapitalInvestment)[2], 1.0);
+        assert_eq!(local_row(&results, SensitivityVariable::Revenue)[2], 0.0);
+        assert!(local_row(&results, SensitivityVariable::TaxRate).iter().all(|value| value.is_nan()));
+        let mut out = [0.0; TORNADO_VARIABLES * LOCAL_SENSITIVITY_RESULT_WIDTH];
+        assert!(!compute_local_sensitivities(FLOWS.as_ptr(), FLOWS.len(), 0.08, 0.3, 0.4, std::ptr::null(), 0.2, -0.01, out.as_mut_ptr()));
+    }
+
+    #[test]
+    #[cfg(target_pointer_width = "64")]
+    fn request_and_response_layouts_are_stable() {
+        use std::mem::{offset_of, size_of};
+        let request = [
+            offset_of!(SensitivityRequest, base_values), offset_of!(SensitivityRequest, len),
+            offset_of!(SensitivityRequest, variable), offset_of!(SensitivityRequest, range_min),
+            offset_of!(SensitivityRequest, range_max), offset_of!(SensitivityRequest, steps),
+            offset_of!(SensitivityRequest, discount_rate), offset_of!(SensitivityRequest, fixed_cost_ratio),
+            offset_of!(SensitivityRequest, variable_cost_ratio), offset_of!(SensitivityRequest, fixed_cost_ratios),
+            offset_of!(SensitivityRequest, variable_cost_ratios), offset_of!(SensitivityRequest, pre_tax_flows),
+            offset_of!(SensitivityRequest, base_tax_rate), offset_of!(SensitivityRequest, cancel_flag),
+            offset_of!(SensitivityRequest, max_threads), offset_of!(SensitivityRequest, metric),
+            offset_of!(SensitivityRequest, spacing), offset_of!(SensitivityRequest, output_mode),
+            offset_of!(SensitivityRequest, base_volume), offset_of!(SensitivityRequest, revenues),
+            offset_of!(SensitivityRequest, costs),
+        ];
+        let mut expected: Vec<usize> = (0..16).map(|field| field * 8).collect();
+        expected.extend([15 * 8 + 4, 16 * 8, 17 * 8, 18 * 8, 19 * 8]);
+        assert_eq!(request.to_vec(), expected);
+        assert_eq!(size_of::<SensitivityRequest>(), 20 * 8);
+
+        let response = [
+            offset_of!(SensitivityResponse, results), offset_of!(SensitivityResponse, results_len),
+            offset_of!(SensitivityResponse, factors), offset_of!(SensitivityResponse, points_written),
+            offset_of!(SensitivityResponse, statuses), offset_of!(SensitivityResponse, normalization),
+        ];
+        assert_eq!(response, [0, 8, 16, 24, 32, 40]);
+        assert_eq!(offset_of!(SensitivityNormalization, mode_used), 8);
+        assert_eq!(size_of::<SensitivityResponse>(), 56);
+    }
+
+    #[test]
+    fn v2_rejects_each_invalid_field() {
+        let base = request(SensitivityVariable::Revenue, 0.5, 1.5, 4);
+        let bad_ratios = [0.3, 1.2, 0.3, 0.3, 0.3];
+        let cases = [
+            (SensitivityRequest { base_values: std::ptr::null(), ..base }, SensitivityStatus::NullPointer),
+            (SensitivityRequest { revenues: FLOWS.as_ptr(), ..base }, SensitivityStatus::IncompleteComponents),
+            (SensitivityRequest { variable: TORNADO_VARIABLES, ..base }, SensitivityStatus::InvalidVariable),
+            (SensitivityRequest { fixed_cost_ratios: bad_ratios.as_ptr(), ..base }, SensitivityStatus::InvalidCostRatio),
+            (SensitivityRequest { variable_cost_ratios: bad_ratios.as_ptr(), ..base }, SensitivityStatus::InvalidCostRatio),
+            (request(SensitivityVariable::TaxRate, 0.0, 0.5, 4), SensitivityStatus::MissingPreTaxFlows),
+            (SensitivityRequest { len: 0, ..base }, SensitivityStatus::LengthMismatch),
+            (SensitivityRequest { metric: SensitivityMetric::Irr as u32, ..request(SensitivityVariable::DiscountRate, 0.0, 0.2, 4) }, SensitivityStatus::MetricNotApplicable),
+            (SensitivityRequest { steps: 0, ..base }, SensitivityStatus::ZeroSteps),
+            (SensitivityRequest { range_min: 2.0, ..base }, SensitivityStatus::InvertedRange),
+            (SensitivityRequest { range_max: f64::NAN, ..base }, SensitivityStatus::InvertedRange),
+            (SensitivityRequest { spacing: SweepSpacing::Logarithmic as u32, range_min: 0.0, ..base }, SensitivityStatus::NonPositiveLogRange),
+            (SensitivityRequest { base_volume: -1.0, ..base }, SensitivityStatus::InvalidBaseVolume),
+            (SensitivityRequest { metric: 3, ..base }, SensitivityStatus::MetricNotApplicable),
+            (SensitivityRequest { spacing: 2, ..base }, SensitivityStatus::UnknownSpacing),
+            (SensitivityRequest { output_mode: u32::MAX, ..base }, SensitivityStatus::UnknownOutputMode),
+        ];
+        for (request, expected) in cases {
+            let (status, results) = run_v2(&request);
+            assert_eq!(status, expected);
+            // Rejected requests leave the results untouched
+            assert!(results.iter().all(|&value| value == -1.0), "{expected:?}");
+        }
+
+        let mut results = [0.0; 4];
+        let mut response = SensitivityResponse {
+            results: results.as_mut_ptr(),
+            results_len: results.len(),
+            factors: std::ptr::null_mut(),
+            points_written: 7,
+            statuses: std::ptr::null_mut(),
+            normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+        };
+        assert_eq!(run_sensitivity_analysis_v2(&base, &mut response), SensitivityStatus::LengthMismatch);
+        assert_eq!(response.points_written, 0);
+        assert_eq!(run_sensitivity_analysis_v2(std::ptr::null(), &mut response), SensitivityStatus::NullPointer);
+        assert_eq!(run_sensitivity_analysis_v2(&base, std::ptr::null_mut()), SensitivityStatus::NullPointer);
+        response.results = std::ptr::null_mut();
+        assert_eq!(run_sensitivity_analysis_v2(&base, &mut response), SensitivityStatus::NullPointer);
+    }
+
+    fn run_metric(request: &SensitivityRequest) -> (SensitivityStatus, Vec<f64>, Vec<SensitivityPointStatus>) {
+        let mut results = vec![f64::NAN; request.steps + 1];
+        let mut statuses = vec![SensitivityPointStatus::Ok; request.steps + 1];
+        let mut response = SensitivityResponse {
+            results: results.as_mut_ptr(),
+            results_len: results.len(),
+            factors: std::ptr::null_mut(),
+            points_written: 0,
+            statuses: statuses.as_mut_ptr(),
+            normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+        };
+        let status = run_sensitivity_analysis_v2(request, &mut response);
+        (status, results, statuses)
+    }
+
+    #[test]
+    fn npv_metric_is_the_npv_of_the_modified_flows() {
+        let (status, results, statuses) = run_metric(&request(SensitivityVariable::Revenue, 0.2, 1.8, 8));
+        assert_eq!(status, SensitivityStatus::Ok);
+        for (i, &npv) in results.iter().enumerate() {
+            let factor = 0.2 + i as f64 * 0.2;
+            let flows: Vec<f64> = FLOWS.iter().enumerate().map(|(year, &flow)| if year > 0 { flow * factor } else { flow }).collect();
+            let (_, single) = run_v2(&request(SensitivityVariable::Revenue, factor, factor, 1));
+            assert_eq!(npv.to_bits(), single[0].to_bits());
+            assert!((npv - calculate_npv(flows.as_ptr(), flows.len(), 0.1)).abs() < 1e-9);
+        }
+        assert!(statuses.iter().all(|&status| status == SensitivityPointStatus::Ok));
+    }
+
+    #[test]
+    fn irr_rises_with_revenue_for_a_conventional_project() {
+        let irr = SensitivityRequest { metric: SensitivityMetric::Irr as u32, ..request(SensitivityVariable::Revenue, 0.2, 1.8, 10) };
+        let (status, results, statuses) = run_metric(&irr);
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert!(results.windows(2).all(|pair| pair[1] > pair[0]), "{results:?}");
+        assert!(statuses.iter().all(|&status| status == SensitivityPointStatus::Ok));
+        let at_base = run_metric(&SensitivityRequest { metric: SensitivityMetric::Irr as u32, ..request(SensitivityVariable::Revenue, 1.0, 1.0, 1) }).1[0];
+        assert!(calculate_npv(FLOWS.as_ptr(), FLOWS.len(), at_base).abs() < 1e-6);
+    }
+
+    #[test]
+    fn missing_metrics_are_nan_with_a_status() {
+        let no_revenue = SensitivityRequest { metric: SensitivityMetric::Irr as u32, ..request(SensitivityVariable::Revenue, 0.0, 1.0, 2) };
+        let (_, results, statuses) = run_metric(&no_revenue);
+        assert!(results[0].is_nan());
+        assert_eq!(statuses[0], SensitivityPointStatus::NoIrr);
+
+        let payback = SensitivityRequest { metric: SensitivityMetric::DiscountedPayback as u32, ..request(SensitivityVariable::Revenue, 0.2, 1.0, 2) };
+        let (status, results, statuses) = run_metric(&payback);
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert!(results[0].is_nan());
+        assert_eq!(statuses[0], SensitivityPointStatus::NeverPaysBack);
+        assert!(results[2] > 0.0 && statuses[2] == SensitivityPointStatus::Ok);
+    }
+
+    fn legacy(base_values: *const f64, len: usize, variable: usize, range_min: f64, range_max: f64, steps: usize) -> (SensitivityStatus, Vec<f64>) {
+        let mut results = vec![0.0; steps + 1];
+        run_sensitivity_analysis(base_values, len, variable, range_min, range_max, steps, 0.1, 0.3, 0.4, results.as_mut_ptr());
+        (sensitivity_last_status(), results)
+    }
+
+    fn all_nan(results: &[f64]) -> bool {
+        results.iter().all(|value| value.is_nan())
+    }
+
+    #[test]
+    fn legacy_sweep_rejects_an_unknown_variable() {
+        let (status, results) = legacy(FLOWS.as_ptr(), FLOWS.len(), TORNADO_VARIABLES, 0.5, 1.5, 4);
+        assert_eq!(status, SensitivityStatus::InvalidVariable);
+        assert!(all_nan(&results));
+    }
+
+    #[test]
+    fn legacy_sweep_rejects_zero_steps() {
+        let (status, results) = legacy(FLOWS.as_ptr(), FLOWS.len(), SensitivityVariable::Revenue as usize, 0.5, 1.5, 0);
+        assert_eq!(status, SensitivityStatus::ZeroSteps);
+        assert!(all_nan(&results));
+    }
+
+    #[test]
+    fn legacy_sweep_rejects_an_inverted_range() {
+        for (range_min, range_max) in [(1.5, 0.5), (f64::NAN, 1.5), (0.5, f64::NAN)] {
+            let (status, results) = legacy(FLOWS.as_ptr(), FLOWS.len(), SensitivityVariable::Revenue as usize, range_min, range_max, 4);
+            assert_eq!(status, SensitivityStatus::InvertedRange);
+            assert!(all_nan(&results));
+        }
+    }
+
+    #[test]
+    fn legacy_sweep_rejects_null_pointers() {
+        let (status, results) = legacy(std::ptr::null(), FLOWS.len(), SensitivityVariable::Revenue as usize, 0.5, 1.5, 4);
+        assert_eq!(status, SensitivityStatus::NullPointer);
+        assert!(all_nan(&results));
+        run_sensitivity_analysis(FLOWS.as_ptr(), FLOWS.len(), 3, 0.5, 1.5, 4, 0.1, 0.3, 0.4, std::ptr::null_mut());
+        assert_eq!(sensitivity_last_status(), SensitivityStatus::NullPointer);
+    }
+
+    #[test]
+    fn legacy_sweep_rejects_empty_flows() {
+        let (status, results) = legacy(FLOWS.as_ptr(), 0, SensitivityVariable::Revenue as usize, 0.5, 1.5, 4);
+        assert_eq!(status, SensitivityStatus::LengthMismatch);
+        assert!(all_nan(&results));
+    }
+
+    #[test]
+    fn legacy_sweep_results_are_unchanged_for_valid_inputs() {
+        // Discount rate sweep: the NPV of the unmodified flows at each rate
+        let (status, results) = legacy(FLOWS.as_ptr(), FLOWS.len(), SensitivityVariable::DiscountRate as usize, 0.0, 0.2, 4);
+        assert_eq!(status, SensitivityStatus::Ok);
+        for (i, &npv) in results.iter().enumerate() {
+            assert!((npv - calculate_npv(FLOWS.as_ptr(), FLOWS.len(), i as f64 * 0.05)).abs() < 1e-9);
+        }
+        assert_eq!(results[0], 500.0);
+
+        // Revenue sweep: every operating year scaled by the factor
+        let (_, results) = legacy(FLOWS.as_ptr(), FLOWS.len(), SensitivityVariable::Revenue as usize, 0.5, 1.5, 2);
+        let discounted_revenue: f64 = FLOWS[1..].iter().enumerate().map(|(t, flow)| flow / 1.1_f64.powi(t as i32 + 1)).sum();
+        for (i, &npv) in results.iter().enumerate() {
+            assert!((npv - (-1000.0 + (0.5 + i as f64 * 0.5) * discounted_revenue)).abs() < 1e-9);
+        }
+        // A failed call is not remembered once a valid one succeeds
+        assert_eq!(sensitivity_last_status(), SensitivityStatus::Ok);
+    }
+
+    #[test]
+    fn logarithmic_spacing_is_geometric() {
+        let log_request = SensitivityRequest { spacing: SweepSpacing::Logarithmic as u32, ..request(SensitivityVariable::DiscountRate, 0.01, 0.30, 10) };
+        let (status, results, factors, _) = run_with_factors(&log_request);
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert!((factors[5] - 0.003_f64.sqrt()).abs() < 1e-12, "{factors:?}");
+        assert_eq!((factors[0], factors[10]), (0.01, 0.30));
+        let ratio = factors[1] / factors[0];
+        assert!(factors.windows(2).all(|pair| (pair[1] / pair[0] - ratio).abs() < 1e-12));
+        assert!((results[5] - calculate_npv(FLOWS.as_ptr(), FLOWS.len(), factors[5])).abs() < 1e-9);
+
+        for range_min in [0.0, -0.01] {
+            let invalid = SensitivityRequest { range_min, ..log_request };
+            assert_eq!(run_with_factors(&invalid).0, SensitivityStatus::NonPositiveLogRange);
+        }
+    }
+
+    #[test]
+    fn linear_spacing_is_the_default() {
+        let mut results = [0.0; 11];
+        run_sensitivity_analysis(FLOWS.as_ptr(), FLOWS.len(), SensitivityVariable::DiscountRate as usize, 0.01, 0.30, 10, 0.1, 0.3, 0.4, results.as_mut_ptr());
+        let linear = SensitivityRequest { spacing: SweepSpacing::Linear as u32, ..request(SensitivityVariable::DiscountRate, 0.01, 0.30, 10) };
+        let (_, expected, factors, _) = run_with_factors(&linear);
+        assert_eq!(results.to_vec(), expected);
+        assert!((factors[5] - 0.155).abs() < 1e-12);
+    }
+
+    fn factorial(variables: &[usize], levels: &[f64], pre_tax_flows: *const f64) -> Option<Vec<f64>> {
+        let mut results = vec![f64::NAN; factorial_sensitivity_result_len(variables.len()).max(1)];
+        run_factorial_sensitivity(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, pre_tax_flows, 0.2,
+            variables.as_ptr(), variables.len(), levels.as_ptr(), results.as_mut_ptr(),
+        ).then_some(results)
+    }
+
+    #[test]
+    fn factorial_result_len_covers_table_and_effects() {
+        assert_eq!(factorial_sensitivity_result_len(2), 9 + 2 + 1);
+        assert_eq!(factorial_sensitivity_result_len(MAX_FACTORIAL_VARIABLES), 729 + 6 + 15);
+        assert_eq!(factorial_sensitivity_result_len(0), 0);
+        assert_eq!(factorial_sensitivity_result_len(MAX_FACTORIAL_VARIABLES + 1), 0);
+    }
+
+    #[test]
+    fn additive_variables_do_not_interact() {
+        // Capex only moves year 0 and revenue only the operating years
+        let variables = [SensitivityVariable::Revenue as usize, SensitivityVariable::CapitalInvestment as usize];
+        let results = factorial(&variables, &[0.8, 1.0, 1.2, 0.9, 1.0, 1.1], std::ptr::null()).unwrap();
+        // Row 4 is the base of both; rows 3 and 5 only move capex
+        let capex_only = factorial(&variables[1..], &[0.9, 1.0, 1.1], std::ptr::null()).unwrap();
+        assert!(results[3..6].iter().zip(&capex_only[..3]).all(|(a, b)| (a - b).abs() < 1e-9));
+        assert!((results[9] - (results[7] - results[1])).abs() < 1e-9);
+        assert!((results[10] - (results[5] - results[3])).abs() < 1e-9);
+        assert!(results[11].abs() < 1e-9, "{results:?}");
+    }
+
+    #[test]
+    fn coupled_volume_and_price_interact() {
+        let variables = [SensitivityVariable::ProductionVolume as usize, SensitivityVariable::Revenue as usize];
+        let results = factorial(&variables, &[0.8, 1.0, 1.2, 0.8, 1.0, 1.2], std::ptr::null()).unwrap();
+        assert!(results[11] > 10.0, "{results:?}");
+        // Row 8 has both variables high, row 6 volume high and price low
+        assert!(results[8] - results[6] > results[2] - results[0]);
+    }
+
+    #[test]
+    fn factorial_rejects_invalid_variables() {
+        let levels = [0.8, 1.0, 1.2, 0.8, 1.0, 1.2];
+        let revenue = SensitivityVariable::Revenue as usize;
+        assert!(factorial(&[revenue, revenue], &levels, std::ptr::null()).is_none());
+        assert!(factorial(&[revenue, TORNADO_VARIABLES], &levels, std::ptr::null()).is_none());
+        assert!(factorial(&[revenue, SensitivityVariable::TaxRate as usize], &levels, std::ptr::null()).is_none());
+
+        let pre_tax = [-1000.0, 375.0, 437.5, 500.0, 562.5];
+        let with_tax = factorial(&[revenue, SensitivityVariable::TaxRate as usize], &[0.8, 1.0, 1.2, 0.15, 0.2, 0.25], pre_tax.as_ptr()).unwrap();
+        assert!(with_tax.iter().all(|value| value.is_finite()));
+    }
+
+    fn run_normalized(request: &SensitivityRequest, output_mode: SensitivityOutputMode) -> (Vec<f64>, SensitivityNormalization) {
+        let mut results = vec![f64::NAN; request.steps + 1];
+        let mut response = SensitivityResponse {
+            results: results.as_mut_ptr(),
+            results_len: results.len(),
+            factors: std::ptr::null_mut(),
+            points_written: 0,
+            statuses: std::ptr::null_mut(),
+            normalization: SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute },
+        };
+        let request = SensitivityRequest { output_mode: output_mode as u32, ..*request };
+        assert_eq!(run_sensitivity_analysis_v2(&request, &mut response), SensitivityStatus::Ok);
+        (results, response.normalization)
+    }
+
+    /// Absolute value reconstructed from a normalized one
+    fn absolute(value: f64, normalization: &SensitivityNormalization) -> f64 {
+        let base = normalization.base_value;
+        match normalization.mode_used {
+            SensitivityOutputMode::Absolute => value,
+            SensitivityOutputMode::DeltaFromBase => base + value,
+            SensitivityOutputMode::PercentOfBase => base + value * base.abs() / 100.0,
+        }
+    }
+
+    #[test]
+    fn output_modes_round_trip_to_absolute_values() {
+        let sweep = request(SensitivityVariable::Revenue, 0.5, 1.5, 10);
+        let (absolute_values, _) = run_normalized(&sweep, SensitivityOutputMode::Absolute);
+        for mode in [SensitivityOutputMode::DeltaFromBase, SensitivityOutputMode::PercentOfBase] {
+            let (values, normalization) = run_normalized(&sweep, mode);
+            assert_eq!(normalization.mode_used, mode);
+            for (value, expected) in values.iter().zip(&absolute_values) {
+                assert!((absolute(*value, &normalization) - expected).abs() < 1e-9);
+            }
+        }
+    }
+
+    #[test]
+    fn percent_of_a_zero_base_falls_back_to_delta() {
+        // Breaks even exactly at the 10% discount rate
+        let flows = [-1000.0, 1100.0];
+        let sweep = SensitivityRequest { base_values: flows.as_ptr(), len: flows.len(), ..request(SensitivityVariable::Revenue, 0.5, 1.5, 4) };
+        let (absolute_values, _) = run_normalized(&sweep, SensitivityOutputMode::Absolute);
+        let (values, normalization) = run_normalized(&sweep, SensitivityOutputMode::PercentOfBase);
+        assert_eq!(normalization.mode_used, SensitivityOutputMode::DeltaFromBase);
+        assert!(values.iter().all(|value| value.is_finite()));
+        for (value, expected) in values.iter().zip(&absolute_values) {
+            assert!((absolute(*value, &normalization) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn tornado_and_spider_output_modes_round_trip() {
+        let (absolute_rows, _) = tornado(SensitivityMetric::Npv, std::ptr::null());
+        let mut percent = [0.0; TORNADO_VARIABLES * 3];
+        let mut order = [0; TORNADO_VARIABLES];
+        let mut normalization = SensitivityNormalization { base_value: f64::NAN, mode_used: SensitivityOutputMode::Absolute };
+        assert!(run_tornado_analysis(
+            FLOWS.as_ptr(), FLOWS.len(), 0.1, 0.3, 0.4, std::ptr::null(), 0.25, 0.8, 1.2,
+            SensitivityMetric::Npv as u32, SensitivityOutputMode::PercentOfBase as u32, percent.as_mut_ptr(), order.as_mut_ptr(), &mut normalization
+        ));
+        assert_eq!(normalization.mode_used, SensitivityOutputMode::PercentOfBase);
+        for (value, expected) in percent.iter().zip(&absolute_rows) {
+            assert!((absolute(*value, &normalization) - expected).abs() < 1e-9 || expected.is_nan());
+        }
+
+        let (absolute_spider, _) = spider(0.3, SensitivityOutputMode::Absolute);
+        let (percent_spider, normalization) = spider(0.3, SensitivityOutputMode::PercentOfBase);
+        for (value, expected) in percent_spider.iter().zip(&absolute_spider) {
+            assert!((absolute(*value, &normalization) - expected).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn asymmetric_volume_range_hits_the_base_volume() {
+        let sweep = SensitivityRequest { base_volume: 1.5e6, ..request(SensitivityVariable::ProductionVolume, 1.2e6, 3.0e6, 6) };
+        let (status, results, factors, _) = run_with_factors(&sweep);
+        assert_eq!(status, SensitivityStatus::Ok);
+        assert_eq!(factors[1], 1.5e6);
+        assert!((results[1] - calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.1)).abs() < 1e-9);
+        // Twice the base volume doubles every operating year
+        let doubled: Vec<f64> = FLOWS.iter().enumerate().map(|(t, flow)| if t == 0 { *flow } else { 2.0 * flow }).collect();
+        assert!((results[6] - calculate_npv(doubled.as_ptr(), doubled.len(), 0.1)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn missing_base_volume_falls_back_to_the_midpoint() {
+        let base = calculate_npv(FLOWS.as_ptr(), FLOWS.len(), 0.1);
+        for base_volume in [0.0, f64::NAN] {
+            let sweep = SensitivityRequest { base_volume, ..request(SensitivityVariable::ProductionVolume, 1.2e6, 3.0e6, 6) };
+            let (status, results) = run_v2(&sweep);
+            assert_eq!(status, SensitivityStatus::Ok);
+            assert!((results[3] - base).abs() < 1e-9);
+        }
+        let negative = SensitivityRequest { base_volume: -1.0, ..request(SensitivityVariable::ProductionVolume, 1.2e6, 3.0e6, 6) };
+        assert_eq!(run_v2(&negative).0, SensitivityStatus::InvalidBaseVolume);
+    }
+
+    // Years 2 and 4 net revenue against larger costs
+    const REVENUES: [f64; 5] = [0.0, 900.0, 500.0, 1300.0, 700.1];
+    const COSTS: [f64; 5] = [1000.0, 300.0, 700.3, 400.0, 333.3];
+
+    fn component_request(variable: SensitivityVariable) -> SensitivityRequest {
+        SensitivityRequest {
+            base_values: std::ptr::null(),
+            revenues: REVENUES.as_ptr(),
+            costs: COSTS.as_ptr(),
+            ..request(variable, 0.5, 1.5, 2)
+        }
+    }
+
+    #[test]
+    fn separate_components_reproduce_the_base_npv() {
+        let net: Vec<f64> = REVENUES.iter().zip(&COSTS).map(|(revenue, cost)| revenue - cost).collect();
+        let base = calculate_npv(net.as_ptr(), net.len(), 0.1);
+        for variable in [SensitivityVariable::ProductionVolume, SensitivityVariable::OperatingCosts, SensitivityVariable::Revenue, SensitivityVariable::CapitalInvestment] {
+            let (status, results) = run_v2(&component_request(variable));
+            assert_eq!(status, SensitivityStatus::Ok);
+            assert_eq!(results[1], base, "{variable:?}");
+        }
+
+        // Both paths start from the base NPV, but away from it the net-flow
+        // path only approximates the mixed-sign years
+        let net_request = SensitivityRequest { base_values: net.as_ptr(), ..request(SensitivityVariable::OperatingCosts, 0.5, 1.5, 2) };
+        let (net_results, component_results) = (run_v2(&net_request).1, run_v2(&component_request(SensitivityVariable::OperatingCosts)).1);
+        assert!((net_results[1] - base).abs() < 1e-9);
+        assert!((net_results[2] - component_results[2]).abs() > 1.0, "{net_results:?} {component_results:?}");
+    }
+
+    #[test]
+    fn components_scale_only_what_they_affect() {
+        let (_, results) = run_v2(&component_request(SensitivityVariable::Revenue));
+        let discounted_revenue: f64 = REVENUES.iter().enumerate().map(|(t, revenue)| revenue / 1.1_f64.powi(t as i32)).sum();
+        assert!((results[2] - results[1] - 0.5 * discounted_revenue).abs() < 1e-9);
+
+        let (_, results) = run_v2(&component_request(SensitivityVariable::CapitalInvestment));
+        assert!((results[0] - results[1] - 500.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn a_lone_component_array_is_rejected() {
+        let revenues_only = SensitivityRequest { costs: std::ptr::null(), ..component_request(SensitivityVariable::Revenue) };
+        assert_eq!(run_v2(&revenues_only).0, SensitivityStatus::IncompleteComponents);
+        let costs_only = SensitivityRequest { revenues: std::ptr::null(), ..component_request(SensitivityVariable::Revenue) };
+        assert_eq!(run_v2(&costs_only).0, SensitivityStatus::IncompleteComponents);
+    }
+}
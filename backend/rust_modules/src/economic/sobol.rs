@@ -0,0 +1,261 @@
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::{Distribution, Normal};
+use std::ffi::c_double;
+use std::sync::atomic::AtomicU8;
+use super::execution::{par_map_chunked, with_thread_limit, CancelFlag, RunStatus};
+use super::monte_carlo::{adjust_operating_flow, percentile_sorted, DrawTally, ShockDistributions, COST, PRICE, PRODUCTION};
+
+/// Factors analysed by `run_sobol_analysis`: price, cost, production and
+/// discount rate, in that order.
+pub const SOBOL_FACTORS: usize = 4;
+
+/// Number of values written per factor by `run_sobol_analysis`.
+///
+/// | index | value |
+/// |-------|-------|
+/// | 0     | first-order index S1 |
+/// | 1-2   | lower, upper bootstrap bound of S1 |
+/// | 3     | total-effect index ST |
+/// | 4-5   | lower, upper bootstrap bound of ST |
+pub const SOBOL_RESULT_WIDTH: usize = 6;
+
+const SOBOL_CHUNK_SIZE: usize = 64;
+const DISCOUNT_RATE: usize = 3;
+// Sampled discount rates are kept above -100%, where discounting breaks down
+const MIN_DISCOUNT_RATE: f64 = -0.99;
+
+/// One point of the input space: per-year (price, cost, production)
+/// multipliers plus the discount rate. Year 0 carries no shocks.
+#[derive(Clone)]
+struct SobolSample {
+    multipliers: Vec<[f64; 3]>,
+    rate: f64,
+}
+
+impl SobolSample {
+    fn draw(shocks: &ShockDistributions, rate: Option<&Normal<f64>>, discount_rate: f64, years: usize, rng: &mut StdRng) -> Self {
+        let mut tally = DrawTally::default();
+        let multipliers = (0..years)
+            .map(|_| [PRICE, COST, PRODUCTION].map(|factor| shocks.draw(factor, rng, &mut tally)))
+            .collect();
+        let rate = rate.map_or(discount_rate, |dist| dist.sample(rng).max(MIN_DISCOUNT_RATE));
+        Self { multipliers, rate }
+    }
+
+    /// This sample with `factor` taken from `other` (one row of A_B^i).
+    fn with_factor_from(&self, other: &Self, factor: usize) -> Self {
+        let mut mixed = self.clone();
+        if factor == DISCOUNT_RATE {
+            mixed.rate = other.rate;
+        } else {
+            for (own, theirs) in mixed.multipliers.iter_mut().zip(&other.multipliers) {
+                own[factor] = theirs[factor];
+            }
+        }
+        mixed
+    }
+
+    fn npv(&self, values: &[f64]) -> f64 {
+        values.iter().zip(&self.multipliers).enumerate().map(|(year, (&value, m))| {
+            let flow = if year == 0 { value } else { adjust_operating_flow(value, m[PRICE], m[COST], m[PRODUCTION]) };
+            flow / (1.0 + self.rate).powi(year as i32)
+        }).sum()
+    }
+}
+
+/// Model evaluations for one row of the Saltelli design.
+struct Evaluation {
+    a: f64,
+    b: f64,
+    a_b: [f64; SOBOL_FACTORS],
+}
+
+/// First-order (Saltelli 2010) and total-effect (Jansen) estimates over the
+/// evaluations selected by `rows`. NaN when the output does not vary.
+fn estimate_indices(evaluations: &[Evaluation], rows: &[usize]) -> ([f64; SOBOL_FACTORS], [f64; SOBOL_FACTORS]) {
+    let n = rows.len() as f64;
+    let mean = rows.iter().map(|&r| evaluations[r].a + evaluations[r].b).sum::<f64>() / (2.0 * n);
+    let variance = rows.iter().map(|&r| {
+        let e = &evaluations[r];
+        (e.a - mean).powi(2) + (e.b - mean).powi(2)
+    }).sum::<f64>() / (2.0 * n);
+    if variance <= 0.0 || !variance.is_finite() {
+        return ([f64::NAN; SOBOL_FACTORS], [f64::NAN; SOBOL_FACTORS]);
+    }
+
+    let first_order = std::array::from_fn(|i| {
+        rows.iter().map(|&r| {
+            let e = &evaluations[r];
+            e.b * (e.a_b[i] - e.a)
+        }).sum::<f64>() / n / variance
+    });
+    let total_effect = std::array::from_fn(|i| {
+        rows.iter().map(|&r| {
+            let e = &evaluations[r];
+            (e.a - e.a_b[i]).powi(2)
+        }).sum::<f64>() / (2.0 * n) / variance
+    });
+    (first_order, total_effect)
+}
+
+/// Variance-based Sobol indices of NPV with respect to price, cost,
+/// production and discount-rate uncertainty.
+///
+/// Uses the Saltelli scheme: two independent sample matrices A and B of
+/// `base_samples` rows each, plus one matrix A_B^i per factor with factor i
+/// taken from B, for `base_samples * (SOBOL_FACTORS + 2)` NPV evaluations.
+/// The price, cost and production multipliers are drawn per operating year
+/// exactly as in the Monte Carlo and treated as one factor each; the
+/// discount rate is normal around `discount_rate` with standard deviation
+/// `discount_rate_uncertainty` (0 keeps it fixed).
+///
+/// Confidence bounds come from `bootstrap_resamples` resamples of the design
+/// rows at `confidence_level` (0 means 95%); with no resamples they are NaN.
+/// Indices are NaN when the NPV does not vary. `results` holds
+/// `SOBOL_FACTORS` rows of `SOBOL_RESULT_WIDTH` values.
+///
+/// # Safety
+/// - base_values points to a valid array of f64 with length len
+/// - results points to a valid array of f64 with length SOBOL_FACTORS * SOBOL_RESULT_WIDTH
+/// - cancel_flag is null or points to an AtomicU8 that outlives the call
+#[no_mangle]
+pub extern "C" fn run_sobol_analysis(
+    base_values: *const c_double,
+    len: usize,
+    base_samples: usize,
+    price_uncertainty: c_double,
+    cost_uncertainty: c_double,
+    production_uncertainty: c_double,
+    discount_rate: c_double,
+    discount_rate_uncertainty: c_double,
+    seed: u64,
+    bootstrap_resamples: usize,
+    confidence_level: c_double,
+    results: *mut c_double,
+    cancel_flag: *const AtomicU8,
+    max_threads: usize
+) -> RunStatus {
+    if base_values.is_null() || results.is_null() || len == 0 || base_samples < 2 {
+        return RunStatus::InvalidInput;
+    }
+    let level = if confidence_level == 0.0 { 0.95 } else { confidence_level };
+    if !(level > 0.0 && level < 1.0) {
+        return RunStatus::InvalidInput;
+    }
+    let shocks = match ShockDistributions::new(price_uncertainty, cost_uncertainty, production_uncertainty) {
+        Some(shocks) => shocks,
+        None => return RunStatus::InvalidInput,
+    };
+    let rate = if discount_rate_uncertainty == 0.0 {
+        None
+    } else {
+        match Normal::new(discount_rate, discount_rate_uncertainty) {
+            Ok(dist) => Some(dist),
+            Err(_) => return RunStatus::InvalidInput,
+        }
+    };
+
+    let values = unsafe { std::slice::from_raw_parts(base_values, len) };
+    let results = unsafe { std::slice::from_raw_parts_mut(results, SOBOL_FACTORS * SOBOL_RESULT_WIDTH) };
+    let cancel = CancelFlag::from_ptr(cancel_flag);
+
+    let evaluations = with_thread_limit(max_threads, || {
+        par_map_chunked(base_samples, SOBOL_CHUNK_SIZE, cancel, |row| {
+            let mut rng = StdRng::seed_from_u64(seed.wrapping_add(row as u64));
+            let a = SobolSample::draw(&shocks, rate.as_ref(), discount_rate, len, &mut rng);
+            let b = SobolSample::draw(&shocks, rate.as_ref(), discount_rate, len, &mut rng);
+            Evaluation {
+                a: a.npv(values),
+                b: b.npv(values),
+                a_b: std::array::from_fn(|i| a.with_factor_from(&b, i).npv(values)),
+            }
+        })
+    });
+    let evaluations = match evaluations {
+        Some(Some(evaluations)) => evaluations,
+        Some(None) => return RunStatus::Cancelled,
+        None => return RunStatus::InvalidInput,
+    };
+
+    let all_rows: Vec<usize> = (0..base_samples).collect();
+    let (first_order, total_effect) = estimate_indices(&evaluations, &all_rows);
+
+    // Resampled estimates per factor: [S1 of each factor, ST of each factor]
+    let mut resampled: Vec<Vec<f64>> = vec![Vec::new(); 2 * SOBOL_FACTORS];
+    let mut rng = StdRng::seed_from_u64(seed.wrapping_add(base_samples as u64));
+    let mut rows = vec![0; base_samples];
+    for _ in 0..bootstrap_resamples {
+        if cancel.is_set() {
+            return RunStatus::Cancelled;
+        }
+        rows.iter_mut().for_each(|row| *row = rng.gen_range(0..base_samples));
+        let (s1, st) = estimate_indices(&evaluations, &rows);
+        for (i, estimate) in s1.into_iter().chain(st).enumerate() {
+            if estimate.is_finite() {
+                resampled[i].push(estimate);
+            }
+        }
+    }
+    let bounds: Vec<(f64, f64)> = resampled.into_iter().map(|mut estimates| {
+        estimates.sort_by(|a, b| a.total_cmp(b));
+        (percentile_sorted(&estimates, (1.0 - level) / 2.0), percentile_sorted(&estimates, (1.0 + level) / 2.0))
+    }).collect();
+
+    for (factor, row) in results.chunks_exact_mut(SOBOL_RESULT_WIDTH).enumerate() {
+        let (s1_lower, s1_upper) = bounds[factor];
+        let (st_lower, st_upper) = bounds[SOBOL_FACTORS + factor];
+        row.copy_from_slice(&[first_order[factor], s1_lower, s1_upper, total_effect[factor], st_lower, st_upper]);
+    }
+    RunStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use std::sync::atomic::{AtomicU8, Ordering};
+
+    const REVENUE_DRIVEN: [f64; 5] = [-1000.0, 400.0, 400.0, 400.0, 400.0];
+
+    fn sobol(uncertainties: [f64; 4], base_samples: usize, cancel_flag: *const AtomicU8) -> (RunStatus, [f64; SOBOL_FACTORS * SOBOL_RESULT_WIDTH]) {
+        let [price, cost, production, rate] = uncertainties;
+        let mut results = [f64::NAN; SOBOL_FACTORS * SOBOL_RESULT_WIDTH];
+        let status = run_sobol_analysis(REVENUE_DRIVEN.as_ptr(), REVENUE_DRIVEN.len(), base_samples, price, cost, production,
+            0.08, rate, 7, 200, 0.0, results.as_mut_ptr(), cancel_flag, 0);
+        (status, results)
+    }
+
+    #[test]
+    fn revenue_driven_project_attributes_its_variance_to_price() {
+        let (status, results) = sobol([0.2, 0.1, 0.01, 0.0], 4000, std::ptr::null());
+        assert_eq!(status, RunStatus::Ok);
+        let rows: Vec<&[f64]> = results.chunks(SOBOL_RESULT_WIDTH).collect();
+        // Price carries nearly all the variance; costs never apply to positive flows
+        assert!(rows[PRICE][0] > 0.9 && rows[PRICE][3] > 0.9, "{:?}", rows[PRICE]);
+        assert!(rows[COST][0].abs() < 0.05 && rows[COST][3].abs() < 0.05, "{:?}", rows[COST]);
+        assert!(rows[DISCOUNT_RATE][3].abs() < 1e-12);
+        for row in &rows[..DISCOUNT_RATE] {
+            assert!(row[1] <= row[0] && row[0] <= row[2], "{row:?}");
+            assert!(row[4] <= row[3] && row[3] <= row[5], "{row:?}");
+        }
+    }
+
+    #[test]
+    fn same_seed_reproduces_the_indices() {
+        let (_, first) = sobol([0.1, 0.1, 0.1, 0.03], 500, std::ptr::null());
+        let (_, second) = sobol([0.1, 0.1, 0.1, 0.03], 500, std::ptr::null());
+        assert_eq!(first.map(f64::to_bits), second.map(f64::to_bits));
+        assert!(first.chunks(SOBOL_RESULT_WIDTH).nth(DISCOUNT_RATE).unwrap()[3] > 0.0);
+    }
+
+    #[test]
+    fn invalid_inputs_and_cancellation() {
+        assert_eq!(sobol([0.1; 4], 1, std::ptr::null()).0, RunStatus::InvalidInput);
+        assert_eq!(sobol([f64::NAN, 0.1, 0.1, 0.0], 100, std::ptr::null()).0, RunStatus::InvalidInput);
+        let flag = AtomicU8::new(0);
+        flag.store(1, Ordering::Relaxed);
+        let (status, results) = sobol([0.1; 4], 100, &flag);
+        assert_eq!(status, RunStatus::Cancelled);
+        assert!(results.iter().all(|value| value.is_nan()));
+    }
+}
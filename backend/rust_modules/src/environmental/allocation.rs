@@ -1,5 +1,9 @@
 use std::ffi::c_double;
+use crate::protein_analysis::RatioUnit;
 
+/// Allocate `impacts` by value share, writing impact × value / Σ values
+/// to `allocation_factors`. Returns false for any input that
+/// `calculate_allocation_checked` rejects.
 #[no_mangle]
 pub extern "C" fn calculate_allocation(
     impacts: *const c_double,
@@ -7,49 +11,1043 @@ pub extern "C" fn calculate_allocation(
     len: usize,
     allocation_factors: *mut c_double
 ) -> bool {
-    let impacts_slice = unsafe { std::slice::from_raw_parts(impacts, len) };
-    let values_slice = unsafe { std::slice::from_raw_parts(values, len) };
-    let factors_slice = unsafe { std::slice::from_raw_parts_mut(allocation_factors, len) };
-    
-    // Calculate total value
-    let total_value: f64 = values_slice.iter().sum();
-    
+    let mut invalid_index = usize::MAX;
+    calculate_allocation_checked(impacts, values, len, allocation_factors, &mut invalid_index) == AllocationStatus::Ok
+}
+
+/// Blend mass and economic allocation factors as
+/// weight × mass + (1 - weight) × economic, with `weight` clamped to
+/// [0, 1]. Factor sets that do not sum to 1 are accepted and the result is
+/// renormalized; returns false for any other input that
+/// `calculate_hybrid_allocation_checked` rejects.
+#[no_mangle]
+pub extern "C" fn calculate_hybrid_allocation(
+    mass_factors: *const c_double,
+    economic_factors: *const c_double,
+    len: usize,
+    weight: c_double,
+    results: *mut c_double
+) -> bool {
+    let (mut invalid_index, mut sum_mismatch) = (usize::MAX, false);
+    calculate_hybrid_allocation_checked(
+        mass_factors,
+        economic_factors,
+        len,
+        weight,
+        FactorSumCheck::Warn as u32,
+        results,
+        &mut invalid_index,
+        &mut sum_mismatch,
+    ) == AllocationStatus::Ok
+}
+
+/// Value-share allocation of several impact categories at once.
+///
+/// `impact_matrix` holds `n_categories` rows of `n_products` impacts
+/// (row-major, e.g. GWP, water, energy, land use and eutrophication). Each
+/// category's total, its row sum, is shared out in proportion to the
+/// product `values`, so every row of `allocated_out` sums to the row total
+/// of `impact_matrix`. The factors, value / total value, are computed once
+/// and written to `factors_out` (`n_products` entries). Returns false on
+/// null pointers, an empty dimension, non-finite impacts, negative or
+/// non-finite values, or a zero total value.
+#[no_mangle]
+pub extern "C" fn calculate_allocation_matrix(
+    impact_matrix: *const c_double,
+    n_categories: usize,
+    values: *const c_double,
+    n_products: usize,
+    factors_out: *mut c_double,
+    allocated_out: *mut c_double
+) -> bool {
+    if impact_matrix.is_null() || values.is_null() || factors_out.is_null() || allocated_out.is_null()
+        || n_categories == 0 || n_products == 0 {
+        return false;
+    }
+    let impacts = unsafe { std::slice::from_raw_parts(impact_matrix, n_categories * n_products) };
+    let values = unsafe { std::slice::from_raw_parts(values, n_products) };
+    if impacts.iter().any(|x| !x.is_finite()) || values.iter().any(|&v| !v.is_finite() || v < 0.0) {
+        return false;
+    }
+    let total_value: f64 = values.iter().sum();
     if total_value <= 0.0 {
         return false;
     }
-    
-    // Calculate allocation factors
-    for i in 0..len {
-        factors_slice[i] = values_slice[i] / total_value;
+
+    let factors: Vec<f64> = values.iter().map(|v| v / total_value).collect();
+    let allocated = unsafe { std::slice::from_raw_parts_mut(allocated_out, n_categories * n_products) };
+    for (row, impact_row) in allocated.chunks_exact_mut(n_products).zip(impacts.chunks_exact(n_products)) {
+        let category_total: f64 = impact_row.iter().sum();
+        for (share, factor) in row.iter_mut().zip(&factors) {
+            *share = category_total * factor;
+        }
     }
-    
-    // Apply allocation factors to impacts
-    for i in 0..len {
-        factors_slice[i] *= impacts_slice[i];
+    unsafe {
+        std::slice::from_raw_parts_mut(factors_out, n_products).copy_from_slice(&factors);
     }
-    
     true
 }
 
+/// Outcome of the keyed allocation functions such as
+/// `calculate_allocation_by_property`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocationStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// `len` was 0
+    Empty = 2,
+    /// An impact, value, factor, mass or property was NaN or infinite
+    NonFiniteValue = 3,
+    /// A value, factor, mass or property was negative
+    NegativeValue = 4,
+    /// The allocation keys sum to zero, so no product can carry a share
+    ZeroTotal = 5,
+    /// A protein content was outside the range of its unit
+    ContentOutOfRange = 6,
+    /// An input factor set did not sum to 1 under `FactorSumCheck::Reject`
+    FactorSumMismatch = 7,
+    /// A content unit id was not a `RatioUnit`
+    UnknownUnit = 8,
+    /// A sum check id was not a `FactorSumCheck`
+    UnknownSumCheck = 9,
+}
+
+/// What `calculate_hybrid_allocation_checked` does when an input factor set
+/// does not sum to 1 within `FACTOR_SUM_TOLERANCE`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactorSumCheck {
+    /// Flag the mismatch and continue; the result is renormalized anyway
+    Warn = 0,
+    /// Fail with `AllocationStatus::FactorSumMismatch`
+    Reject = 1,
+}
+
+impl FactorSumCheck {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Warn),
+            1 => Some(Self::Reject),
+            _ => None,
+        }
+    }
+}
+
+/// Input array that the `invalid_index_out` of
+/// `calculate_hybrid_allocation_matrix` indexes into.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HybridAllocationInput {
+    MassFactors = 0,
+    EconomicFactors = 1,
+    /// `weights_per_category`, indexed by category
+    Weights = 2,
+}
+
+/// How far an allocation factor set may sum from 1 before
+/// `calculate_hybrid_allocation_checked` reports it.
+pub const FACTOR_SUM_TOLERANCE: f64 = 1e-6;
+
+/// Index of the first entry that is not finite or is negative, with the
+/// matching status.
+fn first_invalid_entry(entries: &[f64]) -> Option<(usize, AllocationStatus)> {
+    entries.iter().enumerate().find_map(|(i, &x)| {
+        if !x.is_finite() {
+            Some((i, AllocationStatus::NonFiniteValue))
+        } else if x < 0.0 {
+            Some((i, AllocationStatus::NegativeValue))
+        } else {
+            None
+        }
+    })
+}
+
+/// Allocate each impact by its product's share of the summed `keys`,
+/// writing impact × key / total to `out` as `calculate_allocation` does.
+fn allocate_by_keys(impacts: &[f64], keys: &[f64], out: &mut [f64]) -> AllocationStatus {
+    let total: f64 = keys.iter().sum();
+    if total <= 0.0 {
+        return AllocationStatus::ZeroTotal;
+    }
+    for ((allocated, &key), &impact) in out.iter_mut().zip(keys).zip(impacts) {
+        *allocated = key / total * impact;
+    }
+    AllocationStatus::Ok
+}
+
+/// `calculate_allocation` with validation: each product's allocated
+/// impact is impact × value / Σ values, unchanged for valid input.
+///
+/// `invalid_index_out` receives the index of the first non-finite impact
+/// or of the first non-finite or negative value, and `usize::MAX` when
+/// there is none. Nothing but `invalid_index_out` is written unless the
+/// status is `AllocationStatus::Ok`.
 #[no_mangle]
-pub extern "C" fn calculate_hybrid_allocation(
+pub extern "C" fn calculate_allocation_checked(
+    impacts: *const c_double,
+    values: *const c_double,
+    len: usize,
+    allocation_factors: *mut c_double,
+    invalid_index_out: *mut usize
+) -> AllocationStatus {
+    if impacts.is_null() || values.is_null() || allocation_factors.is_null() || invalid_index_out.is_null() {
+        return AllocationStatus::NullPointer;
+    }
+    unsafe {
+        *invalid_index_out = usize::MAX;
+    }
+    if len == 0 {
+        return AllocationStatus::Empty;
+    }
+    let (impacts, values) = unsafe {
+        (std::slice::from_raw_parts(impacts, len), std::slice::from_raw_parts(values, len))
+    };
+    let invalid = impacts.iter().position(|x| !x.is_finite())
+        .map(|i| (i, AllocationStatus::NonFiniteValue))
+        .or_else(|| first_invalid_entry(values));
+    if let Some((index, status)) = invalid {
+        unsafe {
+            *invalid_index_out = index;
+        }
+        return status;
+    }
+
+    let mut allocated = vec![0.0; len];
+    let status = allocate_by_keys(impacts, values, &mut allocated);
+    if status == AllocationStatus::Ok {
+        unsafe {
+            std::slice::from_raw_parts_mut(allocation_factors, len).copy_from_slice(&allocated);
+        }
+    }
+    status
+}
+
+/// `calculate_hybrid_allocation` with validation, the single-category
+/// case of `calculate_hybrid_allocation_matrix`.
+///
+/// Both factor sets must be finite and non-negative; `invalid_index_out`
+/// receives the first offending index (in `mass_factors` first), and
+/// `usize::MAX` when there is none. A set not summing to 1 within
+/// `FACTOR_SUM_TOLERANCE` sets `sum_mismatch_out` and, when `sum_check` is
+/// the id of `FactorSumCheck::Reject`, fails; an id that is not a
+/// `FactorSumCheck` gives `AllocationStatus::UnknownSumCheck`. The blend
+/// weight × mass + (1 - weight) × economic, with `weight` clamped to
+/// [0, 1], is renormalized so `results` always sums to 1; inputs that
+/// already sum to 1 give the unnormalized blend. Nothing but the two
+/// diagnostics is written unless the status is `AllocationStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn calculate_hybrid_allocation_checked(
     mass_factors: *const c_double,
     economic_factors: *const c_double,
     len: usize,
     weight: c_double,
-    results: *mut c_double
-) -> bool {
-    let mass_slice = unsafe { std::slice::from_raw_parts(mass_factors, len) };
-    let economic_slice = unsafe { std::slice::from_raw_parts(economic_factors, len) };
-    let results_slice = unsafe { std::slice::from_raw_parts_mut(results, len) };
-    
-    // Weight should be between 0 and 1
-    let w = weight.max(0.0).min(1.0);
-    
-    // Calculate hybrid factors
-    for i in 0..len {
-        results_slice[i] = w * mass_slice[i] + (1.0 - w) * economic_slice[i];
-    }
-    
-    true
-} 
\ No newline at end of file
+    sum_check: u32,
+    results: *mut c_double,
+    invalid_index_out: *mut usize,
+    sum_mismatch_out: *mut bool
+) -> AllocationStatus {
+    calculate_hybrid_allocation_matrix(
+        mass_factors,
+        economic_factors,
+        len,
+        &weight,
+        1,
+        sum_check,
+        results,
+        invalid_index_out,
+        std::ptr::null_mut(),
+        sum_mismatch_out,
+    )
+}
+
+/// Hybrid allocation with a separate mass/economic blend per impact
+/// category, e.g. mass-dominant for energy and economic-dominant for GWP.
+///
+/// `weights_per_category` holds the `n_categories` mass weights, each
+/// clamped to [0, 1], and `results` receives `n_categories` rows of
+/// `n_products` factors (row-major), row c blended and renormalized with
+/// weight c exactly as `calculate_hybrid_allocation_checked` does with a
+/// scalar weight. Factor validation, the `sum_check` id and
+/// `sum_mismatch_out` are as there.
+///
+/// `invalid_index_out` receives the first offending index and, when
+/// `invalid_input_out` is non-null, the array it indexes into: a product
+/// for an invalid factor, or a category for a NaN weight, which gives
+/// `AllocationStatus::NonFiniteValue`. It is `usize::MAX`, and
+/// `invalid_input_out` untouched, when there is none.
+#[no_mangle]
+pub extern "C" fn calculate_hybrid_allocation_matrix(
+    mass_factors: *const c_double,
+    economic_factors: *const c_double,
+    n_products: usize,
+    weights_per_category: *const c_double,
+    n_categories: usize,
+    sum_check: u32,
+    results: *mut c_double,
+    invalid_index_out: *mut usize,
+    invalid_input_out: *mut HybridAllocationInput,
+    sum_mismatch_out: *mut bool
+) -> AllocationStatus {
+    if mass_factors.is_null() || economic_factors.is_null() || weights_per_category.is_null() || results.is_null()
+        || invalid_index_out.is_null() || sum_mismatch_out.is_null() {
+        return AllocationStatus::NullPointer;
+    }
+    unsafe {
+        *invalid_index_out = usize::MAX;
+        *sum_mismatch_out = false;
+    }
+    let sum_check = match FactorSumCheck::from_id(sum_check) {
+        Some(sum_check) => sum_check,
+        None => return AllocationStatus::UnknownSumCheck,
+    };
+    if n_products == 0 || n_categories == 0 {
+        return AllocationStatus::Empty;
+    }
+    let (mass, economic, weights) = unsafe {
+        (
+            std::slice::from_raw_parts(mass_factors, n_products),
+            std::slice::from_raw_parts(economic_factors, n_products),
+            std::slice::from_raw_parts(weights_per_category, n_categories),
+        )
+    };
+    let report = |index: usize, input: HybridAllocationInput| unsafe {
+        *invalid_index_out = index;
+        if !invalid_input_out.is_null() {
+            *invalid_input_out = input;
+        }
+    };
+    if let Some(category) = weights.iter().position(|w| w.is_nan()) {
+        report(category, HybridAllocationInput::Weights);
+        return AllocationStatus::NonFiniteValue;
+    }
+    let invalid = first_invalid_entry(mass).map(|invalid| (invalid, HybridAllocationInput::MassFactors))
+        .or_else(|| first_invalid_entry(economic).map(|invalid| (invalid, HybridAllocationInput::EconomicFactors)));
+    if let Some(((index, status), input)) = invalid {
+        report(index, input);
+        return status;
+    }
+
+    let off_unity = |factors: &[f64]| (factors.iter().sum::<f64>() - 1.0).abs() > FACTOR_SUM_TOLERANCE;
+    let mismatch = off_unity(mass) || off_unity(economic);
+    unsafe {
+        *sum_mismatch_out = mismatch;
+    }
+    if mismatch && sum_check == FactorSumCheck::Reject {
+        return AllocationStatus::FactorSumMismatch;
+    }
+
+    let mut blended = vec![0.0; n_categories * n_products];
+    for (row, &weight) in blended.chunks_exact_mut(n_products).zip(weights) {
+        let w = weight.clamp(0.0, 1.0);
+        for ((factor, m), e) in row.iter_mut().zip(mass).zip(economic) {
+            *factor = w * m + (1.0 - w) * e;
+        }
+        let total: f64 = row.iter().sum();
+        if total <= 0.0 {
+            return AllocationStatus::ZeroTotal;
+        }
+        if mismatch {
+            for factor in row.iter_mut() {
+                *factor /= total;
+            }
+        }
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(results, blended.len()).copy_from_slice(&blended);
+    }
+    AllocationStatus::Ok
+}
+
+/// Allocation by a physical property such as energy content (MJ/kg).
+///
+/// Each product's key is mass × property, so a property of 1 for every
+/// product gives mass allocation and the unit price gives economic
+/// allocation, both identical to `calculate_allocation` with the
+/// corresponding values. Like `calculate_allocation`, `factors_out`
+/// receives each product's allocated impact, impact × key / Σ keys.
+/// Nothing is written unless the status is `AllocationStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn calculate_allocation_by_property(
+    impacts: *const c_double,
+    masses: *const c_double,
+    property_values: *const c_double,
+    len: usize,
+    factors_out: *mut c_double
+) -> AllocationStatus {
+    if impacts.is_null() || masses.is_null() || property_values.is_null() || factors_out.is_null() {
+        return AllocationStatus::NullPointer;
+    }
+    if len == 0 {
+        return AllocationStatus::Empty;
+    }
+    let (impacts, masses, properties) = unsafe {
+        (
+            std::slice::from_raw_parts(impacts, len),
+            std::slice::from_raw_parts(masses, len),
+            std::slice::from_raw_parts(property_values, len),
+        )
+    };
+    if impacts.iter().chain(masses).chain(properties).any(|x| !x.is_finite()) {
+        return AllocationStatus::NonFiniteValue;
+    }
+    if masses.iter().chain(properties).any(|&x| x < 0.0) {
+        return AllocationStatus::NegativeValue;
+    }
+
+    let keys: Vec<f64> = masses.iter().zip(properties).map(|(m, p)| m * p).collect();
+    let mut allocated = vec![0.0; len];
+    let status = allocate_by_keys(impacts, &keys, &mut allocated);
+    if status == AllocationStatus::Ok {
+        unsafe {
+            std::slice::from_raw_parts_mut(factors_out, len).copy_from_slice(&allocated);
+        }
+    }
+    status
+}
+
+/// Allocation by protein mass, the share of each output stream in the
+/// total protein leaving the process.
+///
+/// Each stream's key is its mass × protein content, the content given as a
+/// fraction or percentage according to the `RatioUnit` id `content_unit`
+/// and checked against that range as in
+/// `calculate_protein_recovery_checked`. A stream without
+/// protein carries no burden whatever its mass. Like
+/// `calculate_allocation`, `factors_out` receives each stream's allocated
+/// impact. Returns `AllocationStatus::ZeroTotal` when no stream carries
+/// protein; nothing is written unless the status is `AllocationStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn calculate_protein_allocation(
+    impacts: *const c_double,
+    stream_masses: *const c_double,
+    protein_contents: *const c_double,
+    len: usize,
+    content_unit: u32,
+    factors_out: *mut c_double
+) -> AllocationStatus {
+    if impacts.is_null() || stream_masses.is_null() || protein_contents.is_null() || factors_out.is_null() {
+        return AllocationStatus::NullPointer;
+    }
+    if len == 0 {
+        return AllocationStatus::Empty;
+    }
+    let content_unit = match RatioUnit::from_id(content_unit) {
+        Some(unit) => unit,
+        None => return AllocationStatus::UnknownUnit,
+    };
+    let (impacts, masses, contents) = unsafe {
+        (
+            std::slice::from_raw_parts(impacts, len),
+            std::slice::from_raw_parts(stream_masses, len),
+            std::slice::from_raw_parts(protein_contents, len),
+        )
+    };
+    if impacts.iter().chain(masses).any(|x| !x.is_finite()) {
+        return AllocationStatus::NonFiniteValue;
+    }
+    if masses.iter().any(|&m| m < 0.0) {
+        return AllocationStatus::NegativeValue;
+    }
+    let contents: Option<Vec<f64>> = contents.iter().map(|&c| content_unit.to_fraction(c)).collect();
+    let contents = match contents {
+        Some(contents) => contents,
+        None => return AllocationStatus::ContentOutOfRange,
+    };
+
+    let keys: Vec<f64> = masses.iter().zip(&contents).map(|(m, c)| m * c).collect();
+    let mut allocated = vec![0.0; len];
+    let status = allocate_by_keys(impacts, &keys, &mut allocated);
+    if status == AllocationStatus::Ok {
+        unsafe {
+            std::slice::from_raw_parts_mut(factors_out, len).copy_from_slice(&allocated);
+        }
+    }
+    status
+}
+
+/// Hybrid mass weights swept by `run_allocation_sensitivity`: 0.0 to 1.0
+/// in steps of 0.1.
+pub const HYBRID_SWEEP_STEPS: usize = 11;
+
+/// Rows of `run_allocation_sensitivity`: mass, economic, protein and
+/// energy allocation, then the hybrid sweep.
+pub const ALLOCATION_SENSITIVITY_METHODS: usize = 4 + HYBRID_SWEEP_STEPS;
+
+/// Share of each product in the summed keys, or `ZeroTotal` when the keys
+/// sum to zero.
+fn key_shares(keys: &[f64]) -> Result<Vec<f64>, AllocationStatus> {
+    let total: f64 = keys.iter().sum();
+    if total <= 0.0 {
+        return Err(AllocationStatus::ZeroTotal);
+    }
+    Ok(keys.iter().map(|key| key / total).collect())
+}
+
+/// Mass shares, then mass × property shares for each property given, in
+/// the row order of `run_allocation_sensitivity`. A property whose keys
+/// sum to zero leaves its method out; only a zero total mass fails.
+fn method_shares(masses: &[f64], properties: [Option<&[f64]>; 3]) -> Result<[Option<Vec<f64>>; 4], AllocationStatus> {
+    let mut shares = [Some(key_shares(masses)?), None, None, None];
+    for (share, property) in shares.iter_mut().skip(1).zip(properties) {
+        if let Some(values) = property {
+            let keys: Vec<f64> = masses.iter().zip(values).map(|(m, v)| m * v).collect();
+            *share = key_shares(&keys).ok();
+        }
+    }
+    Ok(shares)
+}
+
+/// Allocated impacts under every allocation method at once, for the
+/// method sensitivity table reviewers ask for.
+///
+/// The total of `impacts` (`n_products` entries) is shared out by mass,
+/// by economic value (mass × `prices`), by protein mass (mass ×
+/// `protein_contents`, in the `RatioUnit` with id `content_unit`), by energy (mass ×
+/// `energy_contents`), and by hybrid weight × mass share + (1 - weight) ×
+/// economic share for weights 0.0, 0.1, ..., 1.0. `results` receives
+/// `ALLOCATION_SENSITIVITY_METHODS` rows of `n_products` in that order, so
+/// every computed row sums to the total and hybrid weight 1.0 equals the
+/// mass row. `prices`, `protein_contents` and `energy_contents` are
+/// nullable; a missing array, or one whose keys sum to zero, skips its
+/// method, whose row is filled with NaN and marked false in
+/// `methods_computed_out`. Without economic shares the hybrid sweep is
+/// skipped too, except weight 1.0, the mass row. `min_out` and `max_out`
+/// receive each product's range over the computed rows. Fails with
+/// `AllocationStatus::ZeroTotal` only when the masses sum to zero. Nothing
+/// is written unless the status is `AllocationStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn run_allocation_sensitivity(
+    impacts: *const c_double,
+    masses: *const c_double,
+    prices: *const c_double,
+    protein_contents: *const c_double,
+    energy_contents: *const c_double,
+    n_products: usize,
+    content_unit: u32,
+    results: *mut c_double,
+    methods_computed_out: *mut bool,
+    min_out: *mut c_double,
+    max_out: *mut c_double
+) -> AllocationStatus {
+    if impacts.is_null() || masses.is_null() || results.is_null() || methods_computed_out.is_null()
+        || min_out.is_null() || max_out.is_null() {
+        return AllocationStatus::NullPointer;
+    }
+    if n_products == 0 {
+        return AllocationStatus::Empty;
+    }
+    let content_unit = match RatioUnit::from_id(content_unit) {
+        Some(unit) => unit,
+        None => return AllocationStatus::UnknownUnit,
+    };
+    let optional = |values: *const c_double| {
+        (!values.is_null()).then(|| unsafe { std::slice::from_raw_parts(values, n_products) })
+    };
+    let (impacts, masses) = unsafe {
+        (std::slice::from_raw_parts(impacts, n_products), std::slice::from_raw_parts(masses, n_products))
+    };
+    let (prices, contents, energies) = (optional(prices), optional(protein_contents), optional(energy_contents));
+    let properties = [Some(masses), prices, contents, energies];
+    if impacts.iter().chain(properties.iter().flatten().copied().flatten()).any(|x| !x.is_finite()) {
+        return AllocationStatus::NonFiniteValue;
+    }
+    if [Some(masses), prices, energies].iter().flatten().copied().flatten().any(|&x| x < 0.0) {
+        return AllocationStatus::NegativeValue;
+    }
+    let contents: Option<Option<Vec<f64>>> = contents
+        .map(|contents| contents.iter().map(|&c| content_unit.to_fraction(c)).collect());
+    let contents = match contents {
+        Some(None) => return AllocationStatus::ContentOutOfRange,
+        Some(Some(contents)) => Some(contents),
+        None => None,
+    };
+
+    let shares = method_shares(masses, [prices, contents.as_deref(), energies]);
+    let [mass_shares, economic_shares, protein_shares, energy_shares] = match shares {
+        Ok(shares) => shares,
+        Err(status) => return status,
+    };
+    let mut rows = vec![mass_shares.clone(), economic_shares.clone(), protein_shares, energy_shares];
+    for step in 0..HYBRID_SWEEP_STEPS {
+        let w = step as f64 / (HYBRID_SWEEP_STEPS - 1) as f64;
+        let blend = match (&mass_shares, &economic_shares) {
+            (Some(mass), Some(economic)) => Some(mass.iter().zip(economic).map(|(m, e)| w * m + (1.0 - w) * e).collect()),
+            // All mass, so the economic shares are not needed
+            (Some(mass), None) if w == 1.0 => Some(mass.clone()),
+            _ => None,
+        };
+        rows.push(blend);
+    }
+
+    let total: f64 = impacts.iter().sum();
+    let mut table = vec![f64::NAN; ALLOCATION_SENSITIVITY_METHODS * n_products];
+    let (mut min, mut max) = (vec![f64::INFINITY; n_products], vec![f64::NEG_INFINITY; n_products]);
+    for (row, shares) in table.chunks_exact_mut(n_products).zip(&rows) {
+        if let Some(shares) = shares {
+            for (p, (allocated, share)) in row.iter_mut().zip(shares).enumerate() {
+                *allocated = total * share;
+                min[p] = min[p].min(*allocated);
+                max[p] = max[p].max(*allocated);
+            }
+        }
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(results, table.len()).copy_from_slice(&table);
+        for (computed, row) in std::slice::from_raw_parts_mut(methods_computed_out, ALLOCATION_SENSITIVITY_METHODS)
+            .iter_mut().zip(&rows) {
+            *computed = row.is_some();
+        }
+        std::slice::from_raw_parts_mut(min_out, n_products).copy_from_slice(&min);
+        std::slice::from_raw_parts_mut(max_out, n_products).copy_from_slice(&max);
+    }
+    AllocationStatus::Ok
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matrix(mass: &[f64], economic: &[f64], weights: &[f64]) -> (AllocationStatus, Vec<f64>, usize, Option<HybridAllocationInput>) {
+        let mut results = vec![0.0; mass.len() * weights.len()];
+        let (mut index, mut mismatch) = (0, false);
+        let mut input = None;
+        let mut input_out = HybridAllocationInput::MassFactors;
+        let status = calculate_hybrid_allocation_matrix(
+            mass.as_ptr(), economic.as_ptr(), mass.len(), weights.as_ptr(), weights.len(),
+            FactorSumCheck::Warn as u32, results.as_mut_ptr(), &mut index, &mut input_out, &mut mismatch,
+        );
+        if index != usize::MAX {
+            input = Some(input_out);
+        }
+        (status, results, index, input)
+    }
+
+    #[test]
+    fn matrix_rows_blend_each_category_weight() {
+        let (status, results, index, input) = matrix(&[0.8, 0.2], &[0.4, 0.6], &[1.0, 0.5, 0.0]);
+        assert_eq!(status, AllocationStatus::Ok);
+        assert_eq!((index, input), (usize::MAX, None));
+        let expected = [0.8, 0.2, 0.6, 0.4, 0.4, 0.6];
+        assert!(results.iter().zip(expected).all(|(r, e)| (r - e).abs() < 1e-12));
+    }
+
+    #[test]
+    fn matrix_reports_which_array_is_invalid() {
+        let (status, _, index, input) = matrix(&[0.8, 0.2], &[0.4, -0.6], &[0.5]);
+        assert_eq!((status, index, input), (AllocationStatus::NegativeValue, 1, Some(HybridAllocationInput::EconomicFactors)));
+        let (status, _, index, input) = matrix(&[f64::NAN, 0.2], &[0.4, 0.6], &[0.5]);
+        assert_eq!((status, index, input), (AllocationStatus::NonFiniteValue, 0, Some(HybridAllocationInput::MassFactors)));
+        let (status, _, index, input) = matrix(&[0.8, 0.2], &[0.4, 0.6], &[0.5, 0.2, f64::NAN]);
+        assert_eq!((status, index, input), (AllocationStatus::NonFiniteValue, 2, Some(HybridAllocationInput::Weights)));
+    }
+
+    #[test]
+    fn invalid_input_out_may_be_null() {
+        let (mass, economic, weight) = ([0.8, 0.2], [0.4, 0.6], [f64::NAN]);
+        let mut results = [0.0; 2];
+        let (mut index, mut mismatch) = (0, false);
+        let status = calculate_hybrid_allocation_matrix(
+            mass.as_ptr(), economic.as_ptr(), 2, weight.as_ptr(), 1, FactorSumCheck::Warn as u32,
+            results.as_mut_ptr(), &mut index, std::ptr::null_mut(), &mut mismatch,
+        );
+        assert_eq!((status, index), (AllocationStatus::NonFiniteValue, 0));
+    }
+
+    #[test]
+    fn matrix_rejects_unknown_sum_check() {
+        let mut results = [f64::NAN; 4];
+        let (mut index, mut mismatch) = (0, true);
+        let status = calculate_hybrid_allocation_matrix(
+            [0.8, 0.2].as_ptr(), [0.4, 0.6].as_ptr(), 2, [0.5, 1.0].as_ptr(), 2, u32::MAX,
+            results.as_mut_ptr(), &mut index, std::ptr::null_mut(), &mut mismatch,
+        );
+        assert_eq!((status, index, mismatch), (AllocationStatus::UnknownSumCheck, usize::MAX, false));
+        assert!(results.iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn equal_weights_reproduce_the_scalar_blend() {
+        for economic in [[0.5, 0.25, 0.25], [1.0, 1.0, 2.0]] {
+            let mut single = [f64::NAN; 3];
+            assert!(calculate_hybrid_allocation([0.2, 0.3, 0.5].as_ptr(), economic.as_ptr(), 3, 0.5, single.as_mut_ptr()));
+            let (status, results, _, _) = matrix(&[0.2, 0.3, 0.5], &economic, &[0.5; 4]);
+            assert_eq!(status, AllocationStatus::Ok);
+            for row in results.chunks_exact(3) {
+                assert_eq!(row, single);
+            }
+        }
+    }
+
+    #[test]
+    fn every_matrix_row_is_renormalized() {
+        let (status, results, _, _) = matrix(&[0.2, 0.3, 0.5], &[1.0, 1.0, 2.0], &[0.0, 0.2, 0.9, 1.0]);
+        assert_eq!(status, AllocationStatus::Ok);
+        for row in results.chunks_exact(3) {
+            assert!((row.iter().sum::<f64>() - 1.0).abs() < 1e-12, "{results:?}");
+        }
+    }
+
+    const IMPACTS: [f64; 3] = [60.0, 30.0, 10.0];
+    const MASSES: [f64; 3] = [100.0, 400.0, 500.0];
+
+    struct Sensitivity {
+        status: AllocationStatus,
+        rows: Vec<f64>,
+        computed: [bool; ALLOCATION_SENSITIVITY_METHODS],
+        min: Vec<f64>,
+        max: Vec<f64>,
+    }
+
+    fn sensitivity(prices: Option<&[f64]>, contents: Option<&[f64]>, energies: Option<&[f64]>) -> Sensitivity {
+        sensitivity_in(prices, contents, energies, RatioUnit::Percent as u32)
+    }
+
+    fn sensitivity_in(prices: Option<&[f64]>, contents: Option<&[f64]>, energies: Option<&[f64]>, content_unit: u32) -> Sensitivity {
+        let n = MASSES.len();
+        let mut rows = vec![0.0; ALLOCATION_SENSITIVITY_METHODS * n];
+        let mut computed = [false; ALLOCATION_SENSITIVITY_METHODS];
+        let (mut min, mut max) = (vec![0.0; n], vec![0.0; n]);
+        let pointer = |values: Option<&[f64]>| values.map_or(std::ptr::null(), <[f64]>::as_ptr);
+        let status = run_allocation_sensitivity(
+            IMPACTS.as_ptr(), MASSES.as_ptr(), pointer(prices), pointer(contents), pointer(energies), n,
+            content_unit, rows.as_mut_ptr(), computed.as_mut_ptr(), min.as_mut_ptr(), max.as_mut_ptr(),
+        );
+        Sensitivity { status, rows, computed, min, max }
+    }
+
+    #[test]
+    fn every_method_row_sums_to_the_total_impact() {
+        let result = sensitivity(Some(&[8.0, 0.5, 0.3]), Some(&[80.0, 5.0, 10.0]), Some(&[17.0, 16.0, 12.0]));
+        assert_eq!(result.status, AllocationStatus::Ok);
+        assert!(result.computed.iter().all(|&computed| computed));
+        for row in result.rows.chunks_exact(3) {
+            assert!((row.iter().sum::<f64>() - 100.0).abs() < 1e-9);
+        }
+        // Hybrid weight 0 is the economic row, weight 1 the mass row
+        assert_eq!(result.rows[3..6], result.rows[12..15]);
+        assert_eq!(result.rows[0..3], result.rows[(ALLOCATION_SENSITIVITY_METHODS - 1) * 3..]);
+        assert_eq!(result.rows[0..3], [10.0, 40.0, 50.0]);
+    }
+
+    #[test]
+    fn missing_prices_keep_the_all_mass_hybrid_row() {
+        let result = sensitivity(None, None, Some(&[17.0, 16.0, 12.0]));
+        assert_eq!(result.status, AllocationStatus::Ok);
+        let mut expected = [false; ALLOCATION_SENSITIVITY_METHODS];
+        expected[0] = true;
+        expected[3] = true;
+        expected[ALLOCATION_SENSITIVITY_METHODS - 1] = true;
+        assert_eq!(result.computed, expected);
+        assert!(result.rows[3..6].iter().all(|x| x.is_nan()));
+        assert_eq!(result.rows[0..3], result.rows[(ALLOCATION_SENSITIVITY_METHODS - 1) * 3..]);
+    }
+
+    #[test]
+    fn range_spans_only_the_computed_rows() {
+        let result = sensitivity(None, Some(&[80.0, 5.0, 10.0]), None);
+        let computed_rows = || result.rows.chunks_exact(3).zip(&result.computed).filter(|(_, &computed)| computed);
+        for p in 0..3 {
+            let column: Vec<f64> = computed_rows().map(|(row, _)| row[p]).collect();
+            assert_eq!(result.min[p], column.iter().copied().fold(f64::INFINITY, f64::min));
+            assert_eq!(result.max[p], column.iter().copied().fold(f64::NEG_INFINITY, f64::max));
+        }
+        // Protein allocation puts most of the burden on the concentrate
+        assert!(result.max[0] > 10.0 && result.min[0] == 10.0, "{:?} {:?}", result.min, result.max);
+    }
+
+    #[test]
+    fn zero_total_property_only_skips_its_method() {
+        let result = sensitivity(Some(&[8.0, 0.5, 0.3]), Some(&[0.0; 3]), None);
+        assert_eq!(result.status, AllocationStatus::Ok);
+        assert!(result.computed[1] && !result.computed[2] && !result.computed[3]);
+        assert!(result.rows[6..9].iter().all(|x| x.is_nan()));
+
+        let n = MASSES.len();
+        let (mut rows, mut computed) = (vec![0.0; ALLOCATION_SENSITIVITY_METHODS * n], [false; ALLOCATION_SENSITIVITY_METHODS]);
+        let (mut min, mut max) = (vec![0.0; n], vec![0.0; n]);
+        let status = run_allocation_sensitivity(
+            IMPACTS.as_ptr(), [0.0; 3].as_ptr(), std::ptr::null(), std::ptr::null(), std::ptr::null(), n,
+            RatioUnit::Percent as u32, rows.as_mut_ptr(), computed.as_mut_ptr(), min.as_mut_ptr(), max.as_mut_ptr(),
+        );
+        assert_eq!(status, AllocationStatus::ZeroTotal);
+    }
+
+    #[test]
+    fn sensitivity_rejects_unknown_content_units() {
+        let contents = [0.8, 0.05, 0.1];
+        assert_eq!(sensitivity_in(None, Some(&contents), None, RatioUnit::Fraction as u32).status, AllocationStatus::Ok);
+        let result = sensitivity_in(None, Some(&contents), None, 2);
+        assert_eq!(result.status, AllocationStatus::UnknownUnit);
+        assert!(result.rows.iter().all(|&x| x == 0.0));
+    }
+
+    fn allocation_matrix(impacts: &[f64], n_categories: usize, values: &[f64]) -> Option<(Vec<f64>, Vec<f64>)> {
+        let mut factors = vec![f64::NAN; values.len()];
+        let mut allocated = vec![f64::NAN; impacts.len()];
+        calculate_allocation_matrix(impacts.as_ptr(), n_categories, values.as_ptr(), values.len(), factors.as_mut_ptr(), allocated.as_mut_ptr())
+            .then_some((factors, allocated))
+    }
+
+    #[test]
+    fn each_category_row_sums_to_its_total_impact() {
+        // GWP, water, energy, land use and eutrophication for isolate, starch and fibre
+        let impacts = [
+            1.0, 2.0, 3.0,
+            10.0, 0.0, 5.0,
+            40.0, 25.0, 12.0,
+            0.3, 0.9, 0.6,
+            1e-3, 2e-3, 5e-4,
+        ];
+        let (factors, allocated) = allocation_matrix(&impacts, 5, &[2.0, 1.0, 1.0]).unwrap();
+        assert_eq!(factors, [0.5, 0.25, 0.25]);
+        for (row, impact_row) in allocated.chunks_exact(3).zip(impacts.chunks_exact(3)) {
+            let total: f64 = impact_row.iter().sum();
+            assert!((row.iter().sum::<f64>() - total).abs() < 1e-12 * total.max(1.0));
+            assert!(row.iter().zip(&factors).all(|(share, factor)| (share - total * factor).abs() < 1e-12));
+        }
+        assert_eq!(allocated[3..6], [7.5, 3.75, 3.75]);
+    }
+
+    #[test]
+    fn allocation_matrix_validates_its_inputs() {
+        let impacts = [1.0, 2.0, 3.0, 10.0, 0.0, 5.0];
+        assert!(allocation_matrix(&impacts, 2, &[1.0, -1.0, 1.0]).is_none());
+        assert!(allocation_matrix(&impacts, 2, &[1.0, f64::NAN, 1.0]).is_none());
+        assert!(allocation_matrix(&impacts, 2, &[0.0; 3]).is_none());
+        assert!(allocation_matrix(&[1.0, f64::INFINITY, 3.0], 1, &[1.0; 3]).is_none());
+        assert!(allocation_matrix(&[], 0, &[1.0; 3]).is_none());
+        // A negative impact, e.g. a credit, is allowed
+        assert!(allocation_matrix(&[-1.0, 2.0, 3.0], 1, &[1.0; 3]).is_some());
+    }
+
+    // Isolate, starch-rich coarse fraction and fibre per kg of flour
+    const PRODUCT_IMPACTS: [f64; 3] = [12.3, 4.7, 0.91];
+    const PRODUCT_MASSES: [f64; 3] = [0.27, 0.61, 0.12];
+
+    fn by_property(properties: &[f64]) -> (AllocationStatus, [f64; 3]) {
+        let mut factors = [f64::NAN; 3];
+        let status = calculate_allocation_by_property(
+            PRODUCT_IMPACTS.as_ptr(), PRODUCT_MASSES.as_ptr(), properties.as_ptr(), 3, factors.as_mut_ptr(),
+        );
+        (status, factors)
+    }
+
+    fn by_value(values: &[f64]) -> [f64; 3] {
+        let mut factors = [f64::NAN; 3];
+        assert!(calculate_allocation(PRODUCT_IMPACTS.as_ptr(), values.as_ptr(), 3, factors.as_mut_ptr()));
+        factors
+    }
+
+    #[test]
+    fn unit_property_and_price_reduce_to_mass_and_economic_allocation() {
+        assert_eq!(by_property(&[1.0; 3]), (AllocationStatus::Ok, by_value(&PRODUCT_MASSES)));
+        let prices = [3.1, 0.45, 0.2];
+        let revenues: Vec<f64> = PRODUCT_MASSES.iter().zip(prices).map(|(m, p)| m * p).collect();
+        assert_eq!(by_property(&prices), (AllocationStatus::Ok, by_value(&revenues)));
+    }
+
+    #[test]
+    fn energy_content_shifts_burden_to_the_calorific_fraction() {
+        let (status, energy) = by_property(&[17.0, 15.5, 9.0]);
+        assert_eq!(status, AllocationStatus::Ok);
+        let mass = by_value(&PRODUCT_MASSES);
+        // The fibre fraction has the lowest calorific value, so carries less than by mass
+        assert!(energy[2] < mass[2]);
+        let keys: Vec<f64> = PRODUCT_MASSES.iter().zip([17.0, 15.5, 9.0]).map(|(m, p)| m * p).collect();
+        let total: f64 = keys.iter().sum();
+        assert!(energy.iter().zip(&keys).zip(PRODUCT_IMPACTS).all(|((e, k), i)| (e - k / total * i).abs() < 1e-12));
+    }
+
+    #[test]
+    fn property_allocation_reports_each_failure() {
+        assert_eq!(by_property(&[0.0; 3]).0, AllocationStatus::ZeroTotal);
+        assert_eq!(by_property(&[-1.0, 1.0, 1.0]).0, AllocationStatus::NegativeValue);
+        assert_eq!(by_property(&[f64::NAN, 1.0, 1.0]).0, AllocationStatus::NonFiniteValue);
+        let (status, factors) = by_property(&[0.0; 3]);
+        assert_eq!(status, AllocationStatus::ZeroTotal);
+        assert!(factors.iter().all(|f| f.is_nan()));
+        let mut out = [0.0; 3];
+        assert_eq!(
+            calculate_allocation_by_property(PRODUCT_IMPACTS.as_ptr(), PRODUCT_MASSES.as_ptr(), [1.0; 3].as_ptr(), 0, out.as_mut_ptr()),
+            AllocationStatus::Empty
+        );
+        assert_eq!(
+            calculate_allocation_by_property(PRODUCT_IMPACTS.as_ptr(), std::ptr::null(), [1.0; 3].as_ptr(), 3, out.as_mut_ptr()),
+            AllocationStatus::NullPointer
+        );
+    }
+
+    fn protein_allocation(contents: &[f64], content_unit: RatioUnit) -> (AllocationStatus, [f64; 3]) {
+        let mut factors = [f64::NAN; 3];
+        let status = calculate_protein_allocation(
+            [10.0; 3].as_ptr(), [30.0, 60.0, 500.0].as_ptr(), contents.as_ptr(), 3, content_unit as u32, factors.as_mut_ptr(),
+        );
+        (status, factors)
+    }
+
+    #[test]
+    fn stream_without_protein_carries_no_burden() {
+        // The heaviest stream has no protein
+        let (status, factors) = protein_allocation(&[55.0, 12.0, 0.0], RatioUnit::Percent);
+        assert_eq!(status, AllocationStatus::Ok);
+        assert_eq!(factors[2], 0.0);
+        // Protein masses 16.5 and 7.2
+        assert!((factors[0] - 10.0 * 16.5 / 23.7).abs() < 1e-12 && (factors[1] - 10.0 * 7.2 / 23.7).abs() < 1e-12);
+    }
+
+    #[test]
+    fn fraction_and_percent_contents_agree() {
+        let (_, percent) = protein_allocation(&[55.0, 12.0, 3.0], RatioUnit::Percent);
+        let (status, fraction) = protein_allocation(&[0.55, 0.12, 0.03], RatioUnit::Fraction);
+        assert_eq!(status, AllocationStatus::Ok);
+        assert!(percent.iter().zip(fraction).all(|(p, f)| (p - f).abs() < 1e-12));
+    }
+
+    #[test]
+    fn protein_allocation_rejects_bad_contents() {
+        assert_eq!(protein_allocation(&[55.0, 12.0, 0.0], RatioUnit::Fraction).0, AllocationStatus::ContentOutOfRange);
+        assert_eq!(protein_allocation(&[0.5, -0.1, 0.0], RatioUnit::Fraction).0, AllocationStatus::ContentOutOfRange);
+        assert_eq!(protein_allocation(&[0.5, f64::NAN, 0.0], RatioUnit::Fraction).0, AllocationStatus::ContentOutOfRange);
+        let (status, factors) = protein_allocation(&[0.0; 3], RatioUnit::Fraction);
+        assert_eq!(status, AllocationStatus::ZeroTotal);
+        assert!(factors.iter().all(|f| f.is_nan()));
+    }
+
+    #[test]
+    fn protein_allocation_rejects_unknown_units() {
+        let mut factors = [f64::NAN; 3];
+        let status = calculate_protein_allocation(
+            [10.0; 3].as_ptr(), [30.0, 60.0, 500.0].as_ptr(), [0.5; 3].as_ptr(), 3, 2, factors.as_mut_ptr(),
+        );
+        assert_eq!(status, AllocationStatus::UnknownUnit);
+        assert!(factors.iter().all(|f| f.is_nan()));
+    }
+
+    const MASS: [f64; 3] = [0.2, 0.3, 0.5];
+    const ECONOMIC: [f64; 3] = [0.5, 0.25, 0.25];
+
+    fn checked(impacts: &[f64], values: &[f64]) -> (AllocationStatus, [f64; 3], usize) {
+        let (mut allocated, mut index) = ([f64::NAN; 3], 0);
+        let status = calculate_allocation_checked(impacts.as_ptr(), values.as_ptr(), values.len(), allocated.as_mut_ptr(), &mut index);
+        (status, allocated, index)
+    }
+
+    fn hybrid(mass: &[f64], economic: &[f64], sum_check: FactorSumCheck) -> (AllocationStatus, [f64; 3], usize, bool) {
+        let (mut results, mut index, mut mismatch) = ([f64::NAN; 3], 0, false);
+        let status = calculate_hybrid_allocation_checked(
+            mass.as_ptr(), economic.as_ptr(), mass.len(), 0.4, sum_check as u32, results.as_mut_ptr(), &mut index, &mut mismatch,
+        );
+        (status, results, index, mismatch)
+    }
+
+    #[test]
+    fn valid_inputs_give_the_legacy_results() {
+        let (impacts, values) = ([10.0, 20.0, 30.0], [1.0, 2.0, 3.0]);
+        let mut allocated = [f64::NAN; 3];
+        assert!(calculate_allocation(impacts.as_ptr(), values.as_ptr(), 3, allocated.as_mut_ptr()));
+        let legacy: Vec<f64> = values.iter().zip(impacts).map(|(v, i)| v / 6.0 * i).collect();
+        assert_eq!(allocated.to_vec(), legacy);
+        assert_eq!(checked(&impacts, &values), (AllocationStatus::Ok, allocated, usize::MAX));
+
+        let mut blended = [f64::NAN; 3];
+        assert!(calculate_hybrid_allocation(MASS.as_ptr(), ECONOMIC.as_ptr(), 3, 0.4, blended.as_mut_ptr()));
+        let legacy: Vec<f64> = MASS.iter().zip(ECONOMIC).map(|(m, e)| 0.4 * m + (1.0 - 0.4) * e).collect();
+        assert_eq!(blended.to_vec(), legacy);
+        assert_eq!(hybrid(&MASS, &ECONOMIC, FactorSumCheck::Reject), (AllocationStatus::Ok, blended, usize::MAX, false));
+    }
+
+    #[test]
+    fn allocation_reports_each_rejection() {
+        let status_and_index = |impacts: &[f64], values: &[f64]| {
+            let (status, _, index) = checked(impacts, values);
+            (status, index)
+        };
+        assert_eq!(status_and_index(&[10.0, 20.0, 30.0], &[1.0, -2.0, 3.0]), (AllocationStatus::NegativeValue, 1));
+        assert_eq!(status_and_index(&[10.0, f64::NAN, 30.0], &[1.0, 2.0, 3.0]), (AllocationStatus::NonFiniteValue, 1));
+        assert_eq!(status_and_index(&[10.0, 20.0, 30.0], &[1.0, 2.0, f64::INFINITY]), (AllocationStatus::NonFiniteValue, 2));
+        let (status, allocated, index) = checked(&[10.0, 20.0, 30.0], &[0.0; 3]);
+        assert_eq!((status, index), (AllocationStatus::ZeroTotal, usize::MAX));
+        assert!(allocated.iter().all(|x| x.is_nan()));
+
+        let mut allocated = [0.0; 3];
+        let mut index = 0;
+        assert_eq!(
+            calculate_allocation_checked(std::ptr::null(), [1.0; 3].as_ptr(), 3, allocated.as_mut_ptr(), &mut index),
+            AllocationStatus::NullPointer,
+        );
+        assert_eq!(
+            calculate_allocation_checked([1.0].as_ptr(), [1.0].as_ptr(), 0, allocated.as_mut_ptr(), &mut index),
+            AllocationStatus::Empty,
+        );
+        assert!(!calculate_allocation(std::ptr::null(), [1.0; 3].as_ptr(), 3, allocated.as_mut_ptr()));
+    }
+
+    #[test]
+    fn hybrid_factor_sums_warn_or_reject() {
+        let unnormalized = [1.0, 1.0, 2.0];
+        let (status, _, _, mismatch) = hybrid(&MASS, &unnormalized, FactorSumCheck::Reject);
+        assert_eq!((status, mismatch), (AllocationStatus::FactorSumMismatch, true));
+        let (status, results, _, mismatch) = hybrid(&MASS, &unnormalized, FactorSumCheck::Warn);
+        assert_eq!((status, mismatch), (AllocationStatus::Ok, true));
+        assert!((results.iter().sum::<f64>() - 1.0).abs() < 1e-12, "{results:?}");
+    }
+
+    #[test]
+    fn unknown_sum_check_is_rejected() {
+        let (mut results, mut index, mut mismatch) = ([f64::NAN; 3], 0, false);
+        let status = calculate_hybrid_allocation_checked(
+            MASS.as_ptr(), ECONOMIC.as_ptr(), 3, 0.4, 2, results.as_mut_ptr(), &mut index, &mut mismatch,
+        );
+        assert_eq!(status, AllocationStatus::UnknownSumCheck);
+        assert!(results.iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn hybrid_reports_invalid_factors() {
+        let (status, _, index, _) = hybrid(&MASS, &[0.5, -0.25, 0.75], FactorSumCheck::Warn);
+        assert_eq!((status, index), (AllocationStatus::NegativeValue, 1));
+        let (status, _, index, _) = hybrid(&[0.2, 0.3, f64::NAN], &ECONOMIC, FactorSumCheck::Warn);
+        assert_eq!((status, index), (AllocationStatus::NonFiniteValue, 2));
+        let mut results = [0.0; 3];
+        assert!(!calculate_hybrid_allocation(MASS.as_ptr(), std::ptr::null(), 3, 0.4, results.as_mut_ptr()));
+        assert!(!calculate_hybrid_allocation(MASS.as_ptr(), ECONOMIC.as_ptr(), 0, 0.4, results.as_mut_ptr()));
+    }
+
+    #[test]
+    fn null_pointers_fail_instead_of_crashing() {
+        let (impacts, values, mut results) = ([10.0, 20.0, 30.0], [1.0, 2.0, 3.0], [f64::NAN; 3]);
+        let (null, null_mut) = (std::ptr::null(), std::ptr::null_mut());
+        let mut index = 0;
+        assert!(!calculate_allocation(impacts.as_ptr(), null, 3, results.as_mut_ptr()));
+        assert!(!calculate_allocation(impacts.as_ptr(), values.as_ptr(), 3, null_mut));
+        assert_eq!(
+            calculate_allocation_checked(impacts.as_ptr(), values.as_ptr(), 3, results.as_mut_ptr(), std::ptr::null_mut()),
+            AllocationStatus::NullPointer,
+        );
+        assert!(!calculate_hybrid_allocation(null, ECONOMIC.as_ptr(), 3, 0.4, results.as_mut_ptr()));
+        assert!(!calculate_hybrid_allocation(MASS.as_ptr(), ECONOMIC.as_ptr(), 3, 0.4, null_mut));
+        let mut mismatch = false;
+        assert_eq!(
+            calculate_hybrid_allocation_checked(MASS.as_ptr(), ECONOMIC.as_ptr(), 3, 0.4, FactorSumCheck::Warn as u32, results.as_mut_ptr(), &mut index, std::ptr::null_mut()),
+            AllocationStatus::NullPointer,
+        );
+        assert_eq!(
+            calculate_hybrid_allocation_checked(MASS.as_ptr(), ECONOMIC.as_ptr(), 3, 0.4, FactorSumCheck::Warn as u32, results.as_mut_ptr(), std::ptr::null_mut(), &mut mismatch),
+            AllocationStatus::NullPointer,
+        );
+        assert!(results.iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn nan_hybrid_weight_is_rejected() {
+        let mut results = [f64::NAN; 3];
+        assert!(!calculate_hybrid_allocation(MASS.as_ptr(), ECONOMIC.as_ptr(), 3, f64::NAN, results.as_mut_ptr()));
+        let (mut index, mut mismatch) = (0, false);
+        let status = calculate_hybrid_allocation_checked(
+            MASS.as_ptr(), ECONOMIC.as_ptr(), 3, f64::NAN, FactorSumCheck::Warn as u32, results.as_mut_ptr(), &mut index, &mut mismatch,
+        );
+        assert_eq!((status, index), (AllocationStatus::NonFiniteValue, 0));
+    }
+}
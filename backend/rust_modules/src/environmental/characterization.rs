@@ -0,0 +1,270 @@
+use std::collections::HashMap;
+use std::ffi::c_double;
+
+/// Outcome of `load_characterization_factors`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FactorTableStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// The buffer is not valid UTF-8
+    InvalidUtf8 = 2,
+    /// A row does not have four fields, an id is not an unsigned integer,
+    /// a factor is not a finite number or a unit is empty
+    MalformedRow = 3,
+    /// A (flow, category) pair appears on more than one row
+    DuplicateRow = 4,
+    /// A category is given in a different unit than on its first row
+    UnitMismatch = 5,
+    /// The buffer holds no factor rows
+    Empty = 6,
+}
+
+/// Characterization factors keyed by (flow id, category id), built by
+/// `load_characterization_factors` and used through the opaque handle it
+/// returns.
+#[derive(Debug, Default)]
+pub struct CharacterizationTable {
+    factors: HashMap<(u32, u32), f64>,
+}
+
+/// One parsed CSV row: flow id, category id, factor and unit.
+fn parse_row(line: &str) -> Option<(u32, u32, f64, &str)> {
+    let fields: Vec<&str> = line.split(',').map(str::trim).collect();
+    if fields.len() != 4 || fields[3].is_empty() {
+        return None;
+    }
+    let factor: f64 = fields[2].parse().ok().filter(|f: &f64| f.is_finite())?;
+    Some((fields[0].parse().ok()?, fields[1].parse().ok()?, factor, fields[3]))
+}
+
+/// Whether `line` is a header: none of its id and factor fields parse as
+/// numbers. A data row with a typo in one field is malformed, not a header.
+fn is_header(line: &str) -> bool {
+    line.split(',').take(3).all(|field| field.trim().parse::<f64>().is_err())
+}
+
+impl CharacterizationTable {
+    /// Parse CSV rows of `flow_id,category_id,factor,unit`. Blank lines are
+    /// skipped and the first non-blank line may be a header. Err carries
+    /// the status and the 1-based line number of the offending row.
+    fn parse(text: &str) -> Result<Self, (FactorTableStatus, usize)> {
+        let mut table = Self::default();
+        let mut units: HashMap<u32, &str> = HashMap::new();
+        let mut seen_row = false;
+        for (index, line) in text.lines().enumerate() {
+            let line_number = index + 1;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let first_row = !seen_row;
+            seen_row = true;
+            if first_row && is_header(line) {
+                continue;
+            }
+            let (flow, category, factor, unit) = parse_row(line).ok_or((FactorTableStatus::MalformedRow, line_number))?;
+            if *units.entry(category).or_insert(unit) != unit {
+                return Err((FactorTableStatus::UnitMismatch, line_number));
+            }
+            if table.factors.insert((flow, category), factor).is_some() {
+                return Err((FactorTableStatus::DuplicateRow, line_number));
+            }
+        }
+        if table.factors.is_empty() {
+            return Err((FactorTableStatus::Empty, 0));
+        }
+        Ok(table)
+    }
+
+    fn has_flow(&self, flow: u32) -> bool {
+        self.factors.keys().any(|&(f, _)| f == flow)
+    }
+}
+
+/// Parse characterization factors from an in-memory CSV into a table.
+///
+/// `csv_bytes` holds `len` bytes of UTF-8 rows `flow_id,category_id,factor,unit`,
+/// with unsigned integer ids (the caller keeps the mapping from flow and
+/// category names), an optional header line and blank lines ignored. Every
+/// row of a category must share its unit. On success `handle_out` receives
+/// a table to pass to `lookup_factor` and `apply_factors` and to release
+/// with `free_characterization_factors`. Otherwise `error_line_out`
+/// receives the 1-based line of the offending row (0 when no single line
+/// is at fault) and `handle_out` is left untouched.
+#[no_mangle]
+pub extern "C" fn load_characterization_factors(
+    csv_bytes: *const u8,
+    len: usize,
+    handle_out: *mut *mut CharacterizationTable,
+    error_line_out: *mut usize
+) -> FactorTableStatus {
+    if csv_bytes.is_null() || handle_out.is_null() || error_line_out.is_null() {
+        return FactorTableStatus::NullPointer;
+    }
+    unsafe {
+        *error_line_out = 0;
+    }
+    let bytes = unsafe { std::slice::from_raw_parts(csv_bytes, len) };
+    let text = match std::str::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(_) => return FactorTableStatus::InvalidUtf8,
+    };
+    match CharacterizationTable::parse(text) {
+        Ok(table) => {
+            unsafe {
+                *handle_out = Box::into_raw(Box::new(table));
+            }
+            FactorTableStatus::Ok
+        }
+        Err((status, line)) => {
+            unsafe {
+                *error_line_out = line;
+            }
+            status
+        }
+    }
+}
+
+/// Release a table from `load_characterization_factors`. Null is ignored;
+/// the handle must not be used afterwards.
+#[no_mangle]
+pub extern "C" fn free_characterization_factors(handle: *mut CharacterizationTable) {
+    if !handle.is_null() {
+        drop(unsafe { Box::from_raw(handle) });
+    }
+}
+
+/// Factor of `flow_id` in `category_id`, written to `out`. Returns false
+/// on null pointers or when the table has no such row.
+#[no_mangle]
+pub extern "C" fn lookup_factor(
+    handle: *const CharacterizationTable,
+    flow_id: u32,
+    category_id: u32,
+    out: *mut c_double
+) -> bool {
+    if handle.is_null() || out.is_null() {
+        return false;
+    }
+    let table = unsafe { &*handle };
+    match table.factors.get(&(flow_id, category_id)) {
+        Some(&factor) => {
+            unsafe {
+                *out = factor;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Characterize an inventory with a loaded table.
+///
+/// `inventory_ids` holds the flow id of each of the `len` `amounts`.
+/// `results[c]` receives Σ amount × factor(flow, c) for categories
+/// 0..`n_categories`, a flow without a row for a category contributing
+/// nothing to it. `invalid_index_out` receives the first inventory entry
+/// whose flow is absent from the table or whose amount is not finite, and
+/// `usize::MAX` when all are valid. Returns false on null pointers, an
+/// empty dimension or an invalid entry.
+#[no_mangle]
+pub extern "C" fn apply_factors(
+    handle: *const CharacterizationTable,
+    inventory_ids: *const u32,
+    amounts: *const c_double,
+    len: usize,
+    n_categories: usize,
+    results: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if handle.is_null() || inventory_ids.is_null() || amounts.is_null() || results.is_null()
+        || invalid_index_out.is_null() || len == 0 || n_categories == 0 {
+        return false;
+    }
+    let table = unsafe { &*handle };
+    let (ids, amounts) = unsafe {
+        (std::slice::from_raw_parts(inventory_ids, len), std::slice::from_raw_parts(amounts, len))
+    };
+    let invalid = (0..len).find(|&i| !amounts[i].is_finite() || !table.has_flow(ids[i]));
+    unsafe {
+        *invalid_index_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
+
+    let mut totals = vec![0.0; n_categories];
+    for (&flow, &amount) in ids.iter().zip(amounts) {
+        for (category, total) in totals.iter_mut().enumerate() {
+            if let Some(factor) = u32::try_from(category).ok().and_then(|c| table.factors.get(&(flow, c))) {
+                *total += amount * factor;
+            }
+        }
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(results, n_categories).copy_from_slice(&totals);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn load(csv: &str) -> Result<*mut CharacterizationTable, (FactorTableStatus, usize)> {
+        let mut handle = std::ptr::null_mut();
+        let mut error_line = usize::MAX;
+        match load_characterization_factors(csv.as_ptr(), csv.len(), &mut handle, &mut error_line) {
+            FactorTableStatus::Ok => Ok(handle),
+            status => Err((status, error_line)),
+        }
+    }
+
+    #[test]
+    fn loads_rows_after_a_header_and_blank_lines() {
+        let handle = load("flow_id,category_id,factor,unit\n\n1,0,2.5,kg CO2-eq\n2,0,-1.0,kg CO2-eq\n1,1,0.5,kg PO4-eq\n").unwrap();
+        let mut factor = 0.0;
+        assert!(lookup_factor(handle, 1, 1, &mut factor));
+        assert_eq!(factor, 0.5);
+        assert!(!lookup_factor(handle, 2, 1, &mut factor));
+
+        let (ids, amounts) = ([1, 2, 1], [2.0, 1.0, 4.0]);
+        let mut results = [0.0; 3];
+        let mut invalid = 0;
+        assert!(apply_factors(handle, ids.as_ptr(), amounts.as_ptr(), 3, 3, results.as_mut_ptr(), &mut invalid));
+        assert_eq!(results, [14.0, 3.0, 0.0]);
+        assert_eq!(invalid, usize::MAX);
+
+        let ids = [1, 9];
+        assert!(!apply_factors(handle, ids.as_ptr(), amounts.as_ptr(), 2, 1, results.as_mut_ptr(), &mut invalid));
+        assert_eq!(invalid, 1);
+        free_characterization_factors(handle);
+    }
+
+    #[test]
+    fn malformed_first_row_is_not_taken_for_a_header() {
+        assert_eq!(load("1O,2,3.0,kg\n1,2,4.0,kg\n").unwrap_err(), (FactorTableStatus::MalformedRow, 1));
+    }
+
+    #[test]
+    fn reports_the_offending_line() {
+        assert_eq!(load("1,0,1.0,kg\n2,0,abc,kg\n").unwrap_err(), (FactorTableStatus::MalformedRow, 2));
+        assert_eq!(load("1,0,1.0,kg\n1,0,inf,kg\n").unwrap_err(), (FactorTableStatus::MalformedRow, 2));
+        assert_eq!(load("1,0,1.0\n").unwrap_err(), (FactorTableStatus::MalformedRow, 1));
+        assert_eq!(load("1,0,1.0,kg\n\n1,0,2.0,kg\n").unwrap_err(), (FactorTableStatus::DuplicateRow, 3));
+        assert_eq!(load("1,0,1.0,kg\n2,0,2.0,g\n").unwrap_err(), (FactorTableStatus::UnitMismatch, 2));
+        assert_eq!(load("flow,category,factor,unit\n\n").unwrap_err(), (FactorTableStatus::Empty, 0));
+    }
+
+    #[test]
+    fn rejects_null_pointers_and_invalid_utf8() {
+        let mut handle = std::ptr::null_mut();
+        let mut error_line = 0;
+        let bytes = [0x31, 0xff];
+        assert_eq!(load_characterization_factors(bytes.as_ptr(), 2, &mut handle, &mut error_line), FactorTableStatus::InvalidUtf8);
+        assert_eq!(load_characterization_factors(std::ptr::null(), 0, &mut handle, &mut error_line), FactorTableStatus::NullPointer);
+        assert!(handle.is_null());
+        free_characterization_factors(std::ptr::null_mut());
+    }
+}
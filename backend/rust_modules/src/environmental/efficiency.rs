@@ -12,6 +12,9 @@ pub extern "C" fn calculate_efficiency(
     economic_value / environmental_impact
 }
 
+/// Eco-efficiency of each alternative, value / impact, or 0 where the
+/// impact is not positive. Returns false for any input that
+/// `calculate_eco_efficiency_matrix_checked` rejects.
 #[no_mangle]
 pub extern "C" fn calculate_eco_efficiency_matrix(
     economic_values: *const c_double,
@@ -19,17 +22,308 @@ pub extern "C" fn calculate_eco_efficiency_matrix(
     len: usize,
     results: *mut c_double
 ) -> bool {
+    let mut invalid_index = usize::MAX;
+    calculate_eco_efficiency_matrix_checked(economic_values, environmental_impacts, len, results, &mut invalid_index)
+}
+
+/// `calculate_eco_efficiency_matrix` with NaN screening: `invalid_index_out`
+/// receives the first alternative whose value or impact is not finite, and
+/// `usize::MAX` when all are. Impacts that are not positive still give 0.
+/// Returns false on null pointers, `len` of 0 or a non-finite input; only
+/// `invalid_index_out` is written then.
+#[no_mangle]
+pub extern "C" fn calculate_eco_efficiency_matrix_checked(
+    economic_values: *const c_double,
+    environmental_impacts: *const c_double,
+    len: usize,
+    results: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if economic_values.is_null() || environmental_impacts.is_null() || results.is_null()
+        || invalid_index_out.is_null() || len == 0 {
+        return false;
+    }
     let values_slice = unsafe { std::slice::from_raw_parts(economic_values, len) };
     let impacts_slice = unsafe { std::slice::from_raw_parts(environmental_impacts, len) };
+    let invalid = (0..len).find(|&i| !values_slice[i].is_finite() || !impacts_slice[i].is_finite());
+    unsafe {
+        *invalid_index_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
     let results_slice = unsafe { std::slice::from_raw_parts_mut(results, len) };
-    
     for i in 0..len {
-        if impacts_slice[i] <= 0.0 {
-            results_slice[i] = 0.0;
-        } else {
-            results_slice[i] = values_slice[i] / impacts_slice[i];
+        results_slice[i] = calculate_efficiency(values_slice[i], impacts_slice[i]);
+    }
+    true
+}
+
+/// Eco-efficiency of an alternative relative to a baseline process, e.g.
+/// the conventional wet process, per impact category.
+///
+/// `results[i]` receives (values[i] / impacts[i]) divided by
+/// (baseline_values[i] / baseline_impacts[i]), so 1 means no change and 2
+/// twice the value per unit of impact. `improvement_out[i]` receives the
+/// same comparison as a percentage, (results[i] - 1) * 100, positive when
+/// the alternative is more eco-efficient. `invalid_index_out` receives the
+/// first category whose baseline impact or value is not positive, whose
+/// alternative impact is not positive, or with a non-finite input, and
+/// `usize::MAX` when all are valid. Unlike `calculate_eco_efficiency_matrix`
+/// such categories are errors rather than 0. Returns false on null
+/// pointers, `len` of 0 or an invalid category; only `invalid_index_out`
+/// is written then.
+#[no_mangle]
+pub extern "C" fn calculate_relative_eco_efficiency(
+    values: *const c_double,
+    impacts: *const c_double,
+    baseline_values: *const c_double,
+    baseline_impacts: *const c_double,
+    len: usize,
+    results: *mut c_double,
+    improvement_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if values.is_null() || impacts.is_null() || baseline_values.is_null() || baseline_impacts.is_null()
+        || results.is_null() || improvement_out.is_null() || invalid_index_out.is_null() || len == 0 {
+        return false;
+    }
+    let (values, impacts, baseline_values, baseline_impacts) = unsafe {
+        (
+            std::slice::from_raw_parts(values, len),
+            std::slice::from_raw_parts(impacts, len),
+            std::slice::from_raw_parts(baseline_values, len),
+            std::slice::from_raw_parts(baseline_impacts, len),
+        )
+    };
+    let invalid = (0..len).find(|&i| {
+        let inputs = [values[i], impacts[i], baseline_values[i], baseline_impacts[i]];
+        inputs.iter().any(|v| !v.is_finite()) || impacts[i] <= 0.0 || baseline_values[i] <= 0.0
+            || baseline_impacts[i] <= 0.0
+    });
+    unsafe {
+        *invalid_index_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
+
+    let relative: Vec<f64> = (0..len)
+        .map(|i| (values[i] / impacts[i]) / (baseline_values[i] / baseline_impacts[i]))
+        .collect();
+    unsafe {
+        std::slice::from_raw_parts_mut(results, len).copy_from_slice(&relative);
+        for (improvement, r) in std::slice::from_raw_parts_mut(improvement_out, len).iter_mut().zip(&relative) {
+            *improvement = (r - 1.0) * 100.0;
+        }
+    }
+    true
+}
+
+/// Blocks written by `calculate_impact_intensities`: per kg protein, per
+/// kg product and per protein serving.
+pub const IMPACT_INTENSITY_BLOCKS: usize = 3;
+
+/// Protein in one serving, in kg.
+const SERVING_PROTEIN_KG: f64 = 0.1;
+
+/// Impact intensities of a process's total impacts.
+///
+/// `results` receives `IMPACT_INTENSITY_BLOCKS` blocks of `n_categories`
+/// values: impacts per kg of protein (`protein_output_kg`), per kg of
+/// product (`product_output_kg`) and per 100 g protein serving, one tenth
+/// of the per-kg-protein block. Returns false on null pointers, no
+/// categories, non-finite impacts, or outputs that are not positive and
+/// finite.
+#[no_mangle]
+pub extern "C" fn calculate_impact_intensities(
+    total_impacts: *const c_double,
+    n_categories: usize,
+    protein_output_kg: c_double,
+    product_output_kg: c_double,
+    results: *mut c_double
+) -> bool {
+    if total_impacts.is_null() || results.is_null() || n_categories == 0 {
+        return false;
+    }
+    let outputs = [protein_output_kg, product_output_kg];
+    if outputs.iter().any(|x| !x.is_finite() || *x <= 0.0) {
+        return false;
+    }
+    let impacts = unsafe { std::slice::from_raw_parts(total_impacts, n_categories) };
+    if impacts.iter().any(|x| !x.is_finite()) {
+        return false;
+    }
+    let divisors = [protein_output_kg, product_output_kg, protein_output_kg / SERVING_PROTEIN_KG];
+    let results = unsafe { std::slice::from_raw_parts_mut(results, IMPACT_INTENSITY_BLOCKS * n_categories) };
+    for (block, divisor) in results.chunks_exact_mut(n_categories).zip(divisors) {
+        for (intensity, impact) in block.iter_mut().zip(impacts) {
+            *intensity = impact / divisor;
         }
     }
-    
     true
-} 
\ No newline at end of file
+}
+
+/// Protein productivity, the inverse of the per-kg-protein intensity:
+/// `results[i]` receives kg of protein per unit of impact i, e.g. per kg
+/// CO2-eq or per m³ of water. `invalid_index_out` receives the first
+/// category whose impact is not positive and finite, and `usize::MAX` when
+/// all are. Returns false on null pointers, no categories, an invalid
+/// impact, or a `protein_output_kg` that is not positive and finite.
+#[no_mangle]
+pub extern "C" fn calculate_protein_productivity(
+    total_impacts: *const c_double,
+    n_categories: usize,
+    protein_output_kg: c_double,
+    results: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if total_impacts.is_null() || results.is_null() || invalid_index_out.is_null() || n_categories == 0 {
+        return false;
+    }
+    if !protein_output_kg.is_finite() || protein_output_kg <= 0.0 {
+        return false;
+    }
+    let impacts = unsafe { std::slice::from_raw_parts(total_impacts, n_categories) };
+    let invalid = impacts.iter().position(|x| !x.is_finite() || *x <= 0.0);
+    unsafe {
+        *invalid_index_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
+    let results = unsafe { std::slice::from_raw_parts_mut(results, n_categories) };
+    for (productivity, impact) in results.iter_mut().zip(impacts) {
+        *productivity = protein_output_kg / impact;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASELINE_VALUES: [f64; 2] = [5.0, 7.0];
+    const BASELINE_IMPACTS: [f64; 2] = [2.0, 3.0];
+
+    fn relative(values: &[f64], impacts: &[f64], baseline_impacts: &[f64]) -> Result<([f64; 2], [f64; 2]), usize> {
+        let (mut results, mut improvement, mut invalid) = ([f64::NAN; 2], [f64::NAN; 2], 0);
+        if calculate_relative_eco_efficiency(
+            values.as_ptr(), impacts.as_ptr(), BASELINE_VALUES.as_ptr(), baseline_impacts.as_ptr(), 2,
+            results.as_mut_ptr(), improvement.as_mut_ptr(), &mut invalid,
+        ) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((results, improvement))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn baseline_relative_to_itself_is_one() {
+        assert_eq!(relative(&BASELINE_VALUES, &BASELINE_IMPACTS, &BASELINE_IMPACTS), Ok(([1.0; 2], [0.0; 2])));
+    }
+
+    #[test]
+    fn halving_the_impact_doubles_the_relative_efficiency() {
+        assert_eq!(relative(&BASELINE_VALUES, &[1.0, 3.0], &BASELINE_IMPACTS), Ok(([2.0, 1.0], [100.0, 0.0])));
+        // Doubling the impact is a 50% loss, negative by convention
+        assert_eq!(relative(&BASELINE_VALUES, &[4.0, 3.0], &BASELINE_IMPACTS), Ok(([0.5, 1.0], [-50.0, 0.0])));
+    }
+
+    #[test]
+    fn zero_baseline_impact_is_an_error() {
+        assert_eq!(relative(&BASELINE_VALUES, &[1.0, 3.0], &[2.0, 0.0]), Err(1));
+        assert_eq!(relative(&BASELINE_VALUES, &[0.0, 3.0], &BASELINE_IMPACTS), Err(0));
+        assert_eq!(relative(&[5.0, f64::NAN], &BASELINE_IMPACTS, &BASELINE_IMPACTS), Err(1));
+    }
+
+    // A batch emitting 120 kg CO2-eq and using 35 m³ of water to make 40 kg
+    // of protein in 50 kg of isolate
+    const TOTALS: [f64; 2] = [120.0, 35.0];
+
+    fn intensities(protein_output_kg: f64, product_output_kg: f64) -> Option<[f64; IMPACT_INTENSITY_BLOCKS * 2]> {
+        let mut results = [f64::NAN; IMPACT_INTENSITY_BLOCKS * 2];
+        calculate_impact_intensities(TOTALS.as_ptr(), 2, protein_output_kg, product_output_kg, results.as_mut_ptr())
+            .then_some(results)
+    }
+
+    fn productivity(totals: &[f64], protein_output_kg: f64) -> Result<[f64; 2], usize> {
+        let (mut results, mut invalid) = ([f64::NAN; 2], 0);
+        if calculate_protein_productivity(totals.as_ptr(), 2, protein_output_kg, results.as_mut_ptr(), &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok(results)
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn intensities_are_per_protein_product_and_serving() {
+        let results = intensities(40.0, 50.0).unwrap();
+        assert_eq!(results[..4], [3.0, 0.875, 2.4, 0.7]);
+        for (serving, per_kg) in results[4..].iter().zip(&results[..2]) {
+            assert!((serving - per_kg / 10.0).abs() < 1e-15, "{results:?}");
+        }
+    }
+
+    #[test]
+    fn productivity_is_the_reciprocal_of_the_protein_intensity() {
+        let per_kg = intensities(40.0, 50.0).unwrap();
+        let productivity = productivity(&TOTALS, 40.0).unwrap();
+        for (p, i) in productivity.iter().zip(&per_kg[..2]) {
+            assert!((p * i - 1.0).abs() <= f64::EPSILON, "{p} {i}");
+        }
+    }
+
+    #[test]
+    fn zero_outputs_and_impacts_are_rejected() {
+        for (protein, product) in [(0.0, 50.0), (40.0, 0.0), (-1.0, 50.0), (f64::NAN, 50.0)] {
+            assert!(intensities(protein, product).is_none());
+        }
+        assert_eq!(productivity(&[120.0, 0.0], 40.0), Err(1));
+        assert_eq!(productivity(&[f64::NAN, 35.0], 40.0), Err(0));
+        assert!(productivity(&TOTALS, 0.0).is_err());
+    }
+
+    fn efficiency_matrix(values: &[f64], impacts: &[f64]) -> Result<Vec<f64>, usize> {
+        let (mut results, mut invalid) = (vec![f64::NAN; values.len()], 0);
+        if calculate_eco_efficiency_matrix_checked(values.as_ptr(), impacts.as_ptr(), values.len(), results.as_mut_ptr(), &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok(results)
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn non_positive_impacts_give_zero_efficiency() {
+        let expected = vec![2.0, 0.0, 0.0];
+        assert_eq!(efficiency_matrix(&[6.0, 2.0, 1.0], &[3.0, 0.0, -1.0]), Ok(expected.clone()));
+        let mut results = [f64::NAN; 3];
+        assert!(calculate_eco_efficiency_matrix([6.0, 2.0, 1.0].as_ptr(), [3.0, 0.0, -1.0].as_ptr(), 3, results.as_mut_ptr()));
+        assert_eq!(results.to_vec(), expected);
+    }
+
+    #[test]
+    fn efficiency_matrix_rejects_null_pointers_and_empty_input() {
+        let (values, impacts, mut results, mut invalid) = ([1.0], [1.0], [f64::NAN], 0);
+        let (null, null_mut) = (std::ptr::null(), std::ptr::null_mut());
+        assert!(!calculate_eco_efficiency_matrix(null, impacts.as_ptr(), 1, results.as_mut_ptr()));
+        assert!(!calculate_eco_efficiency_matrix(values.as_ptr(), null, 1, results.as_mut_ptr()));
+        assert!(!calculate_eco_efficiency_matrix(values.as_ptr(), impacts.as_ptr(), 1, null_mut));
+        assert!(!calculate_eco_efficiency_matrix(values.as_ptr(), impacts.as_ptr(), 0, results.as_mut_ptr()));
+        assert!(!calculate_eco_efficiency_matrix_checked(values.as_ptr(), impacts.as_ptr(), 1, results.as_mut_ptr(), std::ptr::null_mut()));
+        assert!(!calculate_eco_efficiency_matrix_checked(values.as_ptr(), impacts.as_ptr(), 0, results.as_mut_ptr(), &mut invalid));
+        assert!(results[0].is_nan());
+    }
+
+    #[test]
+    fn efficiency_matrix_reports_the_first_non_finite_input() {
+        assert_eq!(efficiency_matrix(&[1.0, f64::NAN], &[1.0, 1.0]), Err(1));
+        assert_eq!(efficiency_matrix(&[1.0, 1.0, 1.0], &[1.0, 1.0, f64::INFINITY]), Err(2));
+        assert_eq!(efficiency_matrix(&[f64::NEG_INFINITY, 1.0], &[1.0, f64::NAN]), Err(0));
+        let mut results = [f64::NAN; 2];
+        assert!(!calculate_eco_efficiency_matrix([1.0, f64::NAN].as_ptr(), [1.0, 1.0].as_ptr(), 2, results.as_mut_ptr()));
+    }
+}
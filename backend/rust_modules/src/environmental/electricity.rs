@@ -0,0 +1,213 @@
+use std::ffi::c_double;
+
+/// How far a grid mix's source shares may sum from 1.
+pub const GRID_SHARE_TOLERANCE: f64 = 1e-6;
+
+/// Emission factor of a grid mix, Σ share × factor, or None when a share
+/// is outside [0, 1], the shares do not sum to 1 within
+/// `GRID_SHARE_TOLERANCE`, or a value is not finite.
+fn grid_factor(shares: &[f64], factors: &[f64]) -> Option<f64> {
+    if shares.iter().any(|s| !s.is_finite() || *s < 0.0 || *s > 1.0) || factors.iter().any(|f| !f.is_finite()) {
+        return None;
+    }
+    if (shares.iter().sum::<f64>() - 1.0).abs() > GRID_SHARE_TOLERANCE {
+        return None;
+    }
+    Some(shares.iter().zip(factors).map(|(s, f)| s * f).sum())
+}
+
+/// Emission factor of an electricity grid from its generation mix.
+///
+/// `source_shares` holds each of the `n_sources` generation sources' share
+/// of supply, summing to 1 within `GRID_SHARE_TOLERANCE`, and
+/// `source_factors` their emission factors, e.g. kg CO2-eq/kWh. `result`
+/// receives the share-weighted factor. Returns false on null pointers, no
+/// sources, or shares or factors rejected as described.
+#[no_mangle]
+pub extern "C" fn calculate_grid_emission_factor(
+    source_shares: *const c_double,
+    source_factors: *const c_double,
+    n_sources: usize,
+    result: *mut c_double
+) -> bool {
+    if source_shares.is_null() || source_factors.is_null() || result.is_null() || n_sources == 0 {
+        return false;
+    }
+    let (shares, factors) = unsafe {
+        (std::slice::from_raw_parts(source_shares, n_sources), std::slice::from_raw_parts(source_factors, n_sources))
+    };
+    match grid_factor(shares, factors) {
+        Some(factor) => {
+            unsafe {
+                *result = factor;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Electricity impacts of each process stage at one grid factor.
+///
+/// `results[i]` receives stage_kwh[i] × `grid_factor` and `total_out` the
+/// sum over the `n_stages` stages. Returns false on null pointers, no
+/// stages, negative or non-finite consumption, or a non-finite factor.
+#[no_mangle]
+pub extern "C" fn calculate_electricity_impacts(
+    stage_kwh: *const c_double,
+    n_stages: usize,
+    grid_factor: c_double,
+    results: *mut c_double,
+    total_out: *mut c_double
+) -> bool {
+    if stage_kwh.is_null() || results.is_null() || total_out.is_null() || n_stages == 0 || !grid_factor.is_finite() {
+        return false;
+    }
+    let consumption = unsafe { std::slice::from_raw_parts(stage_kwh, n_stages) };
+    if consumption.iter().any(|kwh| !kwh.is_finite() || *kwh < 0.0) {
+        return false;
+    }
+    unsafe {
+        let results = std::slice::from_raw_parts_mut(results, n_stages);
+        for (impact, kwh) in results.iter_mut().zip(consumption) {
+            *impact = kwh * grid_factor;
+        }
+        *total_out = results.iter().sum();
+    }
+    true
+}
+
+/// Electricity impacts of each stage under several grid mixes at once,
+/// e.g. hydro-dominated and fossil-dominated grids, for a side-by-side
+/// comparison.
+///
+/// `source_shares` holds `n_grids` rows of `n_sources` shares (row-major),
+/// all over the same `source_factors`. `grid_factors_out` receives each
+/// grid's factor as in `calculate_grid_emission_factor`, `impacts_out`
+/// `n_stages` rows of `n_grids` stage impacts and `totals_out` each grid's
+/// total over the stages. `invalid_grid_out` receives the first grid whose
+/// mix is rejected, and `usize::MAX` when none is. Returns false on null
+/// pointers, an empty dimension, invalid consumption or factors, or an
+/// invalid mix.
+#[no_mangle]
+pub extern "C" fn calculate_grid_scenario_impacts(
+    stage_kwh: *const c_double,
+    n_stages: usize,
+    source_shares: *const c_double,
+    source_factors: *const c_double,
+    n_sources: usize,
+    n_grids: usize,
+    grid_factors_out: *mut c_double,
+    impacts_out: *mut c_double,
+    totals_out: *mut c_double,
+    invalid_grid_out: *mut usize
+) -> bool {
+    if stage_kwh.is_null() || source_shares.is_null() || source_factors.is_null() || grid_factors_out.is_null()
+        || impacts_out.is_null() || totals_out.is_null() || invalid_grid_out.is_null()
+        || n_stages == 0 || n_sources == 0 || n_grids == 0 {
+        return false;
+    }
+    let (consumption, shares, factors) = unsafe {
+        (
+            std::slice::from_raw_parts(stage_kwh, n_stages),
+            std::slice::from_raw_parts(source_shares, n_grids * n_sources),
+            std::slice::from_raw_parts(source_factors, n_sources),
+        )
+    };
+    if consumption.iter().any(|kwh| !kwh.is_finite() || *kwh < 0.0) {
+        return false;
+    }
+    let grid_factors: Result<Vec<f64>, usize> = shares.chunks_exact(n_sources).enumerate()
+        .map(|(grid, mix)| grid_factor(mix, factors).ok_or(grid))
+        .collect();
+    unsafe {
+        *invalid_grid_out = grid_factors.as_ref().err().copied().unwrap_or(usize::MAX);
+    }
+    let grid_factors = match grid_factors {
+        Ok(grid_factors) => grid_factors,
+        Err(_) => return false,
+    };
+
+    unsafe {
+        let impacts = std::slice::from_raw_parts_mut(impacts_out, n_stages * n_grids);
+        for (row, kwh) in impacts.chunks_exact_mut(n_grids).zip(consumption) {
+            for (impact, factor) in row.iter_mut().zip(&grid_factors) {
+                *impact = kwh * factor;
+            }
+        }
+        for (grid, total) in std::slice::from_raw_parts_mut(totals_out, n_grids).iter_mut().enumerate() {
+            *total = impacts.iter().skip(grid).step_by(n_grids).sum();
+        }
+        std::slice::from_raw_parts_mut(grid_factors_out, n_grids).copy_from_slice(&grid_factors);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Hydro and natural gas, kg CO2-eq/kWh
+    const SOURCE_FACTORS: [f64; 2] = [0.002, 0.6];
+    const STAGE_KWH: [f64; 2] = [100.0, 50.0];
+
+    fn grid(shares: &[f64]) -> Option<f64> {
+        let mut factor = f64::NAN;
+        calculate_grid_emission_factor(shares.as_ptr(), SOURCE_FACTORS.as_ptr(), shares.len(), &mut factor).then_some(factor)
+    }
+
+    /// Grid factors, stage × grid impacts and grid totals.
+    type Scenarios = ([f64; 2], [f64; 4], [f64; 2]);
+
+    fn scenarios(shares: &[f64]) -> Result<Scenarios, usize> {
+        let (mut factors, mut impacts, mut totals, mut invalid) = ([f64::NAN; 2], [f64::NAN; 4], [f64::NAN; 2], 0);
+        if calculate_grid_scenario_impacts(
+            STAGE_KWH.as_ptr(), 2, shares.as_ptr(), SOURCE_FACTORS.as_ptr(), 2, 2,
+            factors.as_mut_ptr(), impacts.as_mut_ptr(), totals.as_mut_ptr(), &mut invalid,
+        ) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((factors, impacts, totals))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn single_source_grid_reproduces_its_factor() {
+        assert_eq!(grid(&[1.0, 0.0]), Some(0.002));
+        assert_eq!(grid(&[0.0, 1.0]), Some(0.6));
+        let mut factor = f64::NAN;
+        assert!(calculate_grid_emission_factor([0.0, 1.0, 0.0].as_ptr(), [0.002, 0.49, 0.9].as_ptr(), 3, &mut factor));
+        assert_eq!(factor, 0.49);
+    }
+
+    #[test]
+    fn shares_must_form_a_mix() {
+        assert!(grid(&[0.5, 0.6]).is_none());
+        assert!(grid(&[1.2, -0.2]).is_none());
+        assert!(grid(&[f64::NAN, 1.0]).is_none());
+        assert!((grid(&[0.2, 0.8]).unwrap() - 0.4804).abs() < 1e-12);
+    }
+
+    #[test]
+    fn stage_impacts_add_up_to_the_total() {
+        let (mut impacts, mut total) = ([f64::NAN; 2], f64::NAN);
+        assert!(calculate_electricity_impacts(STAGE_KWH.as_ptr(), 2, 0.5, impacts.as_mut_ptr(), &mut total));
+        assert_eq!((impacts, total), ([50.0, 25.0], 75.0));
+        assert!(!calculate_electricity_impacts([100.0, -1.0].as_ptr(), 2, 0.5, impacts.as_mut_ptr(), &mut total));
+        assert!(!calculate_electricity_impacts(STAGE_KWH.as_ptr(), 2, f64::NAN, impacts.as_mut_ptr(), &mut total));
+    }
+
+    #[test]
+    fn scenario_matrix_matches_one_grid_at_a_time() {
+        let (factors, impacts, totals) = scenarios(&[1.0, 0.0, 0.2, 0.8]).unwrap();
+        for (grid, &factor) in factors.iter().enumerate() {
+            let (mut stages, mut total) = ([f64::NAN; 2], f64::NAN);
+            assert!(calculate_electricity_impacts(STAGE_KWH.as_ptr(), 2, factor, stages.as_mut_ptr(), &mut total));
+            assert_eq!([impacts[grid], impacts[2 + grid]], stages);
+            assert_eq!(totals[grid], total);
+        }
+        assert_eq!(factors[0], 0.002);
+        assert_eq!(scenarios(&[1.0, 0.0, 0.2, 0.9]), Err(1));
+    }
+}
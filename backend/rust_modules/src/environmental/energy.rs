@@ -0,0 +1,138 @@
+use std::ffi::c_double;
+
+/// Final energy carriers of `calculate_cumulative_energy_demand`, the
+/// order of its factor arrays: electricity, heat, other.
+pub const ENERGY_CARRIER_COUNT: usize = 3;
+
+/// Whether a set of conversion factors is finite and non-negative.
+fn valid_factors(factors: &[f64]) -> bool {
+    factors.iter().all(|f| f.is_finite() && *f >= 0.0)
+}
+
+/// Primary energy of one stage given its final energy per carrier.
+fn stage_primary_energy(final_energy: [f64; ENERGY_CARRIER_COUNT], factors: &[f64]) -> f64 {
+    final_energy.iter().zip(factors).map(|(e, f)| e * f).sum()
+}
+
+/// Cumulative energy demand (CED) of a process chain in primary energy.
+///
+/// `stage_electricity`, `stage_heat` and `stage_other` hold the final
+/// energy of each of `n_stages` stages and `primary_energy_factors` the
+/// primary energy per unit of final energy for each carrier, in
+/// `ENERGY_CARRIER_COUNT` order (e.g. the grid factor for electricity,
+/// fuel factor / boiler efficiency for heat). `result` receives the total
+/// and `stage_breakdown_out` the `n_stages` stage totals.
+///
+/// `renewable_factors` is nullable; when given it holds the renewable
+/// share of each primary energy factor, and `renewable_split_out` receives
+/// the renewable and non-renewable parts of the total. Returns false on
+/// null pointers, no stages, negative or non-finite energies or factors,
+/// or a renewable factor larger than its primary energy factor.
+#[no_mangle]
+pub extern "C" fn calculate_cumulative_energy_demand(
+    stage_electricity: *const c_double,
+    stage_heat: *const c_double,
+    stage_other: *const c_double,
+    n_stages: usize,
+    primary_energy_factors: *const c_double,
+    renewable_factors: *const c_double,
+    result: *mut c_double,
+    stage_breakdown_out: *mut c_double,
+    renewable_split_out: *mut c_double
+) -> bool {
+    if stage_electricity.is_null() || stage_heat.is_null() || stage_other.is_null() || primary_energy_factors.is_null()
+        || result.is_null() || stage_breakdown_out.is_null() || n_stages == 0 {
+        return false;
+    }
+    if !renewable_factors.is_null() && renewable_split_out.is_null() {
+        return false;
+    }
+    let (electricity, heat, other, factors) = unsafe {
+        (
+            std::slice::from_raw_parts(stage_electricity, n_stages),
+            std::slice::from_raw_parts(stage_heat, n_stages),
+            std::slice::from_raw_parts(stage_other, n_stages),
+            std::slice::from_raw_parts(primary_energy_factors, ENERGY_CARRIER_COUNT),
+        )
+    };
+    let renewable = if renewable_factors.is_null() {
+        None
+    } else {
+        Some(unsafe { std::slice::from_raw_parts(renewable_factors, ENERGY_CARRIER_COUNT) })
+    };
+    if !valid_factors(factors) || !valid_factors(electricity) || !valid_factors(heat) || !valid_factors(other) {
+        return false;
+    }
+    if let Some(renewable) = renewable {
+        if !valid_factors(renewable) || renewable.iter().zip(factors).any(|(r, f)| r > f) {
+            return false;
+        }
+    }
+
+    let stages: Vec<[f64; ENERGY_CARRIER_COUNT]> = (0..n_stages).map(|i| [electricity[i], heat[i], other[i]]).collect();
+    let breakdown: Vec<f64> = stages.iter().map(|&stage| stage_primary_energy(stage, factors)).collect();
+    let total: f64 = breakdown.iter().sum();
+    unsafe {
+        *result = total;
+        std::slice::from_raw_parts_mut(stage_breakdown_out, n_stages).copy_from_slice(&breakdown);
+        if let Some(renewable) = renewable {
+            let renewable_total: f64 = stages.iter().map(|&stage| stage_primary_energy(stage, renewable)).sum();
+            std::slice::from_raw_parts_mut(renewable_split_out, 2)
+                .copy_from_slice(&[renewable_total, total - renewable_total]);
+        }
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct Demand {
+        total: f64,
+        breakdown: Vec<f64>,
+        split: [f64; 2],
+    }
+
+    fn demand(stages: &[[f64; ENERGY_CARRIER_COUNT]], factors: &[f64], renewable: Option<&[f64]>) -> Option<Demand> {
+        let column = |carrier: usize| stages.iter().map(|stage| stage[carrier]).collect::<Vec<f64>>();
+        let (electricity, heat, other) = (column(0), column(1), column(2));
+        let mut breakdown = vec![f64::NAN; stages.len()];
+        let (mut total, mut split) = (f64::NAN, [f64::NAN; 2]);
+        calculate_cumulative_energy_demand(
+            electricity.as_ptr(), heat.as_ptr(), other.as_ptr(), stages.len(), factors.as_ptr(),
+            renewable.map_or(std::ptr::null(), <[f64]>::as_ptr), &mut total, breakdown.as_mut_ptr(), split.as_mut_ptr(),
+        ).then_some(Demand { total, breakdown, split })
+    }
+
+    #[test]
+    fn unit_factors_reduce_to_summation() {
+        let result = demand(&[[2.0, 3.0, 4.0]], &[1.0; 3], None).unwrap();
+        assert_eq!(result.total, 9.0);
+        assert_eq!(result.breakdown, [9.0]);
+        assert!(result.split.iter().all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn stages_are_converted_and_split_by_renewable_share() {
+        // Grid factor 2.5, heat from a boiler at 80% efficiency, other at face value
+        let result = demand(&[[2.0, 3.0, 0.0], [1.0, 0.0, 4.0]], &[2.5, 1.25, 1.0], Some(&[1.0, 0.0, 0.5])).unwrap();
+        assert_eq!(result.breakdown, [8.75, 6.5]);
+        assert_eq!(result.total, 15.25);
+        assert_eq!(result.split, [5.0, 10.25]);
+    }
+
+    #[test]
+    fn invalid_energies_and_factors_are_rejected() {
+        // Renewable part larger than the primary energy factor
+        assert!(demand(&[[2.0, 3.0, 4.0]], &[1.0; 3], Some(&[2.0, 0.0, 0.0])).is_none());
+        assert!(demand(&[[2.0, -3.0, 4.0]], &[1.0; 3], None).is_none());
+        assert!(demand(&[[2.0, 3.0, 4.0]], &[1.0, f64::NAN, 1.0], None).is_none());
+        assert!(demand(&[], &[1.0; 3], None).is_none());
+        let (mut total, mut breakdown) = (f64::NAN, [f64::NAN]);
+        assert!(!calculate_cumulative_energy_demand(
+            [2.0].as_ptr(), [3.0].as_ptr(), [4.0].as_ptr(), 1, [1.0; 3].as_ptr(), [0.5; 3].as_ptr(), &mut total,
+            breakdown.as_mut_ptr(), std::ptr::null_mut(),
+        ));
+    }
+}
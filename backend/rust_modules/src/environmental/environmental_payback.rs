@@ -0,0 +1,132 @@
+use std::ffi::c_double;
+
+/// Years for annual savings to offset an upfront burden, or None when the
+/// inputs are not finite, the upfront impact is negative, or the upgrade
+/// does not reduce the annual impact.
+fn payback_years(upfront_impact: f64, annual_baseline: f64, annual_upgrade: f64) -> Option<f64> {
+    if !upfront_impact.is_finite() || !annual_baseline.is_finite() || !annual_upgrade.is_finite() || upfront_impact < 0.0 {
+        return None;
+    }
+    let annual_saving = annual_baseline - annual_upgrade;
+    if annual_saving <= 0.0 {
+        return None;
+    }
+    Some(upfront_impact / annual_saving)
+}
+
+/// Environmental payback time of a process upgrade such as RF
+/// pretreatment.
+///
+/// `upfront_impact` is the embodied impact of the upgrade, e.g. equipment
+/// manufacturing, and the annual impacts those of operating without and
+/// with it. `result` receives the fractional years until the cumulative
+/// savings equal the upfront impact, upfront / (baseline - upgrade).
+/// Returns false on a null pointer, non-finite inputs, a negative upfront
+/// impact, or an upgrade that does not reduce the annual impact, so it
+/// never pays back.
+#[no_mangle]
+pub extern "C" fn calculate_environmental_payback(
+    upfront_impact: c_double,
+    annual_impact_baseline: c_double,
+    annual_impact_upgrade: c_double,
+    result: *mut c_double
+) -> bool {
+    if result.is_null() {
+        return false;
+    }
+    match payback_years(upfront_impact, annual_impact_baseline, annual_impact_upgrade) {
+        Some(years) => {
+            unsafe {
+                *result = years;
+            }
+            true
+        }
+        None => false,
+    }
+}
+
+/// `calculate_environmental_payback` for each of `n_categories` impact
+/// categories, writing one payback time per category to `results`.
+/// `invalid_index_out` receives the first category that would be rejected,
+/// typically one the upgrade does not improve, and `usize::MAX` when none
+/// is. Returns false on null pointers, no categories or a rejected
+/// category; only `invalid_index_out` is written then.
+#[no_mangle]
+pub extern "C" fn calculate_environmental_payback_batch(
+    upfront_impacts: *const c_double,
+    annual_impacts_baseline: *const c_double,
+    annual_impacts_upgrade: *const c_double,
+    n_categories: usize,
+    results: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if upfront_impacts.is_null() || annual_impacts_baseline.is_null() || annual_impacts_upgrade.is_null()
+        || results.is_null() || invalid_index_out.is_null() || n_categories == 0 {
+        return false;
+    }
+    let (upfront, baseline, upgrade) = unsafe {
+        (
+            std::slice::from_raw_parts(upfront_impacts, n_categories),
+            std::slice::from_raw_parts(annual_impacts_baseline, n_categories),
+            std::slice::from_raw_parts(annual_impacts_upgrade, n_categories),
+        )
+    };
+    let paybacks: Result<Vec<f64>, usize> = (0..n_categories)
+        .map(|i| payback_years(upfront[i], baseline[i], upgrade[i]).ok_or(i))
+        .collect();
+    unsafe {
+        *invalid_index_out = paybacks.as_ref().err().copied().unwrap_or(usize::MAX);
+    }
+    let paybacks = match paybacks {
+        Ok(paybacks) => paybacks,
+        Err(_) => return false,
+    };
+    unsafe {
+        std::slice::from_raw_parts_mut(results, n_categories).copy_from_slice(&paybacks);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn payback(upfront: f64, baseline: f64, upgrade: f64) -> Option<f64> {
+        let mut years = f64::NAN;
+        calculate_environmental_payback(upfront, baseline, upgrade, &mut years).then_some(years)
+    }
+
+    fn batch(upgrade: &[f64]) -> Result<[f64; 2], usize> {
+        let (mut years, mut invalid) = ([f64::NAN; 2], 0);
+        if calculate_environmental_payback_batch(
+            [10.0, 6.0].as_ptr(), [12.5, 3.0].as_ptr(), upgrade.as_ptr(), 2, years.as_mut_ptr(), &mut invalid,
+        ) {
+            assert_eq!(invalid, usize::MAX);
+            Ok(years)
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn upfront_ten_saving_two_and_a_half_pays_back_in_four_years() {
+        assert_eq!(payback(10.0, 12.5, 10.0), Some(4.0));
+        assert_eq!(payback(10.0, 14.0, 10.0), Some(2.5));
+        assert_eq!(payback(0.0, 12.5, 10.0), Some(0.0));
+    }
+
+    #[test]
+    fn upgrade_without_savings_never_pays_back() {
+        assert_eq!(payback(10.0, 10.0, 10.5), None);
+        assert_eq!(payback(10.0, 10.0, 10.0), None);
+        assert_eq!(payback(-1.0, 12.5, 10.0), None);
+        assert_eq!(payback(10.0, f64::NAN, 10.0), None);
+    }
+
+    #[test]
+    fn batch_reports_the_category_that_does_not_pay_back() {
+        assert_eq!(batch(&[10.0, 1.0]), Ok([4.0, 3.0]));
+        assert_eq!(batch(&[10.0, 3.0]), Err(1));
+        assert_eq!(batch(&[13.0, 1.0]), Err(0));
+    }
+}
@@ -0,0 +1,213 @@
+use std::ffi::c_double;
+use super::allocation::calculate_allocation_matrix;
+
+/// Factor taking impacts per `functional_unit_amount` to impacts per
+/// `reference_flow_amount`, or None unless both are positive and finite.
+fn scaling_factor(reference_flow_amount: f64, functional_unit_amount: f64) -> Option<f64> {
+    let valid = |x: f64| x.is_finite() && x > 0.0;
+    if valid(reference_flow_amount) && valid(functional_unit_amount) {
+        Some(reference_flow_amount / functional_unit_amount)
+    } else {
+        None
+    }
+}
+
+/// Express inventory results per functional unit.
+///
+/// `impacts` are the `len` results of producing `functional_unit_amount`
+/// of product, e.g. the 250 kg of protein one batch yields, and
+/// `reference_flow_amount` the amount to express them per, e.g. 1 kg of
+/// protein. `results` receives impacts × reference / functional unit and
+/// `scale_factor_out` that factor. Returns false on null pointers, `len`
+/// of 0, non-finite impacts, or amounts that are not positive and finite.
+#[no_mangle]
+pub extern "C" fn scale_to_functional_unit(
+    impacts: *const c_double,
+    len: usize,
+    reference_flow_amount: c_double,
+    functional_unit_amount: c_double,
+    results: *mut c_double,
+    scale_factor_out: *mut c_double
+) -> bool {
+    if impacts.is_null() || results.is_null() || scale_factor_out.is_null() || len == 0 {
+        return false;
+    }
+    let factor = match scaling_factor(reference_flow_amount, functional_unit_amount) {
+        Some(factor) => factor,
+        None => return false,
+    };
+    let impacts = unsafe { std::slice::from_raw_parts(impacts, len) };
+    if impacts.iter().any(|x| !x.is_finite()) {
+        return false;
+    }
+    unsafe {
+        for (result, impact) in std::slice::from_raw_parts_mut(results, len).iter_mut().zip(impacts) {
+            *result = impact * factor;
+        }
+        *scale_factor_out = factor;
+    }
+    true
+}
+
+/// `scale_to_functional_unit` over several processes at once.
+///
+/// `impacts` holds `n_processes` rows of `len` results (row-major), each
+/// scaled by its own `reference_flow_amounts` and `functional_unit_amounts`
+/// entry into `results`; `scale_factors_out` receives the `n_processes`
+/// factors. Returns false, writing nothing, if any process would be
+/// rejected by `scale_to_functional_unit`.
+#[no_mangle]
+pub extern "C" fn scale_to_functional_unit_batch(
+    impacts: *const c_double,
+    len: usize,
+    n_processes: usize,
+    reference_flow_amounts: *const c_double,
+    functional_unit_amounts: *const c_double,
+    results: *mut c_double,
+    scale_factors_out: *mut c_double
+) -> bool {
+    if impacts.is_null() || reference_flow_amounts.is_null() || functional_unit_amounts.is_null() || results.is_null()
+        || scale_factors_out.is_null() || len == 0 || n_processes == 0 {
+        return false;
+    }
+    let (impacts, references, functional_units) = unsafe {
+        (
+            std::slice::from_raw_parts(impacts, n_processes * len),
+            std::slice::from_raw_parts(reference_flow_amounts, n_processes),
+            std::slice::from_raw_parts(functional_unit_amounts, n_processes),
+        )
+    };
+    if impacts.iter().any(|x| !x.is_finite()) {
+        return false;
+    }
+    let factors: Option<Vec<f64>> = references.iter().zip(functional_units)
+        .map(|(&reference, &functional_unit)| scaling_factor(reference, functional_unit))
+        .collect();
+    let factors = match factors {
+        Some(factors) => factors,
+        None => return false,
+    };
+    unsafe {
+        let results = std::slice::from_raw_parts_mut(results, n_processes * len);
+        for ((row, impact_row), factor) in results.chunks_exact_mut(len).zip(impacts.chunks_exact(len)).zip(&factors) {
+            for (result, impact) in row.iter_mut().zip(impact_row) {
+                *result = impact * factor;
+            }
+        }
+        std::slice::from_raw_parts_mut(scale_factors_out, n_processes).copy_from_slice(&factors);
+    }
+    true
+}
+
+/// From stage-level inventory to allocated impacts per functional unit in
+/// one call.
+///
+/// Impacts are allocated as in `calculate_allocation_matrix`, which writes
+/// `factors_out`, and each product's column is then scaled by
+/// `reference_flow_amount` / `product_amounts[p]`, its output, so
+/// `allocated_out` holds `n_categories` rows of `n_products` impacts per
+/// functional unit of each product. `scale_factors_out` receives the
+/// `n_products` scaling factors. Returns false on input rejected by
+/// `calculate_allocation_matrix` or amounts that are not positive and
+/// finite.
+#[no_mangle]
+pub extern "C" fn allocate_to_functional_unit(
+    impact_matrix: *const c_double,
+    n_categories: usize,
+    values: *const c_double,
+    product_amounts: *const c_double,
+    n_products: usize,
+    reference_flow_amount: c_double,
+    factors_out: *mut c_double,
+    scale_factors_out: *mut c_double,
+    allocated_out: *mut c_double
+) -> bool {
+    if product_amounts.is_null() || scale_factors_out.is_null() || allocated_out.is_null() || n_products == 0 {
+        return false;
+    }
+    let amounts = unsafe { std::slice::from_raw_parts(product_amounts, n_products) };
+    let scales: Option<Vec<f64>> = amounts.iter().map(|&amount| scaling_factor(reference_flow_amount, amount)).collect();
+    let scales = match scales {
+        Some(scales) => scales,
+        None => return false,
+    };
+    let mut allocated = vec![0.0; n_categories * n_products];
+    if !calculate_allocation_matrix(impact_matrix, n_categories, values, n_products, factors_out, allocated.as_mut_ptr()) {
+        return false;
+    }
+    for row in allocated.chunks_exact_mut(n_products) {
+        for (impact, scale) in row.iter_mut().zip(&scales) {
+            *impact *= scale;
+        }
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(allocated_out, allocated.len()).copy_from_slice(&allocated);
+        std::slice::from_raw_parts_mut(scale_factors_out, n_products).copy_from_slice(&scales);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPACTS: [f64; 3] = [3.0, 5.0, 7.0];
+
+    fn scale(reference_flow_amount: f64, functional_unit_amount: f64) -> Option<([f64; 3], f64)> {
+        let (mut results, mut factor) = ([f64::NAN; 3], f64::NAN);
+        scale_to_functional_unit(IMPACTS.as_ptr(), 3, reference_flow_amount, functional_unit_amount, results.as_mut_ptr(), &mut factor)
+            .then_some((results, factor))
+    }
+
+    #[test]
+    fn equal_amounts_are_a_no_op() {
+        assert_eq!(scale(2.0, 2.0), Some((IMPACTS, 1.0)));
+    }
+
+    #[test]
+    fn doubling_the_functional_unit_halves_every_impact() {
+        assert_eq!(scale(2.0, 4.0), Some(([1.5, 2.5, 3.5], 0.5)));
+    }
+
+    #[test]
+    fn non_positive_amounts_are_rejected() {
+        for (reference, functional_unit) in [(2.0, 0.0), (0.0, 2.0), (-1.0, 2.0), (2.0, f64::NAN), (f64::INFINITY, 2.0)] {
+            assert!(scale(reference, functional_unit).is_none());
+        }
+    }
+
+    #[test]
+    fn batch_scales_each_process_by_its_own_factor() {
+        let (mut results, mut factors) = ([f64::NAN; 6], [f64::NAN; 2]);
+        let impacts = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert!(scale_to_functional_unit_batch(
+            impacts.as_ptr(), 3, 2, [1.0, 1.0].as_ptr(), [1.0, 2.0].as_ptr(), results.as_mut_ptr(), factors.as_mut_ptr(),
+        ));
+        assert_eq!(results, [1.0, 2.0, 3.0, 2.0, 2.5, 3.0]);
+        assert_eq!(factors, [1.0, 0.5]);
+        // One bad process rejects the whole batch and writes nothing
+        let (mut results, mut factors) = ([f64::NAN; 6], [f64::NAN; 2]);
+        assert!(!scale_to_functional_unit_batch(
+            impacts.as_ptr(), 3, 2, [1.0, 1.0].as_ptr(), [1.0, 0.0].as_ptr(), results.as_mut_ptr(), factors.as_mut_ptr(),
+        ));
+        assert!(results.iter().chain(&factors).all(|x| x.is_nan()));
+    }
+
+    #[test]
+    fn allocation_is_scaled_per_product_output() {
+        // Two categories over two products with value shares 1:3
+        let (mut factors, mut scales, mut allocated) = ([f64::NAN; 2], [f64::NAN; 2], [f64::NAN; 4]);
+        assert!(allocate_to_functional_unit(
+            [10.0, 30.0, 1.0, 3.0].as_ptr(), 2, [1.0, 3.0].as_ptr(), [2.0, 10.0].as_ptr(), 2, 1.0,
+            factors.as_mut_ptr(), scales.as_mut_ptr(), allocated.as_mut_ptr(),
+        ));
+        assert_eq!(factors, [0.25, 0.75]);
+        assert_eq!(scales, [0.5, 0.1]);
+        let expected = [5.0, 3.0, 0.5, 0.3];
+        assert!(allocated.iter().zip(expected).all(|(a, e)| (a - e).abs() < 1e-12), "{allocated:?}");
+        assert!(!allocate_to_functional_unit(
+            [10.0, 30.0, 1.0, 3.0].as_ptr(), 2, [1.0, 3.0].as_ptr(), [2.0, 0.0].as_ptr(), 2, 1.0,
+            factors.as_mut_ptr(), scales.as_mut_ptr(), allocated.as_mut_ptr(),
+        ));
+    }
+}
@@ -0,0 +1,209 @@
+use std::ffi::c_double;
+
+/// Greenhouse gases accepted by `calculate_gwp`, identified by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GreenhouseGas {
+    CarbonDioxide = 0,
+    MethaneFossil = 1,
+    MethaneBiogenic = 2,
+    NitrousOxide = 3,
+}
+
+/// Number of `GreenhouseGas` variants, the length of the custom factor and
+/// contribution arrays of `calculate_gwp`.
+pub const GREENHOUSE_GAS_COUNT: usize = 4;
+
+impl GreenhouseGas {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::CarbonDioxide),
+            1 => Some(Self::MethaneFossil),
+            2 => Some(Self::MethaneBiogenic),
+            3 => Some(Self::NitrousOxide),
+            _ => None,
+        }
+    }
+}
+
+/// Time horizon of the global warming potentials, passed to `calculate_gwp`
+/// by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum GwpHorizon {
+    Gwp100 = 0,
+    Gwp20 = 1,
+}
+
+impl GwpHorizon {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Gwp100),
+            1 => Some(Self::Gwp20),
+            _ => None,
+        }
+    }
+
+    /// IPCC AR6 WG1 (Table 7.15) factors in kg CO2-eq per kg, indexed by
+    /// `GreenhouseGas`. Biogenic methane uses the non-fossil value, which
+    /// excludes the CO2 its carbon returns to the atmosphere.
+    fn factors(self) -> [f64; GREENHOUSE_GAS_COUNT] {
+        match self {
+            Self::Gwp100 => [1.0, 29.8, 27.0, 273.0],
+            Self::Gwp20 => [1.0, 82.5, 79.7, 273.0],
+        }
+    }
+}
+
+/// Global warming potential of a greenhouse gas inventory in kg CO2-eq.
+///
+/// `gas_amounts` holds `len` emissions in kg, each with its
+/// `GreenhouseGas` id in `gas_ids`. Built-in AR6 factors for the
+/// `GwpHorizon` with id `horizon` are used unless `custom_factors` (nullable, `GREENHOUSE_GAS_COUNT` entries
+/// indexed by gas id) gives a value; NaN entries keep the built-in factor.
+/// `result` receives the total and `contributions_out` the total per gas
+/// (`GREENHOUSE_GAS_COUNT` entries). Unless a pointer is null,
+/// `invalid_index_out` receives the index of the first unknown gas id or
+/// non-finite amount, `usize::MAX` if none. Returns false on null
+/// pointers, `len` of 0, an unknown horizon, an invalid entry or an
+/// infinite custom factor.
+#[no_mangle]
+pub extern "C" fn calculate_gwp(
+    gas_amounts: *const c_double,
+    gas_ids: *const u32,
+    len: usize,
+    horizon: u32,
+    custom_factors: *const c_double,
+    result: *mut c_double,
+    contributions_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if gas_amounts.is_null() || gas_ids.is_null() || result.is_null() || contributions_out.is_null()
+        || invalid_index_out.is_null() {
+        return false;
+    }
+    unsafe {
+        *invalid_index_out = usize::MAX;
+    }
+    if len == 0 {
+        return false;
+    }
+    let horizon = match GwpHorizon::from_id(horizon) {
+        Some(horizon) => horizon,
+        None => return false,
+    };
+    let (amounts, ids) = unsafe {
+        (std::slice::from_raw_parts(gas_amounts, len), std::slice::from_raw_parts(gas_ids, len))
+    };
+    let mut factors = horizon.factors();
+    if !custom_factors.is_null() {
+        let custom = unsafe { std::slice::from_raw_parts(custom_factors, GREENHOUSE_GAS_COUNT) };
+        for (factor, &value) in factors.iter_mut().zip(custom) {
+            if value.is_infinite() {
+                return false;
+            }
+            if !value.is_nan() {
+                *factor = value;
+            }
+        }
+    }
+
+    let gases: Result<Vec<GreenhouseGas>, usize> = ids.iter().zip(amounts).enumerate()
+        .map(|(i, (&id, amount))| match GreenhouseGas::from_id(id) {
+            Some(gas) if amount.is_finite() => Ok(gas),
+            _ => Err(i),
+        })
+        .collect();
+    let gases = match gases {
+        Ok(gases) => gases,
+        Err(index) => {
+            unsafe {
+                *invalid_index_out = index;
+            }
+            return false;
+        }
+    };
+
+    let mut contributions = [0.0; GREENHOUSE_GAS_COUNT];
+    for (&gas, &amount) in gases.iter().zip(amounts) {
+        contributions[gas as usize] += amount * factors[gas as usize];
+    }
+    unsafe {
+        *result = contributions.iter().sum();
+        std::slice::from_raw_parts_mut(contributions_out, GREENHOUSE_GAS_COUNT).copy_from_slice(&contributions);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // 1 t CO2, fossil and biogenic CH4, N2O and a second CO2 line
+    const AMOUNTS: [f64; 5] = [1000.0, 2.0, 3.0, 0.5, 10.0];
+    const IDS: [u32; 5] = [0, 1, 2, 3, 0];
+
+    fn gwp(amounts: &[f64], ids: &[u32], horizon: GwpHorizon, custom: Option<&[f64]>) -> Result<(f64, [f64; GREENHOUSE_GAS_COUNT]), usize> {
+        gwp_by_id(amounts, ids, horizon as u32, custom)
+    }
+
+    fn gwp_by_id(amounts: &[f64], ids: &[u32], horizon: u32, custom: Option<&[f64]>) -> Result<(f64, [f64; GREENHOUSE_GAS_COUNT]), usize> {
+        let (mut total, mut contributions, mut invalid) = (f64::NAN, [f64::NAN; GREENHOUSE_GAS_COUNT], 0);
+        let custom = custom.map_or(std::ptr::null(), <[f64]>::as_ptr);
+        if calculate_gwp(amounts.as_ptr(), ids.as_ptr(), amounts.len(), horizon, custom, &mut total, contributions.as_mut_ptr(), &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((total, contributions))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn one_kg_of_methane_is_its_ar6_gwp100() {
+        assert_eq!(gwp(&[1.0], &[1], GwpHorizon::Gwp100, None).unwrap().0, 29.8);
+        assert_eq!(gwp(&[1.0], &[2], GwpHorizon::Gwp100, None).unwrap().0, 27.0);
+        assert_eq!(gwp(&[1.0], &[3], GwpHorizon::Gwp100, None).unwrap().0, 273.0);
+    }
+
+    #[test]
+    fn inventory_total_is_the_sum_of_contributions() {
+        let (total, contributions) = gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp100, None).unwrap();
+        assert_eq!(contributions, [1010.0, 59.6, 81.0, 136.5]);
+        assert!((total - 1287.1).abs() < 1e-9);
+    }
+
+    #[test]
+    fn gwp20_changes_only_the_short_lived_gases() {
+        let (_, gwp100) = gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp100, None).unwrap();
+        let (_, gwp20) = gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp20, None).unwrap();
+        let gas = |g: GreenhouseGas| g as usize;
+        assert_eq!(gwp20[gas(GreenhouseGas::CarbonDioxide)], gwp100[gas(GreenhouseGas::CarbonDioxide)]);
+        assert!(gwp20[gas(GreenhouseGas::MethaneFossil)] > gwp100[gas(GreenhouseGas::MethaneFossil)]);
+        assert!(gwp20[gas(GreenhouseGas::MethaneBiogenic)] > gwp100[gas(GreenhouseGas::MethaneBiogenic)]);
+        // AR6 gives N2O the same value over both horizons
+        assert_eq!(gwp20[gas(GreenhouseGas::NitrousOxide)], gwp100[gas(GreenhouseGas::NitrousOxide)]);
+    }
+
+    #[test]
+    fn custom_factors_override_only_where_given() {
+        // AR5's N2O value, keeping the built-in factors elsewhere
+        let (_, contributions) = gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp100, Some(&[f64::NAN, f64::NAN, f64::NAN, 265.0])).unwrap();
+        assert_eq!(contributions, [1010.0, 59.6, 81.0, 132.5]);
+        // No inventory entry is at fault, so the index is still written
+        let infinite = [f64::INFINITY, f64::NAN, f64::NAN, f64::NAN];
+        assert_eq!(gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp100, Some(&infinite)), Err(usize::MAX));
+    }
+
+    #[test]
+    fn unknown_horizon_is_rejected() {
+        assert_eq!(gwp_by_id(&AMOUNTS, &IDS, 1, None), gwp(&AMOUNTS, &IDS, GwpHorizon::Gwp20, None));
+        assert_eq!(gwp_by_id(&AMOUNTS, &IDS, 2, None), Err(usize::MAX));
+        assert_eq!(gwp_by_id(&[], &[], 0, None), Err(usize::MAX));
+    }
+
+    #[test]
+    fn unknown_gas_ids_report_their_index() {
+        assert_eq!(gwp(&AMOUNTS, &[0, 1, 7, 3, 0], GwpHorizon::Gwp100, None), Err(2));
+        assert_eq!(gwp(&[1.0, f64::NAN], &[0, 1], GwpHorizon::Gwp100, None), Err(1));
+    }
+}
@@ -1,5 +1,31 @@
 pub mod allocation;
+pub mod characterization;
 pub mod efficiency;
+pub mod electricity;
+pub mod energy;
+pub mod environmental_payback;
+pub mod functional_unit;
+pub mod gwp;
+pub mod normalization;
+pub mod nutrient_emissions;
+pub mod pareto;
+pub mod process_chain;
+pub mod system_expansion;
+pub mod uncertainty;
+pub mod water;
 
 pub use allocation::*;
-pub use efficiency::*; 
\ No newline at end of file
+pub use characterization::*;
+pub use efficiency::*;
+pub use electricity::*;
+pub use energy::*;
+pub use environmental_payback::*;
+pub use functional_unit::*;
+pub use gwp::*;
+pub use normalization::*;
+pub use nutrient_emissions::*;
+pub use pareto::*;
+pub use process_chain::*;
+pub use system_expansion::*;
+pub use uncertainty::*;
+pub use water::*; 
\ No newline at end of file
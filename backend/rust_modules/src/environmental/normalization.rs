@@ -0,0 +1,289 @@
+use std::ffi::c_double;
+
+/// Normalize `impacts` by `references` into `out` and return the index of
+/// the largest normalized score (the first on ties). Err carries the index
+/// of the first reference that is not a positive finite number, or else of
+/// the first non-finite impact.
+fn normalize_row(impacts: &[f64], references: &[f64], out: &mut [f64]) -> Result<usize, usize> {
+    if let Some(index) = references.iter().position(|&r| !r.is_finite() || r <= 0.0) {
+        return Err(index);
+    }
+    if let Some(index) = impacts.iter().position(|x| !x.is_finite()) {
+        return Err(index);
+    }
+    for ((normalized, &impact), &reference) in out.iter_mut().zip(impacts).zip(references) {
+        *normalized = impact / reference;
+    }
+    let largest = (1..out.len()).fold(0, |best, i| if out[i] > out[best] { i } else { best });
+    Ok(largest)
+}
+
+/// Normalize impact categories against external reference values, e.g.
+/// per-capita or per-sector annual impacts, so categories with different
+/// units can be compared on one scale.
+///
+/// `normalized_out[i]` receives impacts[i] / reference_values[i] and
+/// `largest_index_out` the category with the largest normalized score.
+/// `invalid_index_out` receives the index of the first reference that is
+/// zero, negative or not finite (or of a non-finite impact), and
+/// `usize::MAX` when all are valid. Returns false on null pointers, `len`
+/// of 0 or an invalid entry; only `invalid_index_out` is written then.
+#[no_mangle]
+pub extern "C" fn normalize_impacts(
+    impacts: *const c_double,
+    reference_values: *const c_double,
+    len: usize,
+    normalized_out: *mut c_double,
+    largest_index_out: *mut usize,
+    invalid_index_out: *mut usize
+) -> bool {
+    if impacts.is_null() || reference_values.is_null() || normalized_out.is_null() || largest_index_out.is_null()
+        || invalid_index_out.is_null() || len == 0 {
+        return false;
+    }
+    let (impacts, references) = unsafe {
+        (std::slice::from_raw_parts(impacts, len), std::slice::from_raw_parts(reference_values, len))
+    };
+    let mut normalized = vec![0.0; len];
+    let result = normalize_row(impacts, references, &mut normalized);
+    unsafe {
+        *invalid_index_out = result.err().unwrap_or(usize::MAX);
+    }
+    let largest = match result {
+        Ok(largest) => largest,
+        Err(_) => return false,
+    };
+    unsafe {
+        std::slice::from_raw_parts_mut(normalized_out, len).copy_from_slice(&normalized);
+        *largest_index_out = largest;
+    }
+    true
+}
+
+/// Internal normalization: each alternative's impacts as a ratio of the
+/// baseline process's impacts in the same category.
+///
+/// `alternative_impacts` holds `n_alternatives` rows of `n_categories`
+/// (row-major) and `baseline_impacts` one row; `normalized_out` receives
+/// the rows divided by the baseline, so 1 means no change, and
+/// `largest_index_out[a]` the category with the largest ratio for
+/// alternative `a`. Errors are reported as in `normalize_impacts`, with
+/// `invalid_index_out` naming the baseline category that cannot serve as a
+/// reference (or, for a non-finite impact, its row-major index).
+#[no_mangle]
+pub extern "C" fn normalize_impacts_internal(
+    alternative_impacts: *const c_double,
+    baseline_impacts: *const c_double,
+    n_alternatives: usize,
+    n_categories: usize,
+    normalized_out: *mut c_double,
+    largest_index_out: *mut usize,
+    invalid_index_out: *mut usize
+) -> bool {
+    if alternative_impacts.is_null() || baseline_impacts.is_null() || normalized_out.is_null()
+        || largest_index_out.is_null() || invalid_index_out.is_null() || n_alternatives == 0 || n_categories == 0 {
+        return false;
+    }
+    let (alternatives, baseline) = unsafe {
+        (
+            std::slice::from_raw_parts(alternative_impacts, n_alternatives * n_categories),
+            std::slice::from_raw_parts(baseline_impacts, n_categories),
+        )
+    };
+    let invalid = baseline.iter().position(|&r| !r.is_finite() || r <= 0.0)
+        .or_else(|| alternatives.iter().position(|x| !x.is_finite()));
+    unsafe {
+        *invalid_index_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
+    let mut normalized = vec![0.0; n_alternatives * n_categories];
+    let largest: Result<Vec<usize>, usize> = alternatives.chunks_exact(n_categories)
+        .zip(normalized.chunks_exact_mut(n_categories))
+        .map(|(row, out)| normalize_row(row, baseline, out))
+        .collect();
+    let largest = match largest {
+        Ok(largest) => largest,
+        Err(_) => return false,
+    };
+    unsafe {
+        std::slice::from_raw_parts_mut(normalized_out, normalized.len()).copy_from_slice(&normalized);
+        std::slice::from_raw_parts_mut(largest_index_out, n_alternatives).copy_from_slice(&largest);
+    }
+    true
+}
+
+/// How far the weights may sum from 1 when `aggregate_weighted_score` is
+/// asked to reject weights that are not normalized.
+const WEIGHT_SUM_TOLERANCE: f64 = 1e-9;
+
+/// Weighted sum of normalized scores and each category's share of it, or
+/// None on negative or non-finite weights, weights summing to zero, or
+/// (with `require_normalized`) weights not summing to 1.
+fn weighted_score(normalized: &[f64], weights: &[f64], require_normalized: bool) -> Option<(f64, Vec<f64>)> {
+    if weights.iter().any(|&w| !w.is_finite() || w < 0.0) || normalized.iter().any(|x| !x.is_finite()) {
+        return None;
+    }
+    let weight_sum: f64 = weights.iter().sum();
+    if weight_sum <= 0.0 || (require_normalized && (weight_sum - 1.0).abs() > WEIGHT_SUM_TOLERANCE) {
+        return None;
+    }
+    let terms: Vec<f64> = normalized.iter().zip(weights).map(|(x, w)| x * w / weight_sum).collect();
+    let score: f64 = terms.iter().sum();
+    let contributions = terms.iter().map(|term| term / score).collect();
+    Some((score, contributions))
+}
+
+/// Single score from normalized impact categories.
+///
+/// Weights must be non-negative; they are rescaled to sum to 1 unless
+/// `require_normalized` is set, in which case weights not already summing
+/// to 1 are rejected. `score_out` receives Σ wᵢ·xᵢ and
+/// `contributions_out[i]` category i's share wᵢ·xᵢ / score, so the single
+/// score stays explainable (NaN when the score is 0). Returns false on
+/// null pointers, `len` of 0, non-finite scores, or rejected weights.
+#[no_mangle]
+pub extern "C" fn aggregate_weighted_score(
+    normalized_impacts: *const c_double,
+    weights: *const c_double,
+    len: usize,
+    require_normalized: bool,
+    score_out: *mut c_double,
+    contributions_out: *mut c_double
+) -> bool {
+    if normalized_impacts.is_null() || weights.is_null() || score_out.is_null() || contributions_out.is_null() || len == 0 {
+        return false;
+    }
+    let (normalized, weights) = unsafe {
+        (std::slice::from_raw_parts(normalized_impacts, len), std::slice::from_raw_parts(weights, len))
+    };
+    let (score, contributions) = match weighted_score(normalized, weights, require_normalized) {
+        Some(result) => result,
+        None => return false,
+    };
+    unsafe {
+        *score_out = score;
+        std::slice::from_raw_parts_mut(contributions_out, len).copy_from_slice(&contributions);
+    }
+    true
+}
+
+/// `normalize_impacts` followed by `aggregate_weighted_score`, the common
+/// path from raw category impacts to a single score. `invalid_index_out`
+/// names an invalid reference as in `normalize_impacts`. Returns false
+/// when either step fails.
+#[no_mangle]
+pub extern "C" fn normalize_and_aggregate(
+    impacts: *const c_double,
+    reference_values: *const c_double,
+    weights: *const c_double,
+    len: usize,
+    require_normalized: bool,
+    score_out: *mut c_double,
+    contributions_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    let mut normalized = vec![0.0; len];
+    let mut largest = 0;
+    normalize_impacts(impacts, reference_values, len, normalized.as_mut_ptr(), &mut largest, invalid_index_out)
+        && aggregate_weighted_score(normalized.as_ptr(), weights, len, require_normalized, score_out, contributions_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn normalize(impacts: &[f64], references: &[f64]) -> Result<(Vec<f64>, usize), usize> {
+        let mut normalized = vec![f64::NAN; impacts.len()];
+        let (mut largest, mut invalid) = (usize::MAX, 0);
+        if normalize_impacts(impacts.as_ptr(), references.as_ptr(), impacts.len(), normalized.as_mut_ptr(), &mut largest, &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((normalized, largest))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    fn normalize_internal(alternatives: &[f64], baseline: &[f64]) -> Result<(Vec<f64>, Vec<usize>), usize> {
+        let n_alternatives = alternatives.len() / baseline.len();
+        let mut normalized = vec![f64::NAN; alternatives.len()];
+        let (mut largest, mut invalid) = (vec![usize::MAX; n_alternatives], 0);
+        if normalize_impacts_internal(
+            alternatives.as_ptr(), baseline.as_ptr(), n_alternatives, baseline.len(), normalized.as_mut_ptr(), largest.as_mut_ptr(), &mut invalid,
+        ) {
+            Ok((normalized, largest))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn external_normalization_divides_by_the_references() {
+        // GWP (kg CO2e), water (L) and energy (GJ) against per-capita annual references
+        let (normalized, largest) = normalize(&[7400.0, 1.2e5, 3.0], &[7400.0, 6.0e4, 12.0]).unwrap();
+        assert_eq!(normalized, [1.0, 2.0, 0.25]);
+        assert_eq!(largest, 1);
+    }
+
+    #[test]
+    fn internal_normalization_expresses_alternatives_as_ratios() {
+        let (normalized, largest) = normalize_internal(&[1.0, 2.0, 3.0, 2.0, 1.0, 0.5], &[2.0, 2.0, 2.0]).unwrap();
+        assert_eq!(normalized, [0.5, 1.0, 1.5, 1.0, 0.5, 0.25]);
+        assert_eq!(largest, [2, 0]);
+        // The baseline normalized against itself is all ones
+        let (itself, _) = normalize_internal(&[2.0, 5.0, 0.1], &[2.0, 5.0, 0.1]).unwrap();
+        assert_eq!(itself, [1.0; 3]);
+    }
+
+    #[test]
+    fn zero_or_negative_references_report_their_index() {
+        assert_eq!(normalize(&[1.0, 2.0, 3.0], &[1.0, 0.0, -1.0]), Err(1));
+        assert_eq!(normalize(&[1.0, 2.0, 3.0], &[1.0, 1.0, f64::NAN]), Err(2));
+        assert_eq!(normalize(&[1.0, f64::INFINITY, 3.0], &[1.0; 3]), Err(1));
+        assert_eq!(normalize_internal(&[1.0, 2.0, 3.0, 2.0, 1.0, 0.5], &[2.0, 0.0, 1.0]), Err(1));
+        // A non-finite alternative impact is named by its row-major index
+        assert_eq!(normalize_internal(&[1.0, 2.0, 3.0, 2.0, f64::NAN, 0.5], &[2.0; 3]), Err(4));
+    }
+
+    fn aggregate(normalized: &[f64], weights: &[f64], require_normalized: bool) -> Option<(f64, Vec<f64>)> {
+        let (mut score, mut contributions) = (f64::NAN, vec![f64::NAN; normalized.len()]);
+        aggregate_weighted_score(normalized.as_ptr(), weights.as_ptr(), normalized.len(), require_normalized, &mut score, contributions.as_mut_ptr())
+            .then_some((score, contributions))
+    }
+
+    #[test]
+    fn equal_weights_average_the_scores() {
+        let (score, contributions) = aggregate(&[1.0, 3.0], &[1.0, 1.0], false).unwrap();
+        assert_eq!(score, 2.0);
+        assert_eq!(contributions, [0.25, 0.75]);
+        assert_eq!(aggregate(&[1.0, 3.0], &[0.5, 0.5], true), Some((score, contributions)));
+    }
+
+    #[test]
+    fn weights_must_be_non_negative_and_normalized_on_request() {
+        assert!(aggregate(&[1.0, 3.0], &[1.0, 1.0], true).is_none());
+        assert!(aggregate(&[1.0, 3.0], &[-0.5, 1.5], false).is_none());
+        assert!(aggregate(&[1.0, 3.0], &[0.0, 0.0], false).is_none());
+        assert!(aggregate(&[1.0, f64::NAN], &[0.5, 0.5], false).is_none());
+        let (score, contributions) = aggregate(&[0.0, 0.0], &[0.5, 0.5], true).unwrap();
+        assert_eq!(score, 0.0);
+        assert!(contributions.iter().all(|c| c.is_nan()));
+    }
+
+    #[test]
+    fn wrapper_normalizes_then_aggregates() {
+        let (mut score, mut contributions, mut invalid) = (f64::NAN, [f64::NAN; 2], 0);
+        assert!(normalize_and_aggregate(
+            [10.0, 90.0].as_ptr(), [10.0, 30.0].as_ptr(), [2.0, 2.0].as_ptr(), 2, false, &mut score, contributions.as_mut_ptr(), &mut invalid,
+        ));
+        assert_eq!((score, contributions, invalid), (2.0, [0.25, 0.75], usize::MAX));
+        assert!(!normalize_and_aggregate(
+            [10.0, 90.0].as_ptr(), [10.0, 0.0].as_ptr(), [2.0, 2.0].as_ptr(), 2, false, &mut score, contributions.as_mut_ptr(), &mut invalid,
+        ));
+        assert_eq!(invalid, 1);
+        assert!(!normalize_and_aggregate(
+            [10.0, 90.0].as_ptr(), [10.0, 30.0].as_ptr(), [2.0, 2.0].as_ptr(), 2, true, &mut score, contributions.as_mut_ptr(), &mut invalid,
+        ));
+    }
+}
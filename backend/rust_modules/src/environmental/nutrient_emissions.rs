@@ -0,0 +1,286 @@
+use std::ffi::c_double;
+
+/// Substances and compartments accepted by `calculate_eutrophication` and
+/// `calculate_acidification`, amounts in kg of the substance itself (kg
+/// NH3, kg NO3⁻, kg P, ...), each identified by its id in
+/// `substance_ids`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum NutrientEmission {
+    AmmoniaToAir = 0,
+    NitrogenOxidesToAir = 1,
+    SulfurDioxideToAir = 2,
+    NitrateToWater = 3,
+    AmmoniumToWater = 4,
+    NitrogenToWater = 5,
+    PhosphateToWater = 6,
+    PhosphorusToWater = 7,
+    PhosphorusToSoil = 8,
+}
+
+/// Number of `NutrientEmission` variants, the length of the contribution
+/// arrays of the nutrient category functions.
+pub const NUTRIENT_EMISSION_COUNT: usize = 9;
+
+impl NutrientEmission {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::AmmoniaToAir),
+            1 => Some(Self::NitrogenOxidesToAir),
+            2 => Some(Self::SulfurDioxideToAir),
+            3 => Some(Self::NitrateToWater),
+            4 => Some(Self::AmmoniumToWater),
+            5 => Some(Self::NitrogenToWater),
+            6 => Some(Self::PhosphateToWater),
+            7 => Some(Self::PhosphorusToWater),
+            8 => Some(Self::PhosphorusToSoil),
+            _ => None,
+        }
+    }
+}
+
+/// Eutrophication indicator and its characterization factors, selected by
+/// id in `calculate_eutrophication`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EutrophicationMethod {
+    /// CML-IA baseline generic eutrophication, kg PO4³⁻-eq
+    Cml = 0,
+    /// ReCiPe 2016 (hierarchist) freshwater eutrophication, kg P-eq
+    RecipeFreshwater = 1,
+    /// ReCiPe 2016 (hierarchist) marine eutrophication, kg N-eq, covering
+    /// waterborne nitrogen reaching the sea through freshwater
+    RecipeMarine = 2,
+}
+
+impl EutrophicationMethod {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Cml),
+            1 => Some(Self::RecipeFreshwater),
+            2 => Some(Self::RecipeMarine),
+            _ => None,
+        }
+    }
+
+    /// Factors indexed by `NutrientEmission`; substances the indicator
+    /// does not cover have 0.
+    fn factors(self) -> [f64; NUTRIENT_EMISSION_COUNT] {
+        match self {
+            Self::Cml => [0.35, 0.13, 0.0, 0.1, 0.33, 0.42, 1.0, 3.06, 3.06],
+            Self::RecipeFreshwater => [0.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.33, 1.0, 0.1],
+            Self::RecipeMarine => [0.0, 0.0, 0.0, 0.068, 0.233, 0.3, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Acidification indicator and its characterization factors, selected by
+/// id in `calculate_acidification`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AcidificationMethod {
+    /// CML-IA baseline acidification, kg SO2-eq
+    Cml = 0,
+    /// ReCiPe 2016 (hierarchist) terrestrial acidification, kg SO2-eq
+    RecipeTerrestrial = 1,
+}
+
+impl AcidificationMethod {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Cml),
+            1 => Some(Self::RecipeTerrestrial),
+            _ => None,
+        }
+    }
+
+    /// Factors indexed by `NutrientEmission`; only the airborne acidifying
+    /// gases are nonzero.
+    fn factors(self) -> [f64; NUTRIENT_EMISSION_COUNT] {
+        match self {
+            Self::Cml => [1.6, 0.5, 1.2, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+            Self::RecipeTerrestrial => [1.96, 0.36, 1.0, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0],
+        }
+    }
+}
+
+/// Characterize `len` emissions with `factors`, writing the total to
+/// `result` and the total per substance to `contributions_out`, as
+/// `calculate_gwp` does for greenhouse gases. `factors` is None when the
+/// method id was unknown.
+fn characterize(
+    amounts: *const c_double,
+    substance_ids: *const u32,
+    len: usize,
+    factors: Option<[f64; NUTRIENT_EMISSION_COUNT]>,
+    result: *mut c_double,
+    contributions_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    if amounts.is_null() || substance_ids.is_null() || result.is_null() || contributions_out.is_null()
+        || invalid_index_out.is_null() {
+        return false;
+    }
+    unsafe {
+        *invalid_index_out = usize::MAX;
+    }
+    if len == 0 {
+        return false;
+    }
+    let factors = match factors {
+        Some(factors) => factors,
+        None => return false,
+    };
+    let (amounts, ids) = unsafe {
+        (std::slice::from_raw_parts(amounts, len), std::slice::from_raw_parts(substance_ids, len))
+    };
+    let substances: Result<Vec<NutrientEmission>, usize> = ids.iter().zip(amounts).enumerate()
+        .map(|(i, (&id, amount))| match NutrientEmission::from_id(id) {
+            Some(substance) if amount.is_finite() => Ok(substance),
+            _ => Err(i),
+        })
+        .collect();
+    let substances = match substances {
+        Ok(substances) => substances,
+        Err(index) => {
+            unsafe {
+                *invalid_index_out = index;
+            }
+            return false;
+        }
+    };
+
+    let mut contributions = [0.0; NUTRIENT_EMISSION_COUNT];
+    for (&substance, &amount) in substances.iter().zip(amounts) {
+        contributions[substance as usize] += amount * factors[substance as usize];
+    }
+    unsafe {
+        *result = contributions.iter().sum();
+        std::slice::from_raw_parts_mut(contributions_out, NUTRIENT_EMISSION_COUNT).copy_from_slice(&contributions);
+    }
+    true
+}
+
+/// Eutrophication potential of N and P emissions.
+///
+/// `amounts` holds `len` emissions, each with its `NutrientEmission` id in
+/// `substance_ids`, characterized with the built-in midpoint factors of the
+/// `EutrophicationMethod` with id `method`. `result` receives the category total and `contributions_out`
+/// the total per substance (`NUTRIENT_EMISSION_COUNT` entries). Substances
+/// the method does not cover, such as SO2, contribute 0. Unless a pointer
+/// is null, `invalid_index_out` receives the index of the first unknown
+/// substance id or non-finite amount, `usize::MAX` if none. Returns false
+/// on null pointers, `len` of 0, an unknown method or an invalid entry.
+#[no_mangle]
+pub extern "C" fn calculate_eutrophication(
+    amounts: *const c_double,
+    substance_ids: *const u32,
+    len: usize,
+    method: u32,
+    result: *mut c_double,
+    contributions_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    let factors = EutrophicationMethod::from_id(method).map(EutrophicationMethod::factors);
+    characterize(amounts, substance_ids, len, factors, result, contributions_out, invalid_index_out)
+}
+
+/// Acidification potential of airborne NH3, NOx and SO2, with inputs and
+/// outputs as in `calculate_eutrophication` and `method` an
+/// `AcidificationMethod` id; waterborne and soil emissions contribute 0.
+#[no_mangle]
+pub extern "C" fn calculate_acidification(
+    amounts: *const c_double,
+    substance_ids: *const u32,
+    len: usize,
+    method: u32,
+    result: *mut c_double,
+    contributions_out: *mut c_double,
+    invalid_index_out: *mut usize
+) -> bool {
+    let factors = AcidificationMethod::from_id(method).map(AcidificationMethod::factors);
+    characterize(amounts, substance_ids, len, factors, result, contributions_out, invalid_index_out)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    type Characterized = (f64, [f64; NUTRIENT_EMISSION_COUNT]);
+
+    fn eutrophication(amounts: &[f64], ids: &[u32], method: EutrophicationMethod) -> Result<Characterized, usize> {
+        let (mut total, mut contributions, mut invalid) = (f64::NAN, [f64::NAN; NUTRIENT_EMISSION_COUNT], 0);
+        if calculate_eutrophication(amounts.as_ptr(), ids.as_ptr(), ids.len(), method as u32, &mut total, contributions.as_mut_ptr(), &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((total, contributions))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    fn acidification(amounts: &[f64], ids: &[u32], method: AcidificationMethod) -> Result<Characterized, usize> {
+        let (mut total, mut contributions, mut invalid) = (f64::NAN, [f64::NAN; NUTRIENT_EMISSION_COUNT], 0);
+        if calculate_acidification(amounts.as_ptr(), ids.as_ptr(), ids.len(), method as u32, &mut total, contributions.as_mut_ptr(), &mut invalid) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((total, contributions))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn one_kg_of_each_gas_to_air_matches_the_acidification_tables() {
+        let one_kg = |substance: NutrientEmission, method| acidification(&[1.0], &[substance as u32], method).unwrap().0;
+        // CML-IA baseline, kg SO2-eq/kg
+        assert_eq!(one_kg(NutrientEmission::AmmoniaToAir, AcidificationMethod::Cml), 1.6);
+        assert_eq!(one_kg(NutrientEmission::NitrogenOxidesToAir, AcidificationMethod::Cml), 0.5);
+        assert_eq!(one_kg(NutrientEmission::SulfurDioxideToAir, AcidificationMethod::Cml), 1.2);
+        // ReCiPe 2016 hierarchist terrestrial acidification, kg SO2-eq/kg
+        assert_eq!(one_kg(NutrientEmission::AmmoniaToAir, AcidificationMethod::RecipeTerrestrial), 1.96);
+        assert_eq!(one_kg(NutrientEmission::NitrogenOxidesToAir, AcidificationMethod::RecipeTerrestrial), 0.36);
+        assert_eq!(one_kg(NutrientEmission::SulfurDioxideToAir, AcidificationMethod::RecipeTerrestrial), 1.0);
+        assert_eq!(one_kg(NutrientEmission::PhosphorusToWater, AcidificationMethod::Cml), 0.0);
+    }
+
+    #[test]
+    fn single_substances_match_the_eutrophication_tables() {
+        let one_kg = |substance: NutrientEmission, method| eutrophication(&[1.0], &[substance as u32], method).unwrap().0;
+        // CML-IA baseline, kg PO4³⁻-eq/kg
+        assert_eq!(one_kg(NutrientEmission::AmmoniaToAir, EutrophicationMethod::Cml), 0.35);
+        assert_eq!(one_kg(NutrientEmission::NitrogenOxidesToAir, EutrophicationMethod::Cml), 0.13);
+        assert_eq!(one_kg(NutrientEmission::PhosphateToWater, EutrophicationMethod::Cml), 1.0);
+        assert_eq!(one_kg(NutrientEmission::PhosphorusToWater, EutrophicationMethod::Cml), 3.06);
+        // ReCiPe 2016 freshwater eutrophication, kg P-eq/kg
+        assert_eq!(one_kg(NutrientEmission::PhosphorusToWater, EutrophicationMethod::RecipeFreshwater), 1.0);
+        assert_eq!(one_kg(NutrientEmission::SulfurDioxideToAir, EutrophicationMethod::RecipeFreshwater), 0.0);
+    }
+
+    #[test]
+    fn contributions_are_summed_per_substance() {
+        let amounts = [2.0, 1.0, 0.5];
+        let ids = [NutrientEmission::NitrogenToWater as u32, NutrientEmission::NitrateToWater as u32, NutrientEmission::NitrogenToWater as u32];
+        let (total, contributions) = eutrophication(&amounts, &ids, EutrophicationMethod::RecipeMarine).unwrap();
+        assert!((contributions[NutrientEmission::NitrogenToWater as usize] - 2.5 * 0.3).abs() < 1e-12);
+        assert_eq!(contributions[NutrientEmission::NitrateToWater as usize], 0.068);
+        assert!((total - contributions.iter().sum::<f64>()).abs() < 1e-15);
+    }
+
+    #[test]
+    fn unknown_substances_report_their_index() {
+        assert_eq!(eutrophication(&[2.0, 1.0], &[5, 42], EutrophicationMethod::Cml), Err(1));
+        assert_eq!(acidification(&[2.0, 1.0], &[NUTRIENT_EMISSION_COUNT as u32, 0], AcidificationMethod::Cml), Err(0));
+        assert_eq!(acidification(&[1.0, f64::NAN], &[0, 1], AcidificationMethod::Cml), Err(1));
+    }
+
+    #[test]
+    fn unknown_methods_are_rejected() {
+        let (mut total, mut contributions, mut invalid) = (f64::NAN, [f64::NAN; NUTRIENT_EMISSION_COUNT], 0);
+        let (amounts, ids) = ([1.0], [NutrientEmission::AmmoniaToAir as u32]);
+        assert!(!calculate_eutrophication(amounts.as_ptr(), ids.as_ptr(), 1, 3, &mut total, contributions.as_mut_ptr(), &mut invalid));
+        assert_eq!(invalid, usize::MAX);
+        invalid = 0;
+        assert!(!calculate_acidification(amounts.as_ptr(), ids.as_ptr(), 1, 2, &mut total, contributions.as_mut_ptr(), &mut invalid));
+        assert_eq!(invalid, usize::MAX);
+        assert!(total.is_nan() && contributions.iter().all(|c| c.is_nan()));
+    }
+}
@@ -0,0 +1,92 @@
+use std::ffi::c_double;
+
+/// Whether point `a` dominates point `b`: no worse in every objective and
+/// strictly better in at least one. Objectives are oriented so lower is
+/// better.
+fn dominates(a: &[f64], b: &[f64]) -> bool {
+    a.iter().zip(b).all(|(x, y)| x <= y) && a.iter().zip(b).any(|(x, y)| x < y)
+}
+
+/// Non-dominated set of candidate process configurations, e.g. unit cost
+/// against GWP.
+///
+/// `objectives_matrix` holds `n_points` rows of `n_objectives` values
+/// (row-major) and `minimize_flags` whether lower is better for each
+/// objective; maximized objectives such as NPV have the flag false. A
+/// point is on the frontier unless another point is at least as good in
+/// every objective and better in one, so ties and duplicated points are
+/// all kept. `frontier_indices_out` (room for `n_points` entries) receives
+/// the frontier's row indices in ascending order and `n_frontier_out`
+/// their count. Returns false on null pointers, an empty dimension or
+/// non-finite objectives.
+#[no_mangle]
+pub extern "C" fn find_pareto_frontier(
+    objectives_matrix: *const c_double,
+    n_points: usize,
+    n_objectives: usize,
+    minimize_flags: *const bool,
+    frontier_indices_out: *mut usize,
+    n_frontier_out: *mut usize
+) -> bool {
+    if objectives_matrix.is_null() || minimize_flags.is_null() || frontier_indices_out.is_null()
+        || n_frontier_out.is_null() || n_points == 0 || n_objectives == 0 {
+        return false;
+    }
+    let (objectives, minimize) = unsafe {
+        (
+            std::slice::from_raw_parts(objectives_matrix, n_points * n_objectives),
+            std::slice::from_raw_parts(minimize_flags, n_objectives),
+        )
+    };
+    if objectives.iter().any(|x| !x.is_finite()) {
+        return false;
+    }
+
+    let oriented: Vec<f64> = objectives.chunks_exact(n_objectives)
+        .flat_map(|row| row.iter().zip(minimize).map(|(&x, &min)| if min { x } else { -x }))
+        .collect();
+    let points: Vec<&[f64]> = oriented.chunks_exact(n_objectives).collect();
+    let frontier: Vec<usize> = (0..n_points)
+        .filter(|&i| !points.iter().any(|other| dominates(other, points[i])))
+        .collect();
+    unsafe {
+        std::slice::from_raw_parts_mut(frontier_indices_out, frontier.len()).copy_from_slice(&frontier);
+        *n_frontier_out = frontier.len();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frontier(objectives: &[f64], minimize: &[bool]) -> Option<Vec<usize>> {
+        let n_points = objectives.len() / minimize.len();
+        let (mut indices, mut n_frontier) = (vec![usize::MAX; n_points], 0);
+        find_pareto_frontier(objectives.as_ptr(), n_points, minimize.len(), minimize.as_ptr(), indices.as_mut_ptr(), &mut n_frontier)
+            .then(|| indices[..n_frontier].to_vec())
+    }
+
+    #[test]
+    fn strictly_dominated_point_is_excluded() {
+        // Unit cost (minimized) against NPV (maximized); point 1 costs more
+        // and earns less than point 0, and point 2 duplicates point 0
+        let objectives = [1.0, 5.0, 2.0, 4.0, 1.0, 5.0, 0.5, 1.0, 3.0, 9.0];
+        assert_eq!(frontier(&objectives, &[true, false]), Some(vec![0, 2, 3, 4]));
+        // Flipping both directions turns the frontier around
+        assert_eq!(frontier(&objectives, &[false, true]), Some(vec![1, 3, 4]));
+    }
+
+    #[test]
+    fn one_objective_keeps_only_the_best_point() {
+        assert_eq!(frontier(&[3.0, 1.0, 2.0], &[true]), Some(vec![1]));
+        assert_eq!(frontier(&[3.0, 1.0, 2.0], &[false]), Some(vec![0]));
+        assert_eq!(frontier(&[3.0, 1.0, 1.0], &[true]), Some(vec![1, 2]));
+    }
+
+    #[test]
+    fn non_finite_objectives_are_rejected() {
+        assert_eq!(frontier(&[1.0, f64::NAN], &[true, true]), None);
+        assert_eq!(frontier(&[], &[true]), None);
+    }
+}
@@ -0,0 +1,117 @@
+use std::ffi::c_double;
+
+/// Stage-wise impacts of a process chain per kg of final product, e.g.
+/// milling → air classification → RF/IR treatment → drying.
+///
+/// `stage_impacts_matrix` holds `n_stages` rows of `n_categories` impacts
+/// (row-major), each per kg of material entering that stage, and
+/// `stage_yields` the mass yield of each stage in (0, 1]. A stage's impact
+/// is divided by the product of its own and all downstream yields, the kg
+/// it must process per kg of final product, so two stages at 50% yield
+/// put four times the first stage's impact on each final kg.
+/// `per_fu_out` receives the scaled `n_stages` × `n_categories` rows and
+/// `cumulative_out` their running sum along the chain, whose last row is
+/// the chain total per kg of final product.
+///
+/// `invalid_stage_out` receives the first stage with a yield outside
+/// (0, 1] or a non-finite impact, and `usize::MAX` when all are valid.
+/// Returns false on null pointers, an empty dimension or an invalid stage.
+#[no_mangle]
+pub extern "C" fn aggregate_process_chain(
+    stage_impacts_matrix: *const c_double,
+    stage_yields: *const c_double,
+    n_stages: usize,
+    n_categories: usize,
+    cumulative_out: *mut c_double,
+    per_fu_out: *mut c_double,
+    invalid_stage_out: *mut usize
+) -> bool {
+    if stage_impacts_matrix.is_null() || stage_yields.is_null() || cumulative_out.is_null() || per_fu_out.is_null()
+        || invalid_stage_out.is_null() || n_stages == 0 || n_categories == 0 {
+        return false;
+    }
+    let (impacts, yields) = unsafe {
+        (
+            std::slice::from_raw_parts(stage_impacts_matrix, n_stages * n_categories),
+            std::slice::from_raw_parts(stage_yields, n_stages),
+        )
+    };
+    let invalid = (0..n_stages).find(|&stage| {
+        let y = yields[stage];
+        !y.is_finite() || y <= 0.0 || y > 1.0
+            || impacts[stage * n_categories..(stage + 1) * n_categories].iter().any(|x| !x.is_finite())
+    });
+    unsafe {
+        *invalid_stage_out = invalid.unwrap_or(usize::MAX);
+    }
+    if invalid.is_some() {
+        return false;
+    }
+
+    // Yield from each stage's input to the final product
+    let mut downstream_yields = vec![1.0; n_stages];
+    let mut product = 1.0;
+    for stage in (0..n_stages).rev() {
+        product *= yields[stage];
+        downstream_yields[stage] = product;
+    }
+
+    let mut per_fu = vec![0.0; n_stages * n_categories];
+    for ((row, impact_row), downstream) in per_fu.chunks_exact_mut(n_categories)
+        .zip(impacts.chunks_exact(n_categories))
+        .zip(&downstream_yields) {
+        for (scaled, impact) in row.iter_mut().zip(impact_row) {
+            *scaled = impact / downstream;
+        }
+    }
+    let mut cumulative = per_fu.clone();
+    for i in n_categories..cumulative.len() {
+        cumulative[i] += cumulative[i - n_categories];
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(per_fu_out, per_fu.len()).copy_from_slice(&per_fu);
+        std::slice::from_raw_parts_mut(cumulative_out, cumulative.len()).copy_from_slice(&cumulative);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const IMPACTS: [f64; 4] = [1.0, 2.0, 3.0, 4.0];
+
+    fn chain(impacts: &[f64], yields: &[f64]) -> Result<([f64; 4], [f64; 4]), usize> {
+        let (mut cumulative, mut per_fu, mut invalid) = ([f64::NAN; 4], [f64::NAN; 4], 0);
+        if aggregate_process_chain(
+            impacts.as_ptr(), yields.as_ptr(), yields.len(), 4 / yields.len(), cumulative.as_mut_ptr(), per_fu.as_mut_ptr(), &mut invalid,
+        ) {
+            assert_eq!(invalid, usize::MAX);
+            Ok((per_fu, cumulative))
+        } else {
+            Err(invalid)
+        }
+    }
+
+    #[test]
+    fn two_half_yield_stages_quadruple_the_first_stage() {
+        let (per_fu, cumulative) = chain(&IMPACTS, &[0.5, 0.5]).unwrap();
+        assert_eq!(per_fu, [4.0, 8.0, 6.0, 8.0]);
+        assert_eq!(cumulative, [4.0, 8.0, 10.0, 16.0]);
+    }
+
+    #[test]
+    fn full_yields_leave_impacts_unscaled() {
+        let (per_fu, cumulative) = chain(&IMPACTS, &[1.0; 4]).unwrap();
+        assert_eq!(per_fu, IMPACTS);
+        assert_eq!(cumulative, [1.0, 3.0, 6.0, 10.0]);
+    }
+
+    #[test]
+    fn yields_outside_the_unit_interval_are_errors() {
+        assert_eq!(chain(&IMPACTS, &[0.5, 0.0]), Err(1));
+        assert_eq!(chain(&IMPACTS, &[1.1, 0.5]), Err(0));
+        assert_eq!(chain(&IMPACTS, &[0.5, f64::NAN]), Err(1));
+        assert_eq!(chain(&[1.0, 2.0, f64::INFINITY, 4.0], &[0.5, 0.5]), Err(1));
+    }
+}
@@ -0,0 +1,259 @@
+use std::ffi::c_double;
+
+/// Avoided impacts of each by-product, quantity × ratio × factor, as
+/// `n_byproducts` rows of `n_categories`.
+pub(super) fn displaced_impacts(quantities: &[f64], ratios: &[f64], factors: &[f64], n_categories: usize) -> Vec<f64> {
+    let mut displaced = vec![0.0; factors.len()];
+    for (((&quantity, &ratio), row), out) in quantities.iter().zip(ratios)
+        .zip(factors.chunks_exact(n_categories))
+        .zip(displaced.chunks_exact_mut(n_categories)) {
+        for (avoided, &factor) in out.iter_mut().zip(row) {
+            *avoided = quantity * ratio * factor;
+        }
+    }
+    displaced
+}
+
+/// Per-category sum of the rows of `displaced_impacts`.
+pub(super) fn sum_credits(displaced: &[f64], n_categories: usize) -> Vec<f64> {
+    let mut credits = vec![0.0; n_categories];
+    for row in displaced.chunks_exact(n_categories) {
+        for (credit, avoided) in credits.iter_mut().zip(row) {
+            *credit += avoided;
+        }
+    }
+    credits
+}
+
+/// Credits for by-products under system expansion (substitution).
+///
+/// Each by-product displaces `quantity × displacement_ratio` of a market
+/// product whose impacts per unit are given by its row of
+/// `avoided_impact_factors` (`n_byproducts` rows of `n_categories`,
+/// row-major). `credits_out` receives the avoided impact per category,
+/// summed over by-products, and `net_impact_inout`, which holds the main
+/// product's gross impacts on entry, receives gross minus credits.
+/// `net_negative_out[c]` is set when the credit exceeds the gross impact of
+/// category `c`, leaving a negative net impact that should be reported
+/// rather than clipped.
+///
+/// Returns false on null pointers, an empty dimension, non-finite values,
+/// or negative quantities or displacement ratios. Nothing is written on
+/// failure.
+#[no_mangle]
+pub extern "C" fn calculate_substitution_credits(
+    byproduct_quantities: *const c_double,
+    displacement_ratios: *const c_double,
+    avoided_impact_factors: *const c_double,
+    n_byproducts: usize,
+    n_categories: usize,
+    credits_out: *mut c_double,
+    net_impact_inout: *mut c_double,
+    net_negative_out: *mut bool
+) -> bool {
+    if byproduct_quantities.is_null() || displacement_ratios.is_null() || avoided_impact_factors.is_null()
+        || credits_out.is_null() || net_impact_inout.is_null() || net_negative_out.is_null()
+        || n_byproducts == 0 || n_categories == 0 {
+        return false;
+    }
+    let (quantities, ratios, factors, net_impacts) = unsafe {
+        (
+            std::slice::from_raw_parts(byproduct_quantities, n_byproducts),
+            std::slice::from_raw_parts(displacement_ratios, n_byproducts),
+            std::slice::from_raw_parts(avoided_impact_factors, n_byproducts * n_categories),
+            std::slice::from_raw_parts_mut(net_impact_inout, n_categories),
+        )
+    };
+    if quantities.iter().chain(ratios).chain(factors).chain(net_impacts.iter()).any(|x| !x.is_finite())
+        || quantities.iter().chain(ratios).any(|&x| x < 0.0) {
+        return false;
+    }
+
+    let credits = sum_credits(&displaced_impacts(quantities, ratios, factors, n_categories), n_categories);
+    let net_negative = unsafe { std::slice::from_raw_parts_mut(net_negative_out, n_categories) };
+    for ((net, flag), &credit) in net_impacts.iter_mut().zip(net_negative.iter_mut()).zip(&credits) {
+        *net -= credit;
+        *flag = *net < 0.0;
+    }
+    unsafe {
+        std::slice::from_raw_parts_mut(credits_out, n_categories).copy_from_slice(&credits);
+    }
+    true
+}
+
+/// By-products of wet and dry pea fractionation credited by
+/// `calculate_byproduct_credits`, the row order of its breakdown.
+pub const PEA_BYPRODUCT_COUNT: usize = 2;
+
+/// Displacement ratio used when the caller passes NaN.
+const DEFAULT_DISPLACEMENT_RATIO: f64 = 1.0;
+
+/// Avoided-burden credits for the starch and fiber by-products of pea
+/// protein production.
+///
+/// A NaN ratio means the default of 1 (one kg of by-product displaces one
+/// kg of the market product); ratios outside [0, 1] are clamped and set
+/// `ratio_clamped_out`. `starch_impact_factors` and `fiber_impact_factors`
+/// hold the `n_categories` impacts per kg of the displaced products.
+/// `credits_out` receives the per-category credits, identical to
+/// `calculate_substitution_credits` with the same (clamped) inputs, so
+/// they can be subtracted from gross impacts as its `net_impact_inout`
+/// does, and `breakdown_out` `PEA_BYPRODUCT_COUNT` rows of
+/// `n_categories`: starch, then fiber. Returns false on null pointers, no
+/// categories, negative or non-finite masses, or non-finite factors.
+#[no_mangle]
+pub extern "C" fn calculate_byproduct_credits(
+    starch_kg: c_double,
+    fiber_kg: c_double,
+    starch_substitution_ratio: c_double,
+    fiber_substitution_ratio: c_double,
+    starch_impact_factors: *const c_double,
+    fiber_impact_factors: *const c_double,
+    n_categories: usize,
+    credits_out: *mut c_double,
+    breakdown_out: *mut c_double,
+    ratio_clamped_out: *mut bool
+) -> bool {
+    if starch_impact_factors.is_null() || fiber_impact_factors.is_null() || credits_out.is_null()
+        || breakdown_out.is_null() || ratio_clamped_out.is_null() || n_categories == 0 {
+        return false;
+    }
+    let quantities = [starch_kg, fiber_kg];
+    if quantities.iter().any(|q| !q.is_finite() || *q < 0.0) {
+        return false;
+    }
+    let ratios = [starch_substitution_ratio, fiber_substitution_ratio];
+    if ratios.iter().any(|r| r.is_infinite()) {
+        return false;
+    }
+    let ratios = ratios.map(|r| if r.is_nan() { DEFAULT_DISPLACEMENT_RATIO } else { r });
+    let clamped = ratios.map(|r| r.clamp(0.0, 1.0));
+    let mut factors = Vec::with_capacity(PEA_BYPRODUCT_COUNT * n_categories);
+    unsafe {
+        factors.extend_from_slice(std::slice::from_raw_parts(starch_impact_factors, n_categories));
+        factors.extend_from_slice(std::slice::from_raw_parts(fiber_impact_factors, n_categories));
+    }
+    if factors.iter().any(|f| !f.is_finite()) {
+        return false;
+    }
+
+    let displaced = displaced_impacts(&quantities, &clamped, &factors, n_categories);
+    let credits = sum_credits(&displaced, n_categories);
+    unsafe {
+        std::slice::from_raw_parts_mut(credits_out, n_categories).copy_from_slice(&credits);
+        std::slice::from_raw_parts_mut(breakdown_out, displaced.len()).copy_from_slice(&displaced);
+        *ratio_clamped_out = clamped != ratios;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Per kg of concentrate: 2.2 kg coarse starch fraction and 0.5 kg fibre;
+    // factors are GWP (kg CO2e/kg) and water (m³/kg) of the displaced products
+    const QUANTITIES: [f64; 2] = [2.2, 0.5];
+    const FACTORS: [f64; 4] = [1.1, 0.02, 0.6, 0.0];
+    const GROSS: [f64; 2] = [3.5, 0.01];
+
+    fn substitute(ratios: &[f64], gross: [f64; 2]) -> Option<([f64; 2], [f64; 2], [bool; 2])> {
+        let (mut credits, mut net, mut negative) = ([f64::NAN; 2], gross, [false; 2]);
+        calculate_substitution_credits(
+            QUANTITIES.as_ptr(), ratios.as_ptr(), FACTORS.as_ptr(), 2, 2, credits.as_mut_ptr(), net.as_mut_ptr(), negative.as_mut_ptr(),
+        ).then_some((credits, net, negative))
+    }
+
+    #[test]
+    fn zero_displacement_changes_nothing() {
+        let (credits, net, negative) = substitute(&[0.0, 0.0], GROSS).unwrap();
+        assert_eq!(credits, [0.0; 2]);
+        assert_eq!(net, GROSS);
+        assert_eq!(negative, [false; 2]);
+    }
+
+    #[test]
+    fn hand_calculated_net_gwp_is_reproduced() {
+        let (credits, net, negative) = substitute(&[0.8, 0.5], GROSS).unwrap();
+        // 2.2 × 0.8 × 1.1 + 0.5 × 0.5 × 0.6 = 1.936 + 0.15
+        assert!((credits[0] - 2.086).abs() < 1e-12);
+        assert!((net[0] - 1.414).abs() < 1e-12);
+        // The water credit, 0.0352, outweighs the 0.01 gross and is flagged, not clipped
+        assert!((net[1] + 0.0252).abs() < 1e-12);
+        assert_eq!(negative, [false, true]);
+    }
+
+    #[test]
+    fn invalid_inputs_leave_the_impacts_untouched() {
+        for ratios in [[-0.1, 0.5], [f64::NAN, 0.5]] {
+            assert!(substitute(&ratios, GROSS).is_none());
+        }
+        assert!(substitute(&[0.8, 0.5], [f64::INFINITY, 0.0]).is_none());
+        let mut net = GROSS;
+        let (mut credits, mut negative) = ([0.0; 2], [false; 2]);
+        assert!(!calculate_substitution_credits(
+            [-1.0, 0.5].as_ptr(), [0.8, 0.5].as_ptr(), FACTORS.as_ptr(), 2, 2, credits.as_mut_ptr(), net.as_mut_ptr(), negative.as_mut_ptr(),
+        ));
+        assert!(!calculate_substitution_credits(
+            QUANTITIES.as_ptr(), [0.8, 0.5].as_ptr(), FACTORS.as_ptr(), 2, 0, credits.as_mut_ptr(), net.as_mut_ptr(), negative.as_mut_ptr(),
+        ));
+        assert_eq!(net, GROSS);
+    }
+
+    // Per batch: 120 kg starch and 45 kg fibre; GWP, water and energy per kg
+    // of the displaced market products
+    const STARCH_FACTORS: [f64; 3] = [0.8, 0.02, 1.1];
+    const FIBER_FACTORS: [f64; 3] = [0.5, 0.01, 0.3];
+
+    fn byproduct_credits(starch_ratio: f64, fiber_ratio: f64) -> Option<([f64; 3], [f64; PEA_BYPRODUCT_COUNT * 3], bool)> {
+        let (mut credits, mut breakdown, mut clamped) = ([f64::NAN; 3], [f64::NAN; PEA_BYPRODUCT_COUNT * 3], false);
+        calculate_byproduct_credits(
+            120.0, 45.0, starch_ratio, fiber_ratio, STARCH_FACTORS.as_ptr(), FIBER_FACTORS.as_ptr(), 3,
+            credits.as_mut_ptr(), breakdown.as_mut_ptr(), &mut clamped,
+        ).then_some((credits, breakdown, clamped))
+    }
+
+    fn generic_credits(ratios: [f64; 2]) -> [f64; 3] {
+        let factors: Vec<f64> = STARCH_FACTORS.iter().chain(&FIBER_FACTORS).copied().collect();
+        let (mut credits, mut net, mut negative) = ([f64::NAN; 3], [0.0; 3], [false; 3]);
+        assert!(calculate_substitution_credits(
+            [120.0, 45.0].as_ptr(), ratios.as_ptr(), factors.as_ptr(), 2, 3, credits.as_mut_ptr(), net.as_mut_ptr(), negative.as_mut_ptr(),
+        ));
+        credits
+    }
+
+    #[test]
+    fn byproduct_credits_agree_with_the_generic_substitution() {
+        for (starch, fiber) in [(0.9, 1.0), (0.0, 0.5), (1.0, 1.0), (0.35, 0.7)] {
+            let (credits, breakdown, clamped) = byproduct_credits(starch, fiber).unwrap();
+            assert_eq!(credits, generic_credits([starch, fiber]));
+            assert!(!clamped);
+            for (category, credit) in credits.iter().enumerate() {
+                assert_eq!(breakdown[category] + breakdown[3 + category], *credit);
+            }
+        }
+    }
+
+    #[test]
+    fn nan_ratio_defaults_to_full_displacement() {
+        let (credits, breakdown, clamped) = byproduct_credits(f64::NAN, 0.5).unwrap();
+        assert_eq!(credits, generic_credits([1.0, 0.5]));
+        assert_eq!(breakdown[0], 120.0 * 0.8);
+        assert!(!clamped);
+    }
+
+    #[test]
+    fn out_of_range_ratios_are_clamped_and_flagged() {
+        let (credits, breakdown, clamped) = byproduct_credits(1.4, -0.1).unwrap();
+        assert!(clamped);
+        assert_eq!(credits, generic_credits([1.0, 0.0]));
+        assert_eq!(breakdown[3..], [0.0; 3]);
+        assert!(byproduct_credits(f64::INFINITY, 0.5).is_none());
+        let mut credits = [0.0; 3];
+        let (mut breakdown, mut clamped) = ([0.0; 6], false);
+        assert!(!calculate_byproduct_credits(
+            -1.0, 45.0, 1.0, 1.0, STARCH_FACTORS.as_ptr(), FIBER_FACTORS.as_ptr(), 3,
+            credits.as_mut_ptr(), breakdown.as_mut_ptr(), &mut clamped,
+        ));
+    }
+}
@@ -0,0 +1,269 @@
+use std::ffi::c_double;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rand_distr::StandardNormal;
+use rayon::prelude::*;
+use crate::economic::monte_carlo::{mean_and_std, percentile_sorted};
+
+/// Values written by `run_environmental_monte_carlo`: mean, standard
+/// deviation, P5, P50 and P95 of the total impact.
+pub const ENVIRONMENTAL_MONTE_CARLO_RESULT_LEN: usize = 5;
+
+/// Whether a geometric standard deviation is usable: 0 (deterministic) or
+/// at least 1.
+fn valid_gsd(gsd: f64) -> bool {
+    gsd == 0.0 || (gsd.is_finite() && gsd >= 1.0)
+}
+
+/// Propagate characterization factor uncertainty to a total impact.
+///
+/// Each of the `len` inventory flows in `inventory_amounts` is multiplied
+/// by a factor drawn lognormally around `factor_means[i]`, taken as the
+/// median (geometric mean) as in ecoinvent-style data, with geometric
+/// standard deviation `factor_gsds[i]`: factor = median · GSD^z with z
+/// standard normal. A GSD of 0 or 1 keeps the factor deterministic.
+/// Iteration `i` uses an RNG seeded with `seed + i`, as in
+/// `run_economic_monte_carlo_v2`, so results do not depend on threading.
+///
+/// `results` receives `ENVIRONMENTAL_MONTE_CARLO_RESULT_LEN` values: the
+/// mean and population standard deviation of the total impact, then its
+/// P5, P50 and P95. Returns false on null pointers, `len` or `iterations`
+/// of 0, non-finite amounts or factors, or a GSD that is neither 0 nor at
+/// least 1.
+#[no_mangle]
+pub extern "C" fn run_environmental_monte_carlo(
+    inventory_amounts: *const c_double,
+    factor_means: *const c_double,
+    factor_gsds: *const c_double,
+    len: usize,
+    iterations: usize,
+    seed: u64,
+    results: *mut c_double
+) -> bool {
+    if inventory_amounts.is_null() || factor_means.is_null() || factor_gsds.is_null() || results.is_null()
+        || len == 0 || iterations == 0 {
+        return false;
+    }
+    let (amounts, medians, gsds) = unsafe {
+        (
+            std::slice::from_raw_parts(inventory_amounts, len),
+            std::slice::from_raw_parts(factor_means, len),
+            std::slice::from_raw_parts(factor_gsds, len),
+        )
+    };
+    if amounts.iter().chain(medians).any(|v| !v.is_finite()) || !gsds.iter().all(|&gsd| valid_gsd(gsd)) {
+        return false;
+    }
+
+    // ln(GSD) of the uncertain flows only, so deterministic flows draw nothing
+    let uncertain: Vec<(usize, f64)> = gsds.iter().enumerate()
+        .filter(|&(_, &gsd)| gsd > 1.0)
+        .map(|(i, gsd)| (i, gsd.ln()))
+        .collect();
+    let deterministic: f64 = (0..len)
+        .filter(|&i| gsds[i] <= 1.0)
+        .map(|i| amounts[i] * medians[i])
+        .sum();
+
+    let mut totals: Vec<f64> = (0..iterations).into_par_iter().map(|i| {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+        deterministic + uncertain.iter().map(|&(flow, sigma)| {
+            let z: f64 = rng.sample(StandardNormal);
+            amounts[flow] * medians[flow] * (sigma * z).exp()
+        }).sum::<f64>()
+    }).collect();
+
+    let (mean, std_dev) = mean_and_std(&totals);
+    totals.sort_by(f64::total_cmp);
+    unsafe {
+        std::slice::from_raw_parts_mut(results, ENVIRONMENTAL_MONTE_CARLO_RESULT_LEN).copy_from_slice(&[
+            mean,
+            std_dev,
+            percentile_sorted(&totals, 0.05),
+            percentile_sorted(&totals, 0.5),
+            percentile_sorted(&totals, 0.95),
+        ]);
+    }
+    true
+}
+
+/// Values written by `compare_systems_monte_carlo`: mean difference A - B,
+/// the lower and upper bounds of its 95% interval, and P(A < B).
+pub const SYSTEM_COMPARISON_RESULT_LEN: usize = 4;
+
+/// Characterization factors for one iteration: each median times GSD^z,
+/// drawing only for flows with a nonzero ln(GSD).
+fn draw_factors(rng: &mut StdRng, medians: &[f64], log_gsds: &[f64]) -> Vec<f64> {
+    medians.iter().zip(log_gsds).map(|(&median, &sigma)| {
+        if sigma == 0.0 {
+            median
+        } else {
+            let z: f64 = rng.sample(StandardNormal);
+            median * (sigma * z).exp()
+        }
+    }).collect()
+}
+
+fn total_impact(amounts: &[f64], factors: &[f64]) -> f64 {
+    amounts.iter().zip(factors).map(|(a, f)| a * f).sum()
+}
+
+/// Comparative Monte Carlo of two systems sharing characterization factors.
+///
+/// `inventory_a` and `inventory_b` hold the `len` flows of each system,
+/// characterized by factors drawn as in `run_environmental_monte_carlo`
+/// from `shared_factor_means` and `factor_gsds`. With `paired` set each
+/// iteration draws the factors once and applies them to both systems, so
+/// shared uncertainty cancels out of the difference; otherwise each system
+/// draws its own, for factors that are not actually shared. Iteration `i`
+/// is seeded with `seed + i`.
+///
+/// `results` receives `SYSTEM_COMPARISON_RESULT_LEN` values: the mean of
+/// impact A - impact B, its P2.5 and P97.5, and the probability that A has
+/// the lower impact, counting ties as one half so identical systems give
+/// 0.5. Returns false on null pointers, `len` or `iterations` of 0, or
+/// inputs rejected by `run_environmental_monte_carlo`.
+#[no_mangle]
+pub extern "C" fn compare_systems_monte_carlo(
+    inventory_a: *const c_double,
+    inventory_b: *const c_double,
+    shared_factor_means: *const c_double,
+    factor_gsds: *const c_double,
+    len: usize,
+    iterations: usize,
+    seed: u64,
+    paired: bool,
+    results: *mut c_double
+) -> bool {
+    if inventory_a.is_null() || inventory_b.is_null() || shared_factor_means.is_null() || factor_gsds.is_null()
+        || results.is_null() || len == 0 || iterations == 0 {
+        return false;
+    }
+    let (amounts_a, amounts_b, medians, gsds) = unsafe {
+        (
+            std::slice::from_raw_parts(inventory_a, len),
+            std::slice::from_raw_parts(inventory_b, len),
+            std::slice::from_raw_parts(shared_factor_means, len),
+            std::slice::from_raw_parts(factor_gsds, len),
+        )
+    };
+    if amounts_a.iter().chain(amounts_b).chain(medians).any(|v| !v.is_finite()) || !gsds.iter().all(|&gsd| valid_gsd(gsd)) {
+        return false;
+    }
+    let log_gsds: Vec<f64> = gsds.iter().map(|&gsd| if gsd > 1.0 { gsd.ln() } else { 0.0 }).collect();
+
+    let mut differences: Vec<f64> = (0..iterations).into_par_iter().map(|i| {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(i as u64));
+        let factors_a = draw_factors(&mut rng, medians, &log_gsds);
+        let impact_a = total_impact(amounts_a, &factors_a);
+        let impact_b = if paired {
+            total_impact(amounts_b, &factors_a)
+        } else {
+            total_impact(amounts_b, &draw_factors(&mut rng, medians, &log_gsds))
+        };
+        impact_a - impact_b
+    }).collect();
+
+    let (mean, _) = mean_and_std(&differences);
+    let a_lower = differences.iter()
+        .map(|&d| if d < 0.0 { 1.0 } else if d == 0.0 { 0.5 } else { 0.0 })
+        .sum::<f64>() / iterations as f64;
+    differences.sort_by(f64::total_cmp);
+    unsafe {
+        std::slice::from_raw_parts_mut(results, SYSTEM_COMPARISON_RESULT_LEN).copy_from_slice(&[
+            mean,
+            percentile_sorted(&differences, 0.025),
+            percentile_sorted(&differences, 0.975),
+            a_lower,
+        ]);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn monte_carlo(amounts: &[f64], medians: &[f64], gsds: &[f64], iterations: usize, seed: u64) -> Option<[f64; ENVIRONMENTAL_MONTE_CARLO_RESULT_LEN]> {
+        let mut results = [f64::NAN; ENVIRONMENTAL_MONTE_CARLO_RESULT_LEN];
+        run_environmental_monte_carlo(
+            amounts.as_ptr(), medians.as_ptr(), gsds.as_ptr(), amounts.len(), iterations, seed, results.as_mut_ptr(),
+        ).then_some(results)
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn deterministic_factors_give_the_dot_product() {
+        // GSD 0 and 1 both mean no uncertainty: 2 × 1.5 + 3 × 4
+        let results = monte_carlo(&[2.0, 3.0], &[1.5, 4.0], &[0.0, 1.0], 100, 7).unwrap();
+        assert_eq!(results, [15.0, 0.0, 15.0, 15.0, 15.0]);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fixed_seed_is_reproducible() {
+        let run = |seed| monte_carlo(&[2.0, 3.0], &[1.5, 4.0], &[1.5, 2.0], 5000, seed).unwrap();
+        assert_eq!(run(7), run(7));
+        assert_ne!(run(7), run(1_000_007));
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn single_flow_follows_the_lognormal_quantiles() {
+        // Median 3 × 4 = 12, P95 = 12 · GSD^1.645
+        let [_, _, p5, p50, p95] = monte_carlo(&[3.0], &[4.0], &[2.0], 50_000, 1).unwrap();
+        assert!((p50 / 12.0 - 1.0).abs() < 0.02, "{p50}");
+        assert!((p95 / (12.0 * 2f64.powf(1.645)) - 1.0).abs() < 0.03, "{p95}");
+        assert!((p5 / (12.0 / 2f64.powf(1.645)) - 1.0).abs() < 0.03, "{p5}");
+    }
+
+    #[test]
+    fn invalid_inputs_are_rejected() {
+        for gsd in [0.5, -1.0, f64::NAN, f64::INFINITY] {
+            assert!(monte_carlo(&[1.0], &[1.0], &[gsd], 10, 1).is_none());
+        }
+        assert!(monte_carlo(&[f64::NAN], &[1.0], &[0.0], 10, 1).is_none());
+        assert!(monte_carlo(&[1.0], &[1.0], &[0.0], 0, 1).is_none());
+    }
+
+    // Electricity (kWh) and a fertilizer input characterized with shared factors
+    const SHARED_MEDIANS: [f64; 2] = [1.0, 25.0];
+    const SHARED_GSDS: [f64; 2] = [1.5, 2.0];
+
+    fn compare(a: &[f64], b: &[f64], paired: bool, seed: u64) -> [f64; SYSTEM_COMPARISON_RESULT_LEN] {
+        let mut results = [f64::NAN; SYSTEM_COMPARISON_RESULT_LEN];
+        assert!(compare_systems_monte_carlo(
+            a.as_ptr(), b.as_ptr(), SHARED_MEDIANS.as_ptr(), SHARED_GSDS.as_ptr(), 2, 4000, seed, paired, results.as_mut_ptr(),
+        ));
+        results
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn identical_systems_are_a_coin_flip() {
+        assert_eq!(compare(&[10.0, 1.0], &[10.0, 1.0], true, 3), [0.0, 0.0, 0.0, 0.5]);
+        let [mean, lower, upper, a_lower] = compare(&[10.0, 1.0], &[10.0, 1.0], false, 3);
+        assert!((a_lower - 0.5).abs() < 0.05, "{a_lower}");
+        assert!(lower < mean && mean < upper);
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn clearly_better_system_wins_every_paired_draw() {
+        // A uses 10% less of every flow, so it is lower whatever the factors
+        let [mean, lower, upper, a_lower] = compare(&[9.0, 0.9], &[10.0, 1.0], true, 3);
+        assert_eq!(a_lower, 1.0);
+        assert!(mean < 0.0 && upper < 0.0 && lower <= upper);
+        // Unpaired draws let factor noise swamp the 10% difference
+        let unpaired = compare(&[9.0, 0.9], &[10.0, 1.0], false, 3)[3];
+        assert!(0.5 < unpaired && unpaired < 0.9, "{unpaired}");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn comparison_is_reproducible_for_a_seed() {
+        for paired in [true, false] {
+            assert_eq!(compare(&[9.0, 0.9], &[10.0, 1.0], paired, 3), compare(&[9.0, 0.9], &[10.0, 1.0], paired, 3));
+        }
+        assert_ne!(compare(&[9.0, 0.9], &[10.0, 1.0], false, 3), compare(&[9.0, 0.9], &[10.0, 1.0], false, 1_000_003));
+    }
+}
@@ -1,8 +1,13 @@
 // Main library file
 
+// Every exported function is an `extern "C"` entry point that takes raw
+// pointers from the Python bindings and validates them itself.
+#![allow(clippy::not_unsafe_ptr_arg_deref)]
+
 pub mod economic;
 pub mod protein_analysis;
 pub mod environmental;
+mod matrix_ops;
 
 pub use economic::*;
 pub use protein_analysis::*;
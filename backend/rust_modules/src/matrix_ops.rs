@@ -0,0 +1,217 @@
+// Small dense linear algebra helpers shared by the analysis modules.
+// Matrices are row-major slices of length n * n, or rows * cols where noted.
+
+/// Diagonal entries of R below this, relative to unit-norm columns, mark a
+/// rank-deficient least-squares problem.
+const RANK_TOLERANCE: f64 = 1e-10;
+/// Sweep limit for the Jacobi eigenvalue iteration, which normally
+/// converges in well under ten sweeps.
+const MAX_JACOBI_SWEEPS: usize = 100;
+
+/// Cholesky factorization of a symmetric positive-definite matrix.
+///
+/// Returns the lower-triangular factor L (row-major, zeros above the
+/// diagonal) with `matrix = L * L^T`, or None when the matrix is not
+/// positive definite.
+pub(crate) fn cholesky(matrix: &[f64], n: usize) -> Option<Vec<f64>> {
+    if matrix.len() != n * n {
+        return None;
+    }
+
+    let mut lower = vec![0.0; n * n];
+    for i in 0..n {
+        for j in 0..=i {
+            let dot: f64 = (0..j).map(|k| lower[i * n + k] * lower[j * n + k]).sum();
+            if i == j {
+                let diagonal = matrix[i * n + i] - dot;
+                if diagonal <= 0.0 || !diagonal.is_finite() {
+                    return None;
+                }
+                lower[i * n + j] = diagonal.sqrt();
+            } else {
+                lower[i * n + j] = (matrix[i * n + j] - dot) / lower[j * n + j];
+            }
+        }
+    }
+    Some(lower)
+}
+
+/// Multiply a lower-triangular matrix by a vector.
+pub(crate) fn lower_mul_vec(lower: &[f64], x: &[f64], n: usize) -> Vec<f64> {
+    (0..n)
+        .map(|i| (0..=i).map(|k| lower[i * n + k] * x[k]).sum())
+        .collect()
+}
+
+/// Solve `matrix * x = rhs` for a symmetric positive-definite matrix via
+/// its Cholesky factor. None when the matrix is not positive definite.
+pub(crate) fn cholesky_solve(matrix: &[f64], rhs: &[f64], n: usize) -> Option<Vec<f64>> {
+    if rhs.len() != n {
+        return None;
+    }
+    let lower = cholesky(matrix, n)?;
+
+    // Forward substitution for L y = rhs, then back substitution for L^T x = y
+    let mut y = vec![0.0; n];
+    for i in 0..n {
+        let dot: f64 = (0..i).map(|k| lower[i * n + k] * y[k]).sum();
+        y[i] = (rhs[i] - dot) / lower[i * n + i];
+    }
+    let mut x = vec![0.0; n];
+    for i in (0..n).rev() {
+        let dot: f64 = (i + 1..n).map(|k| lower[k * n + i] * x[k]).sum();
+        x[i] = (y[i] - dot) / lower[i * n + i];
+    }
+    Some(x)
+}
+
+/// Least-squares solution of `design * x ≈ rhs` for a row-major
+/// `rows x cols` design matrix, by Householder QR.
+///
+/// Columns are scaled to unit norm before factorizing so the rank test does
+/// not depend on the units of each column. None when there are fewer rows
+/// than columns, a column is zero, or the design is rank deficient.
+pub(crate) fn least_squares(design: &[f64], rhs: &[f64], rows: usize, cols: usize) -> Option<Vec<f64>> {
+    if design.len() != rows * cols || rhs.len() != rows || rows < cols {
+        return None;
+    }
+    let mut a = design.to_vec();
+    let mut b = rhs.to_vec();
+    let mut scales = vec![0.0; cols];
+    for (j, scale) in scales.iter_mut().enumerate() {
+        *scale = (0..rows).map(|i| a[i * cols + j].powi(2)).sum::<f64>().sqrt();
+        if *scale == 0.0 || !scale.is_finite() {
+            return None;
+        }
+        for i in 0..rows {
+            a[i * cols + j] /= *scale;
+        }
+    }
+
+    // Reduce column k below the diagonal with the reflection I - 2vvᵀ/vᵀv
+    for k in 0..cols {
+        let norm = (k..rows).map(|i| a[i * cols + k].powi(2)).sum::<f64>().sqrt();
+        if norm < RANK_TOLERANCE {
+            return None;
+        }
+        let alpha = if a[k * cols + k] > 0.0 { -norm } else { norm };
+        let mut v: Vec<f64> = (k..rows).map(|i| a[i * cols + k]).collect();
+        v[0] -= alpha;
+        let v_norm2: f64 = v.iter().map(|x| x * x).sum();
+        for j in k..cols {
+            let dot: f64 = (k..rows).map(|i| v[i - k] * a[i * cols + j]).sum();
+            let factor = 2.0 * dot / v_norm2;
+            for i in k..rows {
+                a[i * cols + j] -= factor * v[i - k];
+            }
+        }
+        let dot: f64 = (k..rows).map(|i| v[i - k] * b[i]).sum();
+        let factor = 2.0 * dot / v_norm2;
+        for i in k..rows {
+            b[i] -= factor * v[i - k];
+        }
+    }
+
+    // Back substitution for R x = Qᵀ rhs, then undo the column scaling
+    let mut x = vec![0.0; cols];
+    for i in (0..cols).rev() {
+        let dot: f64 = (i + 1..cols).map(|k| a[i * cols + k] * x[k]).sum();
+        x[i] = (b[i] - dot) / a[i * cols + i];
+    }
+    for (value, scale) in x.iter_mut().zip(&scales) {
+        *value /= scale;
+    }
+    Some(x)
+}
+
+/// Eigenvalues of a symmetric matrix in ascending order, by cyclic Jacobi
+/// rotations.
+pub(crate) fn symmetric_eigenvalues(matrix: &[f64], n: usize) -> Vec<f64> {
+    let mut a = matrix.to_vec();
+    for _ in 0..MAX_JACOBI_SWEEPS {
+        let off_diagonal: f64 = (0..n).flat_map(|i| (0..n).filter(move |&j| j != i).map(move |j| (i, j)))
+            .map(|(i, j)| a[i * n + j].powi(2))
+            .sum();
+        let total: f64 = a.iter().map(|x| x * x).sum();
+        if off_diagonal <= f64::EPSILON * f64::EPSILON * total {
+            break;
+        }
+        for p in 0..n {
+            for q in p + 1..n {
+                let apq = a[p * n + q];
+                if apq == 0.0 {
+                    continue;
+                }
+                // Rotation angle zeroing a[p][q]
+                let theta = (a[q * n + q] - a[p * n + p]) / (2.0 * apq);
+                let t = theta.signum() / (theta.abs() + (theta * theta + 1.0).sqrt());
+                let c = 1.0 / (t * t + 1.0).sqrt();
+                let s = t * c;
+                for k in 0..n {
+                    let (akp, akq) = (a[k * n + p], a[k * n + q]);
+                    a[k * n + p] = c * akp - s * akq;
+                    a[k * n + q] = s * akp + c * akq;
+                }
+                for k in 0..n {
+                    let (apk, aqk) = (a[p * n + k], a[q * n + k]);
+                    a[p * n + k] = c * apk - s * aqk;
+                    a[q * n + k] = s * apk + c * aqk;
+                }
+            }
+        }
+    }
+    let mut eigenvalues: Vec<f64> = (0..n).map(|i| a[i * n + i]).collect();
+    eigenvalues.sort_by(f64::total_cmp);
+    eigenvalues
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cholesky_factor_reproduces_the_matrix() {
+        let matrix = [4.0, 2.0, 0.4, 2.0, 2.0, 0.5, 0.4, 0.5, 3.0];
+        let lower = cholesky(&matrix, 3).unwrap();
+        assert_eq!([lower[1], lower[2], lower[5]], [0.0; 3]);
+        for i in 0..3 {
+            for j in 0..3 {
+                let product: f64 = (0..3).map(|k| lower[i * 3 + k] * lower[j * 3 + k]).sum();
+                assert!((product - matrix[i * 3 + j]).abs() < 1e-12);
+            }
+        }
+        assert_eq!(lower_mul_vec(&lower, &[1.0, 0.0, 0.0], 3), vec![lower[0], lower[3], lower[6]]);
+    }
+
+    #[test]
+    fn indefinite_matrices_have_no_factor() {
+        assert!(cholesky(&[1.0, 2.0, 2.0, 1.0], 2).is_none());
+        assert!(cholesky(&[1.0, 0.0, 0.0], 2).is_none());
+    }
+
+    #[test]
+    fn least_squares_fits_an_overdetermined_line() {
+        // y = 1 + 2x with residuals that cancel in the normal equations
+        let design = [1.0, 0.0, 1.0, 1.0, 1.0, 2.0, 1.0, 3.0];
+        let rhs = [1.1, 2.9, 5.1, 6.9];
+        let x = least_squares(&design, &rhs, 4, 2).unwrap();
+        assert!((x[0] - 1.06).abs() < 1e-12 && (x[1] - 1.96).abs() < 1e-12, "{x:?}");
+    }
+
+    #[test]
+    fn rank_deficient_designs_have_no_solution() {
+        assert!(least_squares(&[1.0, 2.0, 2.0, 4.0, 3.0, 6.0], &[1.0, 2.0, 3.0], 3, 2).is_none());
+        assert!(least_squares(&[1.0, 0.0, 1.0, 0.0], &[1.0, 2.0], 2, 2).is_none());
+        assert!(least_squares(&[1.0, 2.0], &[1.0], 1, 2).is_none());
+    }
+
+    #[test]
+    fn eigenvalues_of_known_matrices() {
+        assert_eq!(symmetric_eigenvalues(&[0.0, 1.0, 1.0, 0.0], 2).iter().map(|e| e.round()).collect::<Vec<_>>(), [-1.0, 1.0]);
+        let expected = [2.0 - 2f64.sqrt(), 2.0, 2.0 + 2f64.sqrt()];
+        let eigenvalues = symmetric_eigenvalues(&[2.0, -1.0, 0.0, -1.0, 2.0, -1.0, 0.0, -1.0, 2.0], 3);
+        for (value, expected) in eigenvalues.iter().zip(expected) {
+            assert!((value - expected).abs() < 1e-12, "{eigenvalues:?}");
+        }
+    }
+}
@@ -0,0 +1,152 @@
+use std::ffi::c_double;
+
+/// Indispensable amino acids scored by `calculate_amino_acid_score`, in
+/// the order contents are passed:
+///
+/// | index | amino acid |
+/// |-------|------------|
+/// | 0     | histidine |
+/// | 1     | isoleucine |
+/// | 2     | leucine |
+/// | 3     | lysine |
+/// | 4     | methionine + cysteine |
+/// | 5     | phenylalanine + tyrosine |
+/// | 6     | threonine |
+/// | 7     | tryptophan |
+/// | 8     | valine |
+pub const AMINO_ACID_COUNT: usize = 9;
+
+/// FAO/WHO (1991) scoring pattern for preschool children, in mg/g protein,
+/// the reference used for PDCAAS.
+const FAO_REFERENCE_PATTERN: [f64; AMINO_ACID_COUNT] = [19.0, 28.0, 66.0, 58.0, 25.0, 63.0, 34.0, 11.0, 35.0];
+
+/// Amino acid score of a protein against a reference pattern.
+///
+/// `contents` holds `AMINO_ACID_COUNT` contents in mg/g protein, in the
+/// order documented on `AMINO_ACID_COUNT`. `reference` is a custom pattern
+/// of `reference_len` values in the same order, or null for the FAO/WHO
+/// pattern. `ratios_out` receives content / reference per amino acid,
+/// `score_out` the lowest ratio and `limiting_index_out` its index.
+///
+/// Returns false on null pointers, a length other than `AMINO_ACID_COUNT`,
+/// negative or NaN contents, or a non-positive reference value.
+#[no_mangle]
+pub extern "C" fn calculate_amino_acid_score(
+    contents: *const c_double,
+    len: usize,
+    reference: *const c_double,
+    reference_len: usize,
+    ratios_out: *mut c_double,
+    score_out: *mut c_double,
+    limiting_index_out: *mut usize
+) -> bool {
+    if contents.is_null() || ratios_out.is_null() || score_out.is_null() || limiting_index_out.is_null()
+        || len != AMINO_ACID_COUNT {
+        return false;
+    }
+    let contents = unsafe { std::slice::from_raw_parts(contents, len) };
+    let reference = if reference.is_null() {
+        &FAO_REFERENCE_PATTERN[..]
+    } else if reference_len == len {
+        unsafe { std::slice::from_raw_parts(reference, reference_len) }
+    } else {
+        return false;
+    };
+    if contents.iter().any(|c| c.is_nan() || *c < 0.0) || reference.iter().any(|r| r.is_nan() || *r <= 0.0) {
+        return false;
+    }
+
+    let ratios_out = unsafe { std::slice::from_raw_parts_mut(ratios_out, len) };
+    for (ratio, (content, reference)) in ratios_out.iter_mut().zip(contents.iter().zip(reference)) {
+        *ratio = content / reference;
+    }
+    let (limiting, score) = ratios_out.iter().copied().enumerate()
+        .fold((0, f64::INFINITY), |lowest, (i, ratio)| if ratio < lowest.1 { (i, ratio) } else { lowest });
+    unsafe {
+        *score_out = score;
+        *limiting_index_out = limiting;
+    }
+    true
+}
+
+/// Protein digestibility-corrected amino acid score: amino acid score times
+/// true fecal digestibility, truncated at 1.0. `uncapped_out` receives the
+/// value before truncation. Returns false on null pointers, a negative or
+/// NaN score, or a digestibility outside (0, 1].
+#[no_mangle]
+pub extern "C" fn calculate_pdcaas(
+    amino_acid_score: c_double,
+    digestibility: c_double,
+    pdcaas_out: *mut c_double,
+    uncapped_out: *mut c_double
+) -> bool {
+    if pdcaas_out.is_null() || uncapped_out.is_null() || amino_acid_score.is_nan() || amino_acid_score < 0.0
+        || digestibility.is_nan() || digestibility <= 0.0 || digestibility > 1.0 {
+        return false;
+    }
+    let uncapped = amino_acid_score * digestibility;
+    unsafe {
+        *pdcaas_out = uncapped.min(1.0);
+        *uncapped_out = uncapped;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A typical pea protein isolate, mg/g protein
+    const PEA_ISOLATE: [f64; AMINO_ACID_COUNT] = [25.0, 45.0, 82.0, 72.0, 21.0, 91.0, 37.0, 10.0, 50.0];
+
+    fn score(contents: &[f64], reference: Option<&[f64]>) -> Option<([f64; AMINO_ACID_COUNT], f64, usize)> {
+        let (mut ratios, mut score, mut limiting) = ([f64::NAN; AMINO_ACID_COUNT], f64::NAN, usize::MAX);
+        let (reference, reference_len) = reference.map_or((std::ptr::null(), 0), |pattern| (pattern.as_ptr(), pattern.len()));
+        calculate_amino_acid_score(contents.as_ptr(), contents.len(), reference, reference_len, ratios.as_mut_ptr(), &mut score, &mut limiting)
+            .then_some((ratios, score, limiting))
+    }
+
+    #[test]
+    fn sulphur_amino_acids_limit_pea_protein() {
+        let (ratios, score, limiting) = score(&PEA_ISOLATE, None).unwrap();
+        assert_eq!(limiting, 4);
+        assert!((score - 21.0 / 25.0).abs() < 1e-12);
+        assert!((ratios[3] - 72.0 / 58.0).abs() < 1e-12);
+
+        let (mut pdcaas, mut uncapped) = (f64::NAN, f64::NAN);
+        assert!(calculate_pdcaas(score, 0.98, &mut pdcaas, &mut uncapped));
+        assert!((pdcaas - 0.84 * 0.98).abs() < 1e-12);
+        assert_eq!(pdcaas, uncapped);
+    }
+
+    #[test]
+    fn custom_reference_pattern_changes_the_limiting_amino_acid() {
+        let mut pattern = FAO_REFERENCE_PATTERN;
+        pattern[7] = 20.0;
+        let (_, lowest, limiting) = score(&PEA_ISOLATE, Some(&pattern)).unwrap();
+        assert_eq!((limiting, lowest), (7, 0.5));
+        assert!(score(&PEA_ISOLATE, Some(&pattern[..8])).is_none());
+        pattern[0] = 0.0;
+        assert!(score(&PEA_ISOLATE, Some(&pattern)).is_none());
+    }
+
+    #[test]
+    fn pdcaas_is_truncated_at_one() {
+        let (mut pdcaas, mut uncapped) = (f64::NAN, f64::NAN);
+        assert!(calculate_pdcaas(1.2, 0.95, &mut pdcaas, &mut uncapped));
+        assert_eq!(pdcaas, 1.0);
+        assert!((uncapped - 1.14).abs() < 1e-12);
+        for digestibility in [0.0, 1.01, f64::NAN] {
+            assert!(!calculate_pdcaas(0.84, digestibility, &mut pdcaas, &mut uncapped));
+        }
+        assert!(!calculate_pdcaas(-0.1, 0.9, &mut pdcaas, &mut uncapped));
+    }
+
+    #[test]
+    fn mismatched_lengths_and_negative_contents_are_rejected() {
+        assert!(score(&PEA_ISOLATE[..8], None).is_none());
+        let mut negative = PEA_ISOLATE;
+        negative[2] = -1.0;
+        assert!(score(&negative, None).is_none());
+    }
+}
@@ -0,0 +1,289 @@
+use std::ffi::c_double;
+use super::protein_calculator::SizeBins;
+
+/// Values written per size class by `compute_grade_efficiency`: class
+/// size and fraction of the feed in that class reporting to coarse.
+pub const GRADE_EFFICIENCY_WIDTH: usize = 2;
+
+/// Classes holding less than this share of the feed are left out of the
+/// grade efficiency curve rather than dividing two near-zero fractions.
+const NEGLIGIBLE_CLASS_FRACTION: f64 = 1e-6;
+
+/// Size at which a curve of (size, value) points, NaN entries skipped,
+/// first rises through `level`, interpolated linearly. NaN when the curve
+/// never crosses it from below.
+fn crossing(points: &[(f64, f64)], level: f64) -> f64 {
+    points.iter()
+        .filter(|(_, value)| !value.is_nan())
+        .collect::<Vec<_>>()
+        .windows(2)
+        .find(|pair| pair[0].1 < level && pair[1].1 >= level)
+        .map_or(f64::NAN, |pair| {
+            let ((x0, y0), (x1, y1)) = (*pair[0], *pair[1]);
+            x0 + (x1 - x0) * (level - y0) / (y1 - y0)
+        })
+}
+
+/// Grade efficiency (Tromp) curve of a classifier from feed and coarse
+/// PSDs.
+///
+/// Both mass-weighted PSDs are binned onto `n_bins` common classes as in
+/// `build_psd_histogram`, the range defaulting to span both samples. For
+/// each class the recovery to coarse is
+/// `coarse_split * coarse_fraction / feed_fraction`, where `coarse_split`
+/// is the coarse mass over the feed mass and the fractions are each
+/// sample's share of its own total weight. `curve_out` holds `n_bins` rows
+/// of `GRADE_EFFICIENCY_WIDTH` values; classes with negligible feed weight
+/// get a NaN recovery. `d50c_out` receives the cut size where the curve
+/// rises through 0.5 and `sharpness_out` d75c / d25c; either is NaN when
+/// the curve does not cross the levels involved.
+///
+/// Returns false on null pointers, an empty sample, invalid or
+/// non-binnable input, a coarse split outside (0, 1], or an empty range.
+#[no_mangle]
+pub extern "C" fn compute_grade_efficiency(
+    feed_sizes: *const f64,
+    feed_weights: *const f64,
+    len_f: usize,
+    coarse_sizes: *const f64,
+    coarse_weights: *const f64,
+    len_c: usize,
+    coarse_split: c_double,
+    n_bins: usize,
+    log_spaced: bool,
+    min_override: c_double,
+    max_override: c_double,
+    curve_out: *mut c_double,
+    d50c_out: *mut c_double,
+    sharpness_out: *mut c_double
+) -> bool {
+    if feed_sizes.is_null() || feed_weights.is_null() || coarse_sizes.is_null() || coarse_weights.is_null()
+        || curve_out.is_null() || d50c_out.is_null() || sharpness_out.is_null() || len_f == 0 || len_c == 0 {
+        return false;
+    }
+    if coarse_split.is_nan() || coarse_split <= 0.0 || coarse_split > 1.0 {
+        return false;
+    }
+    let (feed_sizes, feed_weights, coarse_sizes, coarse_weights) = unsafe {
+        (
+            std::slice::from_raw_parts(feed_sizes, len_f),
+            std::slice::from_raw_parts(feed_weights, len_f),
+            std::slice::from_raw_parts(coarse_sizes, len_c),
+            std::slice::from_raw_parts(coarse_weights, len_c),
+        )
+    };
+    if !SizeBins::accepts(feed_sizes, feed_weights, log_spaced)
+        || !SizeBins::accepts(coarse_sizes, coarse_weights, log_spaced) {
+        return false;
+    }
+    let bins = match SizeBins::new(&[feed_sizes, coarse_sizes], n_bins, log_spaced, min_override, max_override) {
+        Some(bins) => bins,
+        None => return false,
+    };
+    let (feed, feed_total, _) = bins.histogram(feed_sizes, feed_weights);
+    let (coarse, coarse_total, _) = bins.histogram(coarse_sizes, coarse_weights);
+    if feed_total <= 0.0 || coarse_total <= 0.0 {
+        return false;
+    }
+
+    let curve: Vec<(f64, f64)> = feed.iter().zip(&coarse).enumerate().map(|(i, (&f, &c))| {
+        let (feed_fraction, coarse_fraction) = (f / feed_total, c / coarse_total);
+        let recovery = if feed_fraction > NEGLIGIBLE_CLASS_FRACTION {
+            coarse_split * coarse_fraction / feed_fraction
+        } else {
+            f64::NAN
+        };
+        (bins.centre(i), recovery)
+    }).collect();
+
+    let curve_out = unsafe { std::slice::from_raw_parts_mut(curve_out, n_bins * GRADE_EFFICIENCY_WIDTH) };
+    for (row, &(size, recovery)) in curve_out.chunks_exact_mut(GRADE_EFFICIENCY_WIDTH).zip(&curve) {
+        row.copy_from_slice(&[size, recovery]);
+    }
+    unsafe {
+        *d50c_out = crossing(&curve, 0.5);
+        *sharpness_out = crossing(&curve, 0.75) / crossing(&curve, 0.25);
+    }
+    true
+}
+
+/// Effective cut size of a classification step and the consistency of its
+/// feed, fine and coarse PSDs.
+///
+/// The three mass-weighted PSDs are binned onto `n_bins` common classes
+/// spanning all samples, each as a share of its own total weight. The feed
+/// is reconstructed as `fine_yield * fine + (1 - fine_yield) * coarse`,
+/// with `fine_yield` the fine mass over the feed mass, and
+/// `inconsistency_out` receives the sum over classes of the squared
+/// difference between measured and reconstructed feed fractions; values
+/// well above zero point at inconsistent lab data or a wrong yield.
+/// `cut_size_out` receives the size where the reconstructed recovery to
+/// coarse rises through 0.5, i.e. where both streams take equal amounts,
+/// or NaN when it never does.
+///
+/// Returns false on null pointers, an empty sample, invalid or
+/// non-binnable input, a fine yield outside (0, 1), or an empty range.
+#[no_mangle]
+pub extern "C" fn estimate_cut_size(
+    feed_sizes: *const f64,
+    feed_weights: *const f64,
+    len_feed: usize,
+    fine_sizes: *const f64,
+    fine_weights: *const f64,
+    len_fine: usize,
+    coarse_sizes: *const f64,
+    coarse_weights: *const f64,
+    len_coarse: usize,
+    fine_yield: c_double,
+    n_bins: usize,
+    log_spaced: bool,
+    cut_size_out: *mut c_double,
+    inconsistency_out: *mut c_double
+) -> bool {
+    if feed_sizes.is_null() || feed_weights.is_null() || fine_sizes.is_null() || fine_weights.is_null()
+        || coarse_sizes.is_null() || coarse_weights.is_null() || cut_size_out.is_null()
+        || inconsistency_out.is_null() || len_feed == 0 || len_fine == 0 || len_coarse == 0 {
+        return false;
+    }
+    if fine_yield.is_nan() || fine_yield <= 0.0 || fine_yield >= 1.0 {
+        return false;
+    }
+    let samples = unsafe {
+        [
+            (std::slice::from_raw_parts(feed_sizes, len_feed), std::slice::from_raw_parts(feed_weights, len_feed)),
+            (std::slice::from_raw_parts(fine_sizes, len_fine), std::slice::from_raw_parts(fine_weights, len_fine)),
+            (std::slice::from_raw_parts(coarse_sizes, len_coarse), std::slice::from_raw_parts(coarse_weights, len_coarse)),
+        ]
+    };
+    if samples.iter().any(|(sizes, weights)| !SizeBins::accepts(sizes, weights, log_spaced)) {
+        return false;
+    }
+    let all_sizes: Vec<&[f64]> = samples.iter().map(|(sizes, _)| *sizes).collect();
+    let bins = match SizeBins::new(&all_sizes, n_bins, log_spaced, f64::NAN, f64::NAN) {
+        Some(bins) => bins,
+        None => return false,
+    };
+    let mut fractions = Vec::with_capacity(samples.len());
+    for (sizes, weights) in samples {
+        let (counts, total, _) = bins.histogram(sizes, weights);
+        if total <= 0.0 {
+            return false;
+        }
+        fractions.push(counts.into_iter().map(|count| count / total).collect::<Vec<f64>>());
+    }
+    let (feed, fine, coarse) = (&fractions[0], &fractions[1], &fractions[2]);
+
+    let mut inconsistency = 0.0;
+    let mut curve = Vec::with_capacity(n_bins);
+    for i in 0..n_bins {
+        let (to_fine, to_coarse) = (fine_yield * fine[i], (1.0 - fine_yield) * coarse[i]);
+        let reconstructed = to_fine + to_coarse;
+        inconsistency += (feed[i] - reconstructed).powi(2);
+        let recovery = if reconstructed > NEGLIGIBLE_CLASS_FRACTION { to_coarse / reconstructed } else { f64::NAN };
+        curve.push((bins.centre(i), recovery));
+    }
+
+    unsafe {
+        *cut_size_out = crossing(&curve, 0.5);
+        *inconsistency_out = inconsistency;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use statrs::distribution::{ContinuousCDF, Normal};
+
+    const N_BINS: usize = 30;
+
+    /// Log-normal feed around 15 um and the coarse weights an S-shaped
+    /// Tromp curve T(d) = 1 / (1 + (18 / d)^3) sends to coarse
+    fn tromp_split() -> (Vec<f64>, Vec<f64>, f64) {
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        let n = 4000;
+        let sizes: Vec<f64> = (0..n).map(|i| (15.0_f64.ln() + 0.7 * standard.inverse_cdf((i as f64 + 0.5) / n as f64)).exp()).collect();
+        let coarse: Vec<f64> = sizes.iter().map(|size| 1.0 / (1.0 + (18.0 / size).powi(3))).collect();
+        let split = coarse.iter().sum::<f64>() / n as f64;
+        (sizes, coarse, split)
+    }
+
+    fn grade_efficiency(min: f64, max: f64) -> (Vec<f64>, f64, f64) {
+        let (sizes, coarse, split) = tromp_split();
+        let feed = vec![1.0; sizes.len()];
+        let mut curve = vec![0.0; N_BINS * GRADE_EFFICIENCY_WIDTH];
+        let (mut d50c, mut sharpness) = (f64::NAN, f64::NAN);
+        assert!(compute_grade_efficiency(
+            sizes.as_ptr(), feed.as_ptr(), sizes.len(), sizes.as_ptr(), coarse.as_ptr(), sizes.len(), split,
+            N_BINS, true, min, max, curve.as_mut_ptr(), &mut d50c, &mut sharpness
+        ));
+        (curve, d50c, sharpness)
+    }
+
+    #[test]
+    fn tromp_cut_size_is_recovered_within_a_bin() {
+        let (sizes, _, _) = tromp_split();
+        let bin_ratio = (sizes[sizes.len() - 1] / sizes[0]).powf(1.0 / N_BINS as f64);
+        let (curve, d50c, sharpness) = grade_efficiency(f64::NAN, f64::NAN);
+        assert!((d50c / 18.0).ln().abs() < bin_ratio.ln(), "{d50c}");
+        // d75c / d25c = 3^(2/3) for an exponent of 3
+        assert!((sharpness / 3.0_f64.powf(2.0 / 3.0) - 1.0).abs() < 0.1, "{sharpness}");
+        let recoveries: Vec<f64> = curve.chunks(GRADE_EFFICIENCY_WIDTH).map(|row| row[1]).filter(|r| !r.is_nan()).collect();
+        assert!(recoveries.first().unwrap() < &0.1 && recoveries.last().unwrap() > &0.9);
+    }
+
+    #[test]
+    fn empty_classes_are_skipped() {
+        // Half of the classes lie above every particle
+        let (curve, d50c, _) = grade_efficiency(1.0, 1000.0);
+        let rows: Vec<&[f64]> = curve.chunks(GRADE_EFFICIENCY_WIDTH).collect();
+        assert!(rows[N_BINS - 1][1].is_nan());
+        assert!(rows.iter().any(|row| row[1].is_finite()));
+        assert!((d50c / 18.0 - 1.0).abs() < 0.3);
+    }
+
+    #[test]
+    fn grade_efficiency_rejects_an_invalid_split() {
+        let (sizes, coarse, _) = tromp_split();
+        let feed = vec![1.0; sizes.len()];
+        let mut curve = vec![0.0; N_BINS * GRADE_EFFICIENCY_WIDTH];
+        let (mut d50c, mut sharpness) = (f64::NAN, f64::NAN);
+        for split in [0.0, 1.5, f64::NAN] {
+            assert!(!compute_grade_efficiency(
+                sizes.as_ptr(), feed.as_ptr(), sizes.len(), sizes.as_ptr(), coarse.as_ptr(), sizes.len(), split,
+                N_BINS, true, f64::NAN, f64::NAN, curve.as_mut_ptr(), &mut d50c, &mut sharpness
+            ));
+        }
+    }
+
+    fn cut_size(fine_yield: f64) -> Option<(f64, f64)> {
+        let (sizes, coarse, _) = tromp_split();
+        let feed = vec![1.0; sizes.len()];
+        let fine: Vec<f64> = coarse.iter().map(|to_coarse| 1.0 - to_coarse).collect();
+        let (mut cut, mut inconsistency) = (f64::NAN, f64::NAN);
+        estimate_cut_size(
+            sizes.as_ptr(), feed.as_ptr(), sizes.len(), sizes.as_ptr(), fine.as_ptr(), sizes.len(),
+            sizes.as_ptr(), coarse.as_ptr(), sizes.len(), fine_yield, N_BINS, true, &mut cut, &mut inconsistency
+        ).then_some((cut, inconsistency))
+    }
+
+    #[test]
+    fn consistent_streams_reconstruct_the_feed() {
+        let (_, _, split) = tromp_split();
+        let (cut, inconsistency) = cut_size(1.0 - split).unwrap();
+        assert!(inconsistency < 1e-20, "{inconsistency}");
+        assert!((cut / 18.0 - 1.0).abs() < 0.1, "{cut}");
+    }
+
+    #[test]
+    fn wrong_yield_is_flagged_as_inconsistent() {
+        let (_, _, split) = tromp_split();
+        let (_, consistent) = cut_size(1.0 - split).unwrap();
+        let (_, inconsistent) = cut_size(1.0 - split + 0.15).unwrap();
+        assert!(inconsistent > 1e-4 && inconsistent > 1e6 * consistent.max(1e-30), "{inconsistent}");
+        for fine_yield in [0.0, 1.0, f64::NAN] {
+            assert!(cut_size(fine_yield).is_none());
+        }
+    }
+}
@@ -0,0 +1,94 @@
+// Nonlinear least-squares helpers shared by the model-fitting entry points.
+
+use crate::matrix_ops::cholesky_solve;
+
+const MAX_ITERATIONS: usize = 200;
+const INITIAL_DAMPING: f64 = 1e-3;
+const MAX_DAMPING: f64 = 1e12;
+/// Relative change in the residual sum of squares treated as converged.
+const SSE_TOLERANCE: f64 = 1e-12;
+
+/// Residual sum of squares of `model` over the data, or None when the model
+/// produces a non-finite value.
+fn sum_of_squares<F>(xs: &[f64], ys: &[f64], params: &[f64], model: &F, gradient: &mut [f64]) -> Option<f64>
+where
+    F: Fn(f64, &[f64], &mut [f64]) -> f64,
+{
+    let sse: f64 = xs.iter().zip(ys).map(|(&x, &y)| (y - model(x, params, gradient)).powi(2)).sum();
+    sse.is_finite().then_some(sse)
+}
+
+/// Levenberg-Marquardt fit of `model` to (xs, ys), starting from `initial`.
+///
+/// `model(x, params, gradient)` returns the model value at `x` and writes
+/// its partial derivatives with respect to each parameter into `gradient`.
+/// Each step solves (JᵀJ + λ·diag(JᵀJ)) δ = Jᵀr with the Cholesky solver,
+/// raising λ tenfold on a rejected step and lowering it on an accepted one.
+/// Returns the fitted parameters, or None when the initial guess is not
+/// finite or the damping grows without an improving step.
+pub(super) fn levenberg_marquardt<F>(xs: &[f64], ys: &[f64], initial: &[f64], model: F) -> Option<Vec<f64>>
+where
+    F: Fn(f64, &[f64], &mut [f64]) -> f64,
+{
+    let n = initial.len();
+    let mut params = initial.to_vec();
+    let mut gradient = vec![0.0; n];
+    let mut sse = sum_of_squares(xs, ys, &params, &model, &mut gradient)?;
+    let mut damping = INITIAL_DAMPING;
+
+    for _ in 0..MAX_ITERATIONS {
+        // Normal equations JᵀJ and Jᵀr
+        let mut jtj = vec![0.0; n * n];
+        let mut jtr = vec![0.0; n];
+        for (&x, &y) in xs.iter().zip(ys) {
+            let residual = y - model(x, &params, &mut gradient);
+            for i in 0..n {
+                jtr[i] += gradient[i] * residual;
+                for j in 0..n {
+                    jtj[i * n + j] += gradient[i] * gradient[j];
+                }
+            }
+        }
+
+        loop {
+            let mut damped = jtj.clone();
+            for i in 0..n {
+                damped[i * n + i] += damping * jtj[i * n + i].max(f64::EPSILON);
+            }
+            let candidate: Option<(Vec<f64>, f64)> = cholesky_solve(&damped, &jtr, n).and_then(|step| {
+                let trial: Vec<f64> = params.iter().zip(&step).map(|(p, s)| p + s).collect();
+                let trial_sse = sum_of_squares(xs, ys, &trial, &model, &mut gradient)?;
+                (trial_sse <= sse).then_some((trial, trial_sse))
+            });
+            match candidate {
+                Some((trial, trial_sse)) => {
+                    let converged = sse - trial_sse <= SSE_TOLERANCE * sse.max(f64::MIN_POSITIVE);
+                    params = trial;
+                    sse = trial_sse;
+                    damping = (damping / 10.0).max(f64::EPSILON);
+                    if converged {
+                        return Some(params);
+                    }
+                    break;
+                }
+                None => {
+                    damping *= 10.0;
+                    if damping > MAX_DAMPING {
+                        // No step improves the fit any further
+                        return Some(params);
+                    }
+                }
+            }
+        }
+    }
+    Some(params)
+}
+
+/// Coefficient of determination of `fitted` against `observed`; 1 when the
+/// observations do not vary and are matched exactly.
+pub(super) fn r_squared(observed: &[f64], fitted: &[f64]) -> f64 {
+    let mean = observed.iter().sum::<f64>() / observed.len() as f64;
+    let total: f64 = observed.iter().map(|y| (y - mean).powi(2)).sum();
+    let residual: f64 = observed.iter().zip(fitted).map(|(y, f)| (y - f).powi(2)).sum();
+    if total > 0.0 { 1.0 - residual / total } else if residual == 0.0 { 1.0 } else { 0.0 }
+}
@@ -0,0 +1,153 @@
+use std::ffi::c_double;
+
+/// Molar gas constant in J/(mol·K).
+const GAS_CONSTANT: f64 = 8.314_462_618;
+
+/// Width of a (time, temperature) row in `calculate_denatured_fraction`.
+pub const TIME_TEMPERATURE_WIDTH: usize = 2;
+
+/// Whether the Arrhenius parameters are usable: a non-negative activation
+/// energy and a positive pre-exponential factor.
+fn valid_arrhenius(activation_energy: f64, pre_exponential: f64) -> bool {
+    activation_energy.is_finite() && activation_energy >= 0.0
+        && pre_exponential.is_finite() && pre_exponential > 0.0
+}
+
+/// First-order rate constant k(T) = A * exp(-Ea / (R * T)).
+fn rate_constant(temperature_k: f64, activation_energy: f64, pre_exponential: f64) -> f64 {
+    pre_exponential * (-activation_energy / (GAS_CONSTANT * temperature_k)).exp()
+}
+
+/// Cumulative denatured protein fraction over a temperature-time profile.
+///
+/// `time_temperature_pairs` holds `len` rows of `TIME_TEMPERATURE_WIDTH`
+/// values: time and temperature in kelvin. Denaturation follows
+/// first-order Arrhenius kinetics with `activation_energy` in J/mol and
+/// `pre_exponential` in inverse time units, so the native fraction decays
+/// as exp(-∫k(T(t)) dt), integrated by the trapezoidal rule between rows;
+/// the result is 1 - exp(-∫k dt). A single row is a profile of zero
+/// duration.
+///
+/// Returns false on null pointers, no rows, non-finite values, a
+/// temperature at or below 0 K, decreasing times, a negative activation
+/// energy or a non-positive pre-exponential factor.
+#[no_mangle]
+pub extern "C" fn calculate_denatured_fraction(
+    time_temperature_pairs: *const c_double,
+    len: usize,
+    activation_energy: c_double,
+    pre_exponential: c_double,
+    result: *mut c_double
+) -> bool {
+    if time_temperature_pairs.is_null() || result.is_null() || len == 0
+        || !valid_arrhenius(activation_energy, pre_exponential) {
+        return false;
+    }
+    let rows = unsafe { std::slice::from_raw_parts(time_temperature_pairs, len * TIME_TEMPERATURE_WIDTH) };
+    if rows.iter().any(|v| !v.is_finite()) {
+        return false;
+    }
+    let rows: Vec<(f64, f64)> = rows.chunks_exact(TIME_TEMPERATURE_WIDTH).map(|row| (row[0], row[1])).collect();
+    if rows.iter().any(|&(_, temperature)| temperature <= 0.0) || rows.windows(2).any(|pair| pair[1].0 < pair[0].0) {
+        return false;
+    }
+
+    let exposure: f64 = rows.windows(2).map(|pair| {
+        let ((t0, temp0), (t1, temp1)) = (pair[0], pair[1]);
+        let k0 = rate_constant(temp0, activation_energy, pre_exponential);
+        let k1 = rate_constant(temp1, activation_energy, pre_exponential);
+        0.5 * (k0 + k1) * (t1 - t0)
+    }).sum();
+    unsafe {
+        *result = 1.0 - (-exposure).exp();
+    }
+    true
+}
+
+/// Denatured fraction after an isothermal hold of `hold_time` at
+/// `temperature_k`, 1 - exp(-k(T) * t), for comparing a profile from
+/// `calculate_denatured_fraction` with an equivalent constant-temperature
+/// treatment. Returns false on a null result pointer, a temperature at or
+/// below 0 K, a negative or non-finite hold time, or invalid Arrhenius
+/// parameters as in `calculate_denatured_fraction`.
+#[no_mangle]
+pub extern "C" fn calculate_isothermal_denatured_fraction(
+    temperature_k: c_double,
+    hold_time: c_double,
+    activation_energy: c_double,
+    pre_exponential: c_double,
+    result: *mut c_double
+) -> bool {
+    if result.is_null() || !temperature_k.is_finite() || temperature_k <= 0.0
+        || !hold_time.is_finite() || hold_time < 0.0 || !valid_arrhenius(activation_energy, pre_exponential) {
+        return false;
+    }
+    unsafe {
+        *result = 1.0 - (-rate_constant(temperature_k, activation_energy, pre_exponential) * hold_time).exp();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Pea globulin-like kinetics: noticeable denaturation over minutes at 90 C
+    const ACTIVATION_ENERGY: f64 = 250e3;
+    const PRE_EXPONENTIAL: f64 = 1e32;
+
+    fn denatured(rows: &[f64]) -> Option<f64> {
+        let mut fraction = f64::NAN;
+        calculate_denatured_fraction(rows.as_ptr(), rows.len() / TIME_TEMPERATURE_WIDTH, ACTIVATION_ENERGY, PRE_EXPONENTIAL, &mut fraction)
+            .then_some(fraction)
+    }
+
+    /// Linear ramp from 300 K to 380 K over 600 s in `steps` segments.
+    fn ramp(steps: usize) -> Vec<f64> {
+        (0..=steps).flat_map(|i| {
+            let t = 600.0 * i as f64 / steps as f64;
+            [t, 300.0 + 80.0 * t / 600.0]
+        }).collect()
+    }
+
+    #[test]
+    fn isothermal_profile_matches_the_closed_form() {
+        let fraction = denatured(&[0.0, 363.15, 60.0, 363.15, 120.0, 363.15]).unwrap();
+        let k = PRE_EXPONENTIAL * (-ACTIVATION_ENERGY / (GAS_CONSTANT * 363.15)).exp();
+        assert!((fraction - (1.0 - (-k * 120.0).exp())).abs() < 1e-12);
+        assert!(fraction > 0.01 && fraction < 0.99, "{fraction}");
+
+        let mut isothermal = f64::NAN;
+        assert!(calculate_isothermal_denatured_fraction(363.15, 120.0, ACTIVATION_ENERGY, PRE_EXPONENTIAL, &mut isothermal));
+        assert!((isothermal - fraction).abs() < 1e-12);
+        assert_eq!(denatured(&[0.0, 363.15]), Some(0.0));
+    }
+
+    #[test]
+    fn finer_steps_converge_on_a_ramp() {
+        let fractions: Vec<f64> = [4, 8, 16, 32, 64, 128].iter().map(|&steps| denatured(&ramp(steps)).unwrap()).collect();
+        let changes: Vec<f64> = fractions.windows(2).map(|pair| (pair[1] - pair[0]).abs()).collect();
+        // The trapezoidal error falls by about four for each halving of the step
+        for pair in changes.windows(2) {
+            assert!(pair[1] < pair[0] / 3.0, "{fractions:?}");
+        }
+        // Coarse steps overestimate the convex rate curve
+        assert!(fractions[0] > fractions[5]);
+        assert!(changes[4] < 1e-2 * fractions[5], "{fractions:?}");
+    }
+
+    #[test]
+    fn invalid_profiles_and_parameters_are_rejected() {
+        assert!(denatured(&[0.0, -1.0]).is_none());
+        assert!(denatured(&[0.0, 0.0]).is_none());
+        assert!(denatured(&[5.0, 300.0, 4.0, 300.0]).is_none());
+        assert!(denatured(&[0.0, 300.0, f64::NAN, 300.0]).is_none());
+        assert!(denatured(&[]).is_none());
+        let mut fraction = f64::NAN;
+        assert!(!calculate_denatured_fraction(ramp(2).as_ptr(), 3, -1.0, PRE_EXPONENTIAL, &mut fraction));
+        assert!(!calculate_denatured_fraction(ramp(2).as_ptr(), 3, ACTIVATION_ENERGY, 0.0, &mut fraction));
+        assert!(!calculate_isothermal_denatured_fraction(0.0, 10.0, ACTIVATION_ENERGY, PRE_EXPONENTIAL, &mut fraction));
+        assert!(!calculate_isothermal_denatured_fraction(300.0, -1.0, ACTIVATION_ENERGY, PRE_EXPONENTIAL, &mut fraction));
+        assert!(fraction.is_nan());
+    }
+}
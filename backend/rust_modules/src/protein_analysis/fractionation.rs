@@ -0,0 +1,756 @@
+use std::ffi::c_double;
+use super::protein_calculator::{calculate_separation_efficiency, WeightedPsd};
+
+/// Protein purity (protein mass fraction) of a stream.
+fn purity(protein_mass: f64, total_mass: f64) -> Option<f64> {
+    if total_mass.is_nan() || total_mass <= 0.0 || protein_mass.is_nan() || protein_mass < 0.0
+        || protein_mass > total_mass {
+        return None;
+    }
+    Some(protein_mass / total_mass)
+}
+
+/// Protein purity of a fraction: protein_mass / total_mass.
+///
+/// Returns false on a null result pointer, a non-positive total mass, or a
+/// protein mass that is negative or exceeds the total.
+#[no_mangle]
+pub extern "C" fn calculate_protein_purity(
+    protein_mass: c_double,
+    total_mass: c_double,
+    result: *mut c_double
+) -> bool {
+    if result.is_null() {
+        return false;
+    }
+    match purity(protein_mass, total_mass) {
+        Some(value) => {
+            unsafe { *result = value; }
+            true
+        }
+        None => false,
+    }
+}
+
+/// Protein purity of several fractions and of the fractions combined.
+///
+/// `proteins` holds protein masses, or protein concentrations (mass
+/// fractions) when `proteins_are_concentrations` is set, in which case the
+/// protein mass of each fraction is concentration * total mass. `results`
+/// receives each fraction's purity and `combined_out` the purity of all
+/// fractions pooled, Σ protein / Σ mass. Returns false on null pointers,
+/// `len` of 0, or any fraction rejected by `calculate_protein_purity`.
+#[no_mangle]
+pub extern "C" fn calculate_protein_purity_batch(
+    proteins: *const c_double,
+    total_masses: *const c_double,
+    len: usize,
+    proteins_are_concentrations: bool,
+    results: *mut c_double,
+    combined_out: *mut c_double
+) -> bool {
+    if proteins.is_null() || total_masses.is_null() || results.is_null() || combined_out.is_null() || len == 0 {
+        return false;
+    }
+    let (proteins, total_masses) = unsafe {
+        (std::slice::from_raw_parts(proteins, len), std::slice::from_raw_parts(total_masses, len))
+    };
+
+    let mut purities = Vec::with_capacity(len);
+    let (mut protein_sum, mut mass_sum) = (0.0, 0.0);
+    for (&protein, &mass) in proteins.iter().zip(total_masses) {
+        let protein_mass = if proteins_are_concentrations { protein * mass } else { protein };
+        match purity(protein_mass, mass) {
+            Some(value) => purities.push(value),
+            None => return false,
+        }
+        protein_sum += protein_mass;
+        mass_sum += mass;
+    }
+
+    unsafe {
+        std::slice::from_raw_parts_mut(results, len).copy_from_slice(&purities);
+        *combined_out = protein_sum / mass_sum;
+    }
+    true
+}
+
+/// Purities and balance check written by `calculate_purity_from_streams`.
+///
+/// Imbalances are (fine + coarse - feed) / feed in percent, for protein and
+/// for total mass; positive values mean more came out than went in.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct StreamPurity {
+    pub feed_purity: c_double,
+    pub fine_purity: c_double,
+    pub coarse_purity: c_double,
+    pub protein_imbalance_pct: c_double,
+    pub mass_imbalance_pct: c_double,
+}
+
+/// Purity of the feed, fine and coarse streams of a classification step,
+/// cross-checked against the protein and mass balances.
+///
+/// Each stream is given as total mass and protein mass. The imbalances are
+/// reported as measured rather than normalized away, so inconsistent lab
+/// numbers show up. Returns false on a null output, a non-positive mass, or
+/// a protein mass that is negative or exceeds its stream's mass.
+#[no_mangle]
+pub extern "C" fn calculate_purity_from_streams(
+    feed_mass: c_double,
+    feed_protein: c_double,
+    fine_mass: c_double,
+    fine_protein: c_double,
+    coarse_mass: c_double,
+    coarse_protein: c_double,
+    outputs: *mut StreamPurity
+) -> bool {
+    if outputs.is_null() {
+        return false;
+    }
+    let (feed_purity, fine_purity, coarse_purity) = match (
+        purity(feed_protein, feed_mass),
+        purity(fine_protein, fine_mass),
+        purity(coarse_protein, coarse_mass),
+    ) {
+        (Some(feed), Some(fine), Some(coarse)) => (feed, fine, coarse),
+        _ => return false,
+    };
+    let imbalance = |fine: f64, coarse: f64, feed: f64| 100.0 * (fine + coarse - feed) / feed;
+    // A protein-free feed leaves the protein balance undefined
+    let protein_imbalance_pct = if feed_protein > 0.0 {
+        imbalance(fine_protein, coarse_protein, feed_protein)
+    } else {
+        f64::NAN
+    };
+
+    unsafe {
+        *outputs = StreamPurity {
+            feed_purity,
+            fine_purity,
+            coarse_purity,
+            protein_imbalance_pct,
+            mass_imbalance_pct: imbalance(fine_mass, coarse_mass, feed_mass),
+        };
+    }
+    true
+}
+
+/// Stage and cumulative efficiencies of a multi-stage separation.
+///
+/// Stage `i` is described by its input and output masses and protein
+/// concentrations. `stage_efficiencies` receives each stage's efficiency
+/// as computed by `calculate_separation_efficiency`; `cumulative_recovery`
+/// and `cumulative_yield` receive the protein recovery and mass yield from
+/// the cascade feed through the end of each stage (running products of the
+/// stage efficiencies and of output / input mass). `continuity_flags[i]` is
+/// set when stage `i`'s input mass differs from stage `i - 1`'s output mass
+/// by more than `tolerance` relative to that output; it is never set for
+/// the first stage.
+///
+/// Returns false on null pointers, no stages, a negative or NaN tolerance,
+/// or a non-positive input mass.
+#[no_mangle]
+pub extern "C" fn calculate_cascade_efficiency(
+    input_masses: *const c_double,
+    output_masses: *const c_double,
+    input_concentrations: *const c_double,
+    output_concentrations: *const c_double,
+    n_stages: usize,
+    tolerance: c_double,
+    stage_efficiencies: *mut c_double,
+    cumulative_recovery: *mut c_double,
+    cumulative_yield: *mut c_double,
+    continuity_flags: *mut bool
+) -> bool {
+    if input_masses.is_null() || output_masses.is_null() || input_concentrations.is_null()
+        || output_concentrations.is_null() || stage_efficiencies.is_null() || cumulative_recovery.is_null()
+        || cumulative_yield.is_null() || continuity_flags.is_null() || n_stages == 0 {
+        return false;
+    }
+    if tolerance.is_nan() || tolerance < 0.0 {
+        return false;
+    }
+    let (input_masses, output_masses, input_concentrations, output_concentrations) = unsafe {
+        (
+            std::slice::from_raw_parts(input_masses, n_stages),
+            std::slice::from_raw_parts(output_masses, n_stages),
+            std::slice::from_raw_parts(input_concentrations, n_stages),
+            std::slice::from_raw_parts(output_concentrations, n_stages),
+        )
+    };
+    if input_masses.iter().any(|m| m.is_nan() || *m <= 0.0) {
+        return false;
+    }
+    let (stage_efficiencies, cumulative_recovery, cumulative_yield, continuity_flags) = unsafe {
+        (
+            std::slice::from_raw_parts_mut(stage_efficiencies, n_stages),
+            std::slice::from_raw_parts_mut(cumulative_recovery, n_stages),
+            std::slice::from_raw_parts_mut(cumulative_yield, n_stages),
+            std::slice::from_raw_parts_mut(continuity_flags, n_stages),
+        )
+    };
+
+    let (mut recovery, mut mass_yield) = (1.0, 1.0);
+    for stage in 0..n_stages {
+        let efficiency = calculate_separation_efficiency(
+            input_masses[stage],
+            output_masses[stage],
+            input_concentrations[stage],
+            output_concentrations[stage],
+        );
+        recovery *= efficiency;
+        mass_yield *= output_masses[stage] / input_masses[stage];
+        stage_efficiencies[stage] = efficiency;
+        cumulative_recovery[stage] = recovery;
+        cumulative_yield[stage] = mass_yield;
+        continuity_flags[stage] = stage > 0 && {
+            let previous = output_masses[stage - 1];
+            (input_masses[stage] - previous).abs() > tolerance * previous.abs()
+        };
+    }
+    true
+}
+
+/// Closure in percent of the product total against the feed, and whether
+/// it is within `tolerance` percentage points of 100.
+fn closure(feed: f64, products: f64, tolerance: f64) -> (f64, bool) {
+    let closure = 100.0 * products / feed;
+    (closure, (closure - 100.0).abs() <= tolerance)
+}
+
+/// Mass balance closure of a fractionation experiment.
+///
+/// `closure_out` receives Σ product masses / feed mass in percent and
+/// `within_tolerance_out` whether it lies within `tolerance` percentage
+/// points of 100; over-closure (e.g. moisture pickup) fails just like
+/// losses do. Returns false on null pointers, a non-positive feed mass, a
+/// negative or NaN product mass, or a negative or NaN tolerance.
+#[no_mangle]
+pub extern "C" fn check_mass_balance(
+    feed_mass: c_double,
+    product_masses: *const c_double,
+    n_products: usize,
+    tolerance: c_double,
+    closure_out: *mut c_double,
+    within_tolerance_out: *mut bool
+) -> bool {
+    if product_masses.is_null() || closure_out.is_null() || within_tolerance_out.is_null() {
+        return false;
+    }
+    if feed_mass.is_nan() || feed_mass <= 0.0 || tolerance.is_nan() || tolerance < 0.0 {
+        return false;
+    }
+    let product_masses = unsafe { std::slice::from_raw_parts(product_masses, n_products) };
+    if product_masses.iter().any(|m| m.is_nan() || *m < 0.0) {
+        return false;
+    }
+
+    let (closure, within) = closure(feed_mass, product_masses.iter().sum(), tolerance);
+    unsafe {
+        *closure_out = closure;
+        *within_tolerance_out = within;
+    }
+    true
+}
+
+/// Protein balance closure: `check_mass_balance` applied to the protein in
+/// each stream, mass * concentration. Returns false additionally on a
+/// non-positive feed concentration or a negative product concentration.
+#[no_mangle]
+pub extern "C" fn check_component_balance(
+    feed_mass: c_double,
+    feed_concentration: c_double,
+    product_masses: *const c_double,
+    product_concentrations: *const c_double,
+    n_products: usize,
+    tolerance: c_double,
+    closure_out: *mut c_double,
+    within_tolerance_out: *mut bool
+) -> bool {
+    if product_masses.is_null() || product_concentrations.is_null() || closure_out.is_null()
+        || within_tolerance_out.is_null() {
+        return false;
+    }
+    if feed_concentration.is_nan() || feed_concentration <= 0.0 {
+        return false;
+    }
+    let (masses, concentrations) = unsafe {
+        (
+            std::slice::from_raw_parts(product_masses, n_products),
+            std::slice::from_raw_parts(product_concentrations, n_products),
+        )
+    };
+    if concentrations.iter().any(|c| c.is_nan() || *c < 0.0) {
+        return false;
+    }
+    let proteins: Vec<f64> = masses.iter().zip(concentrations).map(|(m, c)| m * c).collect();
+    check_mass_balance(
+        feed_mass * feed_concentration,
+        proteins.as_ptr(),
+        n_products,
+        tolerance,
+        closure_out,
+        within_tolerance_out,
+    )
+}
+
+/// Separation efficiency without clamping, optionally gated on the mass
+/// balance.
+///
+/// Unlike `calculate_separation_efficiency`, efficiencies above 1 are
+/// reported as computed so data problems stay visible. When
+/// `require_closure` is set, `product_masses` (all products of the step,
+/// including `output_mass`) must close against `input_mass` within
+/// `tolerance` percentage points as in `check_mass_balance`, otherwise the
+/// call fails. Returns false on a null result pointer, a non-positive input
+/// mass or concentration, or a failed or open balance.
+#[no_mangle]
+pub extern "C" fn calculate_separation_efficiency_checked(
+    input_mass: c_double,
+    output_mass: c_double,
+    input_concentration: c_double,
+    output_concentration: c_double,
+    product_masses: *const c_double,
+    n_products: usize,
+    tolerance: c_double,
+    require_closure: bool,
+    result: *mut c_double
+) -> bool {
+    if result.is_null() || input_mass.is_nan() || input_mass <= 0.0
+        || input_concentration.is_nan() || input_concentration <= 0.0 {
+        return false;
+    }
+    if require_closure {
+        let (mut closure, mut within) = (0.0, false);
+        if !check_mass_balance(input_mass, product_masses, n_products, tolerance, &mut closure, &mut within)
+            || !within {
+            return false;
+        }
+    }
+    unsafe {
+        *result = (output_mass * output_concentration) / (input_mass * input_concentration);
+    }
+    true
+}
+
+/// Metrics written by `calculate_air_classification_metrics`. Yields and
+/// the separation efficiency are fractions of the feed; the coarse-stream
+/// values are NaN when no coarse data is supplied.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct AirClassificationMetrics {
+    /// Fine-fraction protein content / feed protein content
+    pub enrichment_factor: c_double,
+    /// Protein in the fine fraction / protein in the feed
+    pub protein_separation_efficiency: c_double,
+    /// Fine-fraction mass / feed mass
+    pub fine_yield: c_double,
+    /// Coarse-fraction protein content / feed protein content; below 1 when
+    /// protein is depleted from the starch-rich coarse stream
+    pub coarse_depletion_factor: c_double,
+    /// Coarse-fraction mass / feed mass
+    pub coarse_yield: c_double,
+}
+
+/// Standard dry-fractionation metrics of an air classification step.
+///
+/// Protein values are contents (any consistent unit). Pass a NaN or zero
+/// `coarse_mass` when the coarse stream was not measured. Returns false on
+/// a null output or a non-positive feed or fine mass or content, or coarse
+/// data with a non-positive content.
+#[no_mangle]
+pub extern "C" fn calculate_air_classification_metrics(
+    feed_mass: c_double,
+    feed_protein: c_double,
+    fine_mass: c_double,
+    fine_protein: c_double,
+    coarse_mass: c_double,
+    coarse_protein: c_double,
+    outputs: *mut AirClassificationMetrics
+) -> bool {
+    let positive = |x: f64| x > 0.0 && x.is_finite();
+    if outputs.is_null() || !positive(feed_mass) || !positive(feed_protein) || !positive(fine_mass)
+        || !positive(fine_protein) {
+        return false;
+    }
+    let coarse_supplied = positive(coarse_mass);
+    if coarse_supplied && !positive(coarse_protein) {
+        return false;
+    }
+    let (coarse_depletion_factor, coarse_yield) = if coarse_supplied {
+        (coarse_protein / feed_protein, coarse_mass / feed_mass)
+    } else {
+        (f64::NAN, f64::NAN)
+    };
+
+    unsafe {
+        *outputs = AirClassificationMetrics {
+            enrichment_factor: fine_protein / feed_protein,
+            protein_separation_efficiency: (fine_mass * fine_protein) / (feed_mass * feed_protein),
+            fine_yield: fine_mass / feed_mass,
+            coarse_depletion_factor,
+            coarse_yield,
+        };
+    }
+    true
+}
+
+/// Values written per cut size by `compute_yield_purity_curve`: cut size,
+/// fine-fraction yield and fine-fraction purity.
+pub const YIELD_PURITY_WIDTH: usize = 3;
+
+/// Fine-fraction yield and protein purity as a function of classifier cut
+/// size, for an ideal classifier sending everything at or below the cut to
+/// the fine stream.
+///
+/// `weights` are mass weights of the feed PSD. Protein contents per size
+/// come from `protein_contents` (mass fractions), or, when it is null, from
+/// a two-component model: `fine_content` at or below `body_size` (protein
+/// bodies) and `coarse_content` above it. `n_cuts` cut sizes are spaced
+/// evenly from `cut_min` to `cut_max`. Yield and protein below each cut are
+/// interpolated like `compute_fraction_below`; purity is NaN where the
+/// yield is zero.
+///
+/// `results` holds `n_cuts` rows of `YIELD_PURITY_WIDTH` values. Returns
+/// false on null pointers, invalid PSD input, contents outside [0, 1], no
+/// protein in the feed, `n_cuts` of 0, or an inverted or NaN cut range.
+#[no_mangle]
+pub extern "C" fn compute_yield_purity_curve(
+    sizes: *const f64,
+    weights: *const f64,
+    protein_contents: *const f64,
+    len: usize,
+    body_size: c_double,
+    fine_content: c_double,
+    coarse_content: c_double,
+    cut_min: c_double,
+    cut_max: c_double,
+    n_cuts: usize,
+    results: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || results.is_null() || len == 0 || n_cuts == 0 {
+        return false;
+    }
+    if cut_min.is_nan() || cut_max.is_nan() || cut_min > cut_max {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let contents: Vec<f64> = if protein_contents.is_null() {
+        sizes.iter().map(|&d| if d <= body_size { fine_content } else { coarse_content }).collect()
+    } else {
+        unsafe { std::slice::from_raw_parts(protein_contents, len) }.to_vec()
+    };
+    if contents.iter().any(|c| !(0.0..=1.0).contains(c)) {
+        return false;
+    }
+    let proteins: Vec<f64> = weights.iter().zip(&contents).map(|(w, c)| w * c).collect();
+    let (mass, protein) = match (WeightedPsd::new(sizes, weights), WeightedPsd::new(sizes, &proteins)) {
+        (Ok(mass), Ok(protein)) => (mass, protein),
+        _ => return false,
+    };
+
+    let results = unsafe { std::slice::from_raw_parts_mut(results, n_cuts * YIELD_PURITY_WIDTH) };
+    let step = if n_cuts > 1 { (cut_max - cut_min) / (n_cuts - 1) as f64 } else { 0.0 };
+    for (i, row) in results.chunks_exact_mut(YIELD_PURITY_WIDTH).enumerate() {
+        let cut = cut_min + step * i as f64;
+        let fine_yield = mass.fraction_below(cut);
+        let fine_protein = protein.fraction_below(cut) * protein.total_weight;
+        let purity = if fine_yield > 0.0 { fine_protein / (fine_yield * mass.total_weight) } else { f64::NAN };
+        row.copy_from_slice(&[cut, fine_yield, purity]);
+    }
+    true
+}
+
+/// Newton's total separation efficiency of a two-component split.
+///
+/// η = R_target + R_reject - 1, where R_target is the share of the feed's
+/// target component recovered in the product and R_reject the share of the
+/// non-target component kept out of it. 1 is a perfect split, 0 no
+/// separation at all (product composition equal to the feed), and negative
+/// values a split that works against the target; the value is not clamped.
+///
+/// Concentrations are mass fractions of the target and non-target
+/// components in each stream. Returns false on a null result pointer, a
+/// non-positive feed mass, a negative product mass, a concentration outside
+/// [0, 1], a pair of concentrations summing to more than 1, or a feed
+/// lacking either component.
+#[no_mangle]
+pub extern "C" fn calculate_newton_efficiency(
+    feed_mass: c_double,
+    feed_target_concentration: c_double,
+    feed_other_concentration: c_double,
+    product_mass: c_double,
+    product_target_concentration: c_double,
+    product_other_concentration: c_double,
+    result: *mut c_double
+) -> bool {
+    let fraction = |c: f64| (0.0..=1.0).contains(&c);
+    // Allow rounding in compositions that are meant to sum to exactly 1
+    let valid_pair = |target: f64, other: f64| fraction(target) && fraction(other) && target + other <= 1.0 + 1e-9;
+    if result.is_null() || feed_mass.is_nan() || feed_mass <= 0.0 || product_mass.is_nan() || product_mass < 0.0
+        || !valid_pair(feed_target_concentration, feed_other_concentration)
+        || !valid_pair(product_target_concentration, product_other_concentration)
+        || feed_target_concentration == 0.0 || feed_other_concentration == 0.0 {
+        return false;
+    }
+
+    let target_recovery = (product_mass * product_target_concentration) / (feed_mass * feed_target_concentration);
+    let other_rejection = 1.0 - (product_mass * product_other_concentration) / (feed_mass * feed_other_concentration);
+    unsafe {
+        *result = target_recovery + other_rejection - 1.0;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn streams(fine_protein: f64) -> Option<StreamPurity> {
+        let mut outputs = StreamPurity::default();
+        calculate_purity_from_streams(100.0, 23.0, 30.0, fine_protein, 70.0, 8.0, &mut outputs).then_some(outputs)
+    }
+
+    #[test]
+    fn balanced_streams_report_no_imbalance() {
+        let outputs = streams(15.0).unwrap();
+        assert_eq!((outputs.feed_purity, outputs.fine_purity), (0.23, 0.5));
+        assert!((outputs.coarse_purity - 8.0 / 70.0).abs() < 1e-15);
+        assert_eq!((outputs.protein_imbalance_pct, outputs.mass_imbalance_pct), (0.0, 0.0));
+    }
+
+    #[test]
+    fn inconsistent_streams_report_the_imbalance() {
+        // One unit of protein too many in the fine stream: 24 out for 23 in
+        let outputs = streams(16.0).unwrap();
+        assert!((outputs.protein_imbalance_pct - 100.0 / 23.0).abs() < 1e-12);
+        assert_eq!(outputs.mass_imbalance_pct, 0.0);
+        // The measured purity is kept, not normalized to close the balance
+        assert!((outputs.fine_purity - 16.0 / 30.0).abs() < 1e-15);
+    }
+
+    #[test]
+    fn non_positive_stream_masses_are_rejected() {
+        let mut outputs = StreamPurity::default();
+        assert!(!calculate_purity_from_streams(0.0, 0.0, 30.0, 15.0, 70.0, 8.0, &mut outputs));
+        assert!(!calculate_purity_from_streams(100.0, 23.0, -30.0, 0.0, 70.0, 8.0, &mut outputs));
+        assert!(!calculate_purity_from_streams(100.0, 23.0, 30.0, 31.0, 70.0, 8.0, &mut outputs));
+        assert!(!calculate_purity_from_streams(100.0, 23.0, 30.0, 15.0, 70.0, 8.0, std::ptr::null_mut()));
+    }
+
+    #[test]
+    fn batch_purity_propagates_concentrations() {
+        let (mut purities, mut combined) = ([0.0; 2], f64::NAN);
+        assert!(calculate_protein_purity_batch([0.5, 0.2].as_ptr(), [30.0, 70.0].as_ptr(), 2, true, purities.as_mut_ptr(), &mut combined));
+        assert_eq!(purities, [0.5, 0.2]);
+        assert!((combined - (15.0 + 14.0) / 100.0).abs() < 1e-15);
+
+        assert!(calculate_protein_purity_batch([15.0, 14.0].as_ptr(), [30.0, 70.0].as_ptr(), 2, false, purities.as_mut_ptr(), &mut combined));
+        assert_eq!(purities, [0.5, 0.2]);
+        assert!(!calculate_protein_purity_batch([15.0, 71.0].as_ptr(), [30.0, 70.0].as_ptr(), 2, false, purities.as_mut_ptr(), &mut combined));
+    }
+
+    struct Cascade {
+        efficiencies: [f64; 2],
+        recovery: [f64; 2],
+        mass_yield: [f64; 2],
+        flags: [bool; 2],
+    }
+
+    // Stage 1: 100 kg at 0.2 to 40 kg at 0.4 (0.8); stage 2: 40 kg at 0.4 to 30 kg at 0.48 (0.9)
+    fn cascade(second_input_mass: f64) -> Cascade {
+        let mut cascade = Cascade { efficiencies: [f64::NAN; 2], recovery: [f64::NAN; 2], mass_yield: [f64::NAN; 2], flags: [true; 2] };
+        assert!(calculate_cascade_efficiency(
+            [100.0, second_input_mass].as_ptr(), [40.0, 30.0].as_ptr(), [0.2, 0.4].as_ptr(), [0.4, 0.48].as_ptr(), 2, 0.01,
+            cascade.efficiencies.as_mut_ptr(), cascade.recovery.as_mut_ptr(), cascade.mass_yield.as_mut_ptr(), cascade.flags.as_mut_ptr()
+        ));
+        cascade
+    }
+
+    #[test]
+    fn two_stage_cascade_multiplies_the_efficiencies() {
+        let cascade = cascade(40.0);
+        assert!((cascade.efficiencies[0] - 0.8).abs() < 1e-12 && (cascade.efficiencies[1] - 0.9).abs() < 1e-12);
+        assert!((cascade.recovery[0] - 0.8).abs() < 1e-12 && (cascade.recovery[1] - 0.72).abs() < 1e-12);
+        assert!((cascade.mass_yield[1] - 0.3).abs() < 1e-12);
+        assert_eq!(cascade.flags, [false, false]);
+    }
+
+    #[test]
+    fn broken_stream_continuity_is_flagged() {
+        assert_eq!(cascade(45.0).flags, [false, true]);
+        // Within the 1 % tolerance
+        assert_eq!(cascade(40.2).flags, [false, false]);
+    }
+
+    fn mass_balance(products: &[f64]) -> (f64, bool) {
+        let (mut closure, mut within) = (f64::NAN, false);
+        assert!(check_mass_balance(100.0, products.as_ptr(), products.len(), 2.0, &mut closure, &mut within));
+        (closure, within)
+    }
+
+    #[test]
+    fn mass_balance_fails_on_over_and_under_closure() {
+        // Moisture pickup
+        let (closure, within) = mass_balance(&[40.0, 63.0]);
+        assert!((closure - 103.0).abs() < 1e-12 && !within);
+        // Losses
+        let (closure, within) = mass_balance(&[40.0, 55.0]);
+        assert!((closure - 95.0).abs() < 1e-12 && !within);
+        assert!(mass_balance(&[40.0, 59.0]).1);
+
+        let (mut closure, mut within) = (f64::NAN, false);
+        assert!(!check_mass_balance(0.0, [1.0].as_ptr(), 1, 2.0, &mut closure, &mut within));
+        assert!(!check_mass_balance(100.0, [-1.0].as_ptr(), 1, 2.0, &mut closure, &mut within));
+        assert!(!check_mass_balance(100.0, [1.0].as_ptr(), 1, -2.0, &mut closure, &mut within));
+    }
+
+    #[test]
+    fn component_balance_weighs_streams_by_concentration() {
+        let (mut closure, mut within) = (f64::NAN, false);
+        // 40 * 0.45 + 59 * 0.1 = 23.9 protein out for 23 in
+        assert!(check_component_balance(100.0, 0.23, [40.0, 59.0].as_ptr(), [0.45, 0.1].as_ptr(), 2, 2.0, &mut closure, &mut within));
+        assert!((closure - 100.0 * 23.9 / 23.0).abs() < 1e-12 && !within);
+        assert!(check_component_balance(100.0, 0.23, [40.0, 59.0].as_ptr(), [0.4, 0.1].as_ptr(), 2, 2.0, &mut closure, &mut within));
+        assert!(closure < 98.0 && !within);
+        assert!(check_component_balance(100.0, 0.23, [40.0, 59.0].as_ptr(), [0.43, 0.1].as_ptr(), 2, 2.0, &mut closure, &mut within));
+        assert!(within);
+    }
+
+    #[test]
+    fn checked_efficiency_keeps_impossible_values_and_gates_on_closure() {
+        let mut efficiency = f64::NAN;
+        let open = [40.0, 70.0];
+        assert!(!calculate_separation_efficiency_checked(100.0, 40.0, 0.2, 0.6, open.as_ptr(), 2, 2.0, true, &mut efficiency));
+        assert!(efficiency.is_nan());
+        // 40 * 0.6 / (100 * 0.2) = 1.2, which the clamped version reports as 1
+        assert!(calculate_separation_efficiency_checked(100.0, 40.0, 0.2, 0.6, open.as_ptr(), 2, 2.0, false, &mut efficiency));
+        assert!((efficiency - 1.2).abs() < 1e-12);
+        assert_eq!(calculate_separation_efficiency(100.0, 40.0, 0.2, 0.6), 1.0);
+
+        let closed = [40.0, 60.0];
+        assert!(calculate_separation_efficiency_checked(100.0, 40.0, 0.2, 0.4, closed.as_ptr(), 2, 2.0, true, &mut efficiency));
+        assert!((efficiency - 0.8).abs() < 1e-12);
+    }
+
+    fn air_classification(coarse_mass: f64, coarse_protein: f64) -> Option<AirClassificationMetrics> {
+        let mut metrics = AirClassificationMetrics::default();
+        // Feed at 22.9 % protein, 26 % fine yield at 53.7 %, coarse at 12.1 %
+        calculate_air_classification_metrics(100.0, 22.9, 26.0, 53.7, coarse_mass, coarse_protein, &mut metrics)
+            .then_some(metrics)
+    }
+
+    #[test]
+    fn air_classification_metrics_match_the_table_row() {
+        let metrics = air_classification(74.0, 12.1).unwrap();
+        // Reported to two decimals: enrichment 2.34, separation efficiency 0.61
+        let round = |value: f64| (value * 100.0).round() / 100.0;
+        assert_eq!(round(metrics.enrichment_factor), 2.34);
+        assert_eq!(round(metrics.protein_separation_efficiency), 0.61);
+        assert_eq!(round(metrics.coarse_depletion_factor), 0.53);
+        assert_eq!((metrics.fine_yield, metrics.coarse_yield), (0.26, 0.74));
+    }
+
+    #[test]
+    fn coarse_metrics_are_nan_without_coarse_data() {
+        for coarse_mass in [f64::NAN, 0.0] {
+            let metrics = air_classification(coarse_mass, f64::NAN).unwrap();
+            assert!(metrics.coarse_depletion_factor.is_nan() && metrics.coarse_yield.is_nan());
+            assert!((metrics.enrichment_factor - 53.7 / 22.9).abs() < 1e-12);
+        }
+        assert!(air_classification(74.0, 0.0).is_none());
+        let mut metrics = AirClassificationMetrics::default();
+        assert!(!calculate_air_classification_metrics(100.0, 0.0, 26.0, 53.7, f64::NAN, f64::NAN, &mut metrics));
+        assert!(!calculate_air_classification_metrics(100.0, 22.9, -26.0, 53.7, f64::NAN, f64::NAN, &mut metrics));
+    }
+
+    /// Protein bodies around 2 um (30 % of the mass) and starch-rich
+    /// particles around 20 um, each log-normal with sigma 0.3
+    fn two_population_psd() -> (Vec<f64>, Vec<f64>) {
+        use statrs::distribution::{ContinuousCDF, Normal};
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        let n = 300;
+        let mut sizes = Vec::with_capacity(2 * n);
+        let mut weights = Vec::with_capacity(2 * n);
+        for i in 0..n {
+            let z = standard.inverse_cdf((i as f64 + 0.5) / n as f64);
+            sizes.extend([(2.0_f64.ln() + 0.3 * z).exp(), (20.0_f64.ln() + 0.3 * z).exp()]);
+            weights.extend([0.3, 0.7]);
+        }
+        (sizes, weights)
+    }
+
+    #[test]
+    fn purity_falls_as_the_cut_passes_the_protein_bodies() {
+        let (sizes, weights) = two_population_psd();
+        let n_cuts = 22;
+        let mut curve = vec![f64::NAN; n_cuts * YIELD_PURITY_WIDTH];
+        assert!(compute_yield_purity_curve(
+            sizes.as_ptr(), weights.as_ptr(), std::ptr::null(), sizes.len(), 5.0, 0.6, 0.12, 3.0, 60.0, n_cuts, curve.as_mut_ptr()
+        ));
+        let rows: Vec<&[f64]> = curve.chunks(YIELD_PURITY_WIDTH).collect();
+        assert_eq!((rows[0][0], rows[n_cuts - 1][0]), (3.0, 60.0));
+        assert!(rows.windows(2).all(|pair| pair[1][1] >= pair[0][1] && pair[1][2] <= pair[0][2] + 1e-12));
+        assert!((rows[0][2] - 0.6).abs() < 1e-9);
+        // Past the coarse population the fine stream is the whole feed
+        assert!((rows[n_cuts - 1][1] - 1.0).abs() < 1e-9);
+        assert!((rows[n_cuts - 1][2] - (0.3 * 0.6 + 0.7 * 0.12)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn yield_purity_curve_validates_its_inputs() {
+        let (sizes, weights) = two_population_psd();
+        let mut curve = [0.0; 2 * YIELD_PURITY_WIDTH];
+        let run = |contents: *const f64, fine_content: f64, cut_min: f64, cut_max: f64, curve: &mut [f64]| compute_yield_purity_curve(
+            sizes.as_ptr(), weights.as_ptr(), contents, sizes.len(), 5.0, fine_content, 0.12, cut_min, cut_max, 2, curve.as_mut_ptr()
+        );
+        assert!(!run(std::ptr::null(), 1.5, 1.0, 45.0, &mut curve));
+        assert!(!run(std::ptr::null(), 0.6, 45.0, 1.0, &mut curve));
+        assert!(!run(std::ptr::null(), 0.6, f64::NAN, 45.0, &mut curve));
+
+        // Explicit contents equal to the two-component model give the same curve
+        let contents: Vec<f64> = sizes.iter().map(|&size| if size <= 5.0 { 0.6 } else { 0.12 }).collect();
+        let mut explicit = [0.0; 2 * YIELD_PURITY_WIDTH];
+        assert!(run(contents.as_ptr(), 0.0, 1.0, 45.0, &mut explicit));
+        assert!(run(std::ptr::null(), 0.6, 1.0, 45.0, &mut curve));
+        assert_eq!(explicit, curve);
+    }
+
+    fn newton(product_mass: f64, product_target: f64, product_other: f64) -> Option<f64> {
+        let mut efficiency = f64::NAN;
+        // Feed: 100 kg, a quarter of it target component
+        calculate_newton_efficiency(100.0, 0.25, 0.75, product_mass, product_target, product_other, &mut efficiency)
+            .then_some(efficiency)
+    }
+
+    #[test]
+    fn perfect_separation_has_newton_efficiency_one() {
+        assert_eq!(newton(25.0, 1.0, 0.0), Some(1.0));
+    }
+
+    #[test]
+    fn product_with_the_feed_composition_has_newton_efficiency_zero() {
+        assert!(newton(40.0, 0.25, 0.75).unwrap().abs() < 1e-12);
+    }
+
+    #[test]
+    fn adverse_separation_is_negative_and_not_clamped() {
+        // R_target = 4 / 25, R_reject = 1 - 36 / 75
+        assert!((newton(40.0, 0.1, 0.9).unwrap() - (0.16 + 0.52 - 1.0)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn concentration_pairs_above_one_are_rejected() {
+        let mut efficiency = f64::NAN;
+        assert!(!calculate_newton_efficiency(100.0, 0.5, 0.75, 40.0, 0.1, 0.9, &mut efficiency));
+        assert!(newton(40.0, 0.3, 0.8).is_none());
+        assert!(newton(40.0, -0.1, 0.9).is_none());
+        assert!(newton(-1.0, 0.25, 0.75).is_none());
+        // A feed without the non-target component leaves R_reject undefined
+        assert!(!calculate_newton_efficiency(100.0, 1.0, 0.0, 40.0, 1.0, 0.0, &mut efficiency));
+    }
+}
@@ -0,0 +1,123 @@
+use std::ffi::c_double;
+
+/// Reference point of the Adler-Nissen pK temperature relation.
+const PK_REFERENCE: f64 = 7.8;
+const REFERENCE_TEMPERATURE_K: f64 = 298.0;
+/// Temperature coefficient of the relation, in K.
+const PK_TEMPERATURE_COEFFICIENT: f64 = 2400.0;
+
+/// Average pK of the released α-amino groups at `temperature_c` (Adler-Nissen):
+/// pK = 7.8 + (298 - T) / (298 * T) * 2400 with T in kelvin.
+fn amino_pk(temperature_c: f64) -> f64 {
+    let t = temperature_c + 273.15;
+    PK_REFERENCE + (REFERENCE_TEMPERATURE_K - t) / (REFERENCE_TEMPERATURE_K * t) * PK_TEMPERATURE_COEFFICIENT
+}
+
+/// Degree of dissociation of the α-amino groups:
+/// 10^(pH - pK) / (1 + 10^(pH - pK)).
+fn dissociation_degree(ph: f64, pk: f64) -> f64 {
+    let ratio = 10f64.powf(ph - pk);
+    ratio / (1.0 + ratio)
+}
+
+/// Degree of hydrolysis in percent from pH-stat base consumption:
+/// DH = B * Nb / (α * MP * htot) * 100.
+///
+/// `base_volume_ml` and `base_normality` (eq/L) give the base consumed in
+/// meq, `protein_mass_g` is the protein in the reaction and `h_tot` the
+/// peptide bonds per gram of protein (meq/g, about 7.8 for pea). When
+/// `compute_alpha` is set `alpha` is ignored and derived from `ph` and `pk`
+/// instead, with a `pk` of 0 meaning the Adler-Nissen value at
+/// `temperature_c`. A DH above 100% is clamped to 100 with `capped_out`
+/// set, since it points at a measurement or parameter error.
+///
+/// Returns false on null pointers, a NaN or negative base volume, or a
+/// non-positive normality, α, protein mass or htot.
+#[no_mangle]
+pub extern "C" fn calculate_degree_of_hydrolysis(
+    base_volume_ml: c_double,
+    base_normality: c_double,
+    alpha: c_double,
+    protein_mass_g: c_double,
+    h_tot: c_double,
+    compute_alpha: bool,
+    ph: c_double,
+    pk: c_double,
+    temperature_c: c_double,
+    result: *mut c_double,
+    capped_out: *mut bool
+) -> bool {
+    if result.is_null() || capped_out.is_null() {
+        return false;
+    }
+    let alpha = if compute_alpha {
+        let pk = if pk == 0.0 { amino_pk(temperature_c) } else { pk };
+        dissociation_degree(ph, pk)
+    } else {
+        alpha
+    };
+    let positive = |x: f64| x > 0.0 && x.is_finite();
+    if base_volume_ml.is_nan() || base_volume_ml < 0.0 || !positive(base_normality) || !positive(alpha)
+        || !positive(protein_mass_g) || !positive(h_tot) {
+        return false;
+    }
+
+    let dh = base_volume_ml * base_normality / (alpha * protein_mass_g * h_tot) * 100.0;
+    unsafe {
+        *result = dh.min(100.0);
+        *capped_out = dh > 100.0;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn degree_of_hydrolysis(base_volume_ml: f64, alpha: f64, compute_alpha: bool, temperature_c: f64) -> Option<(f64, bool)> {
+        let (mut dh, mut capped) = (f64::NAN, false);
+        calculate_degree_of_hydrolysis(base_volume_ml, 0.5, alpha, 8.0, 7.8, compute_alpha, 8.0, 0.0, temperature_c, &mut dh, &mut capped)
+            .then_some((dh, capped))
+    }
+
+    #[test]
+    fn worked_ph_stat_example() {
+        // 2 mL of 0.5 N NaOH is 1 meq; 1 / (0.885 * 8 g * 7.8 meq/g) = 1.811 %
+        let (dh, capped) = degree_of_hydrolysis(2.0, 0.885, false, 0.0).unwrap();
+        assert!((dh - 100.0 / (0.885 * 8.0 * 7.8)).abs() < 1e-12);
+        assert!((dh - 1.811).abs() < 5e-4);
+        assert!(!capped);
+    }
+
+    #[test]
+    fn dissociation_at_ph_8_and_50_c() {
+        // Adler-Nissen tabulates 1/alpha = 1.13 at pH 8 and 50 C
+        let alpha = dissociation_degree(8.0, amino_pk(50.0));
+        assert!((1.0 / alpha / 1.13 - 1.0).abs() < 0.02, "{}", 1.0 / alpha);
+        assert!((amino_pk(REFERENCE_TEMPERATURE_K - 273.15) - PK_REFERENCE).abs() < 1e-12);
+
+        let (dh, _) = degree_of_hydrolysis(2.0, f64::NAN, true, 50.0).unwrap();
+        assert!((dh - 100.0 / (alpha * 8.0 * 7.8)).abs() < 1e-12);
+        // An explicit pK takes precedence over the temperature relation
+        let (mut explicit, mut capped) = (f64::NAN, false);
+        assert!(calculate_degree_of_hydrolysis(2.0, 0.5, 0.0, 8.0, 7.8, true, 8.0, 8.0, 50.0, &mut explicit, &mut capped));
+        assert!((explicit - 100.0 / (0.5 * 8.0 * 7.8)).abs() < 1e-12);
+    }
+
+    #[test]
+    fn dh_above_100_is_capped_and_flagged() {
+        assert_eq!(degree_of_hydrolysis(200.0, 0.885, false, 0.0), Some((100.0, true)));
+    }
+
+    #[test]
+    fn non_positive_inputs_are_rejected() {
+        assert!(degree_of_hydrolysis(-1.0, 0.885, false, 0.0).is_none());
+        assert!(degree_of_hydrolysis(f64::NAN, 0.885, false, 0.0).is_none());
+        assert!(degree_of_hydrolysis(2.0, 0.0, false, 0.0).is_none());
+        let (mut dh, mut capped) = (f64::NAN, false);
+        assert!(!calculate_degree_of_hydrolysis(2.0, 0.5, 0.885, 0.0, 7.8, false, 0.0, 0.0, 0.0, &mut dh, &mut capped));
+        assert!(!calculate_degree_of_hydrolysis(2.0, 0.5, 0.885, 8.0, 0.0, false, 0.0, 0.0, 0.0, &mut dh, &mut capped));
+        assert!(!calculate_degree_of_hydrolysis(2.0, 0.0, 0.885, 8.0, 7.8, false, 0.0, 0.0, 0.0, &mut dh, &mut capped));
+        assert_eq!(degree_of_hydrolysis(0.0, 0.885, false, 0.0), Some((0.0, false)));
+    }
+}
@@ -0,0 +1,189 @@
+use std::ffi::c_double;
+use super::curve_fit::{levenberg_marquardt, r_squared};
+
+const MIN_KINETICS_POINTS: usize = 4;
+/// Rate constants tried by the grid search fallback, log-spaced over
+/// `GRID_RANGE` decades either side of 1 / t_max.
+const GRID_POINTS: usize = 200;
+const GRID_RANGE: f64 = 3.0;
+
+fn first_order_model(t: f64, params: &[f64], gradient: &mut [f64]) -> f64 {
+    let (k, y_inf) = (params[0], params[1]);
+    let decay = (-k * t).exp();
+    gradient[0] = y_inf * t * decay;
+    gradient[1] = 1.0 - decay;
+    y_inf * (1.0 - decay)
+}
+
+fn sum_of_squares(times: &[f64], yields: &[f64], params: &[f64]) -> f64 {
+    let mut gradient = [0.0; 2];
+    times.iter().zip(yields).map(|(&t, &y)| (y - first_order_model(t, params, &mut gradient)).powi(2)).sum()
+}
+
+/// Starting point: the plateau from the largest yield and the rate constant
+/// from the initial slope k·y_inf, taken from the origin to the first point
+/// after t = 0.
+fn initial_guess(times: &[f64], yields: &[f64]) -> Option<[f64; 2]> {
+    let y_inf = yields.iter().copied().fold(f64::NEG_INFINITY, f64::max);
+    let (t, y) = times.iter().zip(yields).find(|(&t, _)| t > 0.0)?;
+    let k = y / (t * y_inf);
+    (k.is_finite() && k > 0.0 && y_inf > 0.0).then_some([k, y_inf])
+}
+
+/// Decadic log of the smallest and largest rate constant considered.
+fn log_k_bounds(times: &[f64]) -> (f64, f64) {
+    let centre = (1.0 / times[times.len() - 1]).log10();
+    (centre - GRID_RANGE, centre + GRID_RANGE)
+}
+
+/// Best (k, y_inf) over a log-spaced grid of rate constants, with y_inf
+/// solved exactly by linear least squares for each k.
+fn grid_search(times: &[f64], yields: &[f64]) -> Option<[f64; 2]> {
+    let (lo, hi) = log_k_bounds(times);
+    (0..GRID_POINTS).filter_map(|i| {
+        let k = 10f64.powf(lo + (hi - lo) * i as f64 / (GRID_POINTS - 1) as f64);
+        let (gy, gg) = times.iter().zip(yields).fold((0.0, 0.0), |(gy, gg), (&t, &y)| {
+            let g = 1.0 - (-k * t).exp();
+            (gy + g * y, gg + g * g)
+        });
+        let params = [k, gy / gg];
+        let sse = sum_of_squares(times, yields, &params);
+        sse.is_finite().then_some((params, sse))
+    })
+    .min_by(|a, b| a.1.total_cmp(&b.1))
+    .map(|(params, _)| params)
+}
+
+/// Levenberg-Marquardt refinement from `initial`, kept only when the rate
+/// constant stays within the grid range and the plateau is finite; beyond
+/// it the data no longer pin k down and the fit has run off.
+fn refine(times: &[f64], yields: &[f64], initial: &[f64; 2]) -> Option<[f64; 2]> {
+    let params = levenberg_marquardt(times, yields, initial, first_order_model)?;
+    let (lo, hi) = log_k_bounds(times);
+    let log_k = params[0].log10();
+    (log_k >= lo && log_k <= hi && params[1].is_finite()).then_some([params[0], params[1]])
+}
+
+/// Fit first-order extraction kinetics y(t) = y_inf * (1 - exp(-k * t)).
+///
+/// The fit starts from y_inf at the largest yield and k from the initial
+/// slope, and is refined by Levenberg-Marquardt (damped Gauss-Newton). If
+/// that start is unusable or the refinement diverges (k leaving the six
+/// decades around 1 / t_max), the rate constant is instead located by a
+/// grid search over those decades and refined from there, keeping the grid
+/// point if refinement fails again.
+/// `k_out` receives the rate constant in inverse time units, `y_inf_out`
+/// the plateau yield in the units of `yields` and `r_squared_out` the R².
+///
+/// Returns false on null pointers, fewer than 4 points, non-finite values,
+/// negative or not strictly increasing times, or when neither the initial
+/// guess nor the grid gives a usable start.
+#[no_mangle]
+pub extern "C" fn fit_first_order_kinetics(
+    times: *const c_double,
+    yields: *const c_double,
+    len: usize,
+    k_out: *mut c_double,
+    y_inf_out: *mut c_double,
+    r_squared_out: *mut c_double
+) -> bool {
+    if times.is_null() || yields.is_null() || k_out.is_null() || y_inf_out.is_null() || r_squared_out.is_null()
+        || len < MIN_KINETICS_POINTS {
+        return false;
+    }
+    let (times, yields) = unsafe {
+        (std::slice::from_raw_parts(times, len), std::slice::from_raw_parts(yields, len))
+    };
+    if times.iter().chain(yields).any(|x| !x.is_finite()) || times[0] < 0.0
+        || times.windows(2).any(|pair| pair[1] <= pair[0]) {
+        return false;
+    }
+
+    let params = match initial_guess(times, yields).and_then(|initial| refine(times, yields, &initial)) {
+        Some(params) => params,
+        None => match grid_search(times, yields) {
+            Some(start) => refine(times, yields, &start).unwrap_or(start),
+            None => return false,
+        },
+    };
+    let mut gradient = [0.0; 2];
+    let fitted: Vec<f64> = times.iter().map(|&t| first_order_model(t, &params, &mut gradient)).collect();
+    unsafe {
+        *k_out = params[0];
+        *y_inf_out = params[1];
+        *r_squared_out = r_squared(yields, &fitted);
+    }
+    true
+}
+
+/// Yield predicted at time `t` by first-order kinetics with rate constant
+/// `k` and plateau `y_inf`.
+#[no_mangle]
+pub extern "C" fn evaluate_first_order(k: c_double, y_inf: c_double, t: c_double) -> c_double {
+    first_order_model(t, &[k, y_inf], &mut [0.0; 2])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn fit(times: &[f64], yields: &[f64]) -> Option<(f64, f64, f64)> {
+        let (mut k, mut y_inf, mut r_squared) = (f64::NAN, f64::NAN, f64::NAN);
+        fit_first_order_kinetics(times.as_ptr(), yields.as_ptr(), times.len(), &mut k, &mut y_inf, &mut r_squared)
+            .then_some((k, y_inf, r_squared))
+    }
+
+    // `noise` is the standard deviation as a fraction of the plateau
+    fn noisy_curve(k: f64, y_inf: f64, noise: f64) -> (Vec<f64>, Vec<f64>) {
+        use rand::{rngs::StdRng, SeedableRng};
+        use rand_distr::{Distribution, Normal};
+        let mut rng = StdRng::seed_from_u64(3);
+        let normal = Normal::new(0.0, noise * y_inf).unwrap();
+        let times: Vec<f64> = (0..12).map(|i| i as f64 * 5.0).collect();
+        let yields = times.iter().map(|&t| evaluate_first_order(k, y_inf, t) + normal.sample(&mut rng)).collect();
+        (times, yields)
+    }
+
+    #[test]
+    fn rate_constant_is_recovered_from_noisy_data() {
+        for (k, y_inf) in [(0.15, 42.0), (0.05, 80.0), (0.3, 10.0)] {
+            let (times, yields) = noisy_curve(k, y_inf, 0.01);
+            let (fitted_k, fitted_y_inf, r_squared) = fit(&times, &yields).unwrap();
+            assert!((fitted_k / k - 1.0).abs() < 0.05, "k {k}: {fitted_k}");
+            assert!((fitted_y_inf / y_inf - 1.0).abs() < 0.05, "y_inf {y_inf}: {fitted_y_inf}");
+            assert!(r_squared > 0.95);
+        }
+    }
+
+    #[test]
+    fn prediction_round_trips_the_fit() {
+        let (times, yields) = noisy_curve(0.15, 42.0, 0.0);
+        let (k, y_inf, r_squared) = fit(&times, &yields).unwrap();
+        assert!((r_squared - 1.0).abs() < 1e-9);
+        for (&t, &y) in times.iter().zip(&yields) {
+            assert!((evaluate_first_order(k, y_inf, t) - y).abs() < 1e-6);
+        }
+        assert_eq!(evaluate_first_order(k, y_inf, 0.0), 0.0);
+    }
+
+    #[test]
+    fn unusable_initial_guess_falls_back_to_the_grid() {
+        // The first yield after t = 0 is negative, so the initial slope gives no k
+        let times = [0.0, 1.0, 2.0, 4.0, 8.0, 16.0];
+        let yields = [0.0, -0.5, 8.0, 14.0, 19.0, 20.0];
+        assert!(initial_guess(&times, &yields).is_none());
+        let (k, y_inf, _) = fit(&times, &yields).unwrap();
+        assert!(k > 0.0 && (y_inf - 20.0).abs() < 3.0, "{k} {y_inf}");
+    }
+
+    #[test]
+    fn short_or_unordered_series_are_rejected() {
+        let yields = [0.0, 5.0, 8.0, 9.0];
+        assert!(fit(&[0.0, 1.0, 2.0], &yields[..3]).is_none());
+        assert!(fit(&[0.0, 1.0, 1.0, 4.0], &yields).is_none());
+        assert!(fit(&[0.0, 2.0, 1.0, 4.0], &yields).is_none());
+        assert!(fit(&[-1.0, 1.0, 2.0, 4.0], &yields).is_none());
+        assert!(fit(&[0.0, 1.0, 2.0, f64::NAN], &yields).is_none());
+        assert!(fit(&[0.0, 1.0, 2.0, 4.0], &yields).is_some());
+    }
+}
@@ -1,9 +1,75 @@
 // Protein analysis module
 
+mod amino_acids;
+mod classification;
+mod curve_fit;
+mod denaturation;
+mod fractionation;
+mod hydrolysis;
+mod kinetics;
 mod protein_calculator;
+mod psd_bootstrap;
+mod psd_compare;
+mod psd_fit;
+mod psd_modes;
+mod response_surface;
+mod solubility;
 
+pub use amino_acids::{calculate_amino_acid_score, calculate_pdcaas, AMINO_ACID_COUNT};
+pub use classification::{compute_grade_efficiency, estimate_cut_size, GRADE_EFFICIENCY_WIDTH};
+pub use denaturation::{
+    calculate_denatured_fraction,
+    calculate_isothermal_denatured_fraction,
+    TIME_TEMPERATURE_WIDTH,
+};
+pub use fractionation::{
+    calculate_air_classification_metrics,
+    calculate_cascade_efficiency,
+    calculate_newton_efficiency,
+    calculate_protein_purity,
+    calculate_protein_purity_batch,
+    calculate_purity_from_streams,
+    calculate_separation_efficiency_checked,
+    check_component_balance,
+    check_mass_balance,
+    compute_yield_purity_curve,
+    AirClassificationMetrics,
+    StreamPurity,
+    YIELD_PURITY_WIDTH,
+};
+pub use hydrolysis::calculate_degree_of_hydrolysis;
+pub use kinetics::{evaluate_first_order, fit_first_order_kinetics};
 pub use protein_calculator::{
     analyze_particle_distribution,
+    analyze_particle_distribution_batch,
+    analyze_particle_distribution_ex,
+    analyze_particle_distribution_v2,
+    analyze_sieve_distribution,
+    build_psd_histogram,
+    calculate_overall_recovery,
     calculate_protein_recovery,
+    calculate_protein_recovery_checked,
+    calculate_protein_recovery_from_nitrogen,
+    calculate_protein_recovery_mb,
     calculate_separation_efficiency,
+    compute_fraction_below,
+    convert_psd_weighting,
+    correct_protein_content,
+    get_psd_percentiles,
+    nitrogen_to_protein,
+    ModeStatus,
+    OverallRecovery,
+    PercentileMethod,
+    PsdStatus,
+    PsdSummary,
+    PSD_BATCH_WIDTH,
+    RatioUnit,
+    RecoveryStatus,
+    WeightBasis,
 };
+pub use psd_bootstrap::{bootstrap_psd_statistics, PSD_BOOTSTRAP_STATISTICS, PSD_BOOTSTRAP_WIDTH};
+pub use psd_compare::{compare_psd, ks_test_psd, PsdComparison};
+pub use psd_fit::{fit_lognormal_psd, fit_rosin_rammler};
+pub use psd_modes::{detect_psd_modes, PSD_MODE_WIDTH};
+pub use response_surface::{find_stationary_point, fit_response_surface, ResponseSurfaceStatus, StationaryKind};
+pub use solubility::{evaluate_solubility, fit_solubility_profile, SOLUBILITY_PARAMS};
@@ -1,5 +1,6 @@
-use std::ffi::{c_double};
+use std::ffi::c_double;
 use std::cmp::Ordering;
+use rayon::prelude::*;
 
 #[no_mangle]
 pub extern "C" fn calculate_protein_recovery(
@@ -10,91 +11,1358 @@ pub extern "C" fn calculate_protein_recovery(
     (protein_yield * protein_content * separation_efficiency) / 100.0
 }
 
+/// Unit of a ratio passed to or returned by
+/// `calculate_protein_recovery_checked`, which takes it by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RatioUnit {
+    /// 0 to 1
+    Fraction = 0,
+    /// 0 to 100
+    Percent = 1,
+}
+
+impl RatioUnit {
+    pub(crate) fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Fraction),
+            1 => Some(Self::Percent),
+            _ => None,
+        }
+    }
+
+    fn scale(self) -> f64 {
+        match self {
+            Self::Fraction => 1.0,
+            Self::Percent => 100.0,
+        }
+    }
+
+    /// `value` as a fraction, or None outside the unit's range.
+    pub(crate) fn to_fraction(self, value: f64) -> Option<f64> {
+        (0.0..=self.scale()).contains(&value).then(|| value / self.scale())
+    }
+}
+
+/// Outcome of `calculate_protein_recovery_checked`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecoveryStatus {
+    Ok = 0,
+    /// The result pointer was null
+    NullPointer = 1,
+    /// The protein yield was NaN or outside its unit's range
+    YieldOutOfRange = 2,
+    /// The protein content was NaN or outside its unit's range
+    ContentOutOfRange = 3,
+    /// The separation efficiency was NaN or outside its unit's range
+    EfficiencyOutOfRange = 4,
+    /// A unit id was not a `RatioUnit`
+    UnknownUnit = 5,
+}
+
+/// Protein recovery, yield * content * efficiency, with the unit of each
+/// input declared explicitly.
+///
+/// Unlike `calculate_protein_recovery`, which assumes a particular mix of
+/// fractions and percentages, each input is checked against the range of
+/// its declared unit (0-1 or 0-100) so a fraction passed where a percentage
+/// is expected, or the reverse, is caught rather than silently scaled.
+/// The recovery is written in `output_unit`; `result` is left untouched
+/// unless the status is `RecoveryStatus::Ok`. Units are `RatioUnit` ids,
+/// and any other id gives `RecoveryStatus::UnknownUnit`.
+#[no_mangle]
+pub extern "C" fn calculate_protein_recovery_checked(
+    protein_yield: c_double,
+    yield_unit: u32,
+    protein_content: c_double,
+    content_unit: u32,
+    separation_efficiency: c_double,
+    efficiency_unit: u32,
+    output_unit: u32,
+    result: *mut c_double
+) -> RecoveryStatus {
+    if result.is_null() {
+        return RecoveryStatus::NullPointer;
+    }
+    let units = [yield_unit, content_unit, efficiency_unit, output_unit].map(RatioUnit::from_id);
+    let [yield_unit, content_unit, efficiency_unit, output_unit] = match units {
+        [Some(yield_unit), Some(content_unit), Some(efficiency_unit), Some(output_unit)] => {
+            [yield_unit, content_unit, efficiency_unit, output_unit]
+        }
+        _ => return RecoveryStatus::UnknownUnit,
+    };
+    let protein_yield = match yield_unit.to_fraction(protein_yield) {
+        Some(value) => value,
+        None => return RecoveryStatus::YieldOutOfRange,
+    };
+    let protein_content = match content_unit.to_fraction(protein_content) {
+        Some(value) => value,
+        None => return RecoveryStatus::ContentOutOfRange,
+    };
+    let separation_efficiency = match efficiency_unit.to_fraction(separation_efficiency) {
+        Some(value) => value,
+        None => return RecoveryStatus::EfficiencyOutOfRange,
+    };
+    unsafe {
+        *result = protein_yield * protein_content * separation_efficiency * output_unit.scale();
+    }
+    RecoveryStatus::Ok
+}
+
+/// Plausible range of nitrogen-to-protein conversion factors: 6.25 is the
+/// generic Jones factor and 5.36-5.7 are reported for pea.
+const MIN_NITROGEN_FACTOR: f64 = 4.0;
+const MAX_NITROGEN_FACTOR: f64 = 7.0;
+
+/// Protein content from a nitrogen content (e.g. Dumas), in the same units:
+/// nitrogen_content * conversion_factor. The factor must lie within
+/// 4.0-7.0 unless `allow_any_factor` is set, in which case it only has to
+/// be positive. Returns false on a null result pointer, a NaN nitrogen
+/// content, or a rejected factor.
+#[no_mangle]
+pub extern "C" fn nitrogen_to_protein(
+    nitrogen_content: c_double,
+    conversion_factor: c_double,
+    allow_any_factor: bool,
+    result: *mut c_double
+) -> bool {
+    let plausible = (MIN_NITROGEN_FACTOR..=MAX_NITROGEN_FACTOR).contains(&conversion_factor)
+        || (allow_any_factor && conversion_factor > 0.0 && conversion_factor.is_finite());
+    if result.is_null() || nitrogen_content.is_nan() || !plausible {
+        return false;
+    }
+    unsafe {
+        *result = nitrogen_content * conversion_factor;
+    }
+    true
+}
+
+/// `calculate_protein_recovery` with the protein content given as a
+/// nitrogen content and conversion factor, converted as
+/// `nitrogen_to_protein` does. Returns false when the conversion fails.
+#[no_mangle]
+pub extern "C" fn calculate_protein_recovery_from_nitrogen(
+    protein_yield: c_double,
+    nitrogen_content: c_double,
+    conversion_factor: c_double,
+    separation_efficiency: c_double,
+    allow_any_factor: bool,
+    result: *mut c_double
+) -> bool {
+    let mut protein_content = 0.0;
+    if result.is_null()
+        || !nitrogen_to_protein(nitrogen_content, conversion_factor, allow_any_factor, &mut protein_content) {
+        return false;
+    }
+    unsafe {
+        *result = calculate_protein_recovery(protein_yield, protein_content, separation_efficiency);
+    }
+    true
+}
+
+/// Moisture fractions must lie in [0, 1); at 1 there is no dry matter.
+fn valid_moisture(moisture: f64) -> bool {
+    (0.0..1.0).contains(&moisture)
+}
+
+/// Convert a protein content measured at `moisture_fraction` to the content
+/// at `target_moisture_fraction` (0 for dry basis):
+/// content * (1 - target) / (1 - moisture). Returns false on a null result
+/// pointer, a NaN content, or a moisture fraction outside [0, 1).
+#[no_mangle]
+pub extern "C" fn correct_protein_content(
+    protein_content_asis: c_double,
+    moisture_fraction: c_double,
+    target_moisture_fraction: c_double,
+    result: *mut c_double
+) -> bool {
+    if result.is_null() || protein_content_asis.is_nan()
+        || !valid_moisture(moisture_fraction) || !valid_moisture(target_moisture_fraction) {
+        return false;
+    }
+    unsafe {
+        *result = protein_content_asis * (1.0 - target_moisture_fraction) / (1.0 - moisture_fraction);
+    }
+    true
+}
+
+/// Protein recovery in percent from feed to product, with protein contents
+/// given as-is and corrected to dry basis using each stream's moisture.
+///
+/// Masses are dry-matter masses, so recovery is
+/// 100 * product_mass * product_content_db / (feed_mass * feed_content_db).
+/// Returns false on a null result pointer, a moisture fraction outside
+/// [0, 1), or feed protein that is not positive.
+#[no_mangle]
+pub extern "C" fn calculate_protein_recovery_mb(
+    feed_dry_mass: c_double,
+    feed_protein_asis: c_double,
+    feed_moisture: c_double,
+    product_dry_mass: c_double,
+    product_protein_asis: c_double,
+    product_moisture: c_double,
+    result: *mut c_double
+) -> bool {
+    let (mut feed_content, mut product_content) = (0.0, 0.0);
+    if result.is_null()
+        || !correct_protein_content(feed_protein_asis, feed_moisture, 0.0, &mut feed_content)
+        || !correct_protein_content(product_protein_asis, product_moisture, 0.0, &mut product_content) {
+        return false;
+    }
+    let feed_protein = feed_dry_mass * feed_content;
+    if feed_protein.is_nan() || feed_protein <= 0.0 {
+        return false;
+    }
+    unsafe {
+        *result = 100.0 * product_dry_mass * product_content / feed_protein;
+    }
+    true
+}
+
+/// Protein balance written by `calculate_overall_recovery`: total protein
+/// in and out (in mass units), overall recovery in percent, and the
+/// unaccounted loss, protein in minus protein out (negative when more
+/// protein was recovered than fed).
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OverallRecovery {
+    pub protein_in: c_double,
+    pub protein_out: c_double,
+    pub recovery: c_double,
+    pub unaccounted_loss: c_double,
+}
+
+/// Protein masses of (mass, content) streams, or None on a non-finite or
+/// negative mass or a content outside [0, `max_content`].
+fn stream_proteins(masses: &[f64], contents: &[f64], max_content: f64) -> Option<Vec<f64>> {
+    masses.iter().zip(contents).map(|(&mass, &content)| {
+        let valid = mass.is_finite() && mass >= 0.0 && (0.0..=max_content).contains(&content);
+        valid.then(|| mass * content / max_content)
+    }).collect()
+}
+
+/// Overall protein recovery of a process with several input and product
+/// streams.
+///
+/// Each stream is a mass and its protein content, as a fraction or, with
+/// `contents_in_percent`, in percent; a recycle stream fed back into the
+/// process counts as an input. `product_protein_out` receives the protein
+/// mass of each of the `n_outputs` products and `summary_out` the totals,
+/// with recovery = 100 * protein out / protein in; see `OverallRecovery`.
+/// With one input and one product this is the ratio of their protein
+/// masses. Returns false on null pointers, no input or product streams, a
+/// negative or non-finite mass, a content outside its range, or no protein
+/// in the inputs.
+#[no_mangle]
+pub extern "C" fn calculate_overall_recovery(
+    input_masses: *const c_double,
+    input_contents: *const c_double,
+    n_inputs: usize,
+    output_masses: *const c_double,
+    output_contents: *const c_double,
+    n_outputs: usize,
+    contents_in_percent: bool,
+    product_protein_out: *mut c_double,
+    summary_out: *mut OverallRecovery
+) -> bool {
+    if input_masses.is_null() || input_contents.is_null() || output_masses.is_null() || output_contents.is_null()
+        || product_protein_out.is_null() || summary_out.is_null() || n_inputs == 0 || n_outputs == 0 {
+        return false;
+    }
+    let (input_masses, input_contents, output_masses, output_contents) = unsafe {
+        (
+            std::slice::from_raw_parts(input_masses, n_inputs),
+            std::slice::from_raw_parts(input_contents, n_inputs),
+            std::slice::from_raw_parts(output_masses, n_outputs),
+            std::slice::from_raw_parts(output_contents, n_outputs),
+        )
+    };
+    let max_content = if contents_in_percent { 100.0 } else { 1.0 };
+    let (inputs, products) = match (
+        stream_proteins(input_masses, input_contents, max_content),
+        stream_proteins(output_masses, output_contents, max_content),
+    ) {
+        (Some(inputs), Some(products)) => (inputs, products),
+        _ => return false,
+    };
+    let protein_in: f64 = inputs.iter().sum();
+    if protein_in <= 0.0 {
+        return false;
+    }
+    let protein_out: f64 = products.iter().sum();
+
+    unsafe {
+        std::slice::from_raw_parts_mut(product_protein_out, n_outputs).copy_from_slice(&products);
+        *summary_out = OverallRecovery {
+            protein_in,
+            protein_out,
+            recovery: 100.0 * protein_out / protein_in,
+            unaccounted_loss: protein_in - protein_out,
+        };
+    }
+    true
+}
+
+/// Common size classes for binning one or more PSDs, evenly spaced in size
+/// or in ln(size).
+pub(super) struct SizeBins {
+    lo: f64,
+    hi: f64,
+    n_bins: usize,
+    log_spaced: bool,
+    // Lower bound and bin width on the transformed axis
+    t_lo: f64,
+    width: f64,
+}
+
+impl SizeBins {
+    /// Whether a sample can be binned: no NaN, no negative weights, and
+    /// strictly positive sizes for logarithmic bins.
+    pub(super) fn accepts(sizes: &[f64], weights: &[f64], log_spaced: bool) -> bool {
+        !(sizes.iter().any(|x| x.is_nan()) || weights.iter().any(|w| w.is_nan() || *w < 0.0)
+            || (log_spaced && sizes.iter().any(|&x| x <= 0.0)))
+    }
+
+    /// `n_bins` classes over `min_override`..`max_override`, either bound
+    /// falling back to the smallest or largest size across `samples` when
+    /// NaN. None for an empty or non-finite range, or a non-positive lower
+    /// bound with logarithmic bins.
+    pub(super) fn new(samples: &[&[f64]], n_bins: usize, log_spaced: bool, min_override: f64, max_override: f64) -> Option<Self> {
+        let sizes = || samples.iter().flat_map(|sample| sample.iter().copied());
+        let lo = if min_override.is_nan() { sizes().fold(f64::INFINITY, f64::min) } else { min_override };
+        let hi = if max_override.is_nan() { sizes().fold(f64::NEG_INFINITY, f64::max) } else { max_override };
+        if n_bins == 0 || lo >= hi || !lo.is_finite() || !hi.is_finite() || (log_spaced && lo <= 0.0) {
+            return None;
+        }
+        let transform = |x: f64| if log_spaced { x.ln() } else { x };
+        let t_lo = transform(lo);
+        let width = (transform(hi) - t_lo) / n_bins as f64;
+        Some(Self { lo, hi, n_bins, log_spaced, t_lo, width })
+    }
+
+    fn transform(&self, x: f64) -> f64 {
+        if self.log_spaced { x.ln() } else { x }
+    }
+
+    fn untransform(&self, t: f64) -> f64 {
+        if self.log_spaced { t.exp() } else { t }
+    }
+
+    /// Class of `size`, or None outside the range. The last class includes
+    /// its upper edge.
+    pub(super) fn bin(&self, size: f64) -> Option<usize> {
+        if size < self.lo || size > self.hi {
+            return None;
+        }
+        Some((((self.transform(size) - self.t_lo) / self.width) as usize).min(self.n_bins - 1))
+    }
+
+    /// The `n_bins + 1` class edges, with the outer edges pinned to the
+    /// exact bounds.
+    pub(super) fn edges(&self) -> Vec<f64> {
+        let mut edges: Vec<f64> = (0..=self.n_bins).map(|i| self.untransform(self.t_lo + self.width * i as f64)).collect();
+        edges[0] = self.lo;
+        edges[self.n_bins] = self.hi;
+        edges
+    }
+
+    /// Representative size of class `i`: the midpoint on the binning axis
+    /// (the geometric mean of the edges for logarithmic bins).
+    pub(super) fn centre(&self, i: usize) -> f64 {
+        self.untransform(self.t_lo + self.width * (i as f64 + 0.5))
+    }
+
+    /// Weight per class, the total weight and the weight outside the range.
+    pub(super) fn histogram(&self, sizes: &[f64], weights: &[f64]) -> (Vec<f64>, f64, f64) {
+        let mut counts = vec![0.0; self.n_bins];
+        let (mut total, mut clipped) = (0.0, 0.0);
+        for (&size, &weight) in sizes.iter().zip(weights) {
+            total += weight;
+            match self.bin(size) {
+                Some(bin) => counts[bin] += weight,
+                None => clipped += weight,
+            }
+        }
+        (counts, total, clipped)
+    }
+}
+
+/// Basis of the weights passed to the particle size analysis.
+///
+/// Number weights count particles; volume and mass weights give the
+/// amount of material in each size class (mass assumes constant density,
+/// so it is treated like volume). The FFI functions take it by id.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum WeightBasis {
+    Number = 0,
+    Volume = 1,
+    Mass = 2,
+}
+
+impl WeightBasis {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::Number),
+            1 => Some(Self::Volume),
+            2 => Some(Self::Mass),
+            _ => None,
+        }
+    }
+
+    fn is_number(self) -> bool {
+        self == Self::Number
+    }
+}
+
+/// How a percentile is read off the cumulative weight distribution.
+///
+/// With sizes sorted ascending, `C_i` is the cumulative weight fraction up to
+/// and including size `d_i` and `w_i` its own weight fraction.
+/// - `LinearCdf` interpolates linearly between the points (d_i, C_i),
+///   returning the smallest size below C_0.
+/// - `NearestRank` returns the smallest d_i with C_i >= p, without
+///   interpolation.
+/// - `MidpointCdf` places each size at the middle of its own weight,
+///   (d_i, C_i - w_i / 2), and interpolates linearly between those points,
+///   returning the end sizes outside them, as many diffraction instruments do.
+///
+/// For sizes 1..5 with weights 1, 2, 3, 2, 2 the D50 is 8/3, 3 and 3.2
+/// respectively.
+///
+/// Methods are passed as plain integers so that unknown values can be
+/// rejected rather than trusted.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum PercentileMethod {
+    #[default]
+    LinearCdf = 0,
+    NearestRank = 1,
+    MidpointCdf = 2,
+}
+
+impl PercentileMethod {
+    fn from_id(id: u32) -> Option<Self> {
+        match id {
+            0 => Some(Self::LinearCdf),
+            1 => Some(Self::NearestRank),
+            2 => Some(Self::MidpointCdf),
+            _ => None,
+        }
+    }
+}
+
+/// Size ratio beyond which a number-to-volume conversion is flagged: the
+/// largest particle then outweighs a million of the smallest and a handful
+/// of coarse particles dominates the converted distribution.
+const BROAD_SIZE_RATIO: f64 = 100.0;
+
+/// Weights converted between bases by d³ scaling, normalized to sum to 1,
+/// plus whether a number-to-volume conversion spans more than
+/// `BROAD_SIZE_RATIO` in size. None for NaN input, negative weights, a
+/// non-positive size when converting to number weights, or a zero total.
+fn convert_weights(sizes: &[f64], weights: &[f64], from: WeightBasis, to: WeightBasis) -> Option<(Vec<f64>, bool)> {
+    if sizes.iter().any(|x| x.is_nan()) || weights.iter().any(|w| w.is_nan() || *w < 0.0) {
+        return None;
+    }
+    let (converted, broad): (Vec<f64>, bool) = match (from.is_number(), to.is_number()) {
+        (true, false) => {
+            let (lo, hi) = sizes.iter().fold((f64::INFINITY, 0.0_f64), |(lo, hi), &d| (lo.min(d), hi.max(d)));
+            let converted = sizes.iter().zip(weights).map(|(&d, &w)| w * d * d * d).collect();
+            (converted, hi > lo * BROAD_SIZE_RATIO)
+        }
+        (false, true) => {
+            if sizes.iter().any(|&d| d <= 0.0) {
+                return None;
+            }
+            (sizes.iter().zip(weights).map(|(&d, &w)| w / (d * d * d)).collect(), false)
+        }
+        _ => (weights.to_vec(), false),
+    };
+    let total: f64 = converted.iter().sum();
+    if total <= 0.0 || !total.is_finite() {
+        return None;
+    }
+    Some((converted.into_iter().map(|w| w / total).collect(), broad))
+}
+
+/// How the mode in `PsdSummary` was obtained.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ModeStatus {
+    /// Maximum of a Gaussian kernel density estimate
+    #[default]
+    Kde = 0,
+    /// Fewer than `MIN_KDE_POINTS` points; the mode is the weighted median
+    /// and the density is NaN
+    MedianFallback = 1,
+    /// All sizes are identical; the mode is that size and the density NaN
+    PointMass = 2,
+}
+
+/// Points needed before the kernel density estimate is attempted.
+pub(super) const MIN_KDE_POINTS: usize = 5;
+/// Evaluation points of the density between the smallest and largest size.
+const KDE_GRID_POINTS: usize = 512;
+
+/// Statistics written by `analyze_particle_distribution_ex`.
+///
+/// Percentiles, mean and standard deviation are taken in the basis of the
+/// supplied weights. `d43` is the De Brouckere mean D[4,3] and `d32` the
+/// Sauter mean D[3,2]; either is NaN when its denominator is zero.
+/// `span` is (D90 - D10) / D50 and `uniformity` the Hazen uniformity
+/// coefficient D60 / D10 (NaN when D10 is zero). `mode` is the size of
+/// maximum density and `mode_density` the density there, per unit size;
+/// `mode_status` tells how they were obtained. `cv` is the coefficient of
+/// variation std_dev / mean and `quartile_span` the relative interquartile
+/// width (D75 - D25) / D50. `specific_surface_area` is the surface per unit
+/// particle volume, 6 * shape_factor / D[3,2], in inverse size units.
+/// `geometric_mean` is exp of the weighted mean of ln(size) and
+/// `geometric_std_dev` exp of its weighted standard deviation; both are NaN
+/// unless every size is strictly positive.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PsdSummary {
+    pub d10: c_double,
+    pub d50: c_double,
+    pub d90: c_double,
+    pub mean: c_double,
+    pub std_dev: c_double,
+    pub d43: c_double,
+    pub d32: c_double,
+    pub span: c_double,
+    pub uniformity: c_double,
+    pub mode: c_double,
+    pub mode_density: c_double,
+    pub mode_status: ModeStatus,
+    pub d25: c_double,
+    pub d75: c_double,
+    pub cv: c_double,
+    pub quartile_span: c_double,
+    pub specific_surface_area: c_double,
+    pub geometric_mean: c_double,
+    pub geometric_std_dev: c_double,
+}
+
+/// Size/weight pairs sorted by size with their normalized cumulative weights.
+pub(super) struct WeightedPsd {
+    pub(super) size_weight: Vec<(f64, f64)>,
+    pub(super) cumulative: Vec<f64>,
+    pub(super) total_weight: f64,
+}
+
+impl WeightedPsd {
+    /// Fails on non-finite sizes or weights, negative weights, or weights
+    /// that do not sum to a positive total.
+    pub(super) fn new(sizes: &[f64], weights: &[f64]) -> Result<Self, PsdStatus> {
+        if sizes.iter().any(|x| !x.is_finite()) {
+            return Err(PsdStatus::NonFiniteSize);
+        }
+        if weights.iter().any(|x| !x.is_finite()) {
+            return Err(PsdStatus::NonFiniteWeight);
+        }
+        if weights.iter().any(|&x| x < 0.0) {
+            return Err(PsdStatus::NegativeWeight);
+        }
+
+        // Pre-allocate with capacity
+        let mut size_weight: Vec<(f64, f64)> = Vec::with_capacity(sizes.len());
+        size_weight.extend(sizes.iter().zip(weights.iter()).map(|(&s, &w)| (s, w)));
+
+        // Sort by size using unstable sort (faster)
+        size_weight.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
+
+        // Calculate total weight once
+        let total_weight: f64 = weights.iter().sum();
+        if total_weight <= 0.0 {
+            return Err(PsdStatus::ZeroTotalWeight);
+        }
+
+        // Pre-allocate cumulative weights
+        let mut cumulative = Vec::with_capacity(sizes.len());
+        let mut cum_sum = 0.0;
+        let weight_factor = 1.0 / total_weight;
+
+        // Optimize cumulative weight calculation
+        for (_, w) in &size_weight {
+            cum_sum += w * weight_factor;
+            cumulative.push(cum_sum);
+        }
+
+        Ok(Self { size_weight, cumulative, total_weight })
+    }
+
+    /// Weighted mean and standard deviation of size.
+    pub(super) fn mean_and_std(&self) -> (f64, f64) {
+        let weight_factor = 1.0 / self.total_weight;
+
+        // Calculate weighted mean with single pass
+        let weighted_mean: f64 = self.size_weight.iter()
+            .fold(0.0, |acc, (s, w)| acc + s * (w * weight_factor));
+
+        // Calculate weighted variance with single pass
+        let weighted_var: f64 = self.size_weight.iter()
+            .fold(0.0, |acc, (s, w)| {
+                let diff = s - weighted_mean;
+                acc + diff * diff * (w * weight_factor)
+            });
+
+        (weighted_mean, weighted_var.sqrt())
+    }
+
+    /// Size at cumulative fraction `p`, interpolated linearly between the
+    /// bracketing points.
+    pub(super) fn percentile(&self, p: f64) -> f64 {
+        self.percentile_with(p, PercentileMethod::LinearCdf)
+    }
+
+    /// Size at cumulative fraction `p` read off as `method` describes.
+    pub(super) fn percentile_with(&self, p: f64, method: PercentileMethod) -> f64 {
+        let size_weight = &self.size_weight;
+        let len = size_weight.len();
+        if method == PercentileMethod::NearestRank {
+            let idx = self.cumulative.partition_point(|&c| c < p);
+            return size_weight[idx.min(len - 1)].0;
+        }
+        let midpoints: Vec<f64>;
+        let positions = if method == PercentileMethod::MidpointCdf {
+            let weight_factor = 1.0 / self.total_weight;
+            midpoints = self.cumulative.iter().zip(size_weight)
+                .map(|(&c, &(_, w))| c - 0.5 * w * weight_factor)
+                .collect();
+            &midpoints
+        } else {
+            &self.cumulative
+        };
+        // Optimize percentile calculation with binary search
+        match positions.binary_search_by(|&x| x.partial_cmp(&p).unwrap_or(Ordering::Equal)) {
+            Ok(idx) => size_weight[idx].0,
+            Err(0) => size_weight[0].0,
+            Err(idx) if idx >= len => size_weight[len-1].0,
+            Err(idx) => {
+                let (x0, x1) = (size_weight[idx-1].0, size_weight[idx].0);
+                let (y0, y1) = (positions[idx-1], positions[idx]);
+                x0 + (x1 - x0) * (p - y0) / (y1 - y0)
+            }
+        }
+    }
+
+    /// Weighted mean and standard deviation of ln(size), or None unless
+    /// every size is strictly positive.
+    pub(super) fn log_mean_and_std(&self) -> Option<(f64, f64)> {
+        if self.size_weight.iter().any(|&(d, _)| d <= 0.0) {
+            return None;
+        }
+        let weight_factor = 1.0 / self.total_weight;
+        let mu: f64 = self.size_weight.iter().map(|&(d, w)| d.ln() * w * weight_factor).sum();
+        let variance: f64 = self.size_weight.iter()
+            .map(|&(d, w)| (d.ln() - mu).powi(2) * w * weight_factor)
+            .sum();
+        Some((mu, variance.sqrt()))
+    }
+
+    /// Weight fraction at or below `cutoff`, the inverse of `percentile`:
+    /// interpolated linearly between the bracketing points, 0 below the
+    /// smallest size and 1 from the largest size up.
+    pub(super) fn fraction_below(&self, cutoff: f64) -> f64 {
+        let (size_weight, cumulative) = (&self.size_weight, &self.cumulative);
+        let idx = size_weight.partition_point(|&(size, _)| size <= cutoff);
+        if idx == 0 {
+            return 0.0;
+        }
+        if idx >= size_weight.len() {
+            return 1.0;
+        }
+        let (x0, x1) = (size_weight[idx-1].0, size_weight[idx].0);
+        let (y0, y1) = (cumulative[idx-1], cumulative[idx]);
+        if x0 == cutoff {
+            return y0;
+        }
+        y0 + (y1 - y0) * (cutoff - x0) / (x1 - x0)
+    }
+
+    /// Silverman's bandwidth, 0.9 * min(std, IQR / 1.34) * n_eff^(-1/5),
+    /// where n_eff is the Kish effective sample size of the weights.
+    pub(super) fn silverman_bandwidth(&self) -> f64 {
+        let (_, std_dev) = self.mean_and_std();
+        let iqr = (self.percentile(0.75) - self.percentile(0.25)) / 1.34;
+        let spread = if iqr > 0.0 { std_dev.min(iqr) } else { std_dev };
+        0.9 * spread * self.effective_sample_size().powf(-0.2)
+    }
+
+    /// Kish effective sample size (Σw)² / Σw²: the number of equally
+    /// weighted particles carrying the same information as the weights.
+    pub(super) fn effective_sample_size(&self) -> f64 {
+        let sum_sq: f64 = self.size_weight.iter().map(|(_, w)| w * w).sum();
+        self.total_weight * self.total_weight / sum_sq
+    }
+
+    /// Weighted Gaussian kernel density on `KDE_GRID_POINTS` evenly spaced
+    /// points from `padding` bandwidths below the smallest size to `padding`
+    /// above the largest. Returns the first grid point, the grid step and
+    /// the densities.
+    pub(super) fn density_grid(&self, bandwidth: f64, padding: f64) -> (f64, f64, Vec<f64>) {
+        let size_weight = &self.size_weight;
+        let lo = size_weight[0].0 - padding * bandwidth;
+        let hi = size_weight[size_weight.len() - 1].0 + padding * bandwidth;
+
+        let norm = 1.0 / (self.total_weight * bandwidth * (2.0 * std::f64::consts::PI).sqrt());
+        let density = |x: f64| norm * size_weight.iter()
+            .map(|&(d, w)| {
+                let z = (x - d) / bandwidth;
+                w * (-0.5 * z * z).exp()
+            })
+            .sum::<f64>();
+
+        let step = (hi - lo) / (KDE_GRID_POINTS - 1) as f64;
+        let densities = (0..KDE_GRID_POINTS).map(|i| density(lo + step * i as f64)).collect();
+        (lo, step, densities)
+    }
+
+    /// Size and density at the maximum of a weighted Gaussian kernel
+    /// density estimate with Silverman's bandwidth, searched between the
+    /// smallest and largest size.
+    fn mode(&self) -> (f64, f64, ModeStatus) {
+        let size_weight = &self.size_weight;
+        if size_weight.len() < MIN_KDE_POINTS {
+            return (self.percentile(0.5), f64::NAN, ModeStatus::MedianFallback);
+        }
+        let (lo, hi) = (size_weight[0].0, size_weight[size_weight.len() - 1].0);
+        if lo == hi {
+            return (lo, f64::NAN, ModeStatus::PointMass);
+        }
+
+        let (lo, step, densities) = self.density_grid(self.silverman_bandwidth(), 0.0);
+        let (mode, peak) = densities.iter().enumerate()
+            .map(|(i, &f)| (lo + step * i as f64, f))
+            .fold((lo, f64::NEG_INFINITY), |best, (x, f)| if f > best.1 { (x, f) } else { best });
+        (mode, peak, ModeStatus::Kde)
+    }
+
+    /// De Brouckere D[4,3] and Sauter D[3,2] mean diameters.
+    ///
+    /// Number weights use the moment ratios Σw·d⁴/Σw·d³ and Σw·d³/Σw·d²;
+    /// volume or mass weights already carry the d³ factor, which leaves
+    /// Σw·d/Σw and Σw/Σ(w/d). NaN when a denominator is zero.
+    fn moment_means(&self, basis: WeightBasis) -> (f64, f64) {
+        let ratio = |num: f64, den: f64| if den != 0.0 && den.is_finite() { num / den } else { f64::NAN };
+        match basis {
+            WeightBasis::Number => {
+                let (m2, m3, m4) = self.size_weight.iter().fold((0.0, 0.0, 0.0), |(m2, m3, m4), &(d, w)| {
+                    let d2 = d * d;
+                    (m2 + w * d2, m3 + w * d2 * d, m4 + w * d2 * d2)
+                });
+                (ratio(m4, m3), ratio(m3, m2))
+            }
+            WeightBasis::Volume | WeightBasis::Mass => {
+                let (m0, m1, m_inv) = self.size_weight.iter().fold((0.0, 0.0, 0.0), |(m0, m1, m_inv), &(d, w)| {
+                    (m0 + w, m1 + w * d, m_inv + w / d)
+                });
+                (ratio(m1, m0), ratio(m0, m_inv))
+            }
+        }
+    }
+}
+
+/// Outcome of `analyze_particle_distribution_v2`, and of each sample in
+/// `analyze_particle_distribution_batch`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PsdStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// `len` was 0
+    Empty = 2,
+    /// A size was NaN or infinite
+    NonFiniteSize = 3,
+    /// A weight was NaN or infinite
+    NonFiniteWeight = 4,
+    /// A weight was negative
+    NegativeWeight = 5,
+    /// The weights sum to zero
+    ZeroTotalWeight = 6,
+    /// A size was negative
+    NegativeSize = 7,
+    /// `interpolation_method` is not a `PercentileMethod`
+    UnknownMethod = 8,
+}
+
+/// Why a (size, weight) row is invalid: a non-finite or negative value.
+fn row_status(size: f64, weight: f64) -> Option<PsdStatus> {
+    if !size.is_finite() {
+        Some(PsdStatus::NonFiniteSize)
+    } else if !weight.is_finite() {
+        Some(PsdStatus::NonFiniteWeight)
+    } else if size < 0.0 {
+        Some(PsdStatus::NegativeSize)
+    } else if weight < 0.0 {
+        Some(PsdStatus::NegativeWeight)
+    } else {
+        None
+    }
+}
+
+/// Index and reason of the first invalid row.
+pub(super) fn first_invalid_row(sizes: &[f64], weights: &[f64]) -> Option<(usize, PsdStatus)> {
+    sizes.iter().zip(weights).enumerate()
+        .find_map(|(i, (&size, &weight))| row_status(size, weight).map(|status| (i, status)))
+}
+
+/// Values per sample written by `analyze_particle_distribution_batch`:
+/// D10, D50, D90, weighted mean and standard deviation.
+pub const PSD_BATCH_WIDTH: usize = 5;
+
+/// The statistics reported by `analyze_particle_distribution_v2`, in
+/// `PSD_BATCH_WIDTH` order.
+pub(super) fn basic_statistics(psd: &WeightedPsd, method: PercentileMethod) -> [f64; PSD_BATCH_WIDTH] {
+    let (mean, std_dev) = psd.mean_and_std();
+    [
+        psd.percentile_with(0.1, method),
+        psd.percentile_with(0.5, method),
+        psd.percentile_with(0.9, method),
+        mean,
+        std_dev,
+    ]
+}
+
 /// Calculate weighted percentiles and statistics for particle size distribution
+///
+/// Returns false wherever `analyze_particle_distribution_v2` reports a
+/// status other than `PsdStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn analyze_particle_distribution(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    d10: *mut f64,
+    d50: *mut f64,
+    d90: *mut f64,
+    mean: *mut f64,
+    std_dev: *mut f64
+) -> bool {
+    let (mut invalid_index, mut dropped) = (0, 0);
+    analyze_particle_distribution_v2(
+        sizes, weights, len, d10, d50, d90, mean, std_dev, false, &mut invalid_index, &mut dropped,
+        PercentileMethod::LinearCdf as u32,
+    ) == PsdStatus::Ok
+}
+
+/// D10, D50, D90, weighted mean and standard deviation of a particle size
+/// distribution, with a `PsdStatus` saying why the input was rejected.
+///
+/// Rows with a non-finite or negative size or weight are rejected, and
+/// `invalid_index_out` receives the index of the first one (`usize::MAX`
+/// when all rows are valid) so it can be found in large exports. With
+/// `drop_invalid` set such rows are skipped instead and `dropped_out`
+/// receives how many were; the status then only reflects what remains.
+/// The two diagnostic pointers may be null. `interpolation_method` selects
+/// the percentile convention by `PercentileMethod` id. Statistics are
+/// written only when the status is `PsdStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn analyze_particle_distribution_v2(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    d10: *mut f64,
+    d50: *mut f64,
+    d90: *mut f64,
+    mean: *mut f64,
+    std_dev: *mut f64,
+    drop_invalid: bool,
+    invalid_index_out: *mut usize,
+    dropped_out: *mut usize,
+    interpolation_method: u32
+) -> PsdStatus {
+    if sizes.is_null() || weights.is_null() || d10.is_null() || d50.is_null() || d90.is_null()
+        || mean.is_null() || std_dev.is_null() {
+        return PsdStatus::NullPointer;
+    }
+    let interpolation_method = match PercentileMethod::from_id(interpolation_method) {
+        Some(method) => method,
+        None => return PsdStatus::UnknownMethod,
+    };
+    if len == 0 {
+        return PsdStatus::Empty;
+    }
+
+    // Convert raw pointers to slices with safety checks
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let invalid = first_invalid_row(sizes, weights);
+    let mut dropped = 0;
+    let psd = match invalid {
+        Some((_, status)) if !drop_invalid => Err(status),
+        Some(_) => {
+            let (kept_sizes, kept_weights): (Vec<f64>, Vec<f64>) = sizes.iter().zip(weights)
+                .filter(|&(&size, &weight)| row_status(size, weight).is_none())
+                .unzip();
+            dropped = len - kept_sizes.len();
+            if kept_sizes.is_empty() {
+                Err(PsdStatus::Empty)
+            } else {
+                WeightedPsd::new(&kept_sizes, &kept_weights)
+            }
+        }
+        None => WeightedPsd::new(sizes, weights),
+    };
+    unsafe {
+        if !invalid_index_out.is_null() {
+            *invalid_index_out = invalid.map_or(usize::MAX, |(index, _)| index);
+        }
+        if !dropped_out.is_null() {
+            *dropped_out = dropped;
+        }
+    }
+    let psd = match psd {
+        Ok(psd) => psd,
+        Err(status) => return status,
+    };
+    let [p10, p50, p90, weighted_mean, weighted_std] = basic_statistics(&psd, interpolation_method);
+
+    unsafe {
+        *d10 = p10;
+        *d50 = p50;
+        *d90 = p90;
+        *mean = weighted_mean;
+        *std_dev = weighted_std;
+    }
+
+    PsdStatus::Ok
+}
+
+/// `analyze_particle_distribution_v2` over many samples in one call, in
+/// parallel.
+///
+/// Samples are concatenated in `sizes` and `weights`; sample `i` spans
+/// `offsets[i]..offsets[i + 1]`, so `offsets` holds `n_samples + 1`
+/// non-decreasing entries. Each sample writes `PSD_BATCH_WIDTH` values to
+/// its row of `results_out` and its status to `statuses_out[i]`; a rejected
+/// sample gets a row of NaN without affecting the others. Invalid rows are
+/// rejected, not dropped. Returns false on null pointers, no samples,
+/// decreasing offsets or an unknown `interpolation_method`.
+#[no_mangle]
+pub extern "C" fn analyze_particle_distribution_batch(
+    sizes: *const f64,
+    weights: *const f64,
+    offsets: *const usize,
+    n_samples: usize,
+    interpolation_method: u32,
+    results_out: *mut c_double,
+    statuses_out: *mut PsdStatus
+) -> bool {
+    if sizes.is_null() || weights.is_null() || offsets.is_null() || results_out.is_null()
+        || statuses_out.is_null() || n_samples == 0 {
+        return false;
+    }
+    let interpolation_method = match PercentileMethod::from_id(interpolation_method) {
+        Some(method) => method,
+        None => return false,
+    };
+    let offsets = unsafe { std::slice::from_raw_parts(offsets, n_samples + 1) };
+    if offsets.windows(2).any(|pair| pair[1] < pair[0]) {
+        return false;
+    }
+    let total_len = offsets[n_samples];
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, total_len), std::slice::from_raw_parts(weights, total_len))
+    };
+    let (results, statuses) = unsafe {
+        (
+            std::slice::from_raw_parts_mut(results_out, n_samples * PSD_BATCH_WIDTH),
+            std::slice::from_raw_parts_mut(statuses_out, n_samples),
+        )
+    };
+
+    results.par_chunks_mut(PSD_BATCH_WIDTH)
+        .zip(statuses.par_iter_mut())
+        .zip(offsets.par_windows(2))
+        .for_each(|((row, status), span)| {
+            let (sample_sizes, sample_weights) = (&sizes[span[0]..span[1]], &weights[span[0]..span[1]]);
+            let psd = if sample_sizes.is_empty() {
+                Err(PsdStatus::Empty)
+            } else if let Some((_, invalid)) = first_invalid_row(sample_sizes, sample_weights) {
+                Err(invalid)
+            } else {
+                WeightedPsd::new(sample_sizes, sample_weights)
+            };
+            match psd {
+                Ok(psd) => {
+                    row.copy_from_slice(&basic_statistics(&psd, interpolation_method));
+                    *status = PsdStatus::Ok;
+                }
+                Err(invalid) => {
+                    row.fill(f64::NAN);
+                    *status = invalid;
+                }
+            }
+        });
+    true
+}
+
+/// Particle size statistics including the D[4,3] and D[3,2] mean
+/// diameters, span, uniformity coefficient, modal size and coefficient of
+/// variation, and the specific surface area.
+///
+/// `weight_basis` is the `WeightBasis` id of the supplied weights and
+/// selects how D[4,3] and D[3,2] are formed from them. Percentiles, mean, spread and mode are
+/// reported on `report_basis`, converting the weights as
+/// `convert_psd_weighting` does when it differs. `shape_factor` scales the
+/// sphere surface area 6 / D[3,2] for non-spherical particles; 0 means
+/// spheres. `interpolation_method` selects the percentile convention for
+/// D10 through D90 by `PercentileMethod` id.
+///
+/// Returns false on input rejected by `analyze_particle_distribution_v2`
+/// (including negative sizes and unknown methods), an unknown basis id, a
+/// zero D50 or mean (the
+/// relative widths are undefined), a non-positive D[3,2], a negative shape
+/// factor, or weights that cannot be converted.
+#[no_mangle]
+pub extern "C" fn analyze_particle_distribution_ex(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    weight_basis: u32,
+    report_basis: u32,
+    shape_factor: c_double,
+    interpolation_method: u32,
+    summary: *mut PsdSummary
+) -> bool {
+    if sizes.is_null() || weights.is_null() || summary.is_null() || len == 0 {
+        return false;
+    }
+    let interpolation_method = match PercentileMethod::from_id(interpolation_method) {
+        Some(method) => method,
+        None => return false,
+    };
+    let (weight_basis, report_basis) = match (WeightBasis::from_id(weight_basis), WeightBasis::from_id(report_basis)) {
+        (Some(weight_basis), Some(report_basis)) => (weight_basis, report_basis),
+        _ => return false,
+    };
+    let shape_factor = if shape_factor == 0.0 { 1.0 } else { shape_factor };
+    if shape_factor.is_nan() || shape_factor < 0.0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    if first_invalid_row(sizes, weights).is_some() {
+        return false;
+    }
+    let supplied = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+    let (d43, d32) = supplied.moment_means(weight_basis);
+    let psd = if weight_basis.is_number() == report_basis.is_number() {
+        supplied
+    } else {
+        match convert_weights(sizes, weights, weight_basis, report_basis)
+            .and_then(|(converted, _)| WeightedPsd::new(sizes, &converted).ok()) {
+            Some(psd) => psd,
+            None => return false,
+        }
+    };
+    let (mean, std_dev) = psd.mean_and_std();
+    let (geometric_mean, geometric_std_dev) = psd.log_mean_and_std()
+        .map_or((f64::NAN, f64::NAN), |(mu, sigma)| (mu.exp(), sigma.exp()));
+    let percentile = |p| psd.percentile_with(p, interpolation_method);
+    let (d10, d50, d90) = (percentile(0.1), percentile(0.5), percentile(0.9));
+    if d50 == 0.0 || mean == 0.0 || d32.is_nan() || d32 <= 0.0 {
+        return false;
+    }
+    let (d25, d75) = (percentile(0.25), percentile(0.75));
+    let uniformity = if d10 != 0.0 { percentile(0.6) / d10 } else { f64::NAN };
+    let (mode, mode_density, mode_status) = psd.mode();
+
+    unsafe {
+        *summary = PsdSummary {
+            d10,
+            d50,
+            d90,
+            mean,
+            std_dev,
+            d43,
+            d32,
+            span: (d90 - d10) / d50,
+            uniformity,
+            mode,
+            mode_density,
+            mode_status,
+            d25,
+            d75,
+            cv: std_dev / mean,
+            quartile_span: (d75 - d25) / d50,
+            specific_surface_area: 6.0 * shape_factor / d32,
+            geometric_mean,
+            geometric_std_dev,
+        };
+    }
+    true
+}
+
+/// Convert PSD weights between number, volume and mass bases.
+///
+/// Assumes spherical particles of constant density, so volume and mass
+/// weights are interchangeable and number weights scale by d³. The
+/// converted weights are normalized to sum to 1. `broad_out` is set when a
+/// number-to-volume conversion spans a size ratio above 100, where the
+/// largest particles dominate and the result rests on very few counts.
+/// `from_basis` and `to_basis` are `WeightBasis` ids. Returns false on null
+/// pointers, an unknown basis id, empty or NaN input, negative weights, a
+/// non-positive size when converting to number weights, or a zero total.
+#[no_mangle]
+pub extern "C" fn convert_psd_weighting(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    from_basis: u32,
+    to_basis: u32,
+    out_weights: *mut c_double,
+    broad_out: *mut bool
+) -> bool {
+    if sizes.is_null() || weights.is_null() || out_weights.is_null() || broad_out.is_null() || len == 0 {
+        return false;
+    }
+    let (from_basis, to_basis) = match (WeightBasis::from_id(from_basis), WeightBasis::from_id(to_basis)) {
+        (Some(from_basis), Some(to_basis)) => (from_basis, to_basis),
+        _ => return false,
+    };
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let (converted, broad) = match convert_weights(sizes, weights, from_basis, to_basis) {
+        Some(result) => result,
+        None => return false,
+    };
+
+    unsafe {
+        std::slice::from_raw_parts_mut(out_weights, len).copy_from_slice(&converted);
+        *broad_out = broad;
+    }
+    true
+}
+
+/// Sizes at arbitrary cumulative fractions of the distribution.
+///
+/// Each entry of `percentiles` is a fraction strictly between 0 and 1
+/// (0.16 for D16) and is answered in place in `results`, read off as the
+/// `PercentileMethod` with id `interpolation_method` describes; the entries
+/// need not be sorted. Returns false on input rejected by
+/// `analyze_particle_distribution_v2` or a fraction outside (0, 1).
+#[no_mangle]
+pub extern "C" fn get_psd_percentiles(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    percentiles: *const c_double,
+    n_percentiles: usize,
+    interpolation_method: u32,
+    results: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || percentiles.is_null() || results.is_null() || len == 0 {
+        return false;
+    }
+    let interpolation_method = match PercentileMethod::from_id(interpolation_method) {
+        Some(method) => method,
+        None => return false,
+    };
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let percentiles = unsafe { std::slice::from_raw_parts(percentiles, n_percentiles) };
+    if percentiles.iter().any(|&p| p.is_nan() || p <= 0.0 || p >= 1.0) {
+        return false;
+    }
+    let psd = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+
+    let results = unsafe { std::slice::from_raw_parts_mut(results, n_percentiles) };
+    for (result, &p) in results.iter_mut().zip(percentiles) {
+        *result = psd.percentile_with(p, interpolation_method);
+    }
+    true
+}
+
+/// Weight fraction of material at or below each size cutoff.
+///
+/// The inverse of `get_psd_percentiles`: fractions are interpolated between
+/// the bracketing sizes, 0 below the smallest size and 1 at or above the
+/// largest. The fraction retained is 1 minus the result. Returns false on
+/// input rejected by `analyze_particle_distribution_v2` or a NaN cutoff.
+#[no_mangle]
+pub extern "C" fn compute_fraction_below(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    cutoffs: *const c_double,
+    n_cutoffs: usize,
+    fractions_out: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || cutoffs.is_null() || fractions_out.is_null() || len == 0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let cutoffs = unsafe { std::slice::from_raw_parts(cutoffs, n_cutoffs) };
+    if cutoffs.iter().any(|c| c.is_nan()) {
+        return false;
+    }
+    let psd = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+
+    let fractions_out = unsafe { std::slice::from_raw_parts_mut(fractions_out, n_cutoffs) };
+    for (fraction, &cutoff) in fractions_out.iter_mut().zip(cutoffs) {
+        *fraction = psd.fraction_below(cutoff);
+    }
+    true
+}
+
+/// Bin weighted particle sizes into a normalized histogram.
+///
+/// `n_bins` bins span `min_override`..`max_override`, either bound falling
+/// back to the smallest or largest size when NaN. With `log_spaced` the
+/// edges are evenly spaced in ln(size), which needs every size and both
+/// bounds strictly positive. The last bin includes its upper edge.
+///
+/// Particles outside the range are dropped: `counts_out` holds each bin's
+/// share of the in-range weight (summing to 1) and `clipped_fraction_out`
+/// the share of the total weight that was dropped. `edges_out` receives the
+/// `n_bins + 1` bin edges. Returns false on null pointers, empty or NaN
+/// input, negative weights, a non-positive total or in-range weight, or an
+/// empty range.
 #[no_mangle]
-pub extern "C" fn analyze_particle_distribution(
+pub extern "C" fn build_psd_histogram(
     sizes: *const f64,
     weights: *const f64,
     len: usize,
-    d10: *mut f64,
-    d50: *mut f64,
-    d90: *mut f64,
-    mean: *mut f64,
-    std_dev: *mut f64
+    n_bins: usize,
+    log_spaced: bool,
+    min_override: c_double,
+    max_override: c_double,
+    counts_out: *mut c_double,
+    edges_out: *mut c_double,
+    clipped_fraction_out: *mut c_double
 ) -> bool {
-    if sizes.is_null() || weights.is_null() || len == 0 {
+    if sizes.is_null() || weights.is_null() || counts_out.is_null() || edges_out.is_null()
+        || clipped_fraction_out.is_null() || len == 0 || n_bins == 0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    if !SizeBins::accepts(sizes, weights, log_spaced) {
         return false;
     }
+    let bins = match SizeBins::new(&[sizes], n_bins, log_spaced, min_override, max_override) {
+        Some(bins) => bins,
+        None => return false,
+    };
 
-    // Convert raw pointers to slices with safety checks
-    let (sizes, weights) = match (
-        unsafe { std::slice::from_raw_parts(sizes, len) },
-        unsafe { std::slice::from_raw_parts(weights, len) }
-    ) {
-        (s, w) if s.iter().any(|x| x.is_nan()) || w.iter().any(|x| x.is_nan()) => return false,
-        (s, w) => (s, w),
+    let (counts, total, clipped) = bins.histogram(sizes, weights);
+    let in_range = total - clipped;
+    if total <= 0.0 || in_range <= 0.0 {
+        return false;
+    }
+
+    let counts_out = unsafe { std::slice::from_raw_parts_mut(counts_out, n_bins) };
+    let edges_out = unsafe { std::slice::from_raw_parts_mut(edges_out, n_bins + 1) };
+    for (out, count) in counts_out.iter_mut().zip(&counts) {
+        *out = count / in_range;
+    }
+    edges_out.copy_from_slice(&bins.edges());
+    unsafe {
+        *clipped_fraction_out = clipped / total;
+    }
+    true
+}
+
+/// Size at which a sieve bin's passing curve reaches `fraction` of the way
+/// through the bin, for uniform or log-uniform mass within it.
+fn within_bin(lower: f64, upper: f64, fraction: f64, log_uniform: bool) -> f64 {
+    if log_uniform {
+        (lower.ln() + (upper / lower).ln() * fraction).exp()
+    } else {
+        lower + (upper - lower) * fraction
+    }
+}
+
+/// First and second moments of size within a sieve bin.
+fn bin_moments(lower: f64, upper: f64, log_uniform: bool) -> (f64, f64) {
+    if log_uniform {
+        let log_ratio = (upper / lower).ln();
+        ((upper - lower) / log_ratio, (upper * upper - lower * lower) / (2.0 * log_ratio))
+    } else {
+        ((lower + upper) / 2.0, (lower * lower + lower * upper + upper * upper) / 3.0)
+    }
+}
+
+/// D10, D50, D90, mean and standard deviation of sieve data.
+///
+/// `edges` holds the `n_bins + 1` strictly increasing sieve sizes and
+/// `retained_masses` the `n_bins` masses between consecutive edges, finest
+/// first. The cumulative passing curve is built at the edges and
+/// percentiles interpolated within the bin reaching them, with mass spread
+/// uniformly over each bin or, with `log_uniform`, uniformly in ln(size);
+/// the mean and standard deviation follow the same within-bin assumption.
+/// `stats_out` receives `PSD_BATCH_WIDTH` values in the order of
+/// `analyze_particle_distribution_batch`. Returns false on null pointers,
+/// no bins, non-finite or non-increasing edges, a non-positive lowest edge
+/// with `log_uniform`, non-finite or negative masses, or a zero total mass.
+#[no_mangle]
+pub extern "C" fn analyze_sieve_distribution(
+    edges: *const c_double,
+    retained_masses: *const c_double,
+    n_bins: usize,
+    log_uniform: bool,
+    stats_out: *mut c_double
+) -> bool {
+    if edges.is_null() || retained_masses.is_null() || stats_out.is_null() || n_bins == 0 {
+        return false;
+    }
+    let (edges, masses) = unsafe {
+        (std::slice::from_raw_parts(edges, n_bins + 1), std::slice::from_raw_parts(retained_masses, n_bins))
     };
-    
-    // Pre-allocate with capacity
-    let mut size_weight: Vec<(f64, f64)> = Vec::with_capacity(len);
-    size_weight.extend(sizes.iter().zip(weights.iter()).map(|(&s, &w)| (s, w)));
-    
-    // Sort by size using unstable sort (faster)
-    size_weight.sort_unstable_by(|a, b| a.0.partial_cmp(&b.0).unwrap_or(Ordering::Equal));
-    
-    // Calculate total weight once
-    let total_weight: f64 = weights.iter().sum();
-    if total_weight <= 0.0 {
+    if edges.iter().any(|x| !x.is_finite()) || edges.windows(2).any(|pair| pair[1] <= pair[0])
+        || (log_uniform && edges[0] <= 0.0) {
+        return false;
+    }
+    if masses.iter().any(|&m| !m.is_finite() || m < 0.0) {
+        return false;
+    }
+    let total: f64 = masses.iter().sum();
+    if total <= 0.0 {
         return false;
     }
 
-    // Pre-allocate cumulative weights
-    let mut cumulative = Vec::with_capacity(len);
+    let mut passing = Vec::with_capacity(n_bins + 1);
+    passing.push(0.0);
     let mut cum_sum = 0.0;
-    let weight_factor = 1.0 / total_weight;
-    
-    // Optimize cumulative weight calculation
-    for (_, w) in &size_weight {
-        cum_sum += w * weight_factor;
-        cumulative.push(cum_sum);
+    for &mass in masses {
+        cum_sum += mass / total;
+        passing.push(cum_sum);
     }
-    
-    // Calculate weighted mean with single pass
-    let weighted_mean: f64 = size_weight.iter()
-        .fold(0.0, |acc, (s, w)| acc + s * (w * weight_factor));
-    
-    // Calculate weighted variance with single pass
-    let weighted_var: f64 = size_weight.iter()
-        .fold(0.0, |acc, (s, w)| {
-            let diff = s - weighted_mean;
-            acc + diff * diff * (w * weight_factor)
-        });
-    
-    let weighted_std = weighted_var.sqrt();
-    
-    // Optimize percentile calculation with binary search
-    let get_percentile = |p: f64| {
-        match cumulative.binary_search_by(|&x| x.partial_cmp(&p).unwrap_or(Ordering::Equal)) {
-            Ok(idx) => size_weight[idx].0,
-            Err(idx) if idx == 0 => size_weight[0].0,
-            Err(idx) if idx >= len => size_weight[len-1].0,
-            Err(idx) => {
-                let (x0, x1) = (size_weight[idx-1].0, size_weight[idx].0);
-                let (y0, y1) = (cumulative[idx-1], cumulative[idx]);
-                x0 + (x1 - x0) * (p - y0) / (y1 - y0)
-            }
-        }
+    // Bins holding no mass never contain the crossing, so the first bin
+    // whose upper passing value reaches p does
+    let percentile = |p: f64| {
+        let bin = passing[1..].partition_point(|&f| f < p).min(n_bins - 1);
+        let (f0, f1) = (passing[bin], passing[bin + 1]);
+        within_bin(edges[bin], edges[bin + 1], ((p - f0) / (f1 - f0)).clamp(0.0, 1.0), log_uniform)
     };
-    
-    // Calculate percentiles safely
-    unsafe {
-        *d10 = get_percentile(0.1);
-        *d50 = get_percentile(0.5);
-        *d90 = get_percentile(0.9);
-        *mean = weighted_mean;
-        *std_dev = weighted_std;
-    }
-    
+    let (m1, m2) = masses.iter().zip(edges.windows(2)).fold((0.0, 0.0), |(m1, m2), (&mass, pair)| {
+        let (first, second) = bin_moments(pair[0], pair[1], log_uniform);
+        (m1 + mass / total * first, m2 + mass / total * second)
+    });
+
+    let stats_out = unsafe { std::slice::from_raw_parts_mut(stats_out, PSD_BATCH_WIDTH) };
+    stats_out.copy_from_slice(&[percentile(0.1), percentile(0.5), percentile(0.9), m1, (m2 - m1 * m1).max(0.0).sqrt()]);
     true
 }
 
@@ -112,5 +1380,817 @@ pub extern "C" fn calculate_separation_efficiency(
     let efficiency = (output_mass * output_concentration) / (input_mass * input_concentration);
     
     // Clamp between 0 and 1
-    efficiency.max(0.0).min(1.0)
-} 
\ No newline at end of file
+    efficiency.clamp(0.0, 1.0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // The worked example of `PercentileMethod`
+    const SIZES: [f64; 5] = [1.0, 2.0, 3.0, 4.0, 5.0];
+    const WEIGHTS: [f64; 5] = [1.0, 2.0, 3.0, 2.0, 2.0];
+
+    fn d50(method: u32) -> Option<f64> {
+        let mut result = f64::NAN;
+        get_psd_percentiles(SIZES.as_ptr(), WEIGHTS.as_ptr(), SIZES.len(), [0.5].as_ptr(), 1, method, &mut result)
+            .then_some(result)
+    }
+
+    #[test]
+    fn d50_is_pinned_for_each_method() {
+        let expected = [(PercentileMethod::LinearCdf, 8.0 / 3.0), (PercentileMethod::NearestRank, 3.0), (PercentileMethod::MidpointCdf, 3.2)];
+        for (method, d50_expected) in expected {
+            let d50 = d50(method as u32).unwrap();
+            assert!((d50 - d50_expected).abs() < 1e-12, "{method:?}: {d50}");
+        }
+    }
+
+    #[test]
+    fn unknown_method_is_rejected_everywhere() {
+        assert_eq!(d50(3), None);
+        assert_eq!(d50(u32::MAX), None);
+
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [0.0; 5];
+        let status = analyze_particle_distribution_v2(
+            SIZES.as_ptr(), WEIGHTS.as_ptr(), SIZES.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+            false, std::ptr::null_mut(), std::ptr::null_mut(), 3,
+        );
+        assert_eq!(status, PsdStatus::UnknownMethod);
+
+        let mut summary = PsdSummary::default();
+        assert!(!analyze_particle_distribution_ex(
+            SIZES.as_ptr(), WEIGHTS.as_ptr(), SIZES.len(), WeightBasis::Volume as u32, WeightBasis::Volume as u32, 0.0, 7, &mut summary
+        ));
+
+        let offsets = [0, SIZES.len()];
+        let mut row = [0.0; PSD_BATCH_WIDTH];
+        let mut statuses = [PsdStatus::Ok];
+        assert!(!analyze_particle_distribution_batch(
+            SIZES.as_ptr(), WEIGHTS.as_ptr(), offsets.as_ptr(), 1, 3, row.as_mut_ptr(), statuses.as_mut_ptr()
+        ));
+    }
+
+    fn summary(sizes: &[f64], weights: &[f64], weight_basis: WeightBasis) -> Option<PsdSummary> {
+        shaped_summary(sizes, weights, weight_basis, 0.0)
+    }
+
+    #[test]
+    fn moment_means_match_the_published_three_sphere_example() {
+        // One sphere each of 1, 2 and 3 um: D[4,3] = 98/36 = 2.72, D[3,2] = 36/14 = 2.57
+        let summary = summary(&[1.0, 2.0, 3.0], &[1.0, 1.0, 1.0], WeightBasis::Number).unwrap();
+        assert!((summary.d43 / (98.0 / 36.0) - 1.0).abs() < 0.01);
+        assert!((summary.d32 / (36.0 / 14.0) - 1.0).abs() < 0.01);
+    }
+
+    #[test]
+    fn volume_and_mass_weights_give_the_same_moment_means() {
+        let number = summary(&[3.0, 1.0, 2.0], &[1.0, 1.0, 1.0], WeightBasis::Number).unwrap();
+        // The same spheres weighted by volume, n d^3
+        for basis in [WeightBasis::Volume, WeightBasis::Mass] {
+            let weighted = summary(&[3.0, 1.0, 2.0], &[27.0, 1.0, 8.0], basis).unwrap();
+            assert!((weighted.d43 - number.d43).abs() < 1e-12);
+            assert!((weighted.d32 - number.d32).abs() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn zero_denominators_are_rejected() {
+        assert!(summary(&[0.0, 0.0], &[1.0, 1.0], WeightBasis::Number).is_none());
+        assert!(summary(&[1.0, 2.0], &[0.0, 0.0], WeightBasis::Volume).is_none());
+    }
+
+    #[test]
+    fn monodisperse_input_has_zero_span() {
+        let summary = summary(&[5.0; 4], &[1.0; 4], WeightBasis::Volume).unwrap();
+        assert_eq!(summary.span, 0.0);
+        assert_eq!(summary.uniformity, 1.0);
+        assert_eq!(summary.d50, 5.0);
+    }
+
+    #[test]
+    fn bimodal_span_matches_the_hand_computation() {
+        // Cumulative points (2, 0.25), (4, 0.5), (20, 0.75), (40, 1): D10 = 2,
+        // D50 = 4, D60 = 4 + 0.4 * 16 = 10.4, D90 = 20 + 0.6 * 20 = 32
+        let summary = summary(&[2.0, 4.0, 20.0, 40.0], &[1.0; 4], WeightBasis::Volume).unwrap();
+        assert!((summary.span - (32.0 - 2.0) / 4.0).abs() < 1e-12);
+        assert!((summary.uniformity - 10.4 / 2.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn zero_d50_is_an_error() {
+        assert!(summary(&[0.0, 0.0, 3.0], &[1.0, 1.0, 0.1], WeightBasis::Volume).is_none());
+    }
+
+    #[test]
+    fn lognormal_mode_is_found() {
+        use statrs::distribution::{ContinuousCDF, Normal};
+        // Quantiles of ln d ~ N(3, 0.3^2), whose mode is exp(3 - 0.3^2)
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        let n = 4000;
+        let sizes: Vec<f64> = (0..n).map(|i| (3.0 + 0.3 * standard.inverse_cdf((i as f64 + 0.5) / n as f64)).exp()).collect();
+        let summary = summary(&sizes, &vec![1.0; n], WeightBasis::Number).unwrap();
+        assert_eq!(summary.mode_status, ModeStatus::Kde);
+        let expected = (3.0_f64 - 0.09).exp();
+        assert!((summary.mode / expected - 1.0).abs() < 0.03, "{} vs {expected}", summary.mode);
+        assert!(summary.mode < summary.d50);
+        assert!(summary.mode_density > 0.0);
+    }
+
+    #[test]
+    fn identical_sizes_are_a_point_mass() {
+        let summary = summary(&[5.0; 6], &[1.0; 6], WeightBasis::Number).unwrap();
+        assert_eq!((summary.mode, summary.mode_status), (5.0, ModeStatus::PointMass));
+        assert!(summary.mode_density.is_nan());
+    }
+
+    #[test]
+    fn few_points_fall_back_to_the_median() {
+        let summary = summary(&[1.0, 2.0, 3.0], &[1.0; 3], WeightBasis::Number).unwrap();
+        assert_eq!(summary.mode_status, ModeStatus::MedianFallback);
+        assert_eq!(summary.mode, summary.d50);
+        assert!(summary.mode_density.is_nan());
+    }
+
+    #[test]
+    fn relative_widths_are_scale_invariant() {
+        let sizes = [1.0, 2.0, 3.5, 7.0, 9.0, 12.0];
+        let weights = [1.0, 3.0, 2.0, 5.0, 1.0, 0.5];
+        let scaled: Vec<f64> = sizes.iter().map(|size| size * 10.0).collect();
+        let original = summary(&sizes, &weights, WeightBasis::Volume).unwrap();
+        let larger = summary(&scaled, &weights, WeightBasis::Volume).unwrap();
+        assert!((original.cv - original.std_dev / original.mean).abs() < 1e-12);
+        assert!((original.quartile_span - (original.d75 - original.d25) / original.d50).abs() < 1e-12);
+        for (a, b) in [(original.cv, larger.cv), (original.span, larger.span), (original.quartile_span, larger.quartile_span)] {
+            assert!((a - b).abs() < 1e-12, "{a} vs {b}");
+        }
+        assert!((larger.d25 - 10.0 * original.d25).abs() < 1e-9);
+    }
+
+    #[test]
+    fn all_zero_sizes_have_no_cv() {
+        assert!(summary(&[0.0; 6], &[1.0; 6], WeightBasis::Volume).is_none());
+    }
+
+    const HISTOGRAM_SIZES: [f64; 7] = [1.0, 2.0, 3.5, 7.0, 9.0, 12.0, 0.5];
+    const HISTOGRAM_WEIGHTS: [f64; 7] = [1.0, 3.0, 2.0, 5.0, 1.0, 0.5, 0.7];
+
+    fn histogram(sizes: &[f64], n_bins: usize, log_spaced: bool, min: f64, max: f64) -> Option<(Vec<f64>, Vec<f64>, f64)> {
+        let mut counts = vec![f64::NAN; n_bins];
+        let mut edges = vec![f64::NAN; n_bins + 1];
+        let mut clipped = f64::NAN;
+        build_psd_histogram(
+            sizes.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), sizes.len(), n_bins, log_spaced, min, max,
+            counts.as_mut_ptr(), edges.as_mut_ptr(), &mut clipped
+        ).then_some((counts, edges, clipped))
+    }
+
+    #[test]
+    fn histogram_fractions_sum_to_one() {
+        for log_spaced in [false, true] {
+            let (counts, edges, clipped) = histogram(&HISTOGRAM_SIZES, 7, log_spaced, f64::NAN, f64::NAN).unwrap();
+            assert!((counts.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+            assert_eq!((edges[0], edges[7], clipped), (0.5, 12.0, 0.0));
+            assert!(edges.windows(2).all(|pair| pair[1] > pair[0]));
+        }
+        let (_, edges, _) = histogram(&HISTOGRAM_SIZES, 2, true, 1.0, 100.0).unwrap();
+        assert!((edges[1] - 10.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn histogram_range_clips_and_reports_the_dropped_weight() {
+        // 12 and 0.5 fall outside 1..10
+        let (counts, edges, clipped) = histogram(&HISTOGRAM_SIZES, 5, true, 1.0, 10.0).unwrap();
+        assert_eq!((edges[0], edges[5]), (1.0, 10.0));
+        assert!((clipped - 1.2 / 13.2).abs() < 1e-12);
+        assert!((counts.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        // Size 1 lies on the lower edge of the first bin
+        assert!((counts[0] - 1.0 / 12.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn log_histogram_rejects_non_positive_sizes() {
+        assert!(histogram(&[0.0, 1.0], 5, true, f64::NAN, f64::NAN).is_none());
+        assert!(histogram(&[0.0, 1.0], 5, false, f64::NAN, f64::NAN).is_some());
+        assert!(histogram(&HISTOGRAM_SIZES, 5, true, 0.0, 10.0).is_none());
+        assert!(histogram(&HISTOGRAM_SIZES, 0, false, f64::NAN, f64::NAN).is_none());
+    }
+
+    fn percentiles(percentiles: &[f64], method: PercentileMethod) -> Option<Vec<f64>> {
+        let mut results = vec![f64::NAN; percentiles.len()];
+        get_psd_percentiles(
+            HISTOGRAM_SIZES.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), HISTOGRAM_SIZES.len(),
+            percentiles.as_ptr(), percentiles.len(), method as u32, results.as_mut_ptr()
+        ).then_some(results)
+    }
+
+    #[test]
+    fn percentile_query_matches_the_analyzer() {
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [0.0; 5];
+        assert!(analyze_particle_distribution(
+            HISTOGRAM_SIZES.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), HISTOGRAM_SIZES.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev
+        ));
+        // Unsorted requests keep their order
+        let results = percentiles(&[0.9, 0.1, 0.5, 0.99], PercentileMethod::LinearCdf).unwrap();
+        assert_eq!(results[..3], [d90, d10, d50]);
+        assert!(results[3] > d90 && results[3] <= 12.0);
+
+        for method in [PercentileMethod::NearestRank, PercentileMethod::MidpointCdf] {
+            let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [0.0; 5];
+            analyze_particle_distribution_v2(
+                HISTOGRAM_SIZES.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), HISTOGRAM_SIZES.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+                false, std::ptr::null_mut(), std::ptr::null_mut(), method as u32,
+            );
+            assert_eq!(percentiles(&[0.1, 0.5, 0.9], method).unwrap(), [d10, d50, d90]);
+        }
+    }
+
+    #[test]
+    fn percentiles_outside_the_open_interval_are_rejected() {
+        for invalid in [0.0, 1.0, -0.1, f64::NAN] {
+            assert!(percentiles(&[0.5, invalid], PercentileMethod::LinearCdf).is_none());
+        }
+    }
+
+    fn fractions_below(cutoffs: &[f64]) -> Option<Vec<f64>> {
+        let mut fractions = vec![f64::NAN; cutoffs.len()];
+        compute_fraction_below(
+            HISTOGRAM_SIZES.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), HISTOGRAM_SIZES.len(),
+            cutoffs.as_ptr(), cutoffs.len(), fractions.as_mut_ptr()
+        ).then_some(fractions)
+    }
+
+    #[test]
+    fn fraction_below_inverts_the_percentiles() {
+        let requested = [0.1, 0.5, 0.9, 0.3];
+        let sizes = percentiles(&requested, PercentileMethod::LinearCdf).unwrap();
+        for (fraction, p) in fractions_below(&sizes).unwrap().iter().zip(requested) {
+            assert!((fraction - p).abs() < 1e-12, "{fraction} vs {p}");
+        }
+    }
+
+    #[test]
+    fn fraction_below_is_clamped_outside_the_sizes() {
+        assert_eq!(fractions_below(&[0.1, 100.0, 12.0]).unwrap(), [0.0, 1.0, 1.0]);
+        assert!(fractions_below(&[f64::NAN]).is_none());
+    }
+
+    fn shaped_summary(sizes: &[f64], weights: &[f64], weight_basis: WeightBasis, shape_factor: f64) -> Option<PsdSummary> {
+        let mut summary = PsdSummary::default();
+        analyze_particle_distribution_ex(
+            sizes.as_ptr(), weights.as_ptr(), sizes.len(), weight_basis as u32, weight_basis as u32, shape_factor, PercentileMethod::LinearCdf as u32, &mut summary
+        ).then_some(summary)
+    }
+
+    #[test]
+    fn monodisperse_surface_area_is_six_over_d() {
+        for basis in [WeightBasis::Number, WeightBasis::Volume] {
+            let spheres = shaped_summary(&[50.0; 6], &[1.0; 6], basis, 0.0).unwrap();
+            assert!((spheres.specific_surface_area - 6.0 / 50.0).abs() < 1e-15);
+            let shaped = shaped_summary(&[50.0; 6], &[1.0; 6], basis, 1.4).unwrap();
+            assert!((shaped.specific_surface_area - 1.4 * 6.0 / 50.0).abs() < 1e-15);
+        }
+    }
+
+    #[test]
+    fn number_weights_form_the_moments_before_the_ratio() {
+        // One sphere each of 1, 2 and 3 um: D[3,2] = 36/14
+        let summary = shaped_summary(&[1.0, 2.0, 3.0], &[1.0; 3], WeightBasis::Number, 0.0).unwrap();
+        assert!((summary.specific_surface_area - 6.0 * 14.0 / 36.0).abs() < 1e-12);
+        assert!(shaped_summary(&[50.0; 6], &[1.0; 6], WeightBasis::Volume, -1.0).is_none());
+    }
+
+    fn convert(sizes: &[f64], weights: &[f64], from: WeightBasis, to: WeightBasis) -> Option<(Vec<f64>, bool)> {
+        let mut converted = vec![f64::NAN; sizes.len()];
+        let mut precision_warning = false;
+        convert_psd_weighting(sizes.as_ptr(), weights.as_ptr(), sizes.len(), from as u32, to as u32, converted.as_mut_ptr(), &mut precision_warning)
+            .then_some((converted, precision_warning))
+    }
+
+    #[test]
+    fn number_volume_round_trip_recovers_the_weights() {
+        let total: f64 = HISTOGRAM_WEIGHTS.iter().sum();
+        let (volume, warning) = convert(&HISTOGRAM_SIZES, &HISTOGRAM_WEIGHTS, WeightBasis::Number, WeightBasis::Volume).unwrap();
+        assert!(!warning);
+        assert!((volume.iter().sum::<f64>() - 1.0).abs() < 1e-12);
+        // Volume weights are proportional to n d^3
+        assert!((volume[3] / volume[0] - 5.0 * 343.0).abs() < 1e-9);
+        let (number, _) = convert(&HISTOGRAM_SIZES, &volume, WeightBasis::Mass, WeightBasis::Number).unwrap();
+        for (recovered, original) in number.iter().zip(&HISTOGRAM_WEIGHTS) {
+            assert!((recovered - original / total).abs() < 1e-9);
+        }
+    }
+
+    #[test]
+    fn broad_number_to_volume_conversion_is_flagged() {
+        let (_, warning) = convert(&[0.1, 20.0], &[1.0, 1.0], WeightBasis::Number, WeightBasis::Volume).unwrap();
+        assert!(warning);
+    }
+
+    #[test]
+    fn conversion_rejects_unknown_basis_ids() {
+        let mut converted = [f64::NAN; 2];
+        let mut broad = false;
+        for (from, to) in [(3, 1), (0, 3)] {
+            assert!(!convert_psd_weighting([0.1, 20.0].as_ptr(), [1.0, 1.0].as_ptr(), 2, from, to, converted.as_mut_ptr(), &mut broad));
+        }
+        assert!(converted.iter().all(|w| w.is_nan()));
+    }
+
+    #[test]
+    fn summary_converts_to_the_report_basis() {
+        let (volume, _) = convert(&HISTOGRAM_SIZES, &HISTOGRAM_WEIGHTS, WeightBasis::Number, WeightBasis::Volume).unwrap();
+        let mut converted = PsdSummary::default();
+        assert!(analyze_particle_distribution_ex(
+            HISTOGRAM_SIZES.as_ptr(), HISTOGRAM_WEIGHTS.as_ptr(), HISTOGRAM_SIZES.len(), WeightBasis::Number as u32, WeightBasis::Volume as u32,
+            0.0, PercentileMethod::LinearCdf as u32, &mut converted
+        ));
+        let direct = summary(&HISTOGRAM_SIZES, &volume, WeightBasis::Volume).unwrap();
+        for (a, b) in [(converted.d10, direct.d10), (converted.d50, direct.d50), (converted.d90, direct.d90), (converted.mean, direct.mean), (converted.d43, direct.d43)] {
+            assert!((a - b).abs() < 1e-9, "{a} vs {b}");
+        }
+    }
+
+    #[test]
+    fn unknown_basis_ids_are_rejected() {
+        let mut summary = PsdSummary::default();
+        for (weight_basis, report_basis) in [(3, 1), (1, 3), (u32::MAX, 0)] {
+            assert!(!analyze_particle_distribution_ex(
+                SIZES.as_ptr(), WEIGHTS.as_ptr(), SIZES.len(), weight_basis, report_basis, 0.0, 0, &mut summary
+            ));
+        }
+        assert_eq!(summary.d50, 0.0);
+        assert!(analyze_particle_distribution_ex(SIZES.as_ptr(), WEIGHTS.as_ptr(), SIZES.len(), 2, 0, 0.0, 0, &mut summary));
+    }
+
+    #[test]
+    fn zero_moisture_correction_is_a_no_op() {
+        let mut content = f64::NAN;
+        assert!(correct_protein_content(22.0, 0.0, 0.0, &mut content));
+        assert_eq!(content, 22.0);
+    }
+
+    #[test]
+    fn eight_percent_moisture_converts_to_dry_basis() {
+        // 22 % as-is at 8 % moisture is 22 / 0.92 = 23.913 % on dry basis
+        let mut dry = f64::NAN;
+        assert!(correct_protein_content(22.0, 0.08, 0.0, &mut dry));
+        assert!((dry - 23.913_043_478_26).abs() < 1e-9);
+        // and back to 8 % moisture
+        let mut as_is = f64::NAN;
+        assert!(correct_protein_content(dry, 0.0, 0.08, &mut as_is));
+        assert!((as_is - 22.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn moisture_outside_the_unit_interval_is_rejected() {
+        let mut content = f64::NAN;
+        for moisture in [1.0, -0.01, f64::NAN] {
+            assert!(!correct_protein_content(22.0, moisture, 0.0, &mut content));
+            assert!(!correct_protein_content(22.0, 0.0, moisture, &mut content));
+        }
+        assert!(!correct_protein_content(22.0, 0.08, 0.0, std::ptr::null_mut()));
+    }
+
+    #[test]
+    fn moisture_aware_recovery_corrects_both_streams() {
+        let mut recovery = f64::NAN;
+        assert!(calculate_protein_recovery_mb(100.0, 22.0, 0.1, 30.0, 50.0, 0.08, &mut recovery));
+        let expected = 100.0 * 30.0 * (50.0 / 0.92) / (100.0 * 22.0 / 0.9);
+        assert!((recovery - expected).abs() < 1e-9);
+        assert!(!calculate_protein_recovery_mb(100.0, 22.0, 1.0, 30.0, 50.0, 0.08, &mut recovery));
+        assert!(!calculate_protein_recovery_mb(0.0, 22.0, 0.1, 30.0, 50.0, 0.08, &mut recovery));
+    }
+
+    fn recovery_from_nitrogen(conversion_factor: f64) -> f64 {
+        let mut recovery = f64::NAN;
+        assert!(calculate_protein_recovery_from_nitrogen(40.0, 4.0, conversion_factor, 90.0, false, &mut recovery));
+        recovery
+    }
+
+    #[test]
+    fn recovery_scales_with_the_nitrogen_factor() {
+        let jones = recovery_from_nitrogen(6.25);
+        let pea = recovery_from_nitrogen(5.52);
+        assert!((jones - calculate_protein_recovery(40.0, 25.0, 90.0)).abs() < 1e-12);
+        assert!((pea / jones - 5.52 / 6.25).abs() < 1e-12);
+    }
+
+    #[test]
+    fn implausible_nitrogen_factors_need_the_override() {
+        let mut protein = f64::NAN;
+        assert!(nitrogen_to_protein(4.0, 4.0, false, &mut protein));
+        assert!(nitrogen_to_protein(4.0, 7.0, false, &mut protein));
+        assert!(!nitrogen_to_protein(4.0, 3.0, false, &mut protein));
+        assert!(nitrogen_to_protein(4.0, 3.0, true, &mut protein));
+        assert_eq!(protein, 12.0);
+        for factor in [0.0, -3.0, f64::NAN, f64::INFINITY] {
+            assert!(!nitrogen_to_protein(4.0, factor, true, &mut protein));
+        }
+        let mut recovery = f64::NAN;
+        assert!(!calculate_protein_recovery_from_nitrogen(40.0, 4.0, 8.0, 90.0, false, &mut recovery));
+        assert!(recovery.is_nan());
+    }
+
+    fn psd_status(sizes: &[f64], weights: &[f64], method: u32) -> PsdStatus {
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [f64::NAN; 5];
+        analyze_particle_distribution_v2(
+            sizes.as_ptr(), weights.as_ptr(), sizes.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+            false, std::ptr::null_mut(), std::ptr::null_mut(), method,
+        )
+    }
+
+    #[test]
+    fn every_psd_status_is_reachable() {
+        let linear = PercentileMethod::LinearCdf as u32;
+        let cases = [
+            (psd_status(&[1.0, 2.0], &[1.0, 1.0], linear), PsdStatus::Ok),
+            (psd_status(&[], &[], linear), PsdStatus::Empty),
+            (psd_status(&[f64::NAN, 2.0], &[1.0, 1.0], linear), PsdStatus::NonFiniteSize),
+            (psd_status(&[f64::INFINITY, 2.0], &[1.0, 1.0], linear), PsdStatus::NonFiniteSize),
+            (psd_status(&[1.0, 2.0], &[f64::NAN, 1.0], linear), PsdStatus::NonFiniteWeight),
+            (psd_status(&[1.0, 2.0], &[f64::NEG_INFINITY, 1.0], linear), PsdStatus::NonFiniteWeight),
+            (psd_status(&[1.0, 2.0], &[-1.0, 3.0], linear), PsdStatus::NegativeWeight),
+            (psd_status(&[1.0, 2.0], &[0.0, 0.0], linear), PsdStatus::ZeroTotalWeight),
+            (psd_status(&[-1.0, 2.0], &[1.0, 1.0], linear), PsdStatus::NegativeSize),
+            (psd_status(&[1.0, 2.0], &[1.0, 1.0], 3), PsdStatus::UnknownMethod),
+        ];
+        for (i, (status, expected)) in cases.into_iter().enumerate() {
+            assert_eq!(status, expected, "case {i}");
+        }
+    }
+
+    #[test]
+    fn null_pointers_are_reported() {
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [f64::NAN; 5];
+        let null_sizes = analyze_particle_distribution_v2(
+            std::ptr::null(), [1.0].as_ptr(), 1, &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+            false, std::ptr::null_mut(), std::ptr::null_mut(), 0,
+        );
+        let null_output = analyze_particle_distribution_v2(
+            [1.0].as_ptr(), [1.0].as_ptr(), 1, std::ptr::null_mut(), &mut d50, &mut d90, &mut mean, &mut std_dev,
+            false, std::ptr::null_mut(), std::ptr::null_mut(), 0,
+        );
+        assert_eq!((null_sizes, null_output), (PsdStatus::NullPointer, PsdStatus::NullPointer));
+        assert!(d50.is_nan());
+    }
+
+    #[test]
+    fn legacy_analyzer_still_returns_false() {
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [f64::NAN; 5];
+        assert!(!analyze_particle_distribution(
+            [1.0, 2.0].as_ptr(), [0.0, 0.0].as_ptr(), 2, &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev
+        ));
+        assert!(!analyze_particle_distribution(
+            [f64::INFINITY, 2.0].as_ptr(), [1.0, 1.0].as_ptr(), 2, &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev
+        ));
+    }
+
+    struct Diagnosed {
+        status: PsdStatus,
+        invalid_index: usize,
+        dropped: usize,
+        d50: f64,
+    }
+
+    fn diagnose(sizes: &[f64], weights: &[f64], drop_invalid: bool) -> Diagnosed {
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [f64::NAN; 5];
+        let (mut invalid_index, mut dropped) = (0, usize::MAX);
+        let status = analyze_particle_distribution_v2(
+            sizes.as_ptr(), weights.as_ptr(), sizes.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+            drop_invalid, &mut invalid_index, &mut dropped, PercentileMethod::LinearCdf as u32,
+        );
+        Diagnosed { status, invalid_index, dropped, d50 }
+    }
+
+    fn export_rows() -> (Vec<f64>, Vec<f64>) {
+        ((0..3000).map(|i| 1.0 + i as f64 * 0.01).collect(), vec![1.0; 3000])
+    }
+
+    #[test]
+    fn first_bad_row_is_reported_exactly() {
+        let (sizes, mut weights) = export_rows();
+        weights[1234] = -2.0;
+        let rejected = diagnose(&sizes, &weights, false);
+        assert_eq!((rejected.status, rejected.invalid_index), (PsdStatus::NegativeWeight, 1234));
+        assert!(rejected.d50.is_nan());
+
+        let (mut sizes, weights) = export_rows();
+        sizes[7] = -1.0;
+        sizes[1234] = -1.0;
+        let rejected = diagnose(&sizes, &weights, false);
+        assert_eq!((rejected.status, rejected.invalid_index), (PsdStatus::NegativeSize, 7));
+    }
+
+    #[test]
+    fn drop_mode_skips_the_bad_row() {
+        let (sizes, mut weights) = export_rows();
+        weights[1234] = -2.0;
+        let dropped = diagnose(&sizes, &weights, true);
+        assert_eq!((dropped.status, dropped.dropped, dropped.invalid_index), (PsdStatus::Ok, 1, 1234));
+
+        let (mut kept_sizes, mut kept_weights) = export_rows();
+        kept_sizes.remove(1234);
+        kept_weights.remove(1234);
+        assert_eq!(dropped.d50, diagnose(&kept_sizes, &kept_weights, false).d50);
+    }
+
+    #[test]
+    fn clean_export_reports_no_bad_row() {
+        let (sizes, weights) = export_rows();
+        let clean = diagnose(&sizes, &weights, true);
+        assert_eq!((clean.status, clean.invalid_index, clean.dropped), (PsdStatus::Ok, usize::MAX, 0));
+    }
+
+    fn lognormal_sample(scale: f64) -> (Vec<f64>, Vec<f64>) {
+        use statrs::distribution::{ContinuousCDF, Normal};
+        // Quantiles of ln d ~ N(3, 0.3^2) with uneven but balanced weights
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        let n = 4200;
+        let sizes = (0..n).map(|i| scale * (3.0 + 0.3 * standard.inverse_cdf((i as f64 + 0.5) / n as f64)).exp()).collect();
+        let weights = (0..n).map(|i| if i < n / 2 { 1.0 + (i % 7) as f64 } else { 1.0 + ((n - 1 - i) % 7) as f64 }).collect();
+        (sizes, weights)
+    }
+
+    #[test]
+    fn geometric_moments_match_the_generating_parameters() {
+        let (sizes, weights) = lognormal_sample(1.0);
+        let summary = summary(&sizes, &weights, WeightBasis::Volume).unwrap();
+        assert!((summary.geometric_mean / 3.0_f64.exp() - 1.0).abs() < 1e-3, "{}", summary.geometric_mean);
+        assert!((summary.geometric_std_dev / 0.3_f64.exp() - 1.0).abs() < 5e-3, "{}", summary.geometric_std_dev);
+    }
+
+    #[test]
+    fn scaling_sizes_scales_only_the_geometric_mean() {
+        let (sizes, weights) = lognormal_sample(1.0);
+        let (scaled, _) = lognormal_sample(2.5);
+        let original = summary(&sizes, &weights, WeightBasis::Volume).unwrap();
+        let larger = summary(&scaled, &weights, WeightBasis::Volume).unwrap();
+        assert!((larger.geometric_mean / original.geometric_mean - 2.5).abs() < 1e-9);
+        assert!((larger.geometric_std_dev - original.geometric_std_dev).abs() < 1e-9);
+    }
+
+    #[test]
+    fn zero_sizes_leave_the_geometric_moments_undefined() {
+        // Number weights keep D[3,2] finite with a zero size
+        let summary = summary(&[0.0, 2.0, 3.0, 4.0], &[1.0; 4], WeightBasis::Number).unwrap();
+        assert!(summary.geometric_mean.is_nan() && summary.geometric_std_dev.is_nan());
+        assert!(summary.mean > 0.0);
+    }
+
+    fn batch_samples(n_samples: usize, points: usize) -> (Vec<f64>, Vec<f64>, Vec<usize>) {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut offsets = vec![0];
+        let (mut sizes, mut weights) = (Vec::new(), Vec::new());
+        for _ in 0..n_samples {
+            for _ in 0..points {
+                sizes.push(rng.gen_range(0.5..200.0));
+                weights.push(rng.gen_range(0.0..10.0));
+            }
+            offsets.push(sizes.len());
+        }
+        (sizes, weights, offsets)
+    }
+
+    fn batch(sizes: &[f64], weights: &[f64], offsets: &[usize], method: u32) -> Option<(Vec<f64>, Vec<PsdStatus>)> {
+        let n_samples = offsets.len() - 1;
+        let mut results = vec![f64::NAN; n_samples * PSD_BATCH_WIDTH];
+        let mut statuses = vec![PsdStatus::NullPointer; n_samples];
+        analyze_particle_distribution_batch(
+            sizes.as_ptr(), weights.as_ptr(), offsets.as_ptr(), n_samples, method, results.as_mut_ptr(), statuses.as_mut_ptr(),
+        ).then_some((results, statuses))
+    }
+
+    fn looped(sizes: &[f64], weights: &[f64], offsets: &[usize], method: u32) -> (Vec<f64>, Vec<PsdStatus>) {
+        let mut results = Vec::new();
+        let mut statuses = Vec::new();
+        for span in offsets.windows(2) {
+            let mut row = [f64::NAN; PSD_BATCH_WIDTH];
+            let [d10, d50, d90, mean, std_dev] = &mut row;
+            let status = analyze_particle_distribution_v2(
+                sizes[span[0]..].as_ptr(), weights[span[0]..].as_ptr(), span[1] - span[0], d10, d50, d90, mean, std_dev,
+                false, std::ptr::null_mut(), std::ptr::null_mut(), method,
+            );
+            results.extend(row);
+            statuses.push(status);
+        }
+        (results, statuses)
+    }
+
+    #[test]
+    fn batch_matches_looping_the_single_sample_analyzer() {
+        let (mut sizes, mut weights, mut offsets) = batch_samples(200, 2000);
+        weights[3 * 2000 + 17] = -1.0;
+        sizes[50 * 2000 + 5] = f64::NAN;
+        offsets.insert(100, offsets[99]);
+        for method in [0, 1, 2] {
+            let (results, statuses) = batch(&sizes, &weights, &offsets, method).unwrap();
+            let (expected, expected_statuses) = looped(&sizes, &weights, &offsets, method);
+            assert_eq!(statuses, expected_statuses);
+            assert_eq!(statuses[3], PsdStatus::NegativeWeight);
+            assert_eq!(statuses[50], PsdStatus::NonFiniteSize);
+            assert_eq!(statuses[99], PsdStatus::Empty);
+            assert_eq!(statuses.iter().filter(|&&status| status == PsdStatus::Ok).count(), 198);
+            for (value, expected) in results.iter().zip(&expected) {
+                assert!(value.to_bits() == expected.to_bits() || (value.is_nan() && expected.is_nan()), "{value} != {expected}");
+            }
+        }
+    }
+
+    #[test]
+    fn decreasing_offsets_are_rejected() {
+        let (sizes, weights, _) = batch_samples(2, 10);
+        assert!(batch(&sizes, &weights, &[0, 12, 10], 0).is_none());
+        assert!(batch(&sizes, &weights, &[0, 10, 20], 0).is_some());
+    }
+
+    // cargo test --release -- --ignored batch_is_faster
+    #[test]
+    #[ignore]
+    fn batch_is_faster_than_looping() {
+        use std::time::Instant;
+        let (sizes, weights, offsets) = batch_samples(200, 2000);
+        let started = Instant::now();
+        looped(&sizes, &weights, &offsets, 0);
+        let looping = started.elapsed();
+        let started = Instant::now();
+        batch(&sizes, &weights, &offsets, 0).unwrap();
+        let batched = started.elapsed();
+        if std::thread::available_parallelism().map_or(1, |n| n.get()) > 1 {
+            assert!(batched * 3 < looping * 2, "loop {looping:?}, batch {batched:?}");
+        }
+    }
+
+    // A sieve stack with a pan, retained grams finest first; cumulative passing 10, 30, 55, 80, 95, 100 %
+    const SIEVE_EDGES: [f64; 7] = [0.0, 45.0, 63.0, 90.0, 125.0, 180.0, 250.0];
+    const SIEVE_MASSES: [f64; 6] = [10.0, 20.0, 25.0, 25.0, 15.0, 5.0];
+
+    fn sieve(edges: &[f64], masses: &[f64], log_uniform: bool) -> Option<[f64; PSD_BATCH_WIDTH]> {
+        let mut stats = [f64::NAN; PSD_BATCH_WIDTH];
+        (edges.len() == masses.len() + 1
+            && analyze_sieve_distribution(edges.as_ptr(), masses.as_ptr(), masses.len(), log_uniform, stats.as_mut_ptr()))
+            .then_some(stats)
+    }
+
+    #[test]
+    fn sieve_percentiles_match_the_cumulative_passing_curve() {
+        let [d10, d50, d90, mean, _] = sieve(&SIEVE_EDGES, &SIEVE_MASSES, false).unwrap();
+        // D10 sits on the 45 um sieve; D50 is 20/25 of the way through 63-90, D90 2/3 through 125-180
+        assert!((d10 - 45.0).abs() < 1e-9);
+        assert!((d50 - 84.6).abs() < 1e-9, "{d50}");
+        assert!((d90 - (125.0 + 55.0 * 2.0 / 3.0)).abs() < 1e-9, "{d90}");
+        // Bin midpoints weighted by mass fraction
+        assert!((mean - 92.675).abs() < 1e-9, "{mean}");
+    }
+
+    #[test]
+    fn log_uniform_bins_interpolate_in_log_size() {
+        let edges = [20.0, 45.0, 63.0, 90.0, 125.0, 180.0, 250.0];
+        let [_, d50, _, _, _] = sieve(&edges, &SIEVE_MASSES, true).unwrap();
+        assert!((d50 - 63.0 * (90.0_f64 / 63.0).powf(0.8)).abs() < 1e-9, "{d50}");
+        let [_, linear, _, _, _] = sieve(&edges, &SIEVE_MASSES, false).unwrap();
+        assert!(d50 < linear);
+        // A zero-size pan cannot be spread uniformly in log size
+        assert!(sieve(&SIEVE_EDGES, &SIEVE_MASSES, true).is_none());
+    }
+
+    #[test]
+    fn single_uniform_bin_has_the_uniform_moments() {
+        let [d10, d50, _, mean, std_dev] = sieve(&[10.0, 20.0], &[3.0], false).unwrap();
+        assert!((d10 - 11.0).abs() < 1e-9 && (d50 - 15.0).abs() < 1e-9);
+        assert!((mean - 15.0).abs() < 1e-9);
+        assert!((std_dev - 10.0 / 12f64.sqrt()).abs() < 1e-9, "{std_dev}");
+    }
+
+    #[test]
+    fn invalid_sieve_data_is_rejected() {
+        assert!(sieve(&[10.0, 10.0], &[3.0], false).is_none());
+        assert!(sieve(&[20.0, 10.0], &[3.0], false).is_none());
+        assert!(sieve(&[10.0, f64::INFINITY], &[3.0], false).is_none());
+        assert!(sieve(&[10.0, 20.0, 30.0], &[3.0, -1.0], false).is_none());
+        assert!(sieve(&[10.0, 20.0, 30.0], &[0.0, 0.0], false).is_none());
+        assert!(sieve(&[10.0], &[], false).is_none());
+    }
+
+    fn overall_recovery(
+        inputs: &[(f64, f64)],
+        outputs: &[(f64, f64)],
+        contents_in_percent: bool,
+    ) -> Option<(Vec<f64>, OverallRecovery)> {
+        let (input_masses, input_contents): (Vec<f64>, Vec<f64>) = inputs.iter().copied().unzip();
+        let (output_masses, output_contents): (Vec<f64>, Vec<f64>) = outputs.iter().copied().unzip();
+        let mut products = vec![f64::NAN; outputs.len()];
+        let mut summary = OverallRecovery::default();
+        calculate_overall_recovery(
+            input_masses.as_ptr(), input_contents.as_ptr(), inputs.len(), output_masses.as_ptr(), output_contents.as_ptr(),
+            outputs.len(), contents_in_percent, products.as_mut_ptr(), &mut summary,
+        ).then_some((products, summary))
+    }
+
+    #[test]
+    fn single_stream_recovery_is_the_simple_ratio() {
+        let (products, summary) = overall_recovery(&[(100.0, 0.23)], &[(30.0, 0.55)], false).unwrap();
+        assert!((summary.recovery - 100.0 * 30.0 * 0.55 / (100.0 * 0.23)).abs() < 1e-12);
+        assert_eq!(products, [summary.protein_out]);
+        assert_eq!(summary.unaccounted_loss, summary.protein_in - summary.protein_out);
+    }
+
+    #[test]
+    fn feed_recycle_and_two_products_balance() {
+        // Feed and recycle in; protein-rich, starch-rich and fines fractions out
+        let (products, summary) = overall_recovery(
+            &[(100.0, 23.0), (20.0, 40.0)], &[(30.0, 55.0), (60.0, 12.0), (5.0, 20.0)], true,
+        ).unwrap();
+        assert!((summary.protein_in - 31.0).abs() < 1e-12);
+        assert_eq!(products.len(), 3);
+        assert!((products[0] - 16.5).abs() < 1e-12 && (products[1] - 7.2).abs() < 1e-12 && (products[2] - 1.0).abs() < 1e-12);
+        assert_eq!(summary.protein_out, products.iter().sum::<f64>());
+        assert_eq!(summary.unaccounted_loss, summary.protein_in - summary.protein_out);
+        assert!((summary.recovery - 100.0 * 24.7 / 31.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn overall_recovery_validates_streams() {
+        // A percent content passed as a fraction
+        assert!(overall_recovery(&[(100.0, 23.0)], &[(30.0, 0.55)], false).is_none());
+        assert!(overall_recovery(&[(100.0, f64::NAN)], &[(30.0, 0.55)], false).is_none());
+        assert!(overall_recovery(&[(-1.0, 0.2)], &[(30.0, 0.55)], false).is_none());
+        assert!(overall_recovery(&[(100.0, 0.2)], &[(30.0, -0.1)], false).is_none());
+        assert!(overall_recovery(&[(100.0, 0.0)], &[(30.0, 0.5)], false).is_none());
+        assert!(overall_recovery(&[(100.0, 0.2)], &[], false).is_none());
+        assert!(overall_recovery(&[(100.0, 100.0)], &[(30.0, 100.0)], true).is_some());
+    }
+
+    fn in_unit(unit: RatioUnit, fraction: f64) -> f64 {
+        fraction * unit.scale()
+    }
+
+    fn checked_recovery(values: [f64; 3], units: [RatioUnit; 3], output_unit: RatioUnit) -> (RecoveryStatus, f64) {
+        let mut result = f64::NAN;
+        let status = calculate_protein_recovery_checked(
+            values[0], units[0] as u32, values[1], units[1] as u32, values[2], units[2] as u32, output_unit as u32, &mut result,
+        );
+        (status, result)
+    }
+
+    #[test]
+    fn every_unit_combination_gives_the_same_recovery() {
+        use RatioUnit::{Fraction, Percent};
+        for yield_unit in [Fraction, Percent] {
+            for content_unit in [Fraction, Percent] {
+                for efficiency_unit in [Fraction, Percent] {
+                    for output_unit in [Fraction, Percent] {
+                        let units = [yield_unit, content_unit, efficiency_unit];
+                        let values = [in_unit(yield_unit, 0.8), in_unit(content_unit, 0.5), in_unit(efficiency_unit, 0.9)];
+                        let (status, recovery) = checked_recovery(values, units, output_unit);
+                        assert_eq!(status, RecoveryStatus::Ok);
+                        assert!((recovery - in_unit(output_unit, 0.36)).abs() < 1e-12, "{units:?} {output_unit:?}: {recovery}");
+                    }
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn each_out_of_range_input_has_its_status() {
+        use RatioUnit::{Fraction, Percent};
+        let fractions = [Fraction; 3];
+        // The mixed-unit bug: a percent efficiency declared as a fraction
+        let cases = [
+            ([87.0, 0.5, 0.9], RecoveryStatus::YieldOutOfRange),
+            ([-0.1, 0.5, 0.9], RecoveryStatus::YieldOutOfRange),
+            ([0.8, 1.5, 0.9], RecoveryStatus::ContentOutOfRange),
+            ([0.8, f64::NAN, 0.9], RecoveryStatus::ContentOutOfRange),
+            ([0.8, 0.23, 87.0], RecoveryStatus::EfficiencyOutOfRange),
+            ([0.8, 0.5, f64::INFINITY], RecoveryStatus::EfficiencyOutOfRange),
+        ];
+        for (values, expected) in cases {
+            let (status, result) = checked_recovery(values, fractions, Percent);
+            assert_eq!(status, expected, "{values:?}");
+            assert!(result.is_nan());
+        }
+        assert_eq!(checked_recovery([80.0, 101.0, 90.0], [Percent; 3], Percent).0, RecoveryStatus::ContentOutOfRange);
+        assert_eq!(checked_recovery([100.0, 100.0, 100.0], [Percent; 3], Fraction), (RecoveryStatus::Ok, 1.0));
+        assert_eq!(
+            calculate_protein_recovery_checked(0.8, 0, 0.5, 0, 0.9, 0, 1, std::ptr::null_mut()),
+            RecoveryStatus::NullPointer
+        );
+        // The legacy formula is unchanged
+        assert!((calculate_protein_recovery(80.0, 0.5, 90.0) - 36.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn unknown_unit_ids_are_rejected() {
+        for position in 0..4 {
+            let mut units = [0; 4];
+            units[position] = 2;
+            let mut result = f64::NAN;
+            let status = calculate_protein_recovery_checked(0.8, units[0], 0.5, units[1], 0.9, units[2], units[3], &mut result);
+            assert_eq!(status, RecoveryStatus::UnknownUnit, "{units:?}");
+            assert!(result.is_nan());
+        }
+        assert_eq!(RatioUnit::from_id(1), Some(RatioUnit::Percent));
+        assert_eq!(RatioUnit::from_id(u32::MAX), None);
+    }
+}
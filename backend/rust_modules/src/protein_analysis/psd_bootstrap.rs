@@ -0,0 +1,175 @@
+use std::ffi::c_double;
+use rand::{Rng, SeedableRng, rngs::StdRng};
+use rayon::prelude::*;
+use super::protein_calculator::{basic_statistics, first_invalid_row, PercentileMethod, WeightedPsd, PSD_BATCH_WIDTH};
+
+/// Statistics bootstrapped by `bootstrap_psd_statistics`: D10, D50, D90
+/// and the weighted mean.
+pub const PSD_BOOTSTRAP_STATISTICS: usize = 4;
+/// Values written per statistic: point estimate, lower and upper bound.
+pub const PSD_BOOTSTRAP_WIDTH: usize = 3;
+
+/// Fewer replicates than this give percentile intervals too noisy to report.
+const MIN_BOOTSTRAP_REPLICATES: usize = 100;
+const DEFAULT_CONFIDENCE: f64 = 0.95;
+
+/// Quantile `q` of sorted values, interpolated linearly between order
+/// statistics at position (n - 1) * q.
+fn sorted_quantile(sorted: &[f64], q: f64) -> f64 {
+    let position = (sorted.len() - 1) as f64 * q;
+    let below = position.floor() as usize;
+    let above = (below + 1).min(sorted.len() - 1);
+    sorted[below] + (sorted[above] - sorted[below]) * (position - below as f64)
+}
+
+/// Bootstrap confidence intervals for D10, D50, D90 and the mean of a PSD.
+///
+/// Each of the `n_bootstrap` replicates draws `len` particles with
+/// replacement, each with probability proportional to its weight, and
+/// recomputes the statistics as `analyze_particle_distribution` does on the
+/// equally weighted draw. Replicate `r` uses an RNG seeded with `seed + r`,
+/// so results are reproducible regardless of how the replicates are spread
+/// over threads. Intervals use the percentile method at `confidence`
+/// (0 means 95%). Rows are treated as individual particles, so weights
+/// that are counts per size class understate the real sample size.
+///
+/// `results_out` receives `PSD_BOOTSTRAP_STATISTICS` rows of
+/// `PSD_BOOTSTRAP_WIDTH` values: the estimate from the original data
+/// (identical to `analyze_particle_distribution`), then the lower and upper
+/// bounds. Returns false on null pointers, input rejected by
+/// `analyze_particle_distribution_v2`, fewer than 100 replicates, or a
+/// confidence outside (0, 1).
+#[no_mangle]
+pub extern "C" fn bootstrap_psd_statistics(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    n_bootstrap: usize,
+    seed: u64,
+    confidence: c_double,
+    results_out: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || results_out.is_null() || len == 0
+        || n_bootstrap < MIN_BOOTSTRAP_REPLICATES {
+        return false;
+    }
+    let confidence = if confidence == 0.0 { DEFAULT_CONFIDENCE } else { confidence };
+    if confidence.is_nan() || confidence <= 0.0 || confidence >= 1.0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    if first_invalid_row(sizes, weights).is_some() {
+        return false;
+    }
+    let psd = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+    let estimates = basic_statistics(&psd, PercentileMethod::LinearCdf);
+
+    let unit_weights = vec![1.0; len];
+    let replicates: Vec<[f64; PSD_BOOTSTRAP_STATISTICS]> = (0..n_bootstrap).into_par_iter().map(|replicate| {
+        let mut rng = StdRng::seed_from_u64(seed.wrapping_add(replicate as u64));
+        let draw: Vec<f64> = (0..len).map(|_| {
+            let u: f64 = rng.gen();
+            let idx = psd.cumulative.partition_point(|&c| c <= u).min(len - 1);
+            psd.size_weight[idx].0
+        }).collect();
+        // Unit weights over finite sizes always form a valid distribution
+        let statistics = WeightedPsd::new(&draw, &unit_weights)
+            .map_or([f64::NAN; PSD_BATCH_WIDTH], |resampled| {
+                basic_statistics(&resampled, PercentileMethod::LinearCdf)
+            });
+        [statistics[0], statistics[1], statistics[2], statistics[3]]
+    }).collect();
+
+    let tail = (1.0 - confidence) / 2.0;
+    let results_out = unsafe {
+        std::slice::from_raw_parts_mut(results_out, PSD_BOOTSTRAP_STATISTICS * PSD_BOOTSTRAP_WIDTH)
+    };
+    for (statistic, row) in results_out.chunks_exact_mut(PSD_BOOTSTRAP_WIDTH).enumerate() {
+        let mut values: Vec<f64> = replicates.iter().map(|replicate| replicate[statistic]).collect();
+        values.sort_by(f64::total_cmp);
+        row.copy_from_slice(&[estimates[statistic], sorted_quantile(&values, tail), sorted_quantile(&values, 1.0 - tail)]);
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::protein_analysis::protein_calculator::analyze_particle_distribution;
+
+    const RESULTS: usize = PSD_BOOTSTRAP_STATISTICS * PSD_BOOTSTRAP_WIDTH;
+
+    /// `len` log-normal particle sizes with volume weights.
+    fn particles(len: usize) -> (Vec<f64>, Vec<f64>) {
+        use rand_distr::{Distribution, LogNormal};
+        let mut rng = StdRng::seed_from_u64(1);
+        let lognormal = LogNormal::new(3.0, 0.4).unwrap();
+        let sizes: Vec<f64> = (0..len).map(|_| lognormal.sample(&mut rng)).collect();
+        let weights = sizes.iter().map(|d| d * d * d).collect();
+        (sizes, weights)
+    }
+
+    fn bootstrap(sizes: &[f64], weights: &[f64], n_bootstrap: usize, seed: u64, confidence: f64) -> Option<[f64; RESULTS]> {
+        let mut results = [f64::NAN; RESULTS];
+        bootstrap_psd_statistics(sizes.as_ptr(), weights.as_ptr(), sizes.len(), n_bootstrap, seed, confidence, results.as_mut_ptr())
+            .then_some(results)
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn point_estimates_equal_the_analyzer_outputs() {
+        let (sizes, weights) = particles(400);
+        let results = bootstrap(&sizes, &weights, 200, 42, 0.0).unwrap();
+        let [mut d10, mut d50, mut d90, mut mean, mut std_dev] = [f64::NAN; 5];
+        assert!(analyze_particle_distribution(
+            sizes.as_ptr(), weights.as_ptr(), sizes.len(), &mut d10, &mut d50, &mut d90, &mut mean, &mut std_dev,
+        ));
+        assert_eq!([results[0], results[3], results[6], results[9]], [d10, d50, d90, mean]);
+        for row in results.chunks_exact(PSD_BOOTSTRAP_WIDTH) {
+            assert!(row[1] <= row[0] && row[0] <= row[2], "{row:?}");
+        }
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn fixed_seed_is_deterministic() {
+        let (sizes, weights) = particles(300);
+        let first = bootstrap(&sizes, &weights, 200, 42, 0.9).unwrap();
+        assert_eq!(first, bootstrap(&sizes, &weights, 200, 42, 0.9).unwrap());
+        assert_ne!(first, bootstrap(&sizes, &weights, 200, 1_000_042, 0.9).unwrap());
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn interval_shrinks_as_the_sample_grows() {
+        let widths: Vec<f64> = [100, 400, 1600, 6400].iter().map(|&len| {
+            let (sizes, weights) = particles(len);
+            let results = bootstrap(&sizes, &weights, 300, 42, 0.0).unwrap();
+            results[5] - results[4]
+        }).collect();
+        for pair in widths.windows(2) {
+            assert!(pair[1] < pair[0], "{widths:?}");
+        }
+        // Quadrupling the sample roughly halves the D50 interval
+        assert!(widths[3] < widths[0] / 4.0, "{widths:?}");
+    }
+
+    #[test]
+    #[cfg_attr(miri, ignore)]
+    fn too_few_replicates_and_bad_confidence_are_rejected() {
+        let (sizes, weights) = particles(50);
+        assert!(bootstrap(&sizes, &weights, 99, 1, 0.0).is_none());
+        assert!(bootstrap(&sizes, &weights, 100, 1, 0.0).is_some());
+        for confidence in [1.0, -0.5, f64::NAN] {
+            assert!(bootstrap(&sizes, &weights, 100, 1, confidence).is_none());
+        }
+        let mut negative = weights.clone();
+        negative[3] = -1.0;
+        assert!(bootstrap(&sizes, &negative, 100, 1, 0.0).is_none());
+    }
+}
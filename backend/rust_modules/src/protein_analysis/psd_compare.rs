@@ -0,0 +1,308 @@
+use std::ffi::c_double;
+use super::protein_calculator::{first_invalid_row, SizeBins, WeightedPsd};
+
+/// Number of logarithmic classes used for the overlap when the caller
+/// passes 0.
+const DEFAULT_OVERLAP_BINS: usize = 50;
+
+/// Similarity of two PSDs written by `compare_psd`.
+///
+/// `ks_statistic` is the largest gap between the two cumulative weight
+/// distributions (0 for identical samples, 1 for disjoint ones) and
+/// `overlap` the overlapping coefficient of their binned densities, the sum
+/// over classes of the smaller weight fraction (1 for identical samples, 0
+/// for disjoint ones). The D-value differences are sample B minus sample A.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, Default)]
+pub struct PsdComparison {
+    pub ks_statistic: c_double,
+    pub overlap: c_double,
+    pub d10_difference: c_double,
+    pub d50_difference: c_double,
+    pub d90_difference: c_double,
+}
+
+/// Weight fraction at or below `size` on the empirical step CDF.
+fn step_cdf(psd: &WeightedPsd, size: f64) -> f64 {
+    match psd.size_weight.partition_point(|&(d, _)| d <= size) {
+        0 => 0.0,
+        idx => psd.cumulative[idx - 1],
+    }
+}
+
+/// Weighted two-sample Kolmogorov-Smirnov statistic: the empirical CDFs
+/// only change at sample sizes, so the supremum is taken over those.
+fn ks_statistic(a: &WeightedPsd, b: &WeightedPsd) -> f64 {
+    a.size_weight.iter().chain(&b.size_weight)
+        .map(|&(size, _)| (step_cdf(a, size) - step_cdf(b, size)).abs())
+        .fold(0.0, f64::max)
+}
+
+/// Effective sample size below which `ks_test_psd` flags its asymptotic
+/// p-value as unreliable.
+const MIN_KS_EFFECTIVE_SIZE: f64 = 20.0;
+/// Terms of the Kolmogorov series tried before treating it as 1.
+const MAX_KOLMOGOROV_TERMS: usize = 100;
+
+/// Kolmogorov survival function Q(λ) = 2 Σ (-1)^(j-1) exp(-2 j² λ²). The
+/// alternating series converges slowly for small λ, where Q is 1 to
+/// working precision.
+fn kolmogorov_survival(lambda: f64) -> f64 {
+    let mut sum = 0.0;
+    let mut sign = 1.0;
+    for j in 1..=MAX_KOLMOGOROV_TERMS {
+        let term = sign * 2.0 * (-2.0 * (j * j) as f64 * lambda * lambda).exp();
+        sum += term;
+        if term.abs() <= 1e-10 * sum.abs() || term.abs() <= 1e-16 {
+            return sum.clamp(0.0, 1.0);
+        }
+        sign = -sign;
+    }
+    1.0
+}
+
+/// Two-sample Kolmogorov-Smirnov test of whether two PSDs differ.
+///
+/// `statistic_out` receives the weighted KS statistic D, the largest gap
+/// between the two cumulative weight distributions over the merged sizes,
+/// and `p_value_out` the asymptotic p-value Q(λ) with
+/// λ = (√nₑ + 0.12 + 0.11 / √nₑ) · D and nₑ = n_a·n_b / (n_a + n_b), where
+/// each n is the Kish effective sample size (Σw)² / Σw² of its sample.
+/// `small_sample_out` is set when either effective size is below 20, where
+/// the asymptotic distribution is a poor approximation.
+///
+/// The weighted test is approximate: the Kolmogorov distribution assumes
+/// independent, equally weighted observations. Weights from a volume
+/// conversion or a binned instrument output are not particle counts, so
+/// the effective sizes (and the p-value) mean little unless the weights are
+/// proportional to numbers of independently measured particles. Ties
+/// between the samples make the test conservative.
+///
+/// Returns false on null pointers, an empty sample, or input rejected by
+/// `analyze_particle_distribution_v2`.
+#[no_mangle]
+pub extern "C" fn ks_test_psd(
+    sizes_a: *const f64,
+    weights_a: *const f64,
+    len_a: usize,
+    sizes_b: *const f64,
+    weights_b: *const f64,
+    len_b: usize,
+    statistic_out: *mut c_double,
+    p_value_out: *mut c_double,
+    small_sample_out: *mut bool
+) -> bool {
+    if sizes_a.is_null() || weights_a.is_null() || sizes_b.is_null() || weights_b.is_null()
+        || statistic_out.is_null() || p_value_out.is_null() || small_sample_out.is_null()
+        || len_a == 0 || len_b == 0 {
+        return false;
+    }
+    let (sizes_a, weights_a, sizes_b, weights_b) = unsafe {
+        (
+            std::slice::from_raw_parts(sizes_a, len_a),
+            std::slice::from_raw_parts(weights_a, len_a),
+            std::slice::from_raw_parts(sizes_b, len_b),
+            std::slice::from_raw_parts(weights_b, len_b),
+        )
+    };
+    if first_invalid_row(sizes_a, weights_a).is_some() || first_invalid_row(sizes_b, weights_b).is_some() {
+        return false;
+    }
+    let (a, b) = match (WeightedPsd::new(sizes_a, weights_a), WeightedPsd::new(sizes_b, weights_b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return false,
+    };
+
+    let statistic = ks_statistic(&a, &b);
+    let (n_a, n_b) = (a.effective_sample_size(), b.effective_sample_size());
+    let root_n = (n_a * n_b / (n_a + n_b)).sqrt();
+    unsafe {
+        *statistic_out = statistic;
+        *p_value_out = kolmogorov_survival((root_n + 0.12 + 0.11 / root_n) * statistic);
+        *small_sample_out = n_a < MIN_KS_EFFECTIVE_SIZE || n_b < MIN_KS_EFFECTIVE_SIZE;
+    }
+    true
+}
+
+/// Compare two particle size distributions.
+///
+/// Both samples are treated as weighted empirical distributions. The
+/// overlap bins them onto `n_bins` logarithmic classes spanning both
+/// samples (0 means 50), as `build_psd_histogram` does. See
+/// `PsdComparison` for the outputs; D-values use the same interpolation as
+/// `analyze_particle_distribution`.
+///
+/// Returns false on null pointers, an empty sample, input rejected by
+/// `analyze_particle_distribution_v2`, non-positive sizes, or samples that
+/// together hold a single size.
+#[no_mangle]
+pub extern "C" fn compare_psd(
+    sizes_a: *const f64,
+    weights_a: *const f64,
+    len_a: usize,
+    sizes_b: *const f64,
+    weights_b: *const f64,
+    len_b: usize,
+    n_bins: usize,
+    comparison_out: *mut PsdComparison
+) -> bool {
+    if sizes_a.is_null() || weights_a.is_null() || sizes_b.is_null() || weights_b.is_null()
+        || comparison_out.is_null() || len_a == 0 || len_b == 0 {
+        return false;
+    }
+    let n_bins = if n_bins == 0 { DEFAULT_OVERLAP_BINS } else { n_bins };
+    let (sizes_a, weights_a, sizes_b, weights_b) = unsafe {
+        (
+            std::slice::from_raw_parts(sizes_a, len_a),
+            std::slice::from_raw_parts(weights_a, len_a),
+            std::slice::from_raw_parts(sizes_b, len_b),
+            std::slice::from_raw_parts(weights_b, len_b),
+        )
+    };
+    if !SizeBins::accepts(sizes_a, weights_a, true) || !SizeBins::accepts(sizes_b, weights_b, true) {
+        return false;
+    }
+    let (a, b) = match (WeightedPsd::new(sizes_a, weights_a), WeightedPsd::new(sizes_b, weights_b)) {
+        (Ok(a), Ok(b)) => (a, b),
+        _ => return false,
+    };
+    let bins = match SizeBins::new(&[sizes_a, sizes_b], n_bins, true, f64::NAN, f64::NAN) {
+        Some(bins) => bins,
+        None => return false,
+    };
+    let (counts_a, total_a, _) = bins.histogram(sizes_a, weights_a);
+    let (counts_b, total_b, _) = bins.histogram(sizes_b, weights_b);
+    let overlap: f64 = counts_a.iter().zip(&counts_b)
+        .map(|(&ca, &cb)| (ca / total_a).min(cb / total_b))
+        .sum::<f64>()
+        .min(1.0);
+
+    unsafe {
+        *comparison_out = PsdComparison {
+            ks_statistic: ks_statistic(&a, &b),
+            overlap,
+            d10_difference: b.percentile(0.1) - a.percentile(0.1),
+            d50_difference: b.percentile(0.5) - a.percentile(0.5),
+            d90_difference: b.percentile(0.9) - a.percentile(0.9),
+        };
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bell(offset: f64, scale: f64) -> (Vec<f64>, Vec<f64>) {
+        let sizes: Vec<f64> = (1..200).map(|i| offset + scale * i as f64 * 0.5).collect();
+        let weights = (1..200).map(|i| (-(i as f64 * 0.5 - 30.0).powi(2) / 100.0).exp()).collect();
+        (sizes, weights)
+    }
+
+    fn compare(a: &(Vec<f64>, Vec<f64>), b: &(Vec<f64>, Vec<f64>), n_bins: usize) -> Option<PsdComparison> {
+        let mut comparison = PsdComparison::default();
+        compare_psd(a.0.as_ptr(), a.1.as_ptr(), a.0.len(), b.0.as_ptr(), b.1.as_ptr(), b.0.len(), n_bins, &mut comparison)
+            .then_some(comparison)
+    }
+
+    #[test]
+    fn identical_samples_are_indistinguishable() {
+        let sample = bell(0.0, 1.0);
+        let comparison = compare(&sample, &sample, 0).unwrap();
+        assert_eq!(comparison.ks_statistic, 0.0);
+        assert!((comparison.overlap - 1.0).abs() < 1e-12);
+        assert_eq!([comparison.d10_difference, comparison.d50_difference, comparison.d90_difference], [0.0; 3]);
+    }
+
+    #[test]
+    fn disjoint_samples_do_not_overlap() {
+        let comparison = compare(&bell(0.0, 1.0), &bell(1000.0, 10.0), 0).unwrap();
+        assert!((comparison.ks_statistic - 1.0).abs() < 1e-12);
+        assert!(comparison.overlap < 1e-9, "{}", comparison.overlap);
+        assert!(comparison.d50_difference > 1000.0);
+    }
+
+    #[test]
+    fn scaled_sample_shifts_the_d_values_proportionally() {
+        let (a, b) = (bell(0.0, 1.0), bell(0.0, 1.1));
+        let comparison = compare(&a, &b, 30).unwrap();
+        assert!(comparison.ks_statistic > 0.0 && comparison.ks_statistic < 1.0);
+        assert!(comparison.overlap > 0.0 && comparison.overlap < 1.0);
+        assert!(comparison.d10_difference < comparison.d50_difference);
+        assert!(comparison.d50_difference < comparison.d90_difference);
+        // D50 of the bell is about 30, so a 10% larger sample moves it by about 3
+        assert!((comparison.d50_difference - 3.0).abs() < 0.1, "{}", comparison.d50_difference);
+    }
+
+    #[test]
+    fn invalid_samples_are_rejected() {
+        let sample = bell(0.0, 1.0);
+        let mut zero_size = sample.clone();
+        zero_size.0[0] = 0.0;
+        assert!(compare(&sample, &zero_size, 0).is_none());
+        let mut negative = sample.clone();
+        negative.1[4] = -1.0;
+        assert!(compare(&negative, &sample, 0).is_none());
+        let empty = (Vec::new(), Vec::new());
+        assert!(compare(&sample, &empty, 0).is_none());
+        let point = (vec![5.0], vec![1.0]);
+        assert!(compare(&point, &point, 0).is_none());
+    }
+
+    fn ks_test(a: &[f64], wa: &[f64], b: &[f64], wb: &[f64]) -> Option<(f64, f64, bool)> {
+        let (mut statistic, mut p_value, mut small_sample) = (f64::NAN, f64::NAN, false);
+        ks_test_psd(a.as_ptr(), wa.as_ptr(), a.len(), b.as_ptr(), wb.as_ptr(), b.len(), &mut statistic, &mut p_value, &mut small_sample)
+            .then_some((statistic, p_value, small_sample))
+    }
+
+    fn normal_sizes(seed: u64, len: usize, shift: f64) -> Vec<f64> {
+        use rand::{rngs::StdRng, SeedableRng};
+        use rand_distr::{Distribution, Normal};
+        let mut rng = StdRng::seed_from_u64(seed);
+        let normal = Normal::new(30.0, 5.0).unwrap();
+        (0..len).map(|_| normal.sample(&mut rng) + shift).collect()
+    }
+
+    #[test]
+    fn kolmogorov_tail_matches_the_critical_values() {
+        // The familiar 5% and 1% critical values of sqrt(n) D
+        assert!((kolmogorov_survival(1.358) - 0.05).abs() < 1e-3);
+        assert!((kolmogorov_survival(1.628) - 0.01).abs() < 1e-3);
+        assert_eq!(kolmogorov_survival(0.0), 1.0);
+    }
+
+    #[test]
+    fn identical_samples_give_no_evidence_of_a_difference() {
+        let sizes = normal_sizes(9, 300, 0.0);
+        let weights = vec![1.0; 300];
+        let (statistic, p_value, small_sample) = ks_test(&sizes, &weights, &sizes, &weights).unwrap();
+        assert_eq!(statistic, 0.0);
+        assert!((p_value - 1.0).abs() < 1e-9);
+        assert!(!small_sample);
+    }
+
+    #[test]
+    fn shifted_distribution_is_detected() {
+        let weights = vec![1.0; 300];
+        let a = normal_sizes(9, 300, 0.0);
+        let (_, same_population, _) = ks_test(&a, &weights, &normal_sizes(1_000_009, 300, 0.0), &weights).unwrap();
+        assert!(same_population > 0.05, "{same_population}");
+        let (statistic, p_value, _) = ks_test(&a, &weights, &normal_sizes(1_000_009, 300, 3.0), &weights).unwrap();
+        assert!(p_value < 0.01, "{statistic} {p_value}");
+    }
+
+    #[test]
+    fn small_effective_samples_are_flagged() {
+        let a = normal_sizes(9, 300, 0.0);
+        let b = normal_sizes(1_000_009, 300, 3.0);
+        assert!(ks_test(&a[..10], &[1.0; 10], &b[..10], &[1.0; 10]).unwrap().2);
+        // 300 rows, but one dominates the weight, so the Kish size is near 1
+        let mut dominated = vec![1.0; 300];
+        dominated[0] = 1e6;
+        assert!(ks_test(&a, &dominated, &b, &[1.0; 300]).unwrap().2);
+        assert!(ks_test(&a, &[1.0; 300], &[], &[]).is_none());
+        let mut nan = a.clone();
+        nan[1] = f64::NAN;
+        assert!(ks_test(&nan, &[1.0; 300], &b, &[1.0; 300]).is_none());
+    }
+}
@@ -0,0 +1,224 @@
+use statrs::distribution::{ContinuousCDF, Normal};
+use std::ffi::c_double;
+use super::protein_calculator::WeightedPsd;
+
+/// Ordinary least-squares line through (x, y): slope, intercept and R².
+/// None when the x values do not vary.
+fn linear_fit(points: &[(f64, f64)]) -> Option<(f64, f64, f64)> {
+    let n = points.len() as f64;
+    let mean_x = points.iter().map(|p| p.0).sum::<f64>() / n;
+    let mean_y = points.iter().map(|p| p.1).sum::<f64>() / n;
+    let (sxx, sxy, syy) = points.iter().fold((0.0, 0.0, 0.0), |(sxx, sxy, syy), &(x, y)| {
+        let (dx, dy) = (x - mean_x, y - mean_y);
+        (sxx + dx * dx, sxy + dx * dy, syy + dy * dy)
+    });
+    if sxx <= 0.0 {
+        return None;
+    }
+    let slope = sxy / sxx;
+    let r_squared = if syy > 0.0 { sxy * sxy / (sxx * syy) } else { 1.0 };
+    Some((slope, mean_y - slope * mean_x, r_squared))
+}
+
+/// Fit the Rosin-Rammler model R(d) = exp(-(d/d')^n) to a weighted PSD.
+///
+/// R is the weight fraction retained above each size, taken from the same
+/// cumulative distribution as `analyze_particle_distribution`. The model is
+/// linearized as ln(-ln R) = n ln d - n ln d' and fitted by least squares;
+/// `r_squared_out` is the R² of that line. Points with R at 0 or 1 (to
+/// within the rounding of the cumulative sum) or a non-positive size are
+/// skipped. Returns false on null pointers,
+/// invalid input, or fewer than 3 usable points.
+#[no_mangle]
+pub extern "C" fn fit_rosin_rammler(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    d_prime_out: *mut c_double,
+    n_out: *mut c_double,
+    r_squared_out: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || d_prime_out.is_null() || n_out.is_null()
+        || r_squared_out.is_null() || len == 0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    let psd = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+
+    // The running sum behind the cumulative weights can miss 1 by a few
+    // ulps, which would leave a spurious retained fraction near 0 at the
+    // largest size
+    let rounding = len as f64 * f64::EPSILON;
+    let points: Vec<(f64, f64)> = psd.size_weight.iter().zip(&psd.cumulative)
+        .map(|(&(size, _), &passing)| (size, 1.0 - passing))
+        .filter(|&(size, retained)| size > 0.0 && retained > rounding && retained < 1.0 - rounding)
+        .map(|(size, retained)| (size.ln(), (-retained.ln()).ln()))
+        .collect();
+    if points.len() < 3 {
+        return false;
+    }
+    let (slope, intercept, r_squared) = match linear_fit(&points) {
+        Some(fit) if fit.0 > 0.0 => fit,
+        _ => return false,
+    };
+
+    unsafe {
+        *d_prime_out = (-intercept / slope).exp();
+        *n_out = slope;
+        *r_squared_out = r_squared;
+    }
+    true
+}
+
+/// Fit a log-normal distribution to a weighted PSD.
+///
+/// The geometric mean is exp(Σw·ln d / Σw) and the geometric standard
+/// deviation exp of the weighted standard deviation of ln d. `ks_out` is
+/// the Kolmogorov distance: the largest gap between the empirical weighted
+/// CDF and the fitted log-normal CDF, checked on both sides of each step
+/// (0 when all sizes are equal). Returns false on null pointers, invalid
+/// input, or any size that is not strictly positive.
+#[no_mangle]
+pub extern "C" fn fit_lognormal_psd(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    geometric_mean_out: *mut c_double,
+    gsd_out: *mut c_double,
+    ks_out: *mut c_double
+) -> bool {
+    if sizes.is_null() || weights.is_null() || geometric_mean_out.is_null() || gsd_out.is_null()
+        || ks_out.is_null() || len == 0 {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    if sizes.iter().any(|&d| d <= 0.0) {
+        return false;
+    }
+    let psd = match WeightedPsd::new(sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+
+    let (mu, sigma) = match psd.log_mean_and_std() {
+        Some(moments) => moments,
+        None => return false,
+    };
+
+    let ks = match Normal::new(mu, sigma) {
+        Ok(model) => {
+            let mut ks: f64 = 0.0;
+            let mut below = 0.0;
+            for (i, (&(d, _), &cum)) in psd.size_weight.iter().zip(&psd.cumulative).enumerate() {
+                // Tied sizes form one step of the empirical CDF
+                if psd.size_weight.get(i + 1).is_some_and(|next| next.0 == d) {
+                    continue;
+                }
+                let fitted = model.cdf(d.ln());
+                ks = ks.max((fitted - below).abs()).max((cum - fitted).abs());
+                below = cum;
+            }
+            ks
+        }
+        Err(_) => 0.0,
+    };
+
+    unsafe {
+        *geometric_mean_out = mu.exp();
+        *gsd_out = sigma.exp();
+        *ks_out = ks;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rosin_rammler(sizes: &[f64], weights: &[f64]) -> Option<(f64, f64, f64)> {
+        let (mut d_prime, mut n, mut r_squared) = (f64::NAN, f64::NAN, f64::NAN);
+        fit_rosin_rammler(sizes.as_ptr(), weights.as_ptr(), sizes.len(), &mut d_prime, &mut n, &mut r_squared)
+            .then_some((d_prime, n, r_squared))
+    }
+
+    /// Size classes of an exact Rosin-Rammler distribution, each weight
+    /// perturbed by up to `noise` relative, with the coarse tail lumped at 200
+    fn rosin_rammler_sample(d_prime: f64, n: f64, noise: f64) -> (Vec<f64>, Vec<f64>) {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(7);
+        let mut sizes: Vec<f64> = (1..=40).map(|i| i as f64 * 2.0).collect();
+        let mut retained_above = 1.0;
+        let mut weights = Vec::with_capacity(sizes.len() + 1);
+        for &size in &sizes {
+            let retained = (-(size / d_prime).powf(n)).exp();
+            weights.push((retained_above - retained) * (1.0 + noise * rng.gen_range(-1.0..1.0)));
+            retained_above = retained;
+        }
+        sizes.push(200.0);
+        weights.push(retained_above);
+        (sizes, weights)
+    }
+
+    #[test]
+    fn rosin_rammler_parameters_are_recovered() {
+        let (sizes, weights) = rosin_rammler_sample(25.0, 1.8, 0.01);
+        let (d_prime, n, r_squared) = rosin_rammler(&sizes, &weights).unwrap();
+        assert!((d_prime / 25.0 - 1.0).abs() < 0.02, "{d_prime}");
+        assert!((n / 1.8 - 1.0).abs() < 0.02, "{n}");
+        assert!(r_squared > 0.99);
+
+        let (sizes, weights) = rosin_rammler_sample(25.0, 1.8, 0.0);
+        let (d_prime, n, r_squared) = rosin_rammler(&sizes, &weights).unwrap();
+        assert!((d_prime - 25.0).abs() < 1e-6 && (n - 1.8).abs() < 1e-6);
+        assert!((r_squared - 1.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn rosin_rammler_needs_three_usable_points() {
+        // The largest size always has R = 0 and is skipped
+        assert!(rosin_rammler(&[1.0, 2.0, 3.0], &[1.0, 1.0, 1.0]).is_none());
+        assert!(rosin_rammler(&[1.0, 2.0, 3.0, 4.0], &[1.0, 1.0, 1.0, 1.0]).is_some());
+        // Zero sizes are skipped too
+        assert!(rosin_rammler(&[0.0, 0.0, 3.0, 4.0], &[1.0, 1.0, 1.0, 1.0]).is_none());
+    }
+
+    fn lognormal(sizes: &[f64], weights: &[f64]) -> Option<(f64, f64, f64)> {
+        let (mut geometric_mean, mut gsd, mut ks) = (f64::NAN, f64::NAN, f64::NAN);
+        fit_lognormal_psd(sizes.as_ptr(), weights.as_ptr(), sizes.len(), &mut geometric_mean, &mut gsd, &mut ks)
+            .then_some((geometric_mean, gsd, ks))
+    }
+
+    #[test]
+    fn lognormal_parameters_are_recovered() {
+        // Quantiles of ln d ~ N(3, 0.4^2)
+        let standard = Normal::new(0.0, 1.0).unwrap();
+        let n = 2000;
+        let sizes: Vec<f64> = (0..n).map(|i| (3.0 + 0.4 * standard.inverse_cdf((i as f64 + 0.5) / n as f64)).exp()).collect();
+        let (geometric_mean, gsd, ks) = lognormal(&sizes, &vec![1.0; n]).unwrap();
+        assert!((geometric_mean / 3.0_f64.exp() - 1.0).abs() < 1e-3);
+        assert!((gsd / 0.4_f64.exp() - 1.0).abs() < 2e-3);
+        assert!(ks <= 1.0 / n as f64 + 1e-9);
+
+        // Two well separated modes fit a single log-normal poorly
+        let bimodal: Vec<f64> = sizes.iter().map(|size| size / 20.0).chain(sizes.iter().map(|size| size * 20.0)).collect();
+        assert!(lognormal(&bimodal, &vec![1.0; 2 * n]).unwrap().2 > 0.1);
+    }
+
+    #[test]
+    fn lognormal_fit_handles_a_single_size() {
+        assert_eq!(lognormal(&[2.0; 3], &[1.0; 3]), Some((2.0, 1.0, 0.0)));
+    }
+
+    #[test]
+    fn lognormal_fit_rejects_non_positive_sizes() {
+        assert!(lognormal(&[0.0, 2.0], &[1.0, 1.0]).is_none());
+        assert!(lognormal(&[-1.0, 2.0], &[1.0, 1.0]).is_none());
+    }
+}
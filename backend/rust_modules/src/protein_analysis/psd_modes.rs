@@ -0,0 +1,182 @@
+use std::ffi::c_double;
+use super::protein_calculator::{WeightedPsd, MIN_KDE_POINTS};
+
+/// Values written per mode by `detect_psd_modes`: location, density
+/// height and weight fraction.
+pub const PSD_MODE_WIDTH: usize = 3;
+
+/// Minimum prominence, relative to the highest density, used when the
+/// caller passes 0.
+const DEFAULT_PROMINENCE: f64 = 0.05;
+/// Bandwidths of padding on each side of the data so that modes at the
+/// smallest and largest sizes show up as interior maxima.
+const GRID_PADDING: f64 = 3.0;
+
+/// Topographic prominence of the local maximum at `peak`: its height above
+/// the higher of the two lowest points reached before climbing to a taller
+/// peak (or the edge of the grid) on either side.
+fn prominence(densities: &[f64], peak: usize) -> f64 {
+    let height = densities[peak];
+    let base = |range: &mut dyn Iterator<Item = usize>| {
+        range.map(|j| densities[j])
+            .take_while(|&f| f <= height)
+            .fold(height, f64::min)
+    };
+    let left = base(&mut (0..peak).rev());
+    let right = base(&mut (peak + 1..densities.len()));
+    height - left.max(right)
+}
+
+/// Detect the modes of a multi-modal PSD.
+///
+/// A weighted Gaussian kernel density is estimated on ln(size) with
+/// Silverman's bandwidth times `bandwidth_multiplier` (0 means 1). Local
+/// maxima whose prominence is at least `min_prominence` times the highest
+/// density (0 means 5%) are kept, up to `max_modes` of the most prominent.
+///
+/// `modes_out` receives `PSD_MODE_WIDTH` values per mode, ordered by size:
+/// the mode location, the density there per unit ln(size), and the weight
+/// fraction of particles between the density minima separating it from its
+/// neighbouring modes (the fractions sum to 1). `n_modes_out` receives the
+/// number of modes written. Returns false on null pointers, invalid input,
+/// non-positive sizes, fewer than 5 particles or a single repeated size, a
+/// negative parameter, a `min_prominence` of 1 or more, `max_modes` of 0,
+/// or when no peak is prominent enough, in which case `n_modes_out` is 0.
+#[no_mangle]
+pub extern "C" fn detect_psd_modes(
+    sizes: *const f64,
+    weights: *const f64,
+    len: usize,
+    bandwidth_multiplier: c_double,
+    min_prominence: c_double,
+    max_modes: usize,
+    modes_out: *mut c_double,
+    n_modes_out: *mut usize
+) -> bool {
+    if sizes.is_null() || weights.is_null() || modes_out.is_null() || n_modes_out.is_null()
+        || len < MIN_KDE_POINTS || max_modes == 0 {
+        return false;
+    }
+    let multiplier = if bandwidth_multiplier == 0.0 { 1.0 } else { bandwidth_multiplier };
+    let min_prominence = if min_prominence == 0.0 { DEFAULT_PROMINENCE } else { min_prominence };
+    if multiplier.is_nan() || multiplier < 0.0 || !(0.0..1.0).contains(&min_prominence) {
+        return false;
+    }
+    let (sizes, weights) = unsafe {
+        (std::slice::from_raw_parts(sizes, len), std::slice::from_raw_parts(weights, len))
+    };
+    if sizes.iter().any(|&d| d <= 0.0) {
+        return false;
+    }
+    let log_sizes: Vec<f64> = sizes.iter().map(|d| d.ln()).collect();
+    let psd = match WeightedPsd::new(&log_sizes, weights) {
+        Ok(psd) => psd,
+        Err(_) => return false,
+    };
+    let bandwidth = psd.silverman_bandwidth() * multiplier;
+    if bandwidth <= 0.0 || !bandwidth.is_finite() {
+        return false;
+    }
+
+    let (lo, step, densities) = psd.density_grid(bandwidth, GRID_PADDING);
+    let highest = densities.iter().copied().fold(0.0, f64::max);
+    let mut peaks: Vec<(usize, f64)> = (1..densities.len() - 1)
+        .filter(|&i| densities[i] > densities[i - 1] && densities[i] >= densities[i + 1])
+        .map(|i| (i, prominence(&densities, i)))
+        .filter(|&(_, p)| p >= min_prominence * highest)
+        .collect();
+    peaks.sort_by(|a, b| b.1.total_cmp(&a.1));
+    peaks.truncate(max_modes);
+    peaks.sort_by_key(|&(i, _)| i);
+    if peaks.is_empty() {
+        unsafe {
+            *n_modes_out = 0;
+        }
+        return false;
+    }
+
+    // Split the size axis at the density minimum between neighbouring modes
+    let boundaries: Vec<f64> = peaks.windows(2)
+        .map(|pair| {
+            let valley = (pair[0].0..=pair[1].0)
+                .min_by(|&a, &b| densities[a].total_cmp(&densities[b]))
+                .unwrap_or(pair[0].0);
+            lo + step * valley as f64
+        })
+        .collect();
+    let mut fractions = vec![0.0; peaks.len()];
+    for &(log_size, weight) in &psd.size_weight {
+        fractions[boundaries.partition_point(|&b| b < log_size)] += weight / psd.total_weight;
+    }
+
+    let modes_out = unsafe { std::slice::from_raw_parts_mut(modes_out, peaks.len() * PSD_MODE_WIDTH) };
+    for ((row, &(i, _)), fraction) in modes_out.chunks_exact_mut(PSD_MODE_WIDTH).zip(&peaks).zip(fractions) {
+        row.copy_from_slice(&[(lo + step * i as f64).exp(), densities[i], fraction]);
+    }
+    unsafe {
+        *n_modes_out = peaks.len();
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{SeedableRng, rngs::StdRng};
+    use rand_distr::{Distribution, LogNormal};
+
+    /// Equally weighted sizes from a two-component log-normal mixture.
+    fn mixture(first_median: f64, second_median: f64, first_share: f64, n: usize) -> Vec<f64> {
+        let mut rng = StdRng::seed_from_u64(7);
+        let first = LogNormal::new(first_median.ln(), 0.25).unwrap();
+        let second = LogNormal::new(second_median.ln(), 0.25).unwrap();
+        let n_first = (n as f64 * first_share).round() as usize;
+        (0..n).map(|i| if i < n_first { first.sample(&mut rng) } else { second.sample(&mut rng) }).collect()
+    }
+
+    fn modes(sizes: &[f64], min_prominence: f64) -> Option<Vec<[f64; PSD_MODE_WIDTH]>> {
+        let weights = vec![1.0; sizes.len()];
+        let mut out = vec![0.0; 4 * PSD_MODE_WIDTH];
+        let mut n = usize::MAX;
+        let ok = detect_psd_modes(sizes.as_ptr(), weights.as_ptr(), sizes.len(), 0.0, min_prominence, 4,
+            out.as_mut_ptr(), &mut n);
+        ok.then(|| out.chunks_exact(PSD_MODE_WIDTH).take(n).map(|m| [m[0], m[1], m[2]]).collect())
+    }
+
+    #[test]
+    fn well_separated_mixture_has_two_modes_with_mixture_fractions() {
+        let found = modes(&mixture(2.0, 20.0, 0.3, 2000), 0.0).unwrap();
+        assert_eq!(found.len(), 2);
+        assert!((found[0][0] / 2.0 - 1.0).abs() < 0.2, "{found:?}");
+        assert!((found[1][0] / 20.0 - 1.0).abs() < 0.2, "{found:?}");
+        assert!((found[0][2] - 0.3).abs() < 0.03);
+        assert!((found[0][2] + found[1][2] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn merged_mixture_has_one_mode() {
+        let found = modes(&mixture(10.0, 11.0, 0.5, 2000), 0.0).unwrap();
+        assert_eq!(found.len(), 1);
+        assert!((found[0][2] - 1.0).abs() < 1e-12);
+    }
+
+    #[test]
+    fn prominence_of_one_or_more_is_rejected_without_panicking() {
+        let sizes = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        assert!(modes(&sizes, 1.5).is_none());
+        assert!(modes(&sizes, 1.0).is_none());
+        assert!(modes(&sizes, -0.1).is_none());
+    }
+
+    #[test]
+    fn no_prominent_peak_reports_zero_modes() {
+        // Padding stops three bandwidths out, where the density is still
+        // about 1% of the peak, so no peak reaches 99.9% prominence
+        let sizes = [1.0, 2.0, 3.0, 4.0, 5.0, 6.0];
+        let weights = [1.0; 6];
+        let mut out = [0.0; 4 * PSD_MODE_WIDTH];
+        let mut n = usize::MAX;
+        assert!(!detect_psd_modes(sizes.as_ptr(), weights.as_ptr(), 6, 0.0, 0.999, 4, out.as_mut_ptr(), &mut n));
+        assert_eq!(n, 0);
+    }
+}
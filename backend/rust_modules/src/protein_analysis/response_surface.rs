@@ -0,0 +1,287 @@
+use std::ffi::c_double;
+use super::curve_fit::r_squared;
+use crate::matrix_ops::{least_squares, symmetric_eigenvalues};
+
+/// Outcome of `fit_response_surface`.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseSurfaceStatus {
+    Ok = 0,
+    /// A required pointer was null
+    NullPointer = 1,
+    /// No factors, or a factor setting or response that is not finite
+    InvalidInput = 2,
+    /// Fewer design points than model coefficients
+    TooFewPoints = 3,
+    /// The design cannot separate every model term, e.g. a factor held at
+    /// only two levels so its square is confounded with its linear term
+    RankDeficient = 4,
+}
+
+/// Nature of the stationary point found by `find_stationary_point`, from
+/// the signs of the Hessian eigenvalues.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StationaryKind {
+    Maximum = 0,
+    Minimum = 1,
+    Saddle = 2,
+}
+
+/// Number of coefficients of a full quadratic in `n_factors` factors:
+/// intercept, linear terms, squares and pairwise interactions.
+fn coefficient_count(n_factors: usize) -> usize {
+    (n_factors + 1) * (n_factors + 2) / 2
+}
+
+/// Model terms at `x` in coefficient order.
+fn quadratic_terms(x: &[f64]) -> Vec<f64> {
+    let mut terms = Vec::with_capacity(coefficient_count(x.len()));
+    terms.push(1.0);
+    terms.extend(x.iter().copied());
+    terms.extend(x.iter().map(|v| v * v));
+    for i in 0..x.len() {
+        for j in i + 1..x.len() {
+            terms.push(x[i] * x[j]);
+        }
+    }
+    terms
+}
+
+fn evaluate(coefficients: &[f64], x: &[f64]) -> f64 {
+    quadratic_terms(x).iter().zip(coefficients).map(|(t, b)| t * b).sum()
+}
+
+/// Fit a full quadratic response surface to a designed experiment.
+///
+/// `x_matrix` holds `n_points` rows of `n_factors` factor settings and
+/// `y_values` the response at each. The model is
+/// y = b0 + Σ bi·xi + Σ bii·xi² + Σ(i<j) bij·xi·xj, solved by least
+/// squares through a QR factorization. `coeffs_out` receives the
+/// (n_factors + 1)(n_factors + 2) / 2 coefficients in that order, the
+/// interactions ordered (1,2), (1,3), ..., (2,3), ..., and `r_squared_out`
+/// the R². Nothing is written unless the status is
+/// `ResponseSurfaceStatus::Ok`.
+#[no_mangle]
+pub extern "C" fn fit_response_surface(
+    x_matrix: *const c_double,
+    n_points: usize,
+    n_factors: usize,
+    y_values: *const c_double,
+    coeffs_out: *mut c_double,
+    r_squared_out: *mut c_double
+) -> ResponseSurfaceStatus {
+    if x_matrix.is_null() || y_values.is_null() || coeffs_out.is_null() || r_squared_out.is_null() {
+        return ResponseSurfaceStatus::NullPointer;
+    }
+    if n_factors == 0 {
+        return ResponseSurfaceStatus::InvalidInput;
+    }
+    let n_coefficients = coefficient_count(n_factors);
+    if n_points < n_coefficients {
+        return ResponseSurfaceStatus::TooFewPoints;
+    }
+    let (x_matrix, y_values) = unsafe {
+        (std::slice::from_raw_parts(x_matrix, n_points * n_factors), std::slice::from_raw_parts(y_values, n_points))
+    };
+    if x_matrix.iter().chain(y_values).any(|v| !v.is_finite()) {
+        return ResponseSurfaceStatus::InvalidInput;
+    }
+
+    let design: Vec<f64> = x_matrix.chunks_exact(n_factors).flat_map(quadratic_terms).collect();
+    let coefficients = match least_squares(&design, y_values, n_points, n_coefficients) {
+        Some(coefficients) => coefficients,
+        None => return ResponseSurfaceStatus::RankDeficient,
+    };
+    let fitted: Vec<f64> = x_matrix.chunks_exact(n_factors).map(|x| evaluate(&coefficients, x)).collect();
+
+    unsafe {
+        std::slice::from_raw_parts_mut(coeffs_out, n_coefficients).copy_from_slice(&coefficients);
+        *r_squared_out = r_squared(y_values, &fitted);
+    }
+    ResponseSurfaceStatus::Ok
+}
+
+/// Stationary point of a response surface from `fit_response_surface`.
+///
+/// Solves gradient = b + H·x = 0, where b holds the linear coefficients
+/// and the Hessian H has 2·bii on the diagonal and bij off it.
+/// `point_out` receives the `n_factors` factor settings, `value_out` the
+/// predicted response there and `kind_out` whether it is a maximum,
+/// minimum or saddle point. Returns false on null pointers, no factors,
+/// non-finite coefficients, or a singular Hessian (a ridge with no unique
+/// stationary point).
+#[no_mangle]
+pub extern "C" fn find_stationary_point(
+    coeffs: *const c_double,
+    n_factors: usize,
+    point_out: *mut c_double,
+    value_out: *mut c_double,
+    kind_out: *mut StationaryKind
+) -> bool {
+    if coeffs.is_null() || point_out.is_null() || value_out.is_null() || kind_out.is_null() || n_factors == 0 {
+        return false;
+    }
+    let coefficients = unsafe { std::slice::from_raw_parts(coeffs, coefficient_count(n_factors)) };
+    if coefficients.iter().any(|b| !b.is_finite()) {
+        return false;
+    }
+
+    let n = n_factors;
+    let (linear, rest) = coefficients[1..].split_at(n);
+    let (squares, interactions) = rest.split_at(n);
+    let mut hessian = vec![0.0; n * n];
+    for (i, &b) in squares.iter().enumerate() {
+        hessian[i * n + i] = 2.0 * b;
+    }
+    let pairs = (0..n).flat_map(|i| (i + 1..n).map(move |j| (i, j)));
+    for ((i, j), &b) in pairs.zip(interactions) {
+        hessian[i * n + j] = b;
+        hessian[j * n + i] = b;
+    }
+    let negative_gradient: Vec<f64> = linear.iter().map(|b| -b).collect();
+    let point = match least_squares(&hessian, &negative_gradient, n, n) {
+        Some(point) => point,
+        None => return false,
+    };
+    let eigenvalues = symmetric_eigenvalues(&hessian, n);
+    let kind = if eigenvalues.iter().all(|&e| e < 0.0) {
+        StationaryKind::Maximum
+    } else if eigenvalues.iter().all(|&e| e > 0.0) {
+        StationaryKind::Minimum
+    } else {
+        StationaryKind::Saddle
+    };
+
+    unsafe {
+        std::slice::from_raw_parts_mut(point_out, n).copy_from_slice(&point);
+        *value_out = evaluate(coefficients, &point);
+        *kind_out = kind;
+    }
+    true
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // y = 80 - (x1 - 2)² - 0.5 (x2 + 1)² + 0.2 (x1 - 2)(x2 + 1), expanded
+    const SURFACE: [f64; 6] = [75.1, 4.2, -1.4, -1.0, -0.5, 0.2];
+
+    fn grid(levels: &[f64], coefficients: &[f64]) -> (Vec<f64>, Vec<f64>) {
+        let mut x_matrix = Vec::new();
+        let mut y_values = Vec::new();
+        for &a in levels {
+            for &b in levels {
+                x_matrix.extend([a, b]);
+                y_values.push(evaluate(coefficients, &[a, b]));
+            }
+        }
+        (x_matrix, y_values)
+    }
+
+    fn fit(x_matrix: &[f64], n_factors: usize, y_values: &[f64]) -> Result<(Vec<f64>, f64), ResponseSurfaceStatus> {
+        let mut coefficients = vec![f64::NAN; coefficient_count(n_factors)];
+        let mut r_squared = f64::NAN;
+        match fit_response_surface(x_matrix.as_ptr(), y_values.len(), n_factors, y_values.as_ptr(), coefficients.as_mut_ptr(), &mut r_squared) {
+            ResponseSurfaceStatus::Ok => Ok((coefficients, r_squared)),
+            status => Err(status),
+        }
+    }
+
+    fn stationary(coefficients: &[f64], n_factors: usize) -> Option<(Vec<f64>, f64, StationaryKind)> {
+        let mut point = vec![f64::NAN; n_factors];
+        let (mut value, mut kind) = (f64::NAN, StationaryKind::Saddle);
+        find_stationary_point(coefficients.as_ptr(), n_factors, point.as_mut_ptr(), &mut value, &mut kind)
+            .then_some((point, value, kind))
+    }
+
+    #[test]
+    fn known_surface_and_its_maximum_are_recovered() {
+        let (x_matrix, y_values) = grid(&[0.0, 1.0, 2.0, 3.0], &SURFACE);
+        let (coefficients, r_squared) = fit(&x_matrix, 2, &y_values).unwrap();
+        for (fitted, truth) in coefficients.iter().zip(SURFACE) {
+            assert!((fitted - truth).abs() < 1e-9, "{coefficients:?}");
+        }
+        assert!((r_squared - 1.0).abs() < 1e-12);
+
+        let (point, value, kind) = stationary(&coefficients, 2).unwrap();
+        assert!((point[0] - 2.0).abs() < 1e-9 && (point[1] + 1.0).abs() < 1e-9, "{point:?}");
+        assert!((value - 80.0).abs() < 1e-9);
+        assert_eq!(kind, StationaryKind::Maximum);
+    }
+
+    #[test]
+    fn raw_factor_units_with_noise_locate_the_optimum() {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(5);
+        // Classifier speed (rpm), feed rate (kg/h) and moisture (fraction) on very different scales
+        let purity = |x: &[f64]| 80.0 - 1e-6 * (x[0] - 6000.0).powi(2) - 0.3 * (x[1] - 12.0).powi(2)
+            - 2000.0 * (x[2] - 0.09).powi(2) + 1e-4 * (x[0] - 6000.0) * (x[1] - 12.0);
+        let (mut x_matrix, mut y_values) = (Vec::new(), Vec::new());
+        for speed in [4000.0, 6000.0, 8000.0] {
+            for feed in [8.0, 12.0, 16.0] {
+                for moisture in [0.06, 0.09, 0.12] {
+                    let x = [speed, feed, moisture];
+                    x_matrix.extend(x);
+                    y_values.push(purity(&x) + rng.gen_range(-0.01..0.01));
+                }
+            }
+        }
+        let (coefficients, r_squared) = fit(&x_matrix, 3, &y_values).unwrap();
+        assert!(r_squared > 0.999);
+        let (point, value, kind) = stationary(&coefficients, 3).unwrap();
+        assert_eq!(kind, StationaryKind::Maximum);
+        assert!((point[0] / 6000.0 - 1.0).abs() < 0.01 && (point[1] / 12.0 - 1.0).abs() < 0.01, "{point:?}");
+        assert!((point[2] / 0.09 - 1.0).abs() < 0.01, "{point:?}");
+        assert!((value - 80.0).abs() < 0.05);
+    }
+
+    #[test]
+    fn minima_and_saddles_are_classified() {
+        // y = x1² + 2 x2² + 0.5 x1 x2: minimum at the origin
+        let (point, value, kind) = stationary(&[0.0, 0.0, 0.0, 1.0, 2.0, 0.5], 2).unwrap();
+        assert_eq!(kind, StationaryKind::Minimum);
+        assert!(point.iter().all(|x| x.abs() < 1e-12) && value.abs() < 1e-12);
+        // y = 1 + 2 x1 - 3 x2 + x1² - 2 x2² + 0.5 x1 x2, fitted from exact data
+        let saddle = [1.0, 2.0, -3.0, 1.0, -2.0, 0.5];
+        let (x_matrix, y_values) = grid(&[-1.0, 0.0, 1.0, 2.0], &saddle);
+        let (coefficients, _) = fit(&x_matrix, 2, &y_values).unwrap();
+        let (point, _, kind) = stationary(&coefficients, 2).unwrap();
+        assert_eq!(kind, StationaryKind::Saddle);
+        // Gradient 2 + 2 x1 + 0.5 x2 = 0 and -3 - 4 x2 + 0.5 x1 = 0
+        assert!((2.0 + 2.0 * point[0] + 0.5 * point[1]).abs() < 1e-9);
+        assert!((-3.0 - 4.0 * point[1] + 0.5 * point[0]).abs() < 1e-9);
+    }
+
+    #[test]
+    fn ridge_has_no_stationary_point() {
+        // Hessian [[2, 2], [2, 2]] is singular
+        assert!(stationary(&[0.0, 1.0, 1.0, 1.0, 1.0, 2.0], 2).is_none());
+        assert!(stationary(&[0.0, f64::NAN, 1.0, 1.0, 1.0, 0.0], 2).is_none());
+        assert!(stationary(&[1.0], 0).is_none());
+    }
+
+    #[test]
+    fn two_level_and_undersized_designs_are_rejected() {
+        // A replicated 2² factorial cannot separate the squares from the intercept
+        let (mut x_matrix, mut y_values) = (Vec::new(), Vec::new());
+        for a in [-1.0, 1.0] {
+            for b in [-1.0, 1.0] {
+                for _ in 0..2 {
+                    x_matrix.extend([a, b]);
+                    y_values.push(a + b);
+                }
+            }
+        }
+        assert_eq!(fit(&x_matrix, 2, &y_values), Err(ResponseSurfaceStatus::RankDeficient));
+        assert_eq!(fit(&x_matrix[..10], 2, &y_values[..5]), Err(ResponseSurfaceStatus::TooFewPoints));
+        assert_eq!(fit(&[], 0, &[1.0]), Err(ResponseSurfaceStatus::InvalidInput));
+        let (mut x_matrix, mut y_values) = grid(&[0.0, 1.0, 2.0], &SURFACE);
+        x_matrix[3] = f64::INFINITY;
+        assert_eq!(fit(&x_matrix, 2, &y_values), Err(ResponseSurfaceStatus::InvalidInput));
+        x_matrix[3] = 1.0;
+        y_values[0] = f64::NAN;
+        assert_eq!(fit(&x_matrix, 2, &y_values), Err(ResponseSurfaceStatus::InvalidInput));
+    }
+}
@@ -0,0 +1,175 @@
+use std::ffi::c_double;
+use super::curve_fit::{levenberg_marquardt, r_squared};
+use crate::matrix_ops::cholesky_solve;
+
+/// Parameters of the solubility-pH model
+/// S(pH) = exp(c + k * (pH - pI)²), in the order (c, k, pI).
+pub const SOLUBILITY_PARAMS: usize = 3;
+
+const MIN_SOLUBILITY_POINTS: usize = 4;
+
+fn solubility_model(ph: f64, params: &[f64], gradient: &mut [f64]) -> f64 {
+    let (c, k, pi) = (params[0], params[1], params[2]);
+    let offset = ph - pi;
+    let value = (c + k * offset * offset).exp();
+    gradient[0] = value;
+    gradient[1] = value * offset * offset;
+    gradient[2] = -2.0 * value * k * offset;
+    value
+}
+
+/// Starting point from the least-squares parabola through ln(S), which is
+/// exact for noise-free data: ln S = a + b·pH + d·pH² with k = d,
+/// pI = -b / 2d and c = a - d·pI².
+fn initial_guess(ph_values: &[f64], log_solubilities: &[f64]) -> Option<[f64; SOLUBILITY_PARAMS]> {
+    let mut normal = [0.0; 9];
+    let mut rhs = [0.0; 3];
+    for (&x, &y) in ph_values.iter().zip(log_solubilities) {
+        let basis = [1.0, x, x * x];
+        for i in 0..3 {
+            rhs[i] += basis[i] * y;
+            for j in 0..3 {
+                normal[i * 3 + j] += basis[i] * basis[j];
+            }
+        }
+    }
+    let coefficients = cholesky_solve(&normal, &rhs, 3)?;
+    let (a, b, d) = (coefficients[0], coefficients[1], coefficients[2]);
+    if d <= 0.0 {
+        return None;
+    }
+    let pi = -b / (2.0 * d);
+    Some([a - d * pi * pi, d, pi])
+}
+
+/// Fit a solubility-pH profile and estimate the isoelectric point.
+///
+/// The model is S(pH) = exp(c + k * (pH - pI)²), a parabola in ln(S) with
+/// its minimum at pI. It is started from a log-scale quadratic regression
+/// and refined by Levenberg-Marquardt on the original scale. `params_out`
+/// receives `SOLUBILITY_PARAMS` values (c, k, pI), `ph_min_out` the pH of
+/// minimum solubility and `r_squared_out` the R² on the original scale.
+///
+/// Returns false on null pointers, fewer than 4 points, non-finite pH
+/// values, non-finite or non-positive solubilities, a fitted curve without
+/// a minimum, or a minimum outside the measured pH range.
+#[no_mangle]
+pub extern "C" fn fit_solubility_profile(
+    ph_values: *const c_double,
+    solubilities: *const c_double,
+    len: usize,
+    params_out: *mut c_double,
+    ph_min_out: *mut c_double,
+    r_squared_out: *mut c_double
+) -> bool {
+    if ph_values.is_null() || solubilities.is_null() || params_out.is_null() || ph_min_out.is_null()
+        || r_squared_out.is_null() || len < MIN_SOLUBILITY_POINTS {
+        return false;
+    }
+    let (ph_values, solubilities) = unsafe {
+        (std::slice::from_raw_parts(ph_values, len), std::slice::from_raw_parts(solubilities, len))
+    };
+    if ph_values.iter().any(|x| !x.is_finite()) || solubilities.iter().any(|s| !s.is_finite() || *s <= 0.0) {
+        return false;
+    }
+
+    let log_solubilities: Vec<f64> = solubilities.iter().map(|s| s.ln()).collect();
+    let initial = match initial_guess(ph_values, &log_solubilities) {
+        Some(initial) => initial,
+        None => return false,
+    };
+    let params = match levenberg_marquardt(ph_values, solubilities, &initial, solubility_model) {
+        Some(params) if params[1] > 0.0 => params,
+        _ => return false,
+    };
+    let pi = params[2];
+    let (lowest, highest) = ph_values.iter().fold((f64::INFINITY, f64::NEG_INFINITY), |(lo, hi), &x| (lo.min(x), hi.max(x)));
+    if pi <= lowest || pi >= highest {
+        return false;
+    }
+
+    let mut gradient = [0.0; SOLUBILITY_PARAMS];
+    let fitted: Vec<f64> = ph_values.iter().map(|&x| solubility_model(x, &params, &mut gradient)).collect();
+    unsafe {
+        std::slice::from_raw_parts_mut(params_out, SOLUBILITY_PARAMS).copy_from_slice(&params);
+        *ph_min_out = pi;
+        *r_squared_out = r_squared(solubilities, &fitted);
+    }
+    true
+}
+
+/// Solubility predicted at `ph` by parameters from `fit_solubility_profile`;
+/// NaN when `params` is null.
+#[no_mangle]
+pub extern "C" fn evaluate_solubility(params: *const c_double, ph: c_double) -> c_double {
+    if params.is_null() {
+        return f64::NAN;
+    }
+    let params = unsafe { std::slice::from_raw_parts(params, SOLUBILITY_PARAMS) };
+    solubility_model(ph, params, &mut [0.0; SOLUBILITY_PARAMS])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PH: [f64; 8] = [2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0];
+    // ln S = 4.5 + 0.12 (pH - 4.6)^2
+    const TRUTH: [f64; SOLUBILITY_PARAMS] = [4.5, 0.12, 4.6];
+
+    fn fit(ph_values: &[f64], solubilities: &[f64]) -> Option<([f64; SOLUBILITY_PARAMS], f64, f64)> {
+        let (mut params, mut ph_min, mut r_squared) = ([f64::NAN; SOLUBILITY_PARAMS], f64::NAN, f64::NAN);
+        fit_solubility_profile(ph_values.as_ptr(), solubilities.as_ptr(), ph_values.len(), params.as_mut_ptr(), &mut ph_min, &mut r_squared)
+            .then_some((params, ph_min, r_squared))
+    }
+
+    fn profile(noise: f64) -> Vec<f64> {
+        use rand::{rngs::StdRng, Rng, SeedableRng};
+        let mut rng = StdRng::seed_from_u64(3);
+        PH.iter().map(|&ph| evaluate_solubility(TRUTH.as_ptr(), ph) * (1.0 + noise * rng.gen_range(-1.0..1.0))).collect()
+    }
+
+    #[test]
+    fn isoelectric_point_is_recovered_from_noisy_data() {
+        let (params, ph_min, r_squared) = fit(&PH, &profile(0.05)).unwrap();
+        assert!((ph_min - 4.6).abs() < 0.2, "{ph_min}");
+        assert_eq!(ph_min, params[2]);
+        assert!(r_squared > 0.95);
+    }
+
+    #[test]
+    fn noise_free_profile_is_fitted_exactly() {
+        let (params, _, r_squared) = fit(&PH, &profile(0.0)).unwrap();
+        for (fitted, truth) in params.iter().zip(TRUTH) {
+            assert!((fitted - truth).abs() < 1e-6, "{params:?}");
+        }
+        assert!((r_squared - 1.0).abs() < 1e-9);
+        assert!((evaluate_solubility(params.as_ptr(), 4.6) - 4.5_f64.exp()).abs() < 1e-4);
+        assert!(evaluate_solubility(std::ptr::null(), 4.6).is_nan());
+    }
+
+    #[test]
+    fn profiles_without_a_bracketed_minimum_are_rejected() {
+        // Rising throughout: the minimum lies below the measured range
+        assert!(fit(&[5.0, 6.0, 7.0, 8.0], &[10.0, 30.0, 60.0, 80.0]).is_none());
+        // Too few points
+        let solubilities = profile(0.0);
+        assert!(fit(&PH[..3], &solubilities[..3]).is_none());
+        let mut zero = solubilities.clone();
+        zero[2] = 0.0;
+        assert!(fit(&PH, &zero).is_none());
+    }
+
+    #[test]
+    fn non_finite_measurements_are_rejected() {
+        let solubilities = profile(0.0);
+        for bad in [f64::NAN, f64::INFINITY, f64::NEG_INFINITY] {
+            let mut ph_values = PH;
+            ph_values[3] = bad;
+            assert!(fit(&ph_values, &solubilities).is_none());
+            let mut measured = solubilities.clone();
+            measured[3] = bad;
+            assert!(fit(&PH, &measured).is_none());
+        }
+    }
+}